@@ -0,0 +1,95 @@
+//! libtest benches for the paths the crate makes unchecked, run with `cargo bench --bench nightly`.
+//!
+//! Every input goes through `black_box` and every result comes out of `iter`, so the optimizer
+//! can neither fold the loops away nor hoist the index checks out of them; a regression in
+//! `forward_unchecked`, the `BitSet` word kernels or `IndexVec::push` shows up as a slower loop.
+//! `stable.rs` times the same loops without libtest.
+
+#![feature(test, step_trait)]
+
+extern crate test;
+
+use std::iter::Step;
+
+use rust_unstable::bit_set::BitSet;
+use rust_unstable::index_vec::IndexVec;
+use rust_unstable::step::CustomIndex;
+use test::{Bencher, black_box};
+
+const LEN: usize = 100_000;
+
+/// The checked `Step::forward`, which tests for overflow on every step.
+#[bench]
+fn step_forward(b: &mut Bencher) {
+    b.iter(|| {
+        let mut idx = CustomIndex::ZERO;
+        for _ in 0..black_box(LEN) {
+            idx = Step::forward(black_box(idx), 1);
+        }
+        idx
+    });
+}
+
+/// `Step::forward_unchecked`, as range iteration calls it.
+#[bench]
+fn step_forward_unchecked(b: &mut Bencher) {
+    b.iter(|| {
+        let mut idx = CustomIndex::ZERO;
+        for _ in 0..black_box(LEN) {
+            // SAFETY: `LEN` steps from zero stay far below `CustomIndex::MAX`.
+            idx = unsafe { Step::forward_unchecked(black_box(idx), 1) };
+        }
+        idx
+    });
+}
+
+/// A `Range<CustomIndex>`, which steps with `forward_unchecked`.
+#[bench]
+fn step_range(b: &mut Bencher) {
+    b.iter(|| {
+        let end = CustomIndex::from_usize(black_box(LEN));
+        let mut sum = 0u64;
+        for idx in CustomIndex::ZERO..end {
+            sum += black_box(idx).as_u32() as u64;
+        }
+        sum
+    });
+}
+
+fn every_nth(n: usize) -> BitSet<usize> {
+    let mut set = BitSet::new_empty(LEN);
+    for i in (0..LEN).step_by(n) {
+        set.insert(i);
+    }
+    set
+}
+
+/// A union that changes words all over the set.
+#[bench]
+fn bit_set_union(b: &mut Bencher) {
+    let (evens, threes) = (every_nth(2), every_nth(3));
+    b.iter(|| {
+        let mut set = black_box(&evens).clone();
+        let changed = set.union(black_box(&threes));
+        (changed, set)
+    });
+}
+
+/// A union that changes nothing, which still reads every word.
+#[bench]
+fn bit_set_union_unchanged(b: &mut Bencher) {
+    let (mut evens, fours) = (every_nth(2), every_nth(4));
+    b.iter(|| black_box(&mut evens).union(black_box(&fours)));
+}
+
+/// `push`, which checks that the new length is a valid index every time.
+#[bench]
+fn index_vec_push(b: &mut Bencher) {
+    b.iter(|| {
+        let mut v: IndexVec<CustomIndex, u32> = IndexVec::with_capacity(LEN);
+        for i in 0..black_box(LEN) as u32 {
+            black_box(v.push(black_box(i)));
+        }
+        v
+    });
+}
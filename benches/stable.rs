@@ -0,0 +1,94 @@
+//! The benches of `nightly.rs` as criterion benches for stable toolchains, run with
+//! `cargo bench --bench stable`.
+//!
+//! `#[bench]` needs libtest's unstable `test` feature, and criterion is what stable crates use in
+//! its place; it is only a dev-dependency, so crates depending on this one never build it. Every
+//! loop goes through the same `LEN` elements, so the group reports them per second next to the
+//! time per iteration, and each batch of iterations is timed by `time::Throughput::measure`.
+//! `std::hint::black_box` stands in for `test::black_box`. Stable has no `Step` to call, so the
+//! step loops go through `Idx::plus` and `from_u32_unchecked`, the checked and unchecked ends of
+//! what `forward` and `forward_unchecked` do.
+
+use std::hint::black_box;
+
+use criterion::{Bencher, Criterion, criterion_group, criterion_main};
+use rust_unstable::bit_set::BitSet;
+use rust_unstable::index_vec::IndexVec;
+use rust_unstable::step::{CustomIndex, Idx};
+use rust_unstable::time::Throughput;
+
+/// The elements each bench goes through per iteration: steps, bits or pushes.
+const LEN: usize = 100_000;
+
+/// Runs `f` in batches of as many iterations as criterion asks for.
+fn iter<T>(b: &mut Bencher<'_>, mut f: impl FnMut() -> T) {
+    b.iter_custom(|iters| {
+        let ((), batch) = Throughput::measure(iters * LEN as u64, || {
+            for _ in 0..iters {
+                black_box(f());
+            }
+        });
+        batch.elapsed
+    });
+}
+
+fn every_nth(n: usize) -> BitSet<usize> {
+    let mut set = BitSet::new_empty(LEN);
+    for i in (0..LEN).step_by(n) {
+        set.insert(i);
+    }
+    set
+}
+
+fn benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stable");
+    group.throughput(criterion::Throughput::Elements(LEN as u64));
+
+    group.bench_function("step_forward", |b| {
+        iter(b, || {
+            let mut idx = CustomIndex::ZERO;
+            for _ in 0..black_box(LEN) {
+                idx = black_box(idx).plus(1);
+            }
+            idx
+        })
+    });
+    group.bench_function("step_forward_unchecked", |b| {
+        iter(b, || {
+            let mut idx = CustomIndex::ZERO;
+            for _ in 0..black_box(LEN) {
+                // SAFETY: `LEN` steps from zero stay far below `CustomIndex::MAX`.
+                idx = unsafe { CustomIndex::from_u32_unchecked(black_box(idx).as_u32() + 1) };
+            }
+            idx
+        })
+    });
+
+    let (evens, threes, fours) = (every_nth(2), every_nth(3), every_nth(4));
+    group.bench_function("bit_set_union", |b| {
+        iter(b, || {
+            let mut set = black_box(&evens).clone();
+            let changed = set.union(black_box(&threes));
+            (changed, set)
+        })
+    });
+    let mut unchanged = evens.clone();
+    group.bench_function("bit_set_union_unchanged", |b| {
+        iter(b, || black_box(&mut unchanged).union(black_box(&fours)))
+    });
+
+    group.bench_function("index_vec_push", |b| {
+        iter(b, || {
+            let mut v: IndexVec<CustomIndex, u32> = IndexVec::with_capacity(LEN);
+            for i in 0..black_box(LEN) as u32 {
+                black_box(v.push(black_box(i)));
+            }
+            v
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(stable, benches);
+criterion_main!(stable);
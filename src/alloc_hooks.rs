@@ -0,0 +1,179 @@
+//! `std::alloc::set_alloc_error_hook` usage
+//!
+//! Tracking issue [51245].
+//!
+//! `set_alloc_error_hook(hook)` replaces what runs when an infallible allocation fails, just before
+//! the process aborts. The default only prints the size of the allocation, which says little when a
+//! compiler pass runs out of memory somewhere in a dozen growing tables. The hook gets the failed
+//! `Layout`, and may also panic instead of returning, which makes the failure catchable.
+//!
+//! [`growing`] records which container is growing on the current thread while a closure runs, and
+//! [`IndexVec::reserve_tracked`] and [`IndexVec::push_tracked`] wrap their growth in it, naming the
+//! vector type with its index domain. [`install`] sets a hook that prints that name along with the
+//! size, and [`install_panicking`] one that panics with an [`AllocFailure`] instead of aborting, for
+//! tests of code that should survive or report running out of memory. There is no stable
+//! equivalent, so the module is only built with the `nightly` feature.
+//!
+//! [51245]: https://github.com/rust-lang/rust/issues/51245
+
+use core::{error, fmt};
+use std::alloc::{self, Layout};
+use std::any;
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::panic;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+thread_local! {
+    static GROWING: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// A failed allocation, as seen by the hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocFailure {
+    pub size: usize,
+    pub align: usize,
+    /// The container that was growing on the failing thread, if any.
+    pub growing: Option<&'static str>,
+}
+
+impl fmt::Display for AllocFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "memory allocation of {} bytes failed", self.size)?;
+        if let Some(container) = self.growing {
+            write!(f, " while growing {container}")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for AllocFailure {}
+
+impl AllocFailure {
+    fn new(layout: Layout) -> Self {
+        AllocFailure {
+            size: layout.size(),
+            align: layout.align(),
+            growing: current(),
+        }
+    }
+}
+
+/// Runs `f` with `container` recorded as growing on this thread.
+pub fn growing<R>(container: &'static str, f: impl FnOnce() -> R) -> R {
+    /// Restores the outer container, also on unwinding.
+    struct Restore(Option<&'static str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            GROWING.set(self.0);
+        }
+    }
+
+    let _restore = Restore(GROWING.replace(Some(container)));
+    f()
+}
+
+/// The innermost container recorded by [`growing`] on this thread.
+pub fn current() -> Option<&'static str> {
+    GROWING.get()
+}
+
+/// Sets a hook that prints the failed allocation and the growing container to stderr. The
+/// process still aborts afterwards.
+pub fn install() {
+    alloc::set_alloc_error_hook(report);
+}
+
+/// Sets a hook that panics with an [`AllocFailure`] payload, so a test can catch the failure with
+/// `catch_unwind` and check what ran out.
+///
+/// Code is rarely written to be unwound out of an allocation, so this is for tests only.
+pub fn install_panicking() {
+    alloc::set_alloc_error_hook(raise);
+}
+
+/// Restores the default hook.
+pub fn uninstall() {
+    alloc::take_alloc_error_hook();
+}
+
+fn report(layout: Layout) {
+    // Formatting to the unbuffered stderr doesn't allocate.
+    let _ = writeln!(io::stderr(), "{}", AllocFailure::new(layout));
+}
+
+fn raise(layout: Layout) {
+    panic::panic_any(AllocFailure::new(layout));
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Like `reserve`, but names this vector type if the allocation fails.
+    pub fn reserve_tracked(&mut self, additional: usize) {
+        growing(any::type_name::<Self>(), || self.raw.reserve(additional));
+    }
+
+    /// Like `push`, but names this vector type if the allocation fails.
+    pub fn push_tracked(&mut self, value: T) -> I {
+        growing(any::type_name::<Self>(), || self.push(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::AssertUnwindSafe;
+
+    use super::*;
+
+    #[test]
+    fn nested_contexts() {
+        assert_eq!(current(), None);
+        growing("outer", || {
+            assert_eq!(growing("inner", current), Some("inner"));
+            assert_eq!(current(), Some("outer"));
+            let result = panic::catch_unwind(|| growing("unwound", || panic!("grow failed")));
+            assert!(result.is_err());
+            assert_eq!(current(), Some("outer"));
+        });
+        assert_eq!(current(), None);
+
+        let mut v: IndexVec<u32, u8> = IndexVec::new();
+        assert_eq!(v.push_tracked(1), 0);
+        v.reserve_tracked(10);
+        assert!(v.capacity() >= 11);
+    }
+
+    #[test]
+    fn out_of_memory_panics() {
+        install_panicking();
+        let mut v: IndexVec<u32, u8> = IndexVec::from([1, 2]);
+        // Fits in a `Layout`, but no allocator can provide it.
+        let huge = 1 << 62;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| v.reserve_tracked(huge)));
+        let direct = panic::catch_unwind(|| alloc::handle_alloc_error(Layout::new::<[u64; 4]>()));
+        uninstall();
+
+        let failure = *result.unwrap_err().downcast::<AllocFailure>().unwrap();
+        assert!(failure.size >= huge);
+        assert_eq!(failure.align, 1);
+        let container = failure.growing.unwrap();
+        assert!(container.contains("IndexVec<u32, u8>"), "{container}");
+        assert!(failure.to_string().ends_with(container));
+        // The vector is left as it was, and the context is cleared.
+        assert_eq!(v.raw, [1, 2]);
+        assert_eq!(current(), None);
+
+        let failure = *direct.unwrap_err().downcast::<AllocFailure>().unwrap();
+        assert_eq!(
+            failure,
+            AllocFailure {
+                size: 32,
+                align: 8,
+                growing: None
+            }
+        );
+        assert_eq!(failure.to_string(), "memory allocation of 32 bytes failed");
+    }
+}
@@ -0,0 +1,133 @@
+//! `panic::always_abort` usage
+//!
+//! Tracking issue [84438].
+//!
+//! `panic::always_abort()` makes every later panic in the process abort instead of unwinding, as
+//! if it had been built with `panic = "abort"`, and can't be undone. Code running in a signal
+//! handler, in a child after `fork`, or in a callback called from C must not unwind: unwinding out
+//! of a signal handler or across an `extern "C"` frame is undefined behaviour or an abort anyway,
+//! and destructors that run on the way may touch containers the interrupted code left half
+//! updated.
+//!
+//! [`abort_on_panic`] switches the process over, for programs embedding the crate in such places.
+//! [`abort_on_unwind`] is the narrower tool for one call, and works the same everywhere. Without
+//! the `nightly` feature `abort_on_panic` adds a panic hook that aborts once the hook set before it
+//! has printed the message, before any unwinding starts.
+//!
+//! [84438]: https://github.com/rust-lang/rust/issues/84438
+
+use std::{mem, process};
+
+/// Makes every later panic, on any thread, abort the process.
+#[cfg(feature = "nightly")]
+pub fn abort_on_panic() {
+    std::panic::always_abort();
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn abort_on_panic() {
+    portable::abort_on_panic();
+}
+
+/// Runs `f`, aborting the process if it panics instead of unwinding out of this call.
+pub fn abort_on_unwind<R>(f: impl FnOnce() -> R) -> R {
+    /// Only dropped if `f` unwinds.
+    struct AbortOnDrop;
+
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            process::abort();
+        }
+    }
+
+    let guard = AbortOnDrop;
+    let result = f();
+    mem::forget(guard);
+    result
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::process;
+
+    use crate::panic_hooks::update_hook;
+
+    pub(super) fn abort_on_panic() {
+        update_hook(|previous, info| {
+            previous(info);
+            process::abort();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Output};
+    use std::{env, panic};
+
+    use super::*;
+
+    /// Set in a child test process to the way it should make its panic abort.
+    const CHILD: &str = "RUST_UNSTABLE_ABORT_CHILD";
+
+    /// Runs `child` below in a new process of this test binary.
+    fn run_child(mode: &str) -> Output {
+        Command::new(env::current_exe().unwrap())
+            .args(["--exact", "always_abort::tests::child", "--nocapture"])
+            .env(CHILD, mode)
+            .output()
+            .unwrap()
+    }
+
+    /// Does nothing unless run by `run_child`.
+    #[test]
+    fn child() {
+        let Ok(mode) = env::var(CHILD) else {
+            return;
+        };
+        match mode.as_str() {
+            "process" => abort_on_panic(),
+            "portable" => portable::abort_on_panic(),
+            _ => {}
+        }
+        let result = panic::catch_unwind(|| {
+            if mode == "call" {
+                abort_on_unwind(|| panic!("child panicked"))
+            } else {
+                panic!("child panicked")
+            }
+        });
+        println!("caught: {}", result.is_err());
+    }
+
+    #[test]
+    fn panics_abort() {
+        // Without the `nightly` feature, "process" already takes the portable path.
+        let modes: &[&str] = if cfg!(feature = "nightly") {
+            &["process", "call", "portable"]
+        } else {
+            &["process", "call"]
+        };
+        for &mode in modes {
+            let output = run_child(mode);
+            assert!(!output.status.success(), "{mode}");
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(!stdout.contains("caught"), "{mode}: {stdout}");
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::ExitStatusExt;
+                // SIGABRT.
+                assert_eq!(output.status.signal(), Some(6), "{mode}");
+            }
+        }
+
+        let output = run_child("none");
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("caught: true"));
+    }
+
+    #[test]
+    fn no_panic_returns() {
+        assert_eq!(abort_on_unwind(|| 1 + 1), 2);
+    }
+}
@@ -0,0 +1,296 @@
+//! `Arc::new_uninit_slice` and `Arc::get_mut_unchecked` usage
+//!
+//! Tracking issue [63292].
+//!
+//! `Arc::<[T]>::new_uninit_slice(n)` allocates a shared slice of `n` uninitialized elements, and
+//! `assume_init` turns it into an `Arc<[T]>` once they are written, so a table that will be
+//! shared read-only is built in place, where `Arc::from(vec)` allocates a second time and copies.
+//! Writing the elements takes a `&mut` into the `Arc`; `Arc::get_mut` gives one after checking
+//! that the count is one, and the unstable `Arc::get_mut_unchecked` without the check, for code
+//! that knows nobody else holds the `Arc` yet. The same exists for `Rc`.
+//!
+//! An [`ArcTableBuilder`] owns the only `Arc` of a table being filled in index order, and
+//! [`ArcTableBuilder::finish`] hands out the finished [`ArcTable`], which derefs to an
+//! `IndexSlice` and clones by bumping the count. A builder dropped before it is full drops what
+//! it holds. Without the `nightly` feature the elements are reached through `Arc::get_mut`.
+//!
+//! [63292]: https://github.com/rust-lang/rust/issues/63292
+
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::{fmt, ptr};
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+/// The elements of `table`, which no other `Arc` may point to.
+///
+/// # Safety
+///
+/// `table` must be the only `Arc` to its slice, and no `Weak` may exist either.
+#[cfg(feature = "nightly")]
+#[inline]
+unsafe fn slots<T>(table: &mut Arc<[MaybeUninit<T>]>) -> &mut [MaybeUninit<T>] {
+    // SAFETY: the caller guarantees `table` is unique.
+    unsafe { Arc::get_mut_unchecked(table) }
+}
+
+/// The elements of `table`, which no other `Arc` may point to.
+///
+/// # Safety
+///
+/// `table` must be the only `Arc` to its slice, and no `Weak` may exist either.
+#[cfg(not(feature = "nightly"))]
+#[inline]
+unsafe fn slots<T>(table: &mut Arc<[MaybeUninit<T>]>) -> &mut [MaybeUninit<T>] {
+    portable::slots(table)
+}
+
+/// A shared, immutable `[T]` indexed by `I`.
+pub struct ArcTable<I: Idx, T> {
+    raw: Arc<[T]>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T> ArcTable<I, T> {
+    /// A table of `f(i)` for each `i` in `0..len`, in order.
+    pub fn from_fn(len: usize, mut f: impl FnMut(I) -> T) -> Self {
+        let mut builder = ArcTableBuilder::new(len);
+        while !builder.is_full() {
+            let next = I::new(builder.len());
+            builder.push(f(next));
+        }
+        builder.finish()
+    }
+
+    /// The shared slice, for code that doesn't know about `I`.
+    pub fn raw(&self) -> &Arc<[T]> {
+        &self.raw
+    }
+}
+
+impl<I: Idx, T> Clone for ArcTable<I, T> {
+    fn clone(&self) -> Self {
+        ArcTable {
+            raw: Arc::clone(&self.raw),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Idx, T> Deref for ArcTable<I, T> {
+    type Target = IndexSlice<I, T>;
+
+    #[inline]
+    fn deref(&self) -> &IndexSlice<I, T> {
+        IndexSlice::from_raw(&self.raw)
+    }
+}
+
+impl<I: Idx, T: fmt::Debug> fmt::Debug for ArcTable<I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.raw, f)
+    }
+}
+
+/// An [`ArcTable`] of a fixed length being filled in index order.
+pub struct ArcTableBuilder<I: Idx, T> {
+    /// Never cloned, so that it stays the only `Arc` to its slice.
+    table: Arc<[MaybeUninit<T>]>,
+    /// The first `len` elements of `table` are initialized.
+    len: usize,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T> ArcTableBuilder<I, T> {
+    /// A builder for a table of `len` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is more than `I` can index.
+    pub fn new(len: usize) -> Self {
+        assert!(
+            len == 0 || len - 1 <= I::MAX_INDEX,
+            "table too long for its index"
+        );
+        ArcTableBuilder {
+            table: Arc::new_uninit_slice(len),
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.table.len()
+    }
+
+    /// Writes `value` at the next index, and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table is already full.
+    #[track_caller]
+    pub fn push(&mut self, value: T) -> I {
+        assert!(!self.is_full(), "table builder is full");
+        // SAFETY: `table` is never cloned.
+        let slots = unsafe { slots(&mut self.table) };
+        slots[self.len].write(value);
+        self.len += 1;
+        I::new(self.len - 1)
+    }
+
+    /// The elements pushed so far.
+    pub fn as_slice(&self) -> &IndexSlice<I, T> {
+        // SAFETY: the first `len` elements are initialized.
+        IndexSlice::from_raw(unsafe { self.table[..self.len].assume_init_ref() })
+    }
+
+    /// The elements pushed so far, to fix up before they are shared.
+    pub fn as_mut_slice(&mut self) -> &mut IndexSlice<I, T> {
+        let len = self.len;
+        // SAFETY: `table` is never cloned, and its first `len` elements are initialized.
+        IndexSlice::from_raw_mut(unsafe { slots(&mut self.table)[..len].assume_init_mut() })
+    }
+
+    /// Returns the table if it is full, or `self` back if it isn't.
+    pub fn try_finish(self) -> Result<ArcTable<I, T>, Self> {
+        if !self.is_full() {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the `Arc` is moved out only once.
+        let table = unsafe { ptr::read(&this.table) };
+        Ok(ArcTable {
+            // SAFETY: all elements are initialized.
+            raw: unsafe { table.assume_init() },
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table isn't full.
+    #[track_caller]
+    pub fn finish(self) -> ArcTable<I, T> {
+        match self.try_finish() {
+            Ok(table) => table,
+            Err(this) => panic!(
+                "table builder has {} of {} elements",
+                this.len,
+                this.table.len()
+            ),
+        }
+    }
+}
+
+impl<I: Idx, T> Drop for ArcTableBuilder<I, T> {
+    fn drop(&mut self) {
+        let len = self.len;
+        // SAFETY: `table` is never cloned, and its first `len` elements are initialized and not
+        // used again.
+        unsafe { slots(&mut self.table)[..len].assume_init_drop() };
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::MaybeUninit;
+    use std::sync::Arc;
+
+    pub(super) fn slots<T>(table: &mut Arc<[MaybeUninit<T>]>) -> &mut [MaybeUninit<T>] {
+        Arc::get_mut(table).expect("table builder's `Arc` is shared")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    use super::*;
+    use crate::thread_id_value::ThreadIdx;
+
+    #[test]
+    fn builds_and_shares() {
+        let table: ArcTable<u32, String> = ArcTable::from_fn(4, |i| format!("n{i}"));
+        assert_eq!(table[2], "n2");
+        assert_eq!(table.len(), 4);
+        let shared = table.clone();
+        assert_eq!(Arc::strong_count(table.raw()), 2);
+        let len = thread::spawn(move || shared.iter().map(String::len).sum::<usize>())
+            .join()
+            .unwrap();
+        assert_eq!(len, 8);
+        assert_eq!(format!("{table:?}"), r#"["n0", "n1", "n2", "n3"]"#);
+
+        let mut builder: ArcTableBuilder<ThreadIdx, u64> = ArcTableBuilder::new(3);
+        assert_eq!(builder.push(10).index(), 0);
+        builder.push(20);
+        builder.as_mut_slice()[ThreadIdx::new(0)] += 1;
+        assert_eq!(builder.as_slice().raw, [11, 20]);
+        let mut builder = builder.try_finish().unwrap_err();
+        builder.push(30);
+        assert!(builder.is_full());
+        assert_eq!(builder.finish().raw[..], [11, 20, 30]);
+
+        let empty: ArcTable<u32, u8> = ArcTableBuilder::new(0).finish();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn drops_partial_tables() {
+        let value = Arc::new(());
+        let mut builder: ArcTableBuilder<u32, Arc<()>> = ArcTableBuilder::new(3);
+        builder.push(Arc::clone(&value));
+        builder.push(Arc::clone(&value));
+        drop(builder);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // A panic in the middle of `from_fn` drops the elements made before it.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            ArcTable::<u32, Arc<()>>::from_fn(3, |i| {
+                assert!(i < 2, "no third element");
+                Arc::clone(&value)
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let full: ArcTable<u32, Arc<()>> = ArcTable::from_fn(2, |_| Arc::clone(&value));
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(full);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    #[should_panic = "table builder has 1 of 2 elements"]
+    fn finish_requires_full() {
+        let mut builder: ArcTableBuilder<u32, u8> = ArcTableBuilder::new(2);
+        builder.push(1);
+        builder.finish();
+    }
+
+    #[test]
+    fn portable_slots() {
+        let mut table: Arc<[MaybeUninit<u8>]> = Arc::new_uninit_slice(2);
+        portable::slots(&mut table)[1].write(7);
+        // SAFETY: element 1 was just written.
+        assert_eq!(unsafe { table[1].assume_init() }, 7);
+        let shared = Arc::clone(&table);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| portable::slots(&mut table).len()));
+        assert!(result.is_err());
+        drop(shared);
+    }
+}
@@ -0,0 +1,209 @@
+//! `Rc::new_in` and `Arc::new_in` usage
+//!
+//! Tracking issue [32838].
+//!
+//! With the `allocator_api` feature `Rc` and `Arc` take an allocator parameter like the other
+//! std containers: `Rc::new_in(value, alloc)` puts the reference counts and the value in memory
+//! from `alloc`, and gives it back to `alloc` once the last reference is dropped. An arena that
+//! frees all it handed out at once suits data shared within one query and dropped with it: the
+//! counts still decide when each value is dropped, but freeing its memory costs nothing.
+//!
+//! [`Bump`] is such an arena, which never frees anything before it is dropped itself. An
+//! `Rc<T, &Bump>` borrows the arena, so it can't outlive it, and [`with_arena`] makes sure none
+//! escape the query at all: its closure gets an [`Arena`] whose lifetime can't appear in the
+//! result. There is no stable equivalent, so the module is only built with the `nightly`
+//! feature.
+//!
+//! [32838]: https://github.com/rust-lang/rust/issues/32838
+
+use std::alloc::{AllocError, Allocator, Layout};
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::ptr::{Span, dangling};
+
+/// The size of each chunk, unless an allocation needs a larger one.
+const CHUNK_SIZE: usize = 4096;
+
+/// An arena allocator handing out memory from chunks that are freed together when it is dropped.
+pub struct Bump {
+    /// Leaked boxes, freed by `drop`.
+    chunks: RefCell<Vec<NonNull<[MaybeUninit<u8>]>>>,
+    /// The free part of the last chunk.
+    free: Cell<Span<MaybeUninit<u8>>>,
+    allocated: Cell<usize>,
+}
+
+impl Bump {
+    pub const fn new() -> Self {
+        Bump {
+            chunks: RefCell::new(Vec::new()),
+            free: Cell::new(Span::empty()),
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// The bytes handed out so far, alignment padding left out.
+    pub fn allocated(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// The number of chunks taken from the global allocator.
+    pub fn chunks(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    /// `layout` in the free part of the last chunk, or `None` if it doesn't fit.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let mut free = self.free.get();
+        free.take_front(free.start().align_offset(layout.align()))?;
+        let ptr = free.take_front(layout.size())?.cast();
+        self.free.set(free);
+        Some(ptr)
+    }
+}
+
+impl Default for Bump {
+    fn default() -> Self {
+        Bump::new()
+    }
+}
+
+// SAFETY: memory stays valid until the `Bump` is dropped, which can't happen while an `&Bump`
+// using it is alive, and no two allocations overlap.
+unsafe impl Allocator for Bump {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(NonNull::slice_from_raw_parts(dangling(layout), 0));
+        }
+        let ptr = match self.bump(layout) {
+            Some(ptr) => ptr,
+            None => {
+                let size = CHUNK_SIZE.max(layout.size() + layout.align());
+                let chunk = NonNull::from(Box::leak(Box::new_uninit_slice(size)));
+                self.chunks.borrow_mut().push(chunk);
+                // SAFETY: the chunk is freed only when the `Bump` is.
+                self.free.set(unsafe { Span::new(chunk) });
+                self.bump(layout).ok_or(AllocError)?
+            }
+        };
+        self.allocated.set(self.allocated.get() + layout.size());
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {}
+}
+
+impl Drop for Bump {
+    fn drop(&mut self) {
+        for &chunk in self.chunks.get_mut().iter() {
+            // SAFETY: leaked from a `Box`, and freed only here.
+            drop(unsafe { Box::from_raw(chunk.as_ptr()) });
+        }
+    }
+}
+
+/// A [`Bump`] lent to the closure of [`with_arena`], whose lifetime `'a` can't leave it.
+#[derive(Clone, Copy)]
+pub struct Arena<'a> {
+    bump: &'a Bump,
+    /// Invariant in `'a`, so that it can't be shortened or lengthened to match another.
+    _marker: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+impl<'a> Arena<'a> {
+    /// `value` in a new `Rc` in the arena.
+    pub fn rc<T>(self, value: T) -> Rc<T, &'a Bump> {
+        Rc::new_in(value, self.bump)
+    }
+
+    /// `value` in a new `Arc` in the arena. The arena isn't `Sync`, so neither is the `Arc`.
+    pub fn arc<T>(self, value: T) -> Arc<T, &'a Bump> {
+        Arc::new_in(value, self.bump)
+    }
+
+    pub fn bump(self) -> &'a Bump {
+        self.bump
+    }
+}
+
+/// Runs `f` with a new arena, which is freed when `f` returns.
+///
+/// Nothing allocated in the arena can be returned:
+///
+/// ```compile_fail
+/// use rust_unstable::arena::with_arena;
+///
+/// let escaped = with_arena(|arena| arena.rc(1));
+/// ```
+pub fn with_arena<R>(f: impl for<'a> FnOnce(Arena<'a>) -> R) -> R {
+    let bump = Bump::new();
+    f(Arena {
+        bump: &bump,
+        _marker: PhantomData,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    #[test]
+    fn shares_within_a_query() {
+        let dropped = Cell::new(0);
+        struct Counted<'a>(&'a Cell<usize>, u64);
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let total = with_arena(|arena| {
+            let shared = arena.rc(Counted(&dropped, 7));
+            let copies: Vec<_> = (0..3).map(|_| Rc::clone(&shared)).collect();
+            assert_eq!(Rc::strong_count(&shared), 4);
+            assert!(std::ptr::eq(*Rc::allocator(&shared), arena.bump()));
+            drop(shared);
+            assert_eq!(dropped.get(), 0);
+            let total: u64 = copies.iter().map(|c| c.1).sum();
+            drop(copies);
+            // Dropped with the last reference, even though its memory stays in the arena.
+            assert_eq!(dropped.get(), 1);
+            let table = arena.arc(IndexVec::<u32, u64>::from_elem_n(total, 4));
+            table[1]
+        });
+        assert_eq!(total, 21);
+    }
+
+    #[test]
+    fn bumps_and_grows() {
+        let bump = Bump::new();
+        let a = Rc::new_in(1u8, &bump);
+        let b = Rc::new_in(2u64, &bump);
+        assert_eq!(bump.chunks(), 1);
+        // Two counts and the value, each.
+        assert_eq!(bump.allocated(), 2 * 2 * 8 + 8 + 8);
+        assert_eq!(Rc::as_ptr(&b).addr() % 8, 0);
+        assert!(Rc::as_ptr(&a).addr() < Rc::as_ptr(&b).addr());
+
+        let big = Rc::new_in([0u8; CHUNK_SIZE * 2], &bump);
+        assert_eq!(bump.chunks(), 2);
+        let after = Rc::new_in(3u32, &bump);
+        assert_eq!(bump.chunks(), 3);
+        let mut v = Vec::new_in(&bump);
+        v.extend(0u32..1000);
+        assert_eq!(
+            (*a, *b, big.len(), *after, v[999]),
+            (1, 2, CHUNK_SIZE * 2, 3, 999)
+        );
+        let zst = Rc::new_in((), &bump);
+        assert_eq!(*zst, ());
+    }
+}
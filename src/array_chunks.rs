@@ -0,0 +1,231 @@
+//! `slice::as_chunks` usage
+//!
+//! Tracking issue [74985].
+//!
+//! `as_chunks::<N>()` splits a slice into `&[[T; N]]` plus the leftover tail, and `as_rchunks`
+//! does the same with the leftover at the front. Working on `[T; N]` blocks gives the optimizer a
+//! fixed trip count, so simple word-wise loops over them vectorize. The older
+//! `slice::array_chunks` iterator covered the same ground and was removed in favor of these.
+//!
+//! Here the blocks are bitset words: [`count_ones`], [`union_with`] and [`last_set_bit`] run a
+//! fixed-size kernel over each `[u64; N]` block and handle the remainder word by word. Without the
+//! `nightly` feature the split is done in-crate.
+//!
+//! [74985]: https://github.com/rust-lang/rust/issues/74985
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Splits `slice` into `N`-element arrays, starting at the beginning, and a remainder shorter than
+/// `N`.
+///
+/// # Panics
+///
+/// Will panic if `N` is zero.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn as_chunks<T, const N: usize>(slice: &[T]) -> (&[[T; N]], &[T]) {
+    slice.as_chunks()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn as_chunks<T, const N: usize>(slice: &[T]) -> (&[[T; N]], &[T]) {
+    portable::as_chunks(slice)
+}
+
+/// Splits `slice` into `N`-element arrays, starting at the end, and a remainder shorter than `N`.
+///
+/// # Panics
+///
+/// Will panic if `N` is zero.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn as_rchunks<T, const N: usize>(slice: &[T]) -> (&[T], &[[T; N]]) {
+    slice.as_rchunks()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn as_rchunks<T, const N: usize>(slice: &[T]) -> (&[T], &[[T; N]]) {
+    portable::as_rchunks(slice)
+}
+
+/// Mutable version of [`as_chunks`].
+///
+/// # Panics
+///
+/// Will panic if `N` is zero.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn as_chunks_mut<T, const N: usize>(slice: &mut [T]) -> (&mut [[T; N]], &mut [T]) {
+    slice.as_chunks_mut()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn as_chunks_mut<T, const N: usize>(slice: &mut [T]) -> (&mut [[T; N]], &mut [T]) {
+    portable::as_chunks_mut(slice)
+}
+
+/// Counts the set bits in `words`, `N` words at a time.
+pub fn count_ones<const N: usize>(words: &[u64]) -> u64 {
+    let (blocks, rest) = as_chunks::<u64, N>(words);
+    let blocks: u64 = blocks
+        .iter()
+        .map(|block| block.iter().map(|w| w.count_ones() as u64).sum::<u64>())
+        .sum();
+    blocks + rest.iter().map(|w| w.count_ones() as u64).sum::<u64>()
+}
+
+/// Sets `dst` to `dst | src`, `N` words at a time. Returns whether `dst` changed.
+///
+/// # Panics
+///
+/// Will panic if the slices differ in length.
+pub fn union_with<const N: usize>(dst: &mut [u64], src: &[u64]) -> bool {
+    assert_eq!(dst.len(), src.len());
+    let (dst_blocks, dst_rest) = as_chunks_mut::<u64, N>(dst);
+    let (src_blocks, src_rest) = as_chunks::<u64, N>(src);
+
+    let mut changed = 0;
+    for (d, s) in dst_blocks.iter_mut().zip(src_blocks) {
+        changed |= union_block(d, s);
+    }
+    for (d, s) in dst_rest.iter_mut().zip(src_rest) {
+        let new = *d | s;
+        changed |= *d ^ new;
+        *d = new;
+    }
+    changed != 0
+}
+
+/// The fixed-size kernel of [`union_with`]. `changed` is accumulated branch-free so the loop
+/// body stays vectorizable.
+#[inline]
+fn union_block<const N: usize>(dst: &mut [u64; N], src: &[u64; N]) -> u64 {
+    let mut changed = 0;
+    for i in 0..N {
+        let new = dst[i] | src[i];
+        changed |= dst[i] ^ new;
+        dst[i] = new;
+    }
+    changed
+}
+
+/// Returns the position of the highest set bit in `words`, scanning `N` words at a time from the
+/// end.
+pub fn last_set_bit<const N: usize>(words: &[u64]) -> Option<usize> {
+    let (head, blocks) = as_rchunks::<u64, N>(words);
+    let last_in = |offset: usize, words: &[u64]| {
+        let (i, w) = words.iter().enumerate().rfind(|(_, w)| **w != 0)?;
+        Some((offset + i) * WORD_BITS + (WORD_BITS - 1 - w.leading_zeros() as usize))
+    };
+    blocks
+        .iter()
+        .enumerate()
+        .rev()
+        // Skip all-zero blocks with one check per block.
+        .filter(|(_, block)| block.iter().fold(0, |acc, w| acc | w) != 0)
+        .find_map(|(i, block)| last_in(head.len() + i * N, block))
+        .or_else(|| last_in(0, head))
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::slice;
+
+    pub(super) fn as_chunks<T, const N: usize>(slice: &[T]) -> (&[[T; N]], &[T]) {
+        assert!(N != 0, "chunk size must be non-zero");
+        let len = slice.len() / N;
+        let (chunks, rest) = slice.split_at(len * N);
+        // SAFETY: `chunks` is exactly `len * N` elements long, and `[T; N]` has the layout of
+        // `N` consecutive `T`s.
+        let chunks = unsafe { slice::from_raw_parts(chunks.as_ptr().cast(), len) };
+        (chunks, rest)
+    }
+
+    pub(super) fn as_rchunks<T, const N: usize>(slice: &[T]) -> (&[T], &[[T; N]]) {
+        assert!(N != 0, "chunk size must be non-zero");
+        let len = slice.len() / N;
+        let (rest, chunks) = slice.split_at(slice.len() - len * N);
+        // SAFETY: Same as `as_chunks`.
+        let chunks = unsafe { slice::from_raw_parts(chunks.as_ptr().cast(), len) };
+        (rest, chunks)
+    }
+
+    pub(super) fn as_chunks_mut<T, const N: usize>(slice: &mut [T]) -> (&mut [[T; N]], &mut [T]) {
+        assert!(N != 0, "chunk size must be non-zero");
+        let len = slice.len() / N;
+        let (chunks, rest) = slice.split_at_mut(len * N);
+        // SAFETY: Same as `as_chunks`, and `chunks` is borrowed mutably and uniquely.
+        let chunks = unsafe { slice::from_raw_parts_mut(chunks.as_mut_ptr().cast(), len) };
+        (chunks, rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_and_remainders() {
+        let v = [1, 2, 3, 4, 5];
+        assert_eq!(as_chunks::<_, 2>(&v), (&[[1, 2], [3, 4]][..], &[5][..]));
+        assert_eq!(as_rchunks::<_, 2>(&v), (&[1][..], &[[2, 3], [4, 5]][..]));
+        assert_eq!(as_chunks::<_, 8>(&v), (&[][..], &v[..]));
+        assert_eq!(as_chunks::<i32, 1>(&[]), (&[][..], &[][..]));
+    }
+
+    #[test]
+    fn portable_agrees_with_std() {
+        let v: Vec<u64> = (0..11).collect();
+        for len in 0..=v.len() {
+            let v = &v[..len];
+            assert_eq!(portable::as_chunks::<_, 4>(v), v.as_chunks::<4>());
+            assert_eq!(portable::as_rchunks::<_, 4>(v), v.as_rchunks::<4>());
+            let (mut a, mut b) = (v.to_vec(), v.to_vec());
+            assert_eq!(
+                portable::as_chunks_mut::<_, 3>(&mut a),
+                b.as_chunks_mut::<3>()
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn portable_zero_chunks() {
+        portable::as_chunks::<u64, 0>(&[1]);
+    }
+
+    #[test]
+    fn count() {
+        let words = [u64::MAX, 1, 0, 0b1011, 1 << 63];
+        assert_eq!(count_ones::<4>(&words), 64 + 1 + 3 + 1);
+        assert_eq!(count_ones::<1>(&words), count_ones::<8>(&words));
+    }
+
+    #[test]
+    fn union() {
+        let mut dst = [0b0001, 0, 0, 0, 0b1000];
+        assert!(union_with::<4>(&mut dst, &[0b0010, 0, 0, 0, 0b1000]));
+        assert_eq!(dst, [0b0011, 0, 0, 0, 0b1000]);
+        assert!(!union_with::<4>(&mut dst, &[0b0001, 0, 0, 0, 0b1000]));
+        // Only the remainder word changes.
+        assert!(union_with::<4>(&mut dst, &[0, 0, 0, 0, 0b0100]));
+        assert_eq!(dst[4], 0b1100);
+    }
+
+    #[test]
+    fn last_bit() {
+        assert_eq!(last_set_bit::<4>(&[]), None);
+        assert_eq!(last_set_bit::<4>(&[0; 9]), None);
+        let mut words = [0u64; 9];
+        words[0] = 1;
+        assert_eq!(last_set_bit::<4>(&words), Some(0));
+        words[6] = 1 << 5;
+        assert_eq!(last_set_bit::<4>(&words), Some(6 * 64 + 5));
+        words[8] = 1 << 63;
+        assert_eq!(last_set_bit::<4>(&words), Some(9 * 64 - 1));
+        assert_eq!(last_set_bit::<2>(&words[..8]), Some(6 * 64 + 5));
+    }
+}
@@ -0,0 +1,165 @@
+//! `array::try_from_fn` and `<[T; N]>::try_map` usage
+//!
+//! Tracking issues [89379] and [79711].
+//!
+//! `array::try_from_fn(f)` builds a `[T; N]` from a fallible `f(i)`, stopping at the first
+//! failure, and `array.try_map(f)` maps an array with a fallible `f` the same way. Both are generic
+//! over `Try`, so they work with `Option` and `Result` alike. On failure the elements produced so
+//! far are dropped. The stable way is to collect into a `Vec` and convert, which allocates, or an
+//! array of `Option`s that is unwrapped afterwards.
+//!
+//! [`try_from_fn`] and [`try_map`] are the `Result` versions, and [`IndexArray::try_from_fn`] and
+//! [`IndexArray::try_map`] build typed-index arrays on top. `IndexArray::try_from_fn`, and without
+//! the `nightly` feature also `try_from_fn`, fill the array with an `ArrayBuilder`, which drops the
+//! initialized prefix if `f` fails or panics.
+//!
+//! [89379]: https://github.com/rust-lang/rust/issues/89379
+//! [79711]: https://github.com/rust-lang/rust/issues/79711
+
+use crate::index_vec::IndexArray;
+use crate::step::Idx;
+use crate::uninit_array::ArrayBuilder;
+
+/// Creates an array by calling `f` with each index in order, or returns the first error.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn try_from_fn<T, E, const N: usize>(
+    f: impl FnMut(usize) -> Result<T, E>,
+) -> Result<[T; N], E> {
+    std::array::try_from_fn(f)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn try_from_fn<T, E, const N: usize>(
+    f: impl FnMut(usize) -> Result<T, E>,
+) -> Result<[T; N], E> {
+    portable::try_from_fn(f)
+}
+
+/// Maps each element of `array` with `f` in order, or returns the first error.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn try_map<T, U, E, const N: usize>(
+    array: [T; N],
+    f: impl FnMut(T) -> Result<U, E>,
+) -> Result<[U; N], E> {
+    array.try_map(f)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn try_map<T, U, E, const N: usize>(
+    array: [T; N],
+    mut f: impl FnMut(T) -> Result<U, E>,
+) -> Result<[U; N], E> {
+    // The elements not reached yet are dropped with `iter`.
+    let mut iter = array.into_iter();
+    portable::try_from_fn(|_| f(iter.next().unwrap()))
+}
+
+impl<I: Idx, T, const N: usize> IndexArray<I, T, N> {
+    /// Creates an array by calling `f` with each index in order, or returns the first error.
+    #[inline]
+    pub fn try_from_fn<E>(mut f: impl FnMut(I) -> Result<T, E>) -> Result<Self, E> {
+        let mut builder = ArrayBuilder::new();
+        while !builder.is_full() {
+            builder.push(f(I::new(builder.len()))?);
+        }
+        Ok(IndexArray::from_raw(builder.build()))
+    }
+
+    /// Maps each element with `f` in order, or returns the first error.
+    #[inline]
+    pub fn try_map<U, E>(self, f: impl FnMut(T) -> Result<U, E>) -> Result<IndexArray<I, U, N>, E> {
+        try_map(self.raw, f).map(IndexArray::from_raw)
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use crate::uninit_array::ArrayBuilder;
+
+    pub(super) fn try_from_fn<T, E, const N: usize>(
+        mut f: impl FnMut(usize) -> Result<T, E>,
+    ) -> Result<[T; N], E> {
+        // Dropping the builder on an error or panic drops the elements made so far.
+        let mut builder = ArrayBuilder::new();
+        while !builder.is_full() {
+            builder.push(f(builder.len())?);
+        }
+        Ok(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    struct Tracked<'a>(&'a Cell<usize>);
+
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn typed_builders() {
+        let squares: IndexArray<u32, u64, 4> =
+            IndexArray::try_from_fn(|i| u64::from(i).checked_mul(u64::from(i)).ok_or(i)).unwrap();
+        assert_eq!(squares.raw, [0, 1, 4, 9]);
+        let parsed = IndexArray::<u32, _, 3>::from_raw(["1", "22", "x"]).try_map(str::parse::<u8>);
+        assert!(parsed.is_err());
+        let halves = squares.try_map(|n| if n % 2 == 0 { Ok(n / 2) } else { Err(n) });
+        assert_eq!(halves, Err(1));
+        assert_eq!(try_map([2, 4], |n| Ok::<_, ()>(n / 2)), Ok([1, 2]));
+    }
+
+    #[test]
+    fn drops_on_error() {
+        let drops = Cell::new(0);
+        let result: Result<[Tracked<'_>; 5], usize> =
+            try_from_fn(|i| if i < 3 { Ok(Tracked(&drops)) } else { Err(i) });
+        assert_eq!(result.err(), Some(3));
+        assert_eq!(drops.get(), 3);
+
+        // The mapped prefix, the failing element and the rest all get dropped.
+        drops.set(0);
+        let items = [(); 4].map(|()| Tracked(&drops));
+        let mut seen = 0;
+        let result = try_map(items, |t| {
+            seen += 1;
+            if seen == 2 { Err(()) } else { Ok(t) }
+        });
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn portable_drops_on_panic() {
+        let drops = Cell::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            portable::try_from_fn::<_, (), 4>(|i| {
+                assert!(i != 2, "builder panicked");
+                Ok(Tracked(&drops))
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for fail_at in 0..6 {
+            let f = |i: usize| if i == fail_at { Err(i) } else { Ok(i * 3) };
+            let std: Result<[usize; 5], _> = std::array::try_from_fn(f);
+            assert_eq!(std, portable::try_from_fn(f));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! `slice::array_windows` usage
+//!
+//! Tracking issue [75027].
+//!
+//! `array_windows::<N>()` is `windows(N)` with the window length in the type, yielding `&[T; N]`
+//! instead of `&[T]`. The window can be destructured or `each_ref`-ed without a fallible length
+//! check. Without the `nightly` feature the same item type is produced from `windows(N)` with a
+//! `try_into` that cannot fail.
+//!
+//! On an [`IndexSlice`] the windows come with their typed start index, and [`IndexSlice::edges`]
+//! gives the pairwise view used to walk consecutive elements, e.g. the edges of a path.
+//!
+//! [75027]: https://github.com/rust-lang/rust/issues/75027
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Returns an iterator over all contiguous windows of length `N`, together with the index of
+    /// the first element of each window.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `N` is zero.
+    #[inline]
+    pub fn array_windows_enumerated<const N: usize>(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (I, &[T; N])> + ExactSizeIterator + '_ {
+        array_windows::<T, N>(&self.raw)
+            .enumerate()
+            .map(|(n, w)| (I::new(n), w))
+    }
+
+    /// Returns an iterator over each pair of consecutive elements, with both indices.
+    #[inline]
+    pub fn edges(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = ([I; 2], [&T; 2])> + ExactSizeIterator + '_ {
+        self.array_windows_enumerated::<2>()
+            .map(|(i, w)| ([i, i.plus(1)], w.each_ref()))
+    }
+}
+
+#[cfg(feature = "nightly")]
+#[inline]
+fn array_windows<T, const N: usize>(
+    slice: &[T],
+) -> impl DoubleEndedIterator<Item = &[T; N]> + ExactSizeIterator {
+    slice.array_windows::<N>()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+fn array_windows<T, const N: usize>(
+    slice: &[T],
+) -> impl DoubleEndedIterator<Item = &[T; N]> + ExactSizeIterator {
+    portable::array_windows(slice)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn array_windows<T, const N: usize>(
+        slice: &[T],
+    ) -> impl DoubleEndedIterator<Item = &[T; N]> + ExactSizeIterator {
+        slice.windows(N).map(|w| {
+            w.try_into()
+                .expect("`windows(N)` yields slices of length `N`")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    #[test]
+    fn edges() {
+        let path: IndexVec<u32, char> = IndexVec::from(['a', 'b', 'c']);
+        let edges: Vec<_> = path.edges().collect();
+        assert_eq!(edges, [([0, 1], [&'a', &'b']), ([1, 2], [&'b', &'c'])]);
+        assert_eq!(path.edges().next_back(), Some(([1, 2], [&'b', &'c'])));
+    }
+
+    #[test]
+    fn edges_short() {
+        let single: IndexVec<u32, char> = IndexVec::from(['a']);
+        assert_eq!(single.edges().len(), 0);
+        assert_eq!(IndexSlice::<u32, char>::empty().edges().len(), 0);
+    }
+
+    #[test]
+    fn windows_enumerated() {
+        let v: IndexVec<usize, i32> = (1..=5).collect();
+        let sums: Vec<_> = v
+            .array_windows_enumerated::<3>()
+            .map(|(i, [a, b, c])| (i, a + b + c))
+            .collect();
+        assert_eq!(sums, [(0, 6), (1, 9), (2, 12)]);
+    }
+
+    #[test]
+    fn portable_agrees_with_std() {
+        let v = [1, 2, 3, 4];
+        let std: Vec<&[i32; 2]> = v.array_windows::<2>().collect();
+        let portable: Vec<&[i32; 2]> = portable::array_windows::<i32, 2>(&v).collect();
+        assert_eq!(std, portable);
+        assert_eq!(portable::array_windows::<i32, 5>(&v).len(), 0);
+    }
+}
@@ -0,0 +1,168 @@
+//! `core::ascii::Char` usage
+//!
+//! Tracking issue [110998].
+//!
+//! `ascii::Char` is a `u8` restricted to `0..=127`, so the compiler knows the top bit is free:
+//! `Option<ascii::Char>` is still one byte, and a `[ascii::Char]` is valid UTF-8 by construction.
+//! That makes conversions to `&str` infallible and free, where a `[u8]` would need validation.
+//!
+//! Compared to `char`, which is four bytes, a string of `ascii::Char` is a quarter of the size of
+//! the equivalent `Vec<char>` and has the same layout as the `str` it converts to.
+//!
+//! [110998]: https://github.com/rust-lang/rust/issues/110998
+
+use core::{ascii, error, fmt, iter, slice};
+
+/// A string slice made only of ASCII characters.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct AsciiStr([ascii::Char]);
+
+/// The error returned by [`AsciiStr::from_bytes`] for input containing non-ASCII bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AsciiError {
+    valid_up_to: usize,
+}
+
+impl AsciiError {
+    /// Returns the length of the longest valid ASCII prefix of the input.
+    pub fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid ASCII byte at index {}", self.valid_up_to)
+    }
+}
+
+impl error::Error for AsciiError {}
+
+impl AsciiStr {
+    /// Validates that `bytes` is ASCII and views it as an `AsciiStr`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<&Self, AsciiError> {
+        match bytes.as_ascii() {
+            Some(chars) => Ok(Self::from_chars(chars)),
+            None => Err(AsciiError {
+                valid_up_to: bytes.iter().position(|b| !b.is_ascii()).unwrap_or(0),
+            }),
+        }
+    }
+
+    /// Views a slice of ASCII characters as an `AsciiStr`.
+    pub fn from_chars(chars: &[ascii::Char]) -> &Self {
+        // SAFETY: `AsciiStr` is a `repr(transparent)` wrapper around `[ascii::Char]`.
+        unsafe { &*(chars as *const [ascii::Char] as *const Self) }
+    }
+
+    /// Returns the string as a `&str`. Unlike `str::from_utf8`, this cannot fail.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Returns the string as its underlying bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    /// Returns the string as a slice of ASCII characters.
+    pub fn as_chars(&self) -> &[ascii::Char] {
+        &self.0
+    }
+
+    /// Returns an iterator over the characters of the string.
+    pub fn chars(&self) -> iter::Copied<slice::Iter<'_, ascii::Char>> {
+        self.0.iter().copied()
+    }
+
+    /// Returns the length of the string, in bytes and in characters alike.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for &'a AsciiStr {
+    type Error = AsciiError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        AsciiStr::from_bytes(bytes)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a AsciiStr {
+    type Error = AsciiError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        AsciiStr::from_bytes(s.as_bytes())
+    }
+}
+
+impl AsRef<str> for AsciiStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for AsciiStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for AsciiStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes() {
+        let s = AsciiStr::from_bytes(b"index_42").unwrap();
+        assert_eq!(s.as_str(), "index_42");
+        assert_eq!(s.len(), 8);
+        assert_eq!(format!("{s}"), "index_42");
+        assert_eq!(format!("{s:?}"), "\"index_42\"");
+    }
+
+    #[test]
+    fn invalid() {
+        let err = AsciiStr::from_bytes("abc\u{e9}".as_bytes()).unwrap_err();
+        assert_eq!(err.valid_up_to(), 3);
+        assert_eq!(err.to_string(), "invalid ASCII byte at index 3");
+        assert!(<&AsciiStr>::try_from(&[0x80u8][..]).is_err());
+    }
+
+    #[test]
+    fn chars() {
+        let s = <&AsciiStr>::try_from("aB1").unwrap();
+        let upper: Vec<u8> = s.chars().map(|c| c.to_u8().to_ascii_uppercase()).collect();
+        assert_eq!(upper, b"AB1");
+        assert_eq!(s.chars().next(), ascii::Char::from_u8(b'a'));
+        assert_eq!(s.as_chars()[1].to_char(), 'B');
+    }
+
+    #[test]
+    fn layout() {
+        assert_eq!(size_of::<ascii::Char>(), 1);
+        assert_eq!(size_of::<Option<ascii::Char>>(), 1);
+        assert_eq!(size_of::<char>(), 4);
+
+        let s = AsciiStr::from_bytes(b"hello").unwrap();
+        let wide: Vec<char> = s.as_str().chars().collect();
+        assert_eq!(size_of_val(s), 5);
+        assert_eq!(size_of_val(s.as_str()), 5);
+        assert_eq!(size_of_val(wide.as_slice()), 20);
+        // Same bytes, no copy.
+        assert_eq!(s.as_str().as_ptr(), s.as_bytes().as_ptr());
+    }
+}
@@ -0,0 +1,154 @@
+//! `AtomicU32::from_mut_slice` usage
+//!
+//! Tracking issue [76314].
+//!
+//! `AtomicU32::from_mut_slice(&mut [u32])` views plain integers as atomics for as long as the
+//! unique borrow lasts, and `from_mut` does the same for one integer. Data built on one thread can
+//! so be shared for atomic updates in place, without copying it into atomics and back. The view is
+//! sound because the borrow is unique, and only where the atomic type has the same alignment as the
+//! integer: `AtomicU64` is 8-aligned even on targets like 32-bit x86 where `u64` is 4-aligned, and
+//! there `AtomicU64::from_mut` doesn't exist. The `AtomicU32` functions have the same condition,
+//! which the mainstream targets all meet.
+//!
+//! [`IndexVec::publish`] hands an `IndexVec<I, u32>` out as an [`AtomicIndexSlice`], which threads
+//! update through shared references. Once the borrow ends the vector is plain again, holding
+//! everything they wrote. Without the `nightly` feature the slice is cast to atomics after a
+//! compile-time check that the alignments match.
+//!
+//! [76314]: https://github.com/rust-lang/rust/issues/76314
+
+use std::marker::PhantomData;
+use std::ops::Index;
+use std::slice;
+use std::sync::atomic::AtomicU32;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// Views `slice` as atomics.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn as_atomic_slice(slice: &mut [u32]) -> &mut [AtomicU32] {
+    AtomicU32::from_mut_slice(slice)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn as_atomic_slice(slice: &mut [u32]) -> &mut [AtomicU32] {
+    portable::as_atomic_slice(slice)
+}
+
+/// An `IndexSlice<I, u32>` whose elements are updated atomically.
+#[derive(Debug, Clone, Copy)]
+pub struct AtomicIndexSlice<'a, I: Idx> {
+    raw: &'a [AtomicU32],
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<'a, I: Idx> AtomicIndexSlice<'a, I> {
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    pub fn get(&self, index: I) -> Option<&'a AtomicU32> {
+        self.raw.get(index.index())
+    }
+
+    pub fn iter(&self) -> slice::Iter<'a, AtomicU32> {
+        self.raw.iter()
+    }
+}
+
+impl<I: Idx> Index<I> for AtomicIndexSlice<'_, I> {
+    type Output = AtomicU32;
+
+    #[inline]
+    fn index(&self, index: I) -> &AtomicU32 {
+        &self.raw[index.index()]
+    }
+}
+
+impl<I: Idx> IndexVec<I, u32> {
+    /// Shares the elements for atomic updates until the returned slice is dropped.
+    pub fn publish(&mut self) -> AtomicIndexSlice<'_, I> {
+        AtomicIndexSlice {
+            raw: as_atomic_slice(&mut self.raw),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::{align_of, size_of};
+    use std::sync::atomic::AtomicU32;
+
+    const _: () = assert!(
+        align_of::<AtomicU32>() == align_of::<u32>() && size_of::<AtomicU32>() == size_of::<u32>()
+    );
+
+    pub(super) fn as_atomic_slice(slice: &mut [u32]) -> &mut [AtomicU32] {
+        // SAFETY: `AtomicU32` has the size and alignment of `u32`, checked above, and the unique
+        // borrow keeps any non-atomic access out while the atomics are in use.
+        unsafe { &mut *(slice as *mut [u32] as *mut [AtomicU32]) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::align_of;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn publish_counters() {
+        // Built single-threaded, then counted into by several threads.
+        let mut hits: IndexVec<u32, u32> = IndexVec::from_elem_n(0, 8);
+        hits[0] = 100;
+        let shared = hits.publish();
+        thread::scope(|s| {
+            for t in 0..4 {
+                s.spawn(move || {
+                    for i in 0..8 {
+                        shared[i].fetch_add(t, Ordering::Relaxed);
+                    }
+                    shared.get(t).unwrap().fetch_add(100, Ordering::Relaxed);
+                });
+            }
+        });
+        assert_eq!(shared.len(), 8);
+        assert!(shared.get(8).is_none());
+        hits[7] += 1;
+        assert_eq!(hits.raw, [206, 106, 106, 106, 6, 6, 6, 7]);
+    }
+
+    #[test]
+    fn alignments() {
+        assert_eq!(align_of::<AtomicU32>(), align_of::<u32>());
+        // Where these differ, `AtomicU64::from_mut` is not available.
+        assert!(align_of::<AtomicU64>() >= align_of::<u64>());
+        let mut raw = [1u32, 2];
+        let atomics = as_atomic_slice(&mut raw);
+        assert_eq!(atomics.as_ptr().addr() % align_of::<AtomicU32>(), 0);
+        atomics[1].store(5, Ordering::Relaxed);
+        assert_eq!(raw, [1, 5]);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let (mut a, mut b) = ([1u32, 2, 3], [1u32, 2, 3]);
+        let (std, portable) = (as_atomic_slice(&mut a), portable::as_atomic_slice(&mut b));
+        for (x, y) in std.iter().zip(portable.iter()) {
+            x.fetch_add(10, Ordering::Relaxed);
+            y.fetch_add(10, Ordering::Relaxed);
+        }
+        assert_eq!(a, b);
+    }
+}
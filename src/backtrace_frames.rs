@@ -0,0 +1,288 @@
+//! `Backtrace::frames` usage
+//!
+//! Tracking issue [79676].
+//!
+//! `Backtrace::frames()` returns the captured frames as a slice, so code can look at them one by
+//! one instead of only printing the whole trace. A `BacktraceFrame` has no accessors yet, only a
+//! `Debug` impl listing the symbols for the frame, innermost inlined one first, each with its
+//! function name and, if debug info has it, file and line.
+//!
+//! [`frame_summaries`] reads the symbols back out of that, and [`first_caller_frame`] finds the
+//! first one outside std and this crate: the code that called into it. [`IndexVec::push_traced`]
+//! returns a [`Traced`] index which, in debug builds with the opt-in `track_origin` feature,
+//! remembers that frame as the place the index was created, and prints it in its `Debug` output.
+//! A backtrace is only captured the first time an allocation site is reached, and its summary is
+//! kept for good, so each site costs one capture. The module needs the `nightly` feature.
+//!
+//! [79676]: https://github.com/rust-lang/rust/issues/79676
+
+use std::backtrace::Backtrace;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// One symbol of a backtrace frame.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrameSummary {
+    pub function: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl FrameSummary {
+    /// The crate the function is in, if its name starts with a path.
+    pub fn crate_name(&self) -> Option<&str> {
+        let path = self.function.trim_start_matches('<');
+        let (name, _) = path.split_once("::")?;
+        let is_ident = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        is_ident.then_some(name)
+    }
+}
+
+impl fmt::Display for FrameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.function)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " at {file}:{line}"),
+            (Some(file), None) => write!(f, " at {file}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Every symbol of `backtrace`, innermost first. Empty unless it was captured.
+pub fn frame_summaries(backtrace: &Backtrace) -> Vec<FrameSummary> {
+    backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| parse_frame(&format!("{frame:?}")))
+        .collect()
+}
+
+/// The first symbol of `backtrace` outside std and this crate.
+pub fn first_caller_frame(backtrace: &Backtrace) -> Option<FrameSummary> {
+    const SKIPPED: [&str; 4] = ["std", "core", "alloc", env!("CARGO_CRATE_NAME")];
+    frame_summaries(backtrace).into_iter().find(|frame| {
+        frame
+            .crate_name()
+            .is_some_and(|name| !SKIPPED.contains(&name))
+    })
+}
+
+/// Reads the symbols out of a frame's `Debug` output, which looks like
+/// `[{ fn: "name", file: "path", line: 12 }, ...]`.
+fn parse_frame(debug: &str) -> Vec<FrameSummary> {
+    let mut symbols = Vec::new();
+    let mut rest = debug;
+    while let Some(start) = rest.find("{ fn: \"") {
+        let Some((function, after)) = parse_str(&rest[start + "{ fn: \"".len()..]) else {
+            break;
+        };
+        let end = after.find('}').unwrap_or(after.len());
+        let fields = &after[..end];
+        let file = fields
+            .find("file: \"")
+            .and_then(|i| parse_str(&fields[i + "file: \"".len()..]))
+            .map(|(file, _)| file);
+        let line = fields.find("line: ").and_then(|i| {
+            let digits = &fields[i + "line: ".len()..];
+            let len = digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+            digits[..len].parse().ok()
+        });
+        symbols.push(FrameSummary {
+            function,
+            file,
+            line,
+        });
+        rest = &after[end..];
+    }
+    symbols
+}
+
+/// Reads a `Debug` quoted string's contents up to its closing quote, returning them unescaped
+/// and the text after the quote.
+fn parse_str(s: &str) -> Option<(String, &str)> {
+    let mut out = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[i + 1..])),
+            '\\' => out.push(chars.next()?.1),
+            _ => out.push(c),
+        }
+    }
+    None
+}
+
+/// An index that, with the `track_origin` feature in a debug build, knows where it was created.
+///
+/// It compares, hashes and orders as the index alone.
+#[derive(Clone, Copy)]
+pub struct Traced<I> {
+    index: I,
+    #[cfg(all(feature = "track_origin", debug_assertions))]
+    origin: Option<&'static FrameSummary>,
+}
+
+impl<I: Idx> Traced<I> {
+    /// Wraps `index`, recording the caller of the code calling this as its origin.
+    #[track_caller]
+    pub fn new(index: I) -> Self {
+        Traced {
+            index,
+            #[cfg(all(feature = "track_origin", debug_assertions))]
+            origin: origin::of_caller(),
+        }
+    }
+
+    pub fn get(self) -> I {
+        self.index
+    }
+
+    /// Where the index was created, if that is tracked and could be found.
+    pub fn origin(self) -> Option<&'static FrameSummary> {
+        #[cfg(all(feature = "track_origin", debug_assertions))]
+        return self.origin;
+        #[cfg(not(all(feature = "track_origin", debug_assertions)))]
+        None
+    }
+}
+
+impl<I: Idx> fmt::Debug for Traced<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.index)?;
+        if let Some(origin) = self.origin() {
+            write!(f, " (index created here: {origin})")?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: Idx> PartialEq for Traced<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<I: Idx> Eq for Traced<I> {}
+
+impl<I: Idx + Ord> PartialOrd for Traced<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I: Idx + Ord> Ord for Traced<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl<I: Idx> Hash for Traced<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Like `push`, but returns the index as a [`Traced`].
+    #[track_caller]
+    pub fn push_traced(&mut self, d: T) -> Traced<I> {
+        Traced::new(self.push(d))
+    }
+}
+
+#[cfg(all(feature = "track_origin", debug_assertions))]
+mod origin {
+    use std::backtrace::Backtrace;
+    use std::collections::HashMap;
+    use std::panic::Location;
+    use std::sync::{LazyLock, Mutex};
+
+    use super::{FrameSummary, first_caller_frame};
+
+    /// The origin found for each call site of `Traced::new`, kept for good.
+    static SITES: LazyLock<
+        Mutex<HashMap<&'static Location<'static>, Option<&'static FrameSummary>>>,
+    > = LazyLock::new(Mutex::default);
+
+    #[track_caller]
+    pub(super) fn of_caller() -> Option<&'static FrameSummary> {
+        let site = Location::caller();
+        let mut sites = SITES.lock().unwrap_or_else(|e| e.into_inner());
+        *sites.entry(site).or_insert_with(|| {
+            let frame = first_caller_frame(&Backtrace::force_capture())?;
+            Some(Box::leak(Box::new(frame)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frames() {
+        let debug =
+            r#"[{ fn: "<a::B as c::D>::f", file: "/src/a \"b\".rs", line: 12 }, { fn: "g" }]"#;
+        assert_eq!(
+            parse_frame(debug),
+            [
+                FrameSummary {
+                    function: "<a::B as c::D>::f".into(),
+                    file: Some("/src/a \"b\".rs".into()),
+                    line: Some(12),
+                },
+                FrameSummary {
+                    function: "g".into(),
+                    file: None,
+                    line: None,
+                },
+            ]
+        );
+        assert_eq!(parse_frame(debug)[0].crate_name(), Some("a"));
+        assert_eq!(parse_frame(debug)[1].crate_name(), None);
+        assert_eq!(
+            parse_frame(debug)[0].to_string(),
+            r#"`<a::B as c::D>::f` at /src/a "b".rs:12"#
+        );
+        assert!(frame_summaries(&Backtrace::disabled()).is_empty());
+    }
+
+    #[test]
+    fn finds_caller() {
+        let backtrace = Backtrace::force_capture();
+        let frames = frame_summaries(&backtrace);
+        assert!(
+            frames
+                .iter()
+                .any(|f| f.function.ends_with("tests::finds_caller"))
+        );
+        // The test harness calls the test.
+        let caller = first_caller_frame(&backtrace).unwrap();
+        assert_eq!(caller.crate_name(), Some("test"), "{caller}");
+    }
+
+    #[test]
+    fn traced_indices() {
+        let mut v: IndexVec<u32, &str> = IndexVec::new();
+        let a = v.push_traced("a");
+        let b = v.push_traced("b");
+        assert_eq!((a.get(), b.get()), (0, 1));
+        assert_ne!(a, b);
+        assert!(a < b);
+        if cfg!(all(feature = "track_origin", debug_assertions)) {
+            let origin = a.origin().unwrap();
+            assert_eq!(origin.crate_name(), Some("test"));
+            assert!(format!("{a:?}").starts_with("0 (index created here: `test::"));
+        } else {
+            assert!(a.origin().is_none());
+            assert_eq!(format!("{a:?}"), "0");
+        }
+    }
+}
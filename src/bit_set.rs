@@ -0,0 +1,503 @@
+//! `BitSet`, a fixed-size set of indices
+//!
+//! **This is copy of rustc_index**, trimmed down to what the examples in this crate need.
+//!
+//! A `BitSet<T>` stores one bit per index in `0..domain_size`, packed into `u64` words. The word
+//! loops go through the kernels in [`crate::array_chunks`].
+//!
+//! A `BitMatrix<R, C, W>` is a `BitSet<C>` per row `R`. Unlike rustc's, each row is a
+//! `[u64; W]` array, so the column count is capped at `64 * W` when it is created.
+//!
+//! Both print as sets with runs of three or more indices folded into ranges, as in
+//! `{1, 5, 9..12}`, and a matrix as a map from its non-empty rows to those. The entries are
+//! written straight from the words, through `DebugSet::entry_with` ([debug_closure_helpers]) with
+//! the `nightly` feature and wrapper structs without it, never collected into a `Vec`.
+//!
+//! See [`rustc_index::bit_set::DenseBitSet`] and [`rustc_index::bit_set::BitMatrix`].
+//!
+//! [`rustc_index::bit_set::DenseBitSet`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index/src/bit_set.rs#L107
+//! [`rustc_index::bit_set::BitMatrix`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index/src/bit_set.rs
+//! [debug_closure_helpers]: https://github.com/rust-lang/rust/issues/117729
+
+use std::marker::PhantomData;
+use std::{fmt, slice};
+
+use crate::array_chunks;
+use crate::step::Idx;
+
+type Word = u64;
+const WORD_BITS: usize = Word::BITS as usize;
+
+/// A fixed-size bitset type with a dense representation.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BitSet<T: Idx> {
+    domain_size: usize,
+    words: Vec<Word>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Idx> BitSet<T> {
+    /// Creates a new, empty bitset with a given `domain_size`.
+    #[inline]
+    pub fn new_empty(domain_size: usize) -> BitSet<T> {
+        let num_words = num_words(domain_size);
+        BitSet {
+            domain_size,
+            words: vec![0; num_words],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a bitset over `words`, which must hold exactly the words for `domain_size` with no
+    /// bits set past it.
+    #[inline]
+    pub(crate) fn from_words(domain_size: usize, words: Vec<Word>) -> BitSet<T> {
+        debug_assert_eq!(words.len(), num_words(domain_size));
+        BitSet {
+            domain_size,
+            words,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new, filled bitset with a given `domain_size`.
+    #[inline]
+    pub fn new_filled(domain_size: usize) -> BitSet<T> {
+        let mut result = BitSet::new_empty(domain_size);
+        result.insert_all();
+        result
+    }
+
+    /// Gets the domain size.
+    pub fn domain_size(&self) -> usize {
+        self.domain_size
+    }
+
+    /// Clear all elements.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Count the number of set bits in the set.
+    pub fn count(&self) -> usize {
+        array_chunks::count_ones::<4>(&self.words) as usize
+    }
+
+    /// Returns `true` if `self` contains `elem`.
+    #[inline]
+    pub fn contains(&self, elem: T) -> bool {
+        assert!(elem.index() < self.domain_size);
+        let (word_index, mask) = word_index_and_mask(elem);
+        (self.words[word_index] & mask) != 0
+    }
+
+    /// Is the set empty?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|a| *a == 0)
+    }
+
+    /// Insert `elem`. Returns whether the set has changed.
+    #[inline]
+    pub fn insert(&mut self, elem: T) -> bool {
+        assert!(elem.index() < self.domain_size);
+        let (word_index, mask) = word_index_and_mask(elem);
+        let word_ref = &mut self.words[word_index];
+        let word = *word_ref;
+        let new_word = word | mask;
+        *word_ref = new_word;
+        new_word != word
+    }
+
+    /// Sets all bits to true.
+    pub fn insert_all(&mut self) {
+        self.words.fill(!0);
+        self.clear_excess_bits();
+    }
+
+    /// Returns `true` if the set has changed.
+    #[inline]
+    pub fn remove(&mut self, elem: T) -> bool {
+        assert!(elem.index() < self.domain_size);
+        let (word_index, mask) = word_index_and_mask(elem);
+        let word_ref = &mut self.words[word_index];
+        let word = *word_ref;
+        let new_word = word & !mask;
+        *word_ref = new_word;
+        new_word != word
+    }
+
+    /// Sets `self = self | other` and returns `true` if `self` changed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the domain sizes differ.
+    pub fn union(&mut self, other: &BitSet<T>) -> bool {
+        assert_eq!(self.domain_size, other.domain_size);
+        array_chunks::union_with::<4>(&mut self.words, &other.words)
+    }
+
+    /// Returns the largest element of the set.
+    pub fn last_set(&self) -> Option<T> {
+        array_chunks::last_set_bit::<4>(&self.words).map(T::new)
+    }
+
+    /// Iterates over the indices of set bits in a sorted order.
+    #[inline]
+    pub fn iter(&self) -> BitIter<'_, T> {
+        BitIter::new(&self.words)
+    }
+
+    pub fn words(&self) -> &[Word] {
+        &self.words
+    }
+
+    /// Clear excess bits in the final word.
+    fn clear_excess_bits(&mut self) {
+        let num_bits_in_final_word = self.domain_size % WORD_BITS;
+        if num_bits_in_final_word > 0 {
+            let mask = (1 << num_bits_in_final_word) - 1;
+            let final_word_idx = self.words.len() - 1;
+            self.words[final_word_idx] &= mask;
+        }
+    }
+}
+
+impl<T: Idx> fmt::Debug for BitSet<T> {
+    fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_bits::<T>(w, &self.words)
+    }
+}
+
+impl<T: Idx> Extend<T> for BitSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+impl<'a, T: Idx> IntoIterator for &'a BitSet<T> {
+    type Item = T;
+    type IntoIter = BitIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct BitIter<'a, T: Idx> {
+    /// A copy of the current word, but with any already-visited bits cleared.
+    /// (This lets us use `trailing_zeros()` to find the next set bit.) When it
+    /// is reduced to 0, we move onto the next word.
+    word: Word,
+
+    /// The offset (measured in bits) of the current word.
+    offset: usize,
+
+    /// Underlying iterator over the words.
+    iter: slice::Iter<'a, Word>,
+
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Idx> BitIter<'a, T> {
+    #[inline]
+    fn new(words: &'a [Word]) -> BitIter<'a, T> {
+        // We initialize `word` and `offset` to degenerate values. On the first
+        // call to `next()` we will fall through to getting the first word from
+        // `iter`, which sets `word` to the first word (if there is one) and
+        // `offset` to 0. Doing it this way saves us from having to maintain
+        // additional state about whether we have started.
+        BitIter {
+            word: 0,
+            offset: usize::MAX - (WORD_BITS - 1),
+            iter: words.iter(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Idx> Iterator for BitIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if self.word != 0 {
+                // Get the position of the next set bit in the current word,
+                // then clear the bit.
+                let bit_pos = self.word.trailing_zeros() as usize;
+                self.word ^= 1 << bit_pos;
+                return Some(T::new(bit_pos + self.offset));
+            }
+
+            // Move onto the next word. `wrapping_add()` is needed to handle
+            // the degenerate initial value given to `offset` in `new()`.
+            self.word = *self.iter.next()?;
+            self.offset = self.offset.wrapping_add(WORD_BITS);
+        }
+    }
+}
+
+/// A fixed-size 2D bit matrix type with a dense representation, `W` words per row.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BitMatrix<R: Idx, C: Idx, const W: usize> {
+    num_columns: usize,
+    pub(crate) rows: Vec<[Word; W]>,
+    _marker: PhantomData<fn(&R, &C)>,
+}
+
+impl<R: Idx, C: Idx, const W: usize> BitMatrix<R, C, W> {
+    /// Creates a new `rows x columns` matrix, initially empty.
+    pub fn new(num_rows: usize, num_columns: usize) -> BitMatrix<R, C, W> {
+        assert!(
+            num_columns <= W * WORD_BITS,
+            "{num_columns} columns don't fit in {W} words"
+        );
+        BitMatrix {
+            num_columns,
+            rows: vec![[0; W]; num_rows],
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.num_columns
+    }
+
+    /// Sets the cell at `(row, column)` to true. Returns `true` if this changed the matrix.
+    pub fn insert(&mut self, row: R, column: C) -> bool {
+        assert!(column.index() < self.num_columns);
+        let (word_index, mask) = word_index_and_mask(column);
+        let word_ref = &mut self.rows[row.index()][word_index];
+        let word = *word_ref;
+        *word_ref = word | mask;
+        word | mask != word
+    }
+
+    /// Do the bits from `row` contain `column`?
+    pub fn contains(&self, row: R, column: C) -> bool {
+        assert!(column.index() < self.num_columns);
+        let (word_index, mask) = word_index_and_mask(column);
+        (self.rows[row.index()][word_index] & mask) != 0
+    }
+
+    /// Adds the bits from row `read` to the bits from row `write`, and returns `true` if anything
+    /// changed.
+    pub fn union_rows(&mut self, read: R, write: R) -> bool {
+        let src = self.rows[read.index()];
+        array_chunks::union_with::<4>(&mut self.rows[write.index()], &src)
+    }
+
+    /// Iterates through all the columns set to true in a given row of the matrix.
+    pub fn iter(&self, row: R) -> BitIter<'_, C> {
+        BitIter::new(&self.rows[row.index()])
+    }
+}
+
+impl<R: Idx, C: Idx, const W: usize> fmt::Debug for BitMatrix<R, C, W> {
+    fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = w.debug_map();
+        for (r, row) in self.rows.iter().enumerate() {
+            if row.iter().any(|&word| word != 0) {
+                map.key(&R::new(r));
+                #[cfg(feature = "nightly")]
+                map.value_with(|w| fmt_bits::<C>(w, row));
+                #[cfg(not(feature = "nightly"))]
+                map.value(&portable::Bits::<C>::new(row));
+            }
+        }
+        map.finish()
+    }
+}
+
+/// The shortest run of indices that is printed as a range.
+const MIN_RANGE: usize = 3;
+
+/// The runs of consecutive set bits in `words`, as `start..end` indices.
+fn runs(words: &[Word]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut bits = BitIter::<usize>::new(words).peekable();
+    std::iter::from_fn(move || {
+        let start = bits.next()?;
+        let mut end = start + 1;
+        while bits.next_if_eq(&end).is_some() {
+            end += 1;
+        }
+        Some((start, end))
+    })
+}
+
+/// Writes the set bits in `words` as a set of `T`s, with long runs as ranges.
+#[cfg(feature = "nightly")]
+fn fmt_bits<T: Idx>(w: &mut fmt::Formatter<'_>, words: &[Word]) -> fmt::Result {
+    let mut set = w.debug_set();
+    for (start, end) in runs(words) {
+        if end - start >= MIN_RANGE {
+            set.entry_with(|w| write!(w, "{:?}..{:?}", T::new(start), T::new(end)));
+        } else {
+            set.entries((start..end).map(T::new));
+        }
+    }
+    set.finish()
+}
+
+#[cfg(not(feature = "nightly"))]
+fn fmt_bits<T: Idx>(w: &mut fmt::Formatter<'_>, words: &[Word]) -> fmt::Result {
+    portable::fmt_bits::<T>(w, words)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use super::{MIN_RANGE, Word, runs};
+    use crate::step::Idx;
+
+    /// Bits that print like a `BitSet`.
+    pub(super) struct Bits<'a, T> {
+        words: &'a [Word],
+        _marker: PhantomData<fn(&T)>,
+    }
+
+    impl<'a, T> Bits<'a, T> {
+        pub(super) fn new(words: &'a [Word]) -> Self {
+            Bits {
+                words,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<T: Idx> fmt::Debug for Bits<'_, T> {
+        fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt_bits::<T>(w, self.words)
+        }
+    }
+
+    /// A run of indices printed as a range.
+    struct Run<T>(T, T);
+
+    impl<T: fmt::Debug> fmt::Debug for Run<T> {
+        fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(w, "{:?}..{:?}", self.0, self.1)
+        }
+    }
+
+    pub(super) fn fmt_bits<T: Idx>(w: &mut fmt::Formatter<'_>, words: &[Word]) -> fmt::Result {
+        let mut set = w.debug_set();
+        for (start, end) in runs(words) {
+            if end - start >= MIN_RANGE {
+                set.entry(&Run(T::new(start), T::new(end)));
+            } else {
+                set.entries((start..end).map(T::new));
+            }
+        }
+        set.finish()
+    }
+}
+
+#[inline]
+fn num_words<T: Idx>(domain_size: T) -> usize {
+    domain_size.index().div_ceil(WORD_BITS)
+}
+
+#[inline]
+fn word_index_and_mask<T: Idx>(elem: T) -> (usize, Word) {
+    let elem = elem.index();
+    let word_index = elem / WORD_BITS;
+    let mask = 1 << (elem % WORD_BITS);
+    (word_index, mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_remove() {
+        let mut set: BitSet<usize> = BitSet::new_empty(130);
+        assert!(set.is_empty());
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.insert(64));
+        assert!(set.insert(129));
+        assert!(set.contains(64));
+        assert_eq!(set.count(), 3);
+        assert_eq!(set.last_set(), Some(129));
+        assert!(set.remove(129));
+        assert!(!set.remove(129));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3, 64]);
+        assert_eq!(format!("{set:?}"), "{3, 64}");
+    }
+
+    #[test]
+    fn debug_folds_runs() {
+        let mut set: BitSet<u32> = BitSet::new_empty(200);
+        set.extend([1, 5, 6, 9, 10, 11, 62, 63, 64, 65, 199]);
+        assert_eq!(format!("{set:?}"), "{1, 5, 6, 9..12, 62..66, 199}");
+        assert_eq!(format!("{:?}", BitSet::<u32>::new_filled(70)), "{0..70}");
+        assert_eq!(format!("{:?}", BitSet::<u32>::new_empty(70)), "{}");
+        let mut small: BitSet<u32> = BitSet::new_empty(8);
+        small.extend([0, 1, 2, 7]);
+        assert_eq!(format!("{small:#?}"), "{\n    0..3,\n    7,\n}");
+    }
+
+    #[test]
+    fn filled_and_union() {
+        let full: BitSet<u32> = BitSet::new_filled(70);
+        assert_eq!(full.count(), 70);
+        assert_eq!(full.last_set(), Some(69));
+
+        let mut set = BitSet::new_empty(70);
+        set.insert(5);
+        assert!(set.union(&full));
+        assert!(!set.union(&full));
+        assert_eq!(set, full);
+        set.clear();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_domain() {
+        BitSet::<usize>::new_empty(10).insert(10);
+    }
+
+    #[test]
+    fn matrix_rows() {
+        let mut matrix: BitMatrix<u32, usize, 2> = BitMatrix::new(3, 100);
+        assert!(matrix.insert(0, 1));
+        assert!(!matrix.insert(0, 1));
+        assert!(matrix.insert(1, 99));
+        assert!(matrix.union_rows(0, 1));
+        assert!(!matrix.union_rows(0, 1));
+        assert!(matrix.contains(1, 1));
+        assert!(!matrix.contains(2, 1));
+        assert_eq!(matrix.iter(1).collect::<Vec<_>>(), [1, 99]);
+        assert_eq!(format!("{matrix:?}"), "{0: {1}, 1: {1, 99}}");
+        for c in 10..20 {
+            matrix.insert(2, c);
+        }
+        assert_eq!(format!("{matrix:?}"), "{0: {1}, 1: {1, 99}, 2: {10..20}}");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut set: BitSet<u32> = BitSet::new_empty(300);
+        set.extend([0, 2, 3, 4, 63, 64, 100, 101, 200, 201, 202, 203, 299]);
+        let full: BitSet<u32> = BitSet::new_filled(130);
+        for words in [&set.words[..], &full.words, &[]] {
+            let from_std = fmt::from_fn(|w| fmt_bits::<u32>(w, words));
+            let portable = portable::Bits::<u32>::new(words);
+            assert_eq!(format!("{from_std:?}"), format!("{portable:?}"));
+            assert_eq!(format!("{from_std:#?}"), format!("{portable:#?}"));
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! `BTreeMap` cursor usage
+//!
+//! Tracking issue [107540].
+//!
+//! `BTreeMap::lower_bound`/`upper_bound` (and their `_mut` forms) return a cursor sitting in the
+//! gap between two entries. From there both neighbors can be peeked, and a `CursorMut` can insert
+//! into or remove from that gap and walk on without a fresh lookup from the root for every step.
+//!
+//! [`OrderedIndexMap`] is a `BTreeMap` keyed by an [`Idx`], used for the neighbor queries,
+//! ordered insertion and range edits cursors make cheap. Without the `nightly` feature the same
+//! operations are done with `range` queries and one lookup per edit.
+//!
+//! [107540]: https://github.com/rust-lang/rust/issues/107540
+
+use alloc::collections::BTreeMap;
+use core::marker::PhantomData;
+#[cfg(feature = "nightly")]
+use core::ops::Bound;
+use core::ops::Range;
+use core::{error, fmt};
+
+use crate::step::Idx;
+
+/// An ordered map from `I` to `V`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OrderedIndexMap<I: Idx, V> {
+    map: BTreeMap<usize, V>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+/// The error returned by [`OrderedIndexMap::insert_after`] when the key would not go directly
+/// after the anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnorderedKey {
+    pub key: usize,
+}
+
+impl fmt::Display for UnorderedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {} is not ordered between its neighbors", self.key)
+    }
+}
+
+impl error::Error for UnorderedKey {}
+
+impl<I: Idx, V> OrderedIndexMap<I, V> {
+    pub const fn new() -> Self {
+        OrderedIndexMap {
+            map: BTreeMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn insert(&mut self, key: I, value: V) -> Option<V> {
+        self.map.insert(key.index(), value)
+    }
+
+    pub fn get(&self, key: I) -> Option<&V> {
+        self.map.get(&key.index())
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (I, &V)> + ExactSizeIterator {
+        self.map.iter().map(|(&k, v)| (I::new(k), v))
+    }
+
+    /// Returns the entries with the largest key below `key` and the smallest key above it.
+    #[cfg(feature = "nightly")]
+    pub fn neighbors(&self, key: I) -> [Option<(I, &V)>; 2] {
+        let key = key.index();
+        let prev = self.map.lower_bound(Bound::Included(&key)).peek_prev();
+        let next = self.map.upper_bound(Bound::Included(&key)).peek_next();
+        [prev, next].map(|e| e.map(|(&k, v)| (I::new(k), v)))
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn neighbors(&self, key: I) -> [Option<(I, &V)>; 2] {
+        portable::neighbors(&self.map, key.index()).map(|e| e.map(|(&k, v)| (I::new(k), v)))
+    }
+
+    /// Inserts `key` right after the entry for `anchor`, which requires that `key` is greater
+    /// than `anchor` and smaller than the next key in the map.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `anchor` is not in the map.
+    #[cfg(feature = "nightly")]
+    pub fn insert_after(&mut self, anchor: I, key: I, value: V) -> Result<(), UnorderedKey> {
+        let anchor = anchor.index();
+        let mut cursor = self.map.upper_bound_mut(Bound::Included(&anchor));
+        assert!(
+            cursor.peek_prev().is_some_and(|(&k, _)| k == anchor),
+            "anchor not in map"
+        );
+        let key = key.index();
+        cursor
+            .insert_after(key, value)
+            .map_err(|_| UnorderedKey { key })
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn insert_after(&mut self, anchor: I, key: I, value: V) -> Result<(), UnorderedKey> {
+        portable::insert_after(&mut self.map, anchor.index(), key.index(), value)
+    }
+
+    /// Keeps only the entries in `range` for which `f` returns true, in one walk over the range.
+    /// Returns the number of removed entries.
+    #[cfg(feature = "nightly")]
+    pub fn retain_range(&mut self, range: Range<I>, mut f: impl FnMut(I, &mut V) -> bool) -> usize {
+        let end = range.end.index();
+        let mut cursor = self
+            .map
+            .lower_bound_mut(Bound::Included(&range.start.index()));
+        let mut removed = 0;
+        while let Some((&k, v)) = cursor.peek_next() {
+            if k >= end {
+                break;
+            }
+            if f(I::new(k), v) {
+                cursor.next();
+            } else {
+                cursor.remove_next();
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn retain_range(&mut self, range: Range<I>, mut f: impl FnMut(I, &mut V) -> bool) -> usize {
+        portable::retain_range(
+            &mut self.map,
+            range.start.index()..range.end.index(),
+            |k, v| f(I::new(k), v),
+        )
+    }
+
+    /// Removes all entries in `range`. Returns how many there were.
+    pub fn remove_range(&mut self, range: Range<I>) -> usize {
+        self.retain_range(range, |_, _| false)
+    }
+}
+
+impl<I: Idx, V> Default for OrderedIndexMap<I, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Idx, V: fmt::Debug> fmt::Debug for OrderedIndexMap<I, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<I: Idx, V> FromIterator<(I, V)> for OrderedIndexMap<I, V> {
+    fn from_iter<T: IntoIterator<Item = (I, V)>>(iter: T) -> Self {
+        OrderedIndexMap {
+            map: iter.into_iter().map(|(k, v)| (k.index(), v)).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+    use core::ops::{Bound, Range};
+
+    use super::UnorderedKey;
+
+    pub(super) fn neighbors<V>(map: &BTreeMap<usize, V>, key: usize) -> [Option<(&usize, &V)>; 2] {
+        let prev = map.range(..key).next_back();
+        let next = map.range((Bound::Excluded(key), Bound::Unbounded)).next();
+        [prev, next]
+    }
+
+    pub(super) fn insert_after<V>(
+        map: &mut BTreeMap<usize, V>,
+        anchor: usize,
+        key: usize,
+        value: V,
+    ) -> Result<(), UnorderedKey> {
+        assert!(map.contains_key(&anchor), "anchor not in map");
+        let next = map
+            .range((Bound::Excluded(anchor), Bound::Unbounded))
+            .next();
+        if key <= anchor || next.is_some_and(|(&n, _)| key >= n) {
+            return Err(UnorderedKey { key });
+        }
+        map.insert(key, value);
+        Ok(())
+    }
+
+    pub(super) fn retain_range<V>(
+        map: &mut BTreeMap<usize, V>,
+        range: Range<usize>,
+        mut f: impl FnMut(usize, &mut V) -> bool,
+    ) -> usize {
+        if range.start >= range.end {
+            return 0;
+        }
+        let doomed: Vec<usize> = map
+            .range_mut(range)
+            .filter_map(|(&k, v)| (!f(k, v)).then_some(k))
+            .collect();
+        for k in &doomed {
+            map.remove(k);
+        }
+        doomed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(keys: &[u32]) -> OrderedIndexMap<u32, char> {
+        keys.iter()
+            .map(|&k| (k, char::from(b'a' + k as u8)))
+            .collect()
+    }
+
+    #[test]
+    fn neighbors() {
+        let m = map(&[2, 5, 9]);
+        assert_eq!(m.neighbors(5), [Some((2, &'c')), Some((9, &'j'))]);
+        assert_eq!(m.neighbors(6), [Some((5, &'f')), Some((9, &'j'))]);
+        assert_eq!(m.neighbors(2), [None, Some((5, &'f'))]);
+        assert_eq!(m.neighbors(10), [Some((9, &'j')), None]);
+        assert_eq!(map(&[]).neighbors(0), [None, None]);
+    }
+
+    #[test]
+    fn ordered_insert() {
+        let mut m = map(&[2, 5]);
+        assert_eq!(m.insert_after(2, 3, 'x'), Ok(()));
+        assert_eq!(m.insert_after(3, 4, 'y'), Ok(()));
+        assert_eq!(m.insert_after(5, 100, 'z'), Ok(()));
+        let err = m.insert_after(2, 4, 'w').unwrap_err();
+        assert_eq!(err, UnorderedKey { key: 4 });
+        assert_eq!(
+            err.to_string(),
+            "key 4 is not ordered between its neighbors"
+        );
+        assert_eq!(m.insert_after(5, 1, 'w'), Err(UnorderedKey { key: 1 }));
+        assert_eq!(
+            m.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            [2, 3, 4, 5, 100]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_after_missing_anchor() {
+        let _ = map(&[2]).insert_after(1, 3, 'x');
+    }
+
+    #[test]
+    fn range_surgery() {
+        let mut m = map(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(
+            m.retain_range(2..7, |k, v| {
+                *v = v.to_ascii_uppercase();
+                k % 2 == 0
+            }),
+            2
+        );
+        assert_eq!(
+            format!("{m:?}"),
+            "{0: 'a', 1: 'b', 2: 'C', 4: 'E', 6: 'G', 7: 'h'}"
+        );
+        assert_eq!(m.remove_range(1..5), 3);
+        assert_eq!(m.remove_range(5..5), 0);
+        assert_eq!(m.iter().map(|(k, _)| k).collect::<Vec<_>>(), [0, 6, 7]);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let keys = [1, 4, 5, 9];
+        let m = map(&keys);
+        for k in 0..11u32 {
+            let portable =
+                portable::neighbors(&m.map, k as usize).map(|e| e.map(|(&k, v)| (k as u32, v)));
+            assert_eq!(portable, m.neighbors(k));
+        }
+        for anchor in keys {
+            for k in 0..11u32 {
+                let (mut std, mut portable) = (m.clone(), m.map.clone());
+                assert_eq!(
+                    std.insert_after(anchor, k, '_'),
+                    portable::insert_after(&mut portable, anchor as usize, k as usize, '_')
+                );
+                assert_eq!(std.map, portable);
+            }
+        }
+        for start in 0..11u32 {
+            for end in start..11 {
+                let (mut std, mut portable) = (m.clone(), m.map.clone());
+                let pred = |k: usize| k % 4 != 1;
+                assert_eq!(
+                    std.retain_range(start..end, |k, _| pred(k as usize)),
+                    portable::retain_range(&mut portable, start as usize..end as usize, |k, _| {
+                        pred(k)
+                    })
+                );
+                assert_eq!(std.map, portable);
+            }
+        }
+    }
+}
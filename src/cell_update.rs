@@ -0,0 +1,99 @@
+//! `Cell::update` and `Cell::as_array_of_cells` usage
+//!
+//! Tracking issues [50186] and [88248].
+//!
+//! `cell.update(f)` replaces a `Copy` value with `f` of it, a `get` and `set` pair in one call.
+//! `Cell::<[T; N]>::as_array_of_cells` views a cell holding an array as an array of cells, so one
+//! element can be updated on its own while the whole array is still read in one `get`. Together they
+//! make a set of counters that lives in a single `Cell`, with plain loads and stores and none of the
+//! borrow flag a `RefCell` checks on every access.
+//!
+//! [`Counters`] is such a set, meant for a `thread_local!`. `counting_alloc::thread_stats` and
+//! `raw_entry::interner_stats` report the current thread's allocations and interner lookups
+//! from one each.
+//!
+//! [50186]: https://github.com/rust-lang/rust/issues/50186
+//! [88248]: https://github.com/rust-lang/rust/issues/88248
+
+use std::cell::Cell;
+use std::fmt;
+
+/// `N` counters in one `Cell`.
+pub struct Counters<const N: usize> {
+    values: Cell<[usize; N]>,
+}
+
+impl<const N: usize> Counters<N> {
+    pub const fn new() -> Self {
+        Counters {
+            values: Cell::new([0; N]),
+        }
+    }
+
+    /// The cell of counter `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= N`.
+    #[inline]
+    pub fn counter(&self, i: usize) -> &Cell<usize> {
+        &self.values.as_array_of_cells()[i]
+    }
+
+    /// Adds `n` to counter `i`, wrapping around on overflow.
+    #[inline]
+    pub fn add(&self, i: usize, n: usize) {
+        self.counter(i).update(|c| c.wrapping_add(n));
+    }
+
+    /// Raises counter `i` to `n` if it is lower.
+    #[inline]
+    pub fn max(&self, i: usize, n: usize) {
+        self.counter(i).update(|c| c.max(n));
+    }
+
+    pub fn get(&self) -> [usize; N] {
+        self.values.get()
+    }
+
+    /// Returns the counters and sets them to zero.
+    pub fn take(&self) -> [usize; N] {
+        self.values.replace([0; N])
+    }
+}
+
+impl<const N: usize> Default for Counters<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for Counters<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Counters").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_counters() {
+        const HITS: usize = 0;
+        const MISSES: usize = 1;
+        const LONGEST: usize = 2;
+        let counters = Counters::<3>::new();
+        for word in ["a", "bcd", "a", "ef"] {
+            let i = if word == "a" { HITS } else { MISSES };
+            counters.add(i, 1);
+            counters.max(LONGEST, word.len());
+        }
+        assert_eq!(counters.get(), [2, 2, 3]);
+        counters.counter(HITS).set(usize::MAX);
+        counters.add(HITS, 2);
+        assert_eq!(format!("{counters:?}"), "Counters([1, 2, 3])");
+        assert_eq!(counters.take(), [1, 2, 3]);
+        assert_eq!(counters.get(), [0; 3]);
+    }
+}
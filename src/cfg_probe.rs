@@ -0,0 +1,110 @@
+//! `#[cfg_accessible]` and `cfg(version)` usage
+//!
+//! Tracking issues [64797] and [64796].
+//!
+//! `#[cfg_accessible(path)]` keeps the item it is on only if `path` names an item that is there
+//! and visible from where the attribute is, and `#[cfg(version("1.81"))]` only if the compiler is
+//! at least that version. A feature of the crate then builds or not depending on the toolchain it
+//! meets instead of on a flag the user has to get right: a module wrapping an unstable std API is
+//! left out when the API isn't in that std instead of breaking the build. Both are unstable
+//! themselves, so they can only be used on nightly, and a crate that builds on stable too still
+//! needs a cargo feature around them.
+//!
+//! The modules that only build with the `nightly` feature keep that gate, which stays the way to
+//! leave them out, and stable toolchains never see the probes behind it. With the feature each
+//! also probes for the std item it wraps, and `metavar_concat`, which wraps syntax rather than an
+//! item, for the compiler version that added it; `decl_macro` is older than any toolchain the
+//! crate builds with and has no probe. A path probe only covers the item, not the `#![feature]`
+//! the crate root enables for it: `cfg_accessible` can't gate a crate attribute, and naming a
+//! feature the compiler doesn't know is an error (E0635) whatever is built. So it leaves a module
+//! out when the feature is still there but the item was renamed, moved or removed under it, and
+//! once the feature itself goes its line in the crate root has to go too. `metavar_concat`'s
+//! feature is behind the same `cfg(version)` as the module.
+//!
+//! The crate root declares these modules with `probed_modules!`, which makes each `mod` item
+//! and its entry in [`PROBES`] from the same line, so that what the tests check of the probes is
+//! what gates the modules: on a nightly with all the APIs, the probes build exactly what the
+//! feature does.
+//!
+//! [64797]: https://github.com/rust-lang/rust/issues/64797
+//! [64796]: https://github.com/rust-lang/rust/issues/64796
+
+/// A module gated on a cargo feature and on a probe of the toolchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub module: &'static str,
+    /// Whether the feature and target let the module build, before probing.
+    pub gated: bool,
+    /// The probe, a `cfg_accessible` of a path or a `cfg(version)`.
+    pub needs: &'static str,
+    /// Whether the module is built, with both the gate and the probe.
+    pub built: bool,
+}
+
+/// A [`Probe`] through the same attributes as the `mod` item `probed_modules!` makes.
+macro_rules! probe {
+    ($module:ident if $gate:meta, $probe:meta) => {
+        $crate::cfg_probe::Probe {
+            module: stringify!($module),
+            gated: cfg!($gate),
+            needs: stringify!($probe),
+            built: {
+                #[allow(dead_code)]
+                const BUILT: bool = false;
+                {
+                    // Shadows the `false` above if it is kept.
+                    #[cfg($gate)]
+                    #[$probe]
+                    const BUILT: bool = true;
+                    BUILT
+                }
+            },
+        }
+    };
+}
+
+/// Declares each `module` as `#[cfg(gate)] #[probe] pub mod module;`, and lists them in
+/// [`PROBES`], in the order given.
+macro_rules! probed_modules {
+    ($($module:ident if $gate:meta, $probe:meta;)+) => {
+        $(
+            #[cfg($gate)]
+            #[$probe]
+            pub mod $module;
+        )+
+
+        const PROBED_MODULES: &[$crate::cfg_probe::Probe] =
+            &[$($crate::cfg_probe::probe!($module if $gate, $probe)),+];
+    };
+}
+
+pub(crate) use {probe, probed_modules};
+
+/// Every module built on a probe, as the crate root declares them.
+pub const PROBES: &[Probe] = crate::PROBED_MODULES;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_agree_with_the_feature() {
+        for probe in PROBES {
+            assert_eq!(probe.built, probe.gated, "{probe:?}");
+        }
+        let unique_rc = PROBES.iter().find(|p| p.module == "unique_rc").unwrap();
+        assert_eq!(unique_rc.gated, cfg!(feature = "nightly"));
+        assert_eq!(unique_rc.needs, "cfg_accessible(std::rc::UniqueRc)");
+    }
+
+    #[test]
+    fn missing_apis_are_left_out() {
+        let removed = probe!(removed if feature = "nightly", cfg_accessible(std::rc::RemovedRc));
+        let future = probe!(future if feature = "nightly", cfg(version("1.999")));
+        for probe in [removed, future] {
+            assert_eq!(probe.gated, cfg!(feature = "nightly"));
+            assert!(!probe.built, "{probe:?}");
+        }
+        assert_eq!(future.needs, r#"cfg(version("1.999"))"#);
+    }
+}
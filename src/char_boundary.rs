@@ -0,0 +1,107 @@
+//! `str::floor_char_boundary`/`str::ceil_char_boundary` usage
+//!
+//! Tracking issue [93743].
+//!
+//! Slicing a `str` at a byte index that is not a char boundary panics, which makes naive truncation
+//! like `&s[..max_bytes]` a latent bug for any non-ASCII input. The rounding methods snap an index
+//! to the nearest boundary below or above it. Under the `nightly` feature they forward to std,
+//! otherwise they scan for the nearest byte that is not a UTF-8 continuation byte, at most 3 bytes
+//! away.
+//!
+//! [93743]: https://github.com/rust-lang/rust/issues/93743
+
+/// Returns the closest char boundary at or below `index`, or `s.len()` if `index` is past the end.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn floor_char_boundary(s: &str, index: usize) -> usize {
+    s.floor_char_boundary(index)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn floor_char_boundary(s: &str, index: usize) -> usize {
+    portable::floor_char_boundary(s, index)
+}
+
+/// Returns the closest char boundary at or above `index`, or `s.len()` if `index` is past the end.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn ceil_char_boundary(s: &str, index: usize) -> usize {
+    s.ceil_char_boundary(index)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn ceil_char_boundary(s: &str, index: usize) -> usize {
+    portable::ceil_char_boundary(s, index)
+}
+
+/// Returns the longest prefix of `s` that is at most `max_bytes` long, without splitting a char.
+#[inline]
+pub fn truncate_to_boundary(s: &str, max_bytes: usize) -> &str {
+    &s[..floor_char_boundary(s, max_bytes)]
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn floor_char_boundary(s: &str, index: usize) -> usize {
+        if index >= s.len() {
+            return s.len();
+        }
+        // A char is at most 4 bytes, so a boundary is at most 3 bytes below.
+        (index.saturating_sub(3)..=index)
+            .rev()
+            .find(|&i| s.is_char_boundary(i))
+            .unwrap()
+    }
+
+    pub(super) fn ceil_char_boundary(s: &str, index: usize) -> usize {
+        if index > s.len() {
+            return s.len();
+        }
+        (index..=s.len()).find(|&i| s.is_char_boundary(i)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 'a' is 1 byte, 'é' 2, '€' 3 and '🦀' 4.
+    const MIXED: &str = "a\u{e9}\u{20ac}\u{1F980}";
+
+    #[test]
+    fn floor_and_ceil() {
+        assert_eq!(floor_char_boundary(MIXED, 2), 1);
+        assert_eq!(ceil_char_boundary(MIXED, 2), 3);
+        assert_eq!(floor_char_boundary(MIXED, 3), 3);
+        assert_eq!(ceil_char_boundary(MIXED, 3), 3);
+        assert_eq!(floor_char_boundary(MIXED, 9), 6);
+        assert_eq!(ceil_char_boundary(MIXED, 7), 10);
+        assert_eq!(floor_char_boundary(MIXED, 100), MIXED.len());
+        assert_eq!(ceil_char_boundary(MIXED, 100), MIXED.len());
+    }
+
+    #[test]
+    fn portable_agrees_with_std() {
+        for s in ["", "ascii", MIXED, "\u{1F980}\u{1F980}"] {
+            for i in 0..=s.len() + 2 {
+                assert_eq!(
+                    portable::floor_char_boundary(s, i),
+                    s.floor_char_boundary(i)
+                );
+                assert_eq!(portable::ceil_char_boundary(s, i), s.ceil_char_boundary(i));
+            }
+        }
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(truncate_to_boundary(MIXED, 0), "");
+        assert_eq!(truncate_to_boundary(MIXED, 2), "a");
+        assert_eq!(truncate_to_boundary(MIXED, 3), "a\u{e9}");
+        assert_eq!(truncate_to_boundary(MIXED, 9), "a\u{e9}\u{20ac}");
+        assert_eq!(truncate_to_boundary(MIXED, 10), MIXED);
+        assert_eq!(truncate_to_boundary("short", 64), "short");
+    }
+}
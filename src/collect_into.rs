@@ -0,0 +1,107 @@
+//! `Iterator::collect_into` usage
+//!
+//! Tracking issue [94780].
+//!
+//! `iter.collect_into(&mut c)` extends an existing collection `c` instead of building a new one,
+//! and hands back `&mut c` for chaining. Dataflow-style loops recompute a set or vector on every
+//! round; collecting into one cleared container keeps its allocation across rounds, where
+//! `collect()` would allocate a fresh one each time.
+//!
+//! [`BitSet::collect_into`] and [`IndexVec::collect_into`] clear the container and refill it.
+//! Without the `nightly` feature they fall back to `clear()` followed by `extend`, which is what
+//! std does as well.
+//!
+//! [94780]: https://github.com/rust-lang/rust/issues/94780
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+impl<T: Idx> BitSet<T> {
+    /// Clears `self` and inserts the items of `iter`, keeping the existing words.
+    #[cfg(feature = "nightly")]
+    pub fn collect_into(&mut self, iter: impl IntoIterator<Item = T>) -> &mut Self {
+        self.clear();
+        iter.into_iter().collect_into(self)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn collect_into(&mut self, iter: impl IntoIterator<Item = T>) -> &mut Self {
+        self.clear();
+        self.extend(iter);
+        self
+    }
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Clears `self` and pushes the items of `iter`, keeping the existing allocation.
+    #[cfg(feature = "nightly")]
+    pub fn collect_into(&mut self, iter: impl IntoIterator<Item = T>) -> &mut Self {
+        self.clear();
+        iter.into_iter().collect_into(self)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn collect_into(&mut self, iter: impl IntoIterator<Item = T>) -> &mut Self {
+        self.clear();
+        self.extend(iter);
+        self
+    }
+}
+
+/// Grows `start` by the successors of its members until nothing changes, rebuilding the next
+/// round's set from the current one. Returns the closure and the number of rounds.
+///
+/// Both sets are allocated once, then swapped and refilled.
+pub fn reachable<T: Idx, It>(start: BitSet<T>, mut succ: impl FnMut(T) -> It) -> (BitSet<T>, usize)
+where
+    It: IntoIterator<Item = T>,
+{
+    let mut current = start;
+    let mut next = BitSet::new_empty(current.domain_size());
+    let mut rounds = 0;
+    loop {
+        rounds += 1;
+        next.collect_into(current.iter().chain(current.iter().flat_map(&mut succ)));
+        if next == current {
+            return (current, rounds);
+        }
+        std::mem::swap(&mut current, &mut next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refill_index_vec() {
+        let mut v: IndexVec<u32, u32> = IndexVec::with_capacity(8);
+        let ptr = v.raw.as_ptr();
+        assert_eq!(v.collect_into(0..5).raw, [0, 1, 2, 3, 4]);
+        assert_eq!(v.collect_into([7, 8]).raw, [7, 8]);
+        // Fits in the original allocation, so no reallocation happened.
+        assert_eq!(v.raw.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn refill_bit_set() {
+        let mut set: BitSet<usize> = BitSet::new_filled(100);
+        assert_eq!(
+            set.collect_into([3, 64]).iter().collect::<Vec<_>>(),
+            [3, 64]
+        );
+        assert_eq!(set.collect_into([]).count(), 0);
+    }
+
+    #[test]
+    fn reachability() {
+        // 0 -> 1 -> 2 -> 3, and 5 -> 6.
+        let succ = |i: usize| (i != 3 && i != 4 && i < 6).then_some(i + 1);
+        let mut start = BitSet::new_empty(8);
+        start.insert(0);
+        let (reached, rounds) = reachable(start, succ);
+        assert_eq!(reached.iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(rounds, 4);
+    }
+}
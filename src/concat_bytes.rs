@@ -0,0 +1,188 @@
+//! `concat_bytes!` usage
+//!
+//! Tracking issue [87555].
+//!
+//! `concat_bytes!(a, b, ...)` joins byte literals, byte string literals and arrays of them into one
+//! `&'static [u8; N]` at compile time, the way `concat!` joins string literals into a `&str`. It
+//! takes only literals, or macro calls that expand to them, so the parts of a header are macros
+//! rather than `const`s, but the length of the result is worked out for the caller.
+//!
+//! The blobs `read_buf` writes have no marker of what they are. [`write_index_vec_tagged`] and
+//! [`write_bit_set_tagged`] put a header in front of theirs: the crate's magic bytes, a byte for
+//! the kind of blob, and the format version. [`read_index_vec_tagged`] and
+//! [`read_bit_set_tagged`] check it before reading the rest, so that a blob of the wrong kind or
+//! version fails to load instead of loading as garbage. Without the `nightly` feature the headers
+//! are put together by a `const fn` that copies the parts into an array of a length given by hand,
+//! and fails the build if the length is wrong.
+//!
+//! [87555]: https://github.com/rust-lang/rust/issues/87555
+
+use std::io::{self, Read, Write};
+
+use crate::bit_set::BitSet;
+use crate::first_chunk::has_magic;
+use crate::index_vec::{IndexSlice, IndexVec};
+use crate::read_buf::{read_bit_set, read_index_vec, write_bit_set, write_index_vec};
+use crate::step::Idx;
+
+macro_rules! magic {
+    () => {
+        b"RUIX"
+    };
+}
+
+/// The format version, little-endian.
+macro_rules! version {
+    () => {
+        [1, 0]
+    };
+}
+
+#[cfg(feature = "nightly")]
+macro_rules! header {
+    ($kind:literal) => {
+        *concat_bytes!(magic!(), $kind, version!())
+    };
+}
+
+#[cfg(not(feature = "nightly"))]
+macro_rules! header {
+    ($kind:literal) => {
+        portable::concat(&[magic!(), &[$kind], &version!()])
+    };
+}
+
+/// The length of a header: four magic bytes, the kind and a `u16` version.
+pub const HEADER_LEN: usize = 7;
+
+/// The header of an `IndexVec` blob.
+pub const INDEX_VEC_HEADER: [u8; HEADER_LEN] = header!(b'V');
+
+/// The header of a `BitSet` blob.
+pub const BIT_SET_HEADER: [u8; HEADER_LEN] = header!(b'B');
+
+/// Reads a header, and fails unless it is `expected`.
+fn read_header(reader: &mut impl Read, expected: &[u8; HEADER_LEN]) -> io::Result<()> {
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    if !has_magic(&header, magic!()) {
+        return invalid("not a blob of this crate".to_string());
+    }
+    if header[4] != expected[4] {
+        return invalid(format!(
+            "expected a {:?} blob, found {:?}",
+            expected[4] as char, header[4] as char
+        ));
+    }
+    if header[5..] != expected[5..] {
+        let version = u16::from_le_bytes([header[5], header[6]]);
+        return invalid(format!("unsupported format version {version}"));
+    }
+    Ok(())
+}
+
+/// Writes `v` as a blob with a header, for [`read_index_vec_tagged`].
+pub fn write_index_vec_tagged<I: Idx>(
+    writer: &mut impl Write,
+    v: &IndexSlice<I, u32>,
+) -> io::Result<()> {
+    writer.write_all(&INDEX_VEC_HEADER)?;
+    write_index_vec(writer, v)
+}
+
+/// Reads a blob written by [`write_index_vec_tagged`].
+pub fn read_index_vec_tagged<I: Idx>(reader: &mut impl Read) -> io::Result<IndexVec<I, u32>> {
+    read_header(reader, &INDEX_VEC_HEADER)?;
+    read_index_vec(reader)
+}
+
+/// Writes `set` as a blob with a header, for [`read_bit_set_tagged`].
+pub fn write_bit_set_tagged<T: Idx>(writer: &mut impl Write, set: &BitSet<T>) -> io::Result<()> {
+    writer.write_all(&BIT_SET_HEADER)?;
+    write_bit_set(writer, set)
+}
+
+/// Reads a blob written by [`write_bit_set_tagged`].
+pub fn read_bit_set_tagged<T: Idx>(reader: &mut impl Read) -> io::Result<BitSet<T>> {
+    read_header(reader, &BIT_SET_HEADER)?;
+    read_bit_set(reader)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    /// `parts` one after the other.
+    ///
+    /// # Panics
+    ///
+    /// Panics, which in a `const` fails the build, unless the parts add up to `N` bytes.
+    pub(super) const fn concat<const N: usize>(parts: &[&[u8]]) -> [u8; N] {
+        let mut out = [0; N];
+        let (mut part, mut len) = (0, 0);
+        while part < parts.len() {
+            let bytes = parts[part];
+            assert!(len + bytes.len() <= N, "parts longer than the array");
+            let mut i = 0;
+            while i < bytes.len() {
+                out[len + i] = bytes[i];
+                i += 1;
+            }
+            len += bytes.len();
+            part += 1;
+        }
+        assert!(len == N, "parts shorter than the array");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers() {
+        assert_eq!(&INDEX_VEC_HEADER, b"RUIXV\x01\x00");
+        assert_eq!(&BIT_SET_HEADER, b"RUIXB\x01\x00");
+        assert_eq!(portable::concat::<3>(&[b"a", &[], b"bc"]), *b"abc");
+    }
+
+    #[test]
+    fn tagged_round_trips() {
+        let v: IndexVec<u32, u32> = IndexVec::from([4, 5, 6]);
+        let mut blob = Vec::new();
+        write_index_vec_tagged(&mut blob, &v).unwrap();
+        assert_eq!(blob[..HEADER_LEN], INDEX_VEC_HEADER);
+        assert_eq!(read_index_vec_tagged::<u32>(&mut &blob[..]).unwrap(), v);
+
+        let mut set: BitSet<usize> = BitSet::new_empty(70);
+        set.insert(69);
+        let mut blob = Vec::new();
+        write_bit_set_tagged(&mut blob, &set).unwrap();
+        assert_eq!(read_bit_set_tagged::<usize>(&mut &blob[..]).unwrap(), set);
+    }
+
+    #[test]
+    fn rejects_other_blobs() {
+        let v: IndexVec<u32, u32> = IndexVec::from([1]);
+        let mut blob = Vec::new();
+        write_index_vec_tagged(&mut blob, &v).unwrap();
+        let err = read_bit_set_tagged::<usize>(&mut &blob[..]).unwrap_err();
+        assert_eq!(err.to_string(), "expected a 'B' blob, found 'V'");
+
+        blob[5] = 2;
+        let err = read_index_vec_tagged::<u32>(&mut &blob[..]).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported format version 2");
+
+        let mut untagged = Vec::new();
+        write_index_vec(&mut untagged, &v).unwrap();
+        let err = read_index_vec_tagged::<u32>(&mut &untagged[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let portable: [u8; HEADER_LEN] = portable::concat(&[magic!(), b"V", &version!()]);
+        assert_eq!(portable, INDEX_VEC_HEADER);
+    }
+}
@@ -0,0 +1,119 @@
+//! `core::intrinsics::const_eval_select` usage
+//!
+//! Tracking issue [124625].
+//!
+//! `const_eval_select(args, in_const, at_runtime)` calls the `const fn` `in_const` when evaluated
+//! at compile time and the ordinary `fn` `at_runtime` otherwise. Both must behave the same, but
+//! the runtime one may do what const evaluation can't, such as formatting a panic message, or use
+//! a faster method. It is how std gives, e.g., `<[T]>::copy_from_slice` a detailed message at
+//! runtime.
+//!
+//! [`check_index`] validates an index against its type's maximum, as the `from_usize` and
+//! `from_u32` constructors of `CustomIndex` do. In const context it is a plain `assert!`. At
+//! runtime the out-of-range branch is marked `unlikely` and moved into a cold function that reports
+//! the value, so the inlined check is one comparison. Without the `nightly` feature the `assert!`
+//! is used for both, with the shorter message.
+//!
+//! [124625]: https://github.com/rust-lang/rust/issues/124625
+
+/// Panics if `value` exceeds `max`.
+#[cfg(feature = "nightly")]
+#[inline]
+#[track_caller]
+pub const fn check_index(value: usize, max: usize) {
+    core::intrinsics::const_eval_select((value, max), check_in_const, check_at_runtime)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+#[track_caller]
+pub const fn check_index(value: usize, max: usize) {
+    check_in_const(value, max)
+}
+
+#[track_caller]
+const fn check_in_const(value: usize, max: usize) {
+    assert!(value <= max, "index out of range for its type");
+}
+
+#[cfg(feature = "nightly")]
+#[inline]
+#[track_caller]
+fn check_at_runtime(value: usize, max: usize) {
+    if core::intrinsics::unlikely(value > max) {
+        index_out_of_range(value, max)
+    }
+}
+
+#[cfg(feature = "nightly")]
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn index_out_of_range(value: usize, max: usize) -> ! {
+    panic!("index out of range for its type: {value} > {max}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: usize = 0xFFFF_FF00;
+
+    /// Evaluated by the compiler, so it goes through `check_in_const`.
+    const CHECKED: [usize; 3] = {
+        check_index(0, MAX);
+        check_index(MAX, MAX);
+        check_index(7, 7);
+        [0, MAX, 7]
+    };
+
+    #[test]
+    fn in_range() {
+        assert_eq!(CHECKED, [0, MAX, 7]);
+        for value in [0, 1, MAX - 1, MAX] {
+            check_index(std::hint::black_box(value), MAX);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of range for its type")]
+    fn out_of_range() {
+        check_index(std::hint::black_box(MAX + 1), MAX);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    #[should_panic(expected = "index out of range for its type: 4294967041 > 4294967040")]
+    fn runtime_message_has_value() {
+        check_index(std::hint::black_box(MAX + 1), MAX);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    const LEN: usize = 100_000;
+
+    #[bench]
+    fn check_index_loop(b: &mut Bencher) {
+        b.iter(|| {
+            for i in 0..black_box(LEN) {
+                check_index(black_box(i), LEN);
+            }
+        });
+    }
+
+    /// The const-friendly `assert!` called at runtime.
+    #[bench]
+    fn check_in_const_loop(b: &mut Bencher) {
+        b.iter(|| {
+            for i in 0..black_box(LEN) {
+                check_in_const(black_box(i), LEN);
+            }
+        });
+    }
+}
@@ -0,0 +1,397 @@
+//! `GlobalAlloc` and `Allocator` usage
+//!
+//! Tracking issue [32838].
+//!
+//! A `GlobalAlloc` set with `#[global_allocator]` sees every heap allocation of the program, so a
+//! wrapper around `System` can keep counts before forwarding the call. The unstable `Allocator`
+//! trait is the per-collection counterpart: `Vec::new_in(&alloc)` sends only that vector's memory
+//! to `alloc`, and its `grow` and `shrink` replace `realloc`.
+//!
+//! [`CountingAlloc`] implements both. It keeps the live bytes, their peak and the number of
+//! allocations, read with [`CountingAlloc::memory_stats`]. For a finer split, a [`Container`]
+//! static names a part of the program: while the guard from [`Container::enter`] is alive, the
+//! current thread's allocations are also counted there, whichever container type they come from.
+//! [`thread_stats`] counts every allocation of the current thread. Only the `Allocator` impl needs
+//! the `nightly` feature.
+//!
+//! [32838]: https://github.com/rust-lang/rust/issues/32838
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::cell_update::Counters;
+
+#[cfg(feature = "nightly")]
+use std::alloc::{AllocError, Allocator};
+#[cfg(feature = "nightly")]
+use std::ptr::NonNull;
+
+/// Wraps an allocator, counting what goes through it.
+pub struct CountingAlloc<A> {
+    inner: A,
+    live: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicUsize,
+}
+
+/// A snapshot of a [`CountingAlloc`]'s counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Bytes allocated and not yet freed.
+    pub live: usize,
+    /// The highest `live` since creation or the last [`CountingAlloc::reset_peak`].
+    pub peak: usize,
+    /// Allocations and reallocations.
+    pub allocations: usize,
+}
+
+/// A named part of the program that allocations are attributed to. Meant for a `static`.
+pub struct Container {
+    name: &'static str,
+    allocations: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+/// What was allocated inside a [`Container`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContainerStats {
+    /// Allocations and reallocations.
+    pub allocations: usize,
+    /// Bytes requested, counting only the growth of a reallocation.
+    pub bytes: usize,
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<&'static Container>> = const { Cell::new(None) };
+    /// The current thread's allocations and bytes, as in [`ContainerStats`].
+    static THREAD: Counters<2> = const { Counters::new() };
+}
+
+/// The counter in `THREAD` of each field of [`ContainerStats`].
+const ALLOCATIONS: usize = 0;
+const BYTES: usize = 1;
+
+/// What the current thread allocated through any [`CountingAlloc`], counted like a [`Container`].
+pub fn thread_stats() -> ContainerStats {
+    let counters = THREAD.with(Counters::get);
+    ContainerStats {
+        allocations: counters[ALLOCATIONS],
+        bytes: counters[BYTES],
+    }
+}
+
+/// Attributes the current thread's allocations to a [`Container`] until dropped.
+#[must_use = "allocations are only attributed while the guard is alive"]
+pub struct ContainerGuard {
+    outer: Option<&'static Container>,
+    /// Tied to the thread it was created on.
+    _marker: PhantomData<*const ()>,
+}
+
+impl<A> CountingAlloc<A> {
+    pub const fn new(inner: A) -> Self {
+        CountingAlloc {
+            inner,
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            live: self.live.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Lowers the peak to the current live bytes, to measure the peak of what follows.
+    pub fn reset_peak(&self) {
+        self.peak
+            .store(self.live.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    fn record_alloc(&self, size: usize) {
+        let live = self.live.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(live, Ordering::Relaxed);
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        Container::record(size);
+    }
+
+    fn record_dealloc(&self, size: usize) {
+        self.live.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    fn record_realloc(&self, old_size: usize, new_size: usize) {
+        if new_size >= old_size {
+            self.record_alloc(new_size - old_size);
+        } else {
+            self.record_dealloc(old_size - new_size);
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            Container::record(0);
+        }
+    }
+}
+
+// SAFETY: every call is forwarded to `inner` unchanged.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: the caller upholds `alloc`'s contract.
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: the caller upholds `alloc_zeroed`'s contract.
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds `dealloc`'s contract.
+        unsafe { self.inner.dealloc(ptr, layout) };
+        self.record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: the caller upholds `realloc`'s contract.
+        let new = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            self.record_realloc(layout.size(), new_size);
+        }
+        new
+    }
+}
+
+// SAFETY: every call is forwarded to `inner` unchanged.
+#[cfg(feature = "nightly")]
+unsafe impl<A: Allocator> Allocator for CountingAlloc<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.record_alloc(layout.size());
+        Ok(ptr)
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate_zeroed(layout)?;
+        self.record_alloc(layout.size());
+        Ok(ptr)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // SAFETY: the caller upholds `deallocate`'s contract.
+        unsafe { self.inner.deallocate(ptr, layout) };
+        self.record_dealloc(layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // SAFETY: the caller upholds `grow`'s contract.
+        let new = unsafe { self.inner.grow(ptr, old_layout, new_layout)? };
+        self.record_realloc(old_layout.size(), new_layout.size());
+        Ok(new)
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // SAFETY: the caller upholds `grow_zeroed`'s contract.
+        let new = unsafe { self.inner.grow_zeroed(ptr, old_layout, new_layout)? };
+        self.record_realloc(old_layout.size(), new_layout.size());
+        Ok(new)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // SAFETY: the caller upholds `shrink`'s contract.
+        let new = unsafe { self.inner.shrink(ptr, old_layout, new_layout)? };
+        self.record_realloc(old_layout.size(), new_layout.size());
+        Ok(new)
+    }
+}
+
+impl Container {
+    pub const fn new(name: &'static str) -> Self {
+        Container {
+            name,
+            allocations: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Attributes this thread's allocations to `self` until the guard is dropped. Guards nest,
+    /// and only the innermost container counts.
+    pub fn enter(&'static self) -> ContainerGuard {
+        ContainerGuard {
+            outer: CURRENT.replace(Some(self)),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn stats(&self) -> ContainerStats {
+        ContainerStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Counts an allocation of `bytes` for the current thread and its container, if any.
+    fn record(bytes: usize) {
+        // Allocations during thread teardown can't be attributed, and must not panic.
+        let _ = THREAD.try_with(|counters| {
+            counters.add(ALLOCATIONS, 1);
+            counters.add(BYTES, bytes);
+        });
+        if let Ok(Some(container)) = CURRENT.try_with(Cell::get) {
+            container.allocations.fetch_add(1, Ordering::Relaxed);
+            container.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        CURRENT.set(self.outer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::System;
+
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::index_vec::IndexVec;
+
+    /// The test binary's allocator. Other tests run in parallel, so only the per-container
+    /// counts, which are per thread, are exact.
+    #[global_allocator]
+    static GLOBAL: CountingAlloc<System> = CountingAlloc::new(System);
+
+    #[test]
+    fn counts_live_and_peak() {
+        let alloc = CountingAlloc::new(System);
+        let (small, big) = (Layout::new::<[u8; 16]>(), Layout::new::<[u64; 64]>());
+        // SAFETY: both layouts are non-zero sized, and each pointer is freed once with its
+        // layout.
+        unsafe {
+            let a = alloc.alloc(small);
+            let b = alloc.alloc_zeroed(big);
+            alloc.dealloc(b, big);
+            let a = alloc.realloc(a, small, 64);
+            assert_eq!(
+                alloc.memory_stats(),
+                MemoryStats {
+                    live: 64,
+                    peak: 16 + 512,
+                    allocations: 3
+                }
+            );
+            alloc.reset_peak();
+            let a = alloc.realloc(a, Layout::from_size_align(64, 1).unwrap(), 8);
+            alloc.dealloc(a, Layout::new::<[u8; 8]>());
+        }
+        assert_eq!(
+            alloc.memory_stats(),
+            MemoryStats {
+                live: 0,
+                peak: 64,
+                allocations: 4
+            }
+        );
+    }
+
+    #[test]
+    fn attributes_to_containers() {
+        static ANALYSIS: Container = Container::new("analysis");
+        static INNER: Container = Container::new("inner");
+        let before = GLOBAL.memory_stats().allocations;
+        {
+            let _analysis = ANALYSIS.enter();
+            let v: IndexVec<u32, u64> = IndexVec::from_elem_n(0, 100);
+            let set: BitSet<u32> = BitSet::new_empty(1000);
+            {
+                let _inner = INNER.enter();
+                drop(Box::new([0u8; 24]));
+            }
+            drop((v, set));
+        }
+        drop(Vec::<u8>::with_capacity(8));
+
+        assert_eq!(ANALYSIS.name(), "analysis");
+        assert_eq!(
+            ANALYSIS.stats(),
+            ContainerStats {
+                allocations: 2,
+                bytes: 100 * 8 + 16 * 8
+            }
+        );
+        assert_eq!(
+            INNER.stats(),
+            ContainerStats {
+                allocations: 1,
+                bytes: 24
+            }
+        );
+        assert!(GLOBAL.memory_stats().allocations >= before + 4);
+    }
+
+    #[test]
+    fn counts_per_thread() {
+        let allocate = || {
+            let before = thread_stats();
+            let v: Vec<u64> = Vec::with_capacity(32);
+            drop(Box::new([0u8; 24]));
+            drop(v);
+            let after = thread_stats();
+            (
+                after.allocations - before.allocations,
+                after.bytes - before.bytes,
+            )
+        };
+        assert_eq!(allocate(), (2, 32 * 8 + 24));
+        let other = std::thread::spawn(allocate).join().unwrap();
+        assert_eq!(other, (2, 32 * 8 + 24));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn per_collection_allocator() {
+        let alloc = CountingAlloc::new(System);
+        let mut v = Vec::new_in(&alloc);
+        v.extend(0u32..100);
+        let grown = alloc.memory_stats();
+        assert_eq!(grown.live, v.capacity() * 4);
+        v.truncate(10);
+        v.shrink_to_fit();
+        assert_eq!(alloc.memory_stats().live, 40);
+        drop(v);
+        let stats = alloc.memory_stats();
+        assert_eq!((stats.live, stats.peak), (0, grown.peak));
+        assert!(stats.allocations > grown.allocations);
+    }
+}
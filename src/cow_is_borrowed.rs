@@ -0,0 +1,125 @@
+//! `Cow::is_borrowed` and `Cow::is_owned` usage
+//!
+//! Tracking issue [65143].
+//!
+//! `Cow::is_borrowed(&c)` and `Cow::is_owned(&c)` say which variant a `Cow` holds, and so whether
+//! the function that returned it had to allocate, without a `match` or `matches!` on the variants.
+//! They are associated functions rather than methods, so that a `Cow<'_, T>` doesn't lose `T`'s
+//! own methods of the same name through `Deref`.
+//!
+//! A [`CowTally`] counts the `Cow`s it is shown, by variant. The interner keeps two of them per
+//! thread, reported in `raw_entry::InternerStats`: one for `remove_matches::normalized`, where
+//! owned means a name wasn't normal already, and one for the strings `StrInterner::intern_cow`
+//! adds, where borrowed means a copy was made. Without the `nightly` feature [`is_borrowed`] and
+//! [`is_owned`] match on the variant.
+//!
+//! [65143]: https://github.com/rust-lang/rust/issues/65143
+
+// The variant of a `&Cow` is the point here, which a `&B` wouldn't have.
+#![allow(clippy::ptr_arg)]
+
+use std::borrow::Cow;
+
+/// Whether `c` borrows its data, so that `to_mut` would clone it.
+#[cfg(feature = "nightly")]
+#[inline]
+pub const fn is_borrowed<B: ?Sized + ToOwned>(c: &Cow<'_, B>) -> bool {
+    Cow::is_borrowed(c)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub const fn is_borrowed<B: ?Sized + ToOwned>(c: &Cow<'_, B>) -> bool {
+    portable::is_borrowed(c)
+}
+
+/// Whether `c` owns its data.
+#[cfg(feature = "nightly")]
+#[inline]
+pub const fn is_owned<B: ?Sized + ToOwned>(c: &Cow<'_, B>) -> bool {
+    Cow::is_owned(c)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub const fn is_owned<B: ?Sized + ToOwned>(c: &Cow<'_, B>) -> bool {
+    !portable::is_borrowed(c)
+}
+
+/// Counts of borrowed and owned `Cow`s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CowTally {
+    pub borrowed: usize,
+    pub owned: usize,
+}
+
+impl CowTally {
+    /// Counts `c`.
+    pub fn record<B: ?Sized + ToOwned>(&mut self, c: &Cow<'_, B>) {
+        if is_borrowed(c) {
+            self.borrowed += 1;
+        } else {
+            self.owned += 1;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.borrowed + self.owned
+    }
+
+    /// The share of owned `Cow`s, or zero if none were counted.
+    pub fn owned_share(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.owned as f64 / total as f64,
+        }
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::borrow::Cow;
+
+    pub(super) const fn is_borrowed<B: ?Sized + ToOwned>(c: &Cow<'_, B>) -> bool {
+        matches!(c, Cow::Borrowed(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_and_tally() {
+        let borrowed: Cow<'_, str> = Cow::Borrowed("a");
+        let owned: Cow<'_, str> = Cow::Owned("b".to_string());
+        assert!(is_borrowed(&borrowed) && !is_owned(&borrowed));
+        assert!(is_owned(&owned) && !is_borrowed(&owned));
+
+        let mut tally = CowTally::default();
+        assert_eq!(tally.owned_share(), 0.0);
+        for c in [&borrowed, &owned, &borrowed, &borrowed] {
+            tally.record(c);
+        }
+        assert_eq!(
+            tally,
+            CowTally {
+                borrowed: 3,
+                owned: 1
+            }
+        );
+        assert_eq!((tally.total(), tally.owned_share()), (4, 0.25));
+        tally.record(&Cow::<[u8]>::Owned(vec![1]));
+        assert_eq!(tally.owned, 2);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let cows: [Cow<'_, [u8]>; 2] = [Cow::Borrowed(&[1]), Cow::Owned(vec![])];
+        for c in &cows {
+            assert_eq!(portable::is_borrowed(c), Cow::is_borrowed(c));
+            assert_eq!(!portable::is_borrowed(c), Cow::is_owned(c));
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! `SystemTime::saturating_add` and `SystemTime::MAX` usage
+//!
+//! Tracking issues [151199] and [149067].
+//!
+//! `SystemTime` arithmetic that goes past what the platform can represent panics with `+`, and
+//! gives `None` with `checked_add`, leaving the caller to decide what a clamped time should be.
+//! `SystemTime::saturating_add`, `saturating_sub` and `saturating_duration_since` clamp to the
+//! ends of the range, now known as `SystemTime::MIN` and `SystemTime::MAX`. The same for `Instant`
+//! has long been stable, as `checked_add` and `saturating_duration_since`.
+//!
+//! A [`Deadline`] is an `Instant`, or never, which a timeout too long to represent becomes instead
+//! of a panic. [`Deadline::remaining`] and the comparisons saturate. Deadlines convert to and
+//! from wall-clock times for showing to users and taking from them, guarded so that a time in the
+//! past is a deadline that has passed and a far future one clamps. `Ticker::run_for` runs a
+//! ticker until one. Without the `nightly` feature the clamping is found with `checked_add`.
+//!
+//! [151199]: https://github.com/rust-lang/rust/issues/151199
+//! [149067]: https://github.com/rust-lang/rust/issues/149067
+
+use std::cmp::Ordering;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A point in monotonic time, or never.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Deadline {
+    at: Option<Instant>,
+}
+
+impl Deadline {
+    pub const NEVER: Deadline = Deadline { at: None };
+
+    pub fn at(instant: Instant) -> Self {
+        Deadline { at: Some(instant) }
+    }
+
+    /// The deadline `timeout` from now, or never if that can't be represented.
+    pub fn after(timeout: Duration) -> Self {
+        Deadline {
+            at: Instant::now().checked_add(timeout),
+        }
+    }
+
+    /// The deadline as an `Instant`, or `None` if it is never.
+    pub fn instant(self) -> Option<Instant> {
+        self.at
+    }
+
+    /// The deadline `duration` later, or `None` if that can't be represented. Never stays never.
+    pub fn checked_add(self, duration: Duration) -> Option<Deadline> {
+        match self.at {
+            Some(at) => at.checked_add(duration).map(Deadline::at),
+            None => Some(Deadline::NEVER),
+        }
+    }
+
+    /// The time left until the deadline: zero if it has passed, `Duration::MAX` if it is never.
+    pub fn remaining(self) -> Duration {
+        match self.at {
+            Some(at) => at.saturating_duration_since(Instant::now()),
+            None => Duration::MAX,
+        }
+    }
+
+    pub fn has_passed(self) -> bool {
+        self.at.is_some_and(|at| at <= Instant::now())
+    }
+
+    /// The deadline at the wall-clock time `time`, or one that has already passed if `time` is
+    /// earlier than the clock says now.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let left = saturating_duration_since(time, SystemTime::now());
+        Deadline::after(left)
+    }
+
+    /// The wall-clock time of the deadline, as far as the clocks agree now, clamped to the
+    /// latest time this platform can represent. `None` if it is never.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let at = self.at?;
+        let now = Instant::now();
+        let wall = SystemTime::now();
+        Some(match at.checked_duration_since(now) {
+            Some(ahead) => saturating_add(wall, ahead),
+            None => saturating_sub(wall, now - at),
+        })
+    }
+}
+
+/// Later deadlines are greater, and never is greater than all of them.
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.at, other.at) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Deadline::at(instant)
+    }
+}
+
+#[cfg(feature = "nightly")]
+fn saturating_add(time: SystemTime, duration: Duration) -> SystemTime {
+    time.saturating_add(duration)
+}
+
+#[cfg(not(feature = "nightly"))]
+fn saturating_add(time: SystemTime, duration: Duration) -> SystemTime {
+    portable::saturating_add(time, duration)
+}
+
+#[cfg(feature = "nightly")]
+fn saturating_sub(time: SystemTime, duration: Duration) -> SystemTime {
+    time.saturating_sub(duration)
+}
+
+#[cfg(not(feature = "nightly"))]
+fn saturating_sub(time: SystemTime, duration: Duration) -> SystemTime {
+    portable::saturating_sub(time, duration)
+}
+
+#[cfg(feature = "nightly")]
+fn saturating_duration_since(time: SystemTime, earlier: SystemTime) -> Duration {
+    time.saturating_duration_since(earlier)
+}
+
+#[cfg(not(feature = "nightly"))]
+fn saturating_duration_since(time: SystemTime, earlier: SystemTime) -> Duration {
+    portable::saturating_duration_since(time, earlier)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::time::{Duration, SystemTime};
+
+    /// Moves `time` by as much of `duration` as `step` allows, halving what is left to move by
+    /// whenever a step would overflow, which ends at the limit.
+    fn saturating(
+        mut time: SystemTime,
+        duration: Duration,
+        step: fn(SystemTime, Duration) -> Option<SystemTime>,
+    ) -> SystemTime {
+        if let Some(moved) = step(time, duration) {
+            return moved;
+        }
+        let mut by = duration;
+        while !by.is_zero() {
+            match step(time, by) {
+                Some(moved) => time = moved,
+                None => by /= 2,
+            }
+        }
+        time
+    }
+
+    pub(super) fn saturating_add(time: SystemTime, duration: Duration) -> SystemTime {
+        saturating(time, duration, |t, d| t.checked_add(d))
+    }
+
+    pub(super) fn saturating_sub(time: SystemTime, duration: Duration) -> SystemTime {
+        saturating(time, duration, |t, d| t.checked_sub(d))
+    }
+
+    pub(super) fn saturating_duration_since(time: SystemTime, earlier: SystemTime) -> Duration {
+        time.duration_since(earlier).unwrap_or(Duration::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn remaining_and_order() {
+        let soon = Deadline::after(SECOND);
+        let later = soon.checked_add(SECOND).unwrap();
+        assert!(soon < later && later < Deadline::NEVER);
+        assert_eq!(soon.min(Deadline::NEVER), soon);
+        assert!(!soon.has_passed());
+        assert!(soon.remaining() <= SECOND && soon.remaining() > Duration::ZERO);
+
+        let past = Deadline::at(Instant::now() - SECOND);
+        assert!(past.has_passed());
+        assert_eq!(past.remaining(), Duration::ZERO);
+
+        assert_eq!(Deadline::after(Duration::MAX), Deadline::NEVER);
+        assert_eq!(soon.checked_add(Duration::MAX), None);
+        assert_eq!(Deadline::NEVER.checked_add(SECOND), Some(Deadline::NEVER));
+        assert_eq!(Deadline::NEVER.remaining(), Duration::MAX);
+        assert!(!Deadline::NEVER.has_passed());
+    }
+
+    #[test]
+    fn wall_clock() {
+        let wall = SystemTime::now() + 60 * SECOND;
+        let deadline = Deadline::from_system_time(wall);
+        let back = deadline.to_system_time().unwrap();
+        let error = back.duration_since(wall).unwrap_or_else(|e| e.duration());
+        assert!(error < SECOND, "{error:?}");
+
+        let past = Deadline::from_system_time(SystemTime::UNIX_EPOCH);
+        assert!(past.has_passed());
+        let behind = Deadline::at(Instant::now() - SECOND)
+            .to_system_time()
+            .unwrap();
+        assert!(behind < SystemTime::now());
+        assert_eq!(Deadline::NEVER.to_system_time(), None);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let now = SystemTime::now();
+        for d in [Duration::ZERO, SECOND, Duration::MAX] {
+            assert_eq!(portable::saturating_add(now, d), now.saturating_add(d));
+            assert_eq!(portable::saturating_sub(now, d), now.saturating_sub(d));
+        }
+        assert_eq!(
+            portable::saturating_add(now, Duration::MAX),
+            SystemTime::MAX
+        );
+        assert_eq!(
+            portable::saturating_sub(now, Duration::MAX),
+            SystemTime::MIN
+        );
+        let earlier = now - SECOND;
+        for (a, b) in [(now, earlier), (earlier, now)] {
+            assert_eq!(
+                portable::saturating_duration_since(a, b),
+                a.saturating_duration_since(b)
+            );
+        }
+    }
+}
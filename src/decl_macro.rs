@@ -0,0 +1,169 @@
+//! `macro` (macros 2.0) usage
+//!
+//! Tracking issue [39412].
+//!
+//! `pub macro name($args) { ... }` declares a macro as an ordinary item: it has a visibility and a
+//! path like a function, so it is imported with `use` and needs no `#[macro_export]`, which would
+//! put it at the root of the crate. Its body is hygienic where a `macro_rules!` body only is for
+//! local variables: paths in it resolve where the macro is defined, so it names the crate's items
+//! as `crate::...` or through the defining module's imports, without `$crate`, and a caller
+//! can't shadow them by having items of the same names in scope. Only the tokens passed in, like
+//! the type name, resolve where the macro is called. Items that the macro refers to still have to
+//! be public for callers in other crates, as a private function in the expansion is an error there.
+//!
+//! [`enum_idx`] is the `macro` form of [`enum_idx!`](crate::enum_idx), with the same input. Both
+//! expand to the same two impls, token for token but for `$crate`, and are written out twice, so
+//! the tests expand the crate with `rustc -Zunpretty=expanded` and compare what each front end
+//! generated for the same enum, besides checking that the impls behave the same. The count and
+//! order check come from the same helper as in `enum_idx!`, so its callers enable
+//! `macro_metavar_expr` too. There is no stable equivalent, so the module is only built with the
+//! `nightly` feature.
+//!
+//! [39412]: https://github.com/rust-lang/rust/issues/39412
+
+/// Implements `Idx` and `EnumIdx` for a fieldless enum, given all of its variants in order, like
+/// [`enum_idx!`](crate::enum_idx).
+pub macro enum_idx($t:ident { $($v:ident),+ $(,)? }) {
+    impl crate::step::Idx for $t {
+        const MAX_INDEX: usize = <$t as crate::variant_count::EnumIdx>::COUNT - 1;
+
+        #[inline]
+        fn new(idx: usize) -> Self {
+            <$t as crate::variant_count::EnumIdx>::VARIANTS[idx]
+        }
+
+        #[inline]
+        fn index(self) -> usize {
+            // An exhaustive `match`, so that a variant missing from the list doesn't compile.
+            match self {
+                $($t::$v => $t::$v as usize),+
+            }
+        }
+    }
+
+    impl crate::variant_count::EnumIdx for $t {
+        const VARIANTS: &'static [Self] = &[$($t::$v),+];
+
+        const COUNT: usize = crate::__enum_idx_count!($t { $($v),+ });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::step::Idx;
+    use crate::variant_count::EnumIdx;
+
+    macro_rules! phases {
+        ($name:ident) => {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            enum $name {
+                Parse,
+                Resolve,
+                Typeck,
+                Codegen,
+            }
+        };
+    }
+
+    phases!(ByRules);
+    phases!(ByMacro);
+
+    crate::enum_idx!(ByRules {
+        Parse,
+        Resolve,
+        Typeck,
+        Codegen
+    });
+    super::enum_idx!(ByMacro {
+        Parse,
+        Resolve,
+        Typeck,
+        Codegen
+    });
+
+    /// The impls generated for `ty` in `expanded`, with `ty` replaced by `T`.
+    fn generated_impls(expanded: &str, ty: &str) -> String {
+        let mut impls = String::new();
+        let mut end = None;
+        for line in expanded.lines() {
+            let trimmed = line.trim_start();
+            if end.is_none()
+                && trimmed.starts_with("impl crate::")
+                && trimmed.ends_with(&format!(" for {ty} {{"))
+            {
+                end = Some(format!("{}}}", &line[..line.len() - trimmed.len()]));
+            }
+            if let Some(close) = &end {
+                impls.push_str(&line.replace(ty, "T"));
+                impls.push('\n');
+                if line == close {
+                    end = None;
+                }
+            }
+        }
+        impls
+    }
+
+    #[test]
+    fn expansions_match() {
+        // `cargo +nightly test` puts its toolchain first for the `rustc` it runs.
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = std::process::Command::new(rustc)
+            .args(["--edition", "2024", "--crate-type", "lib", "--crate-name"])
+            .args(["rust_unstable", "--test", "--cfg", r#"feature="nightly""#])
+            .args(["--cfg", r#"feature="std""#])
+            .args(["-Zunpretty=expanded", "-A", "warnings"])
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/src/lib.rs"))
+            .env("CARGO_CRATE_NAME", "rust_unstable")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let expanded = String::from_utf8(output.stdout).unwrap();
+        let by_rules = generated_impls(&expanded, "ByRules");
+        assert!(by_rules.contains("impl crate::step::Idx for T {"));
+        assert!(by_rules.contains("impl crate::variant_count::EnumIdx for T {"));
+        assert_eq!(by_rules, generated_impls(&expanded, "ByMacro"));
+    }
+
+    #[test]
+    fn front_ends_agree() {
+        assert_eq!(ByRules::COUNT, ByMacro::COUNT);
+        assert_eq!(ByRules::MAX_INDEX, ByMacro::MAX_INDEX);
+        let by_rules: Vec<String> = ByRules::VARIANTS.iter().map(|v| format!("{v:?}")).collect();
+        let by_macro: Vec<String> = ByMacro::VARIANTS.iter().map(|v| format!("{v:?}")).collect();
+        assert_eq!(by_rules, by_macro);
+        for i in 0..ByMacro::COUNT {
+            assert_eq!(ByRules::new(i).index(), ByMacro::new(i).index());
+            assert_eq!(
+                format!("{:?}", ByRules::new(i)),
+                format!("{:?}", ByMacro::new(i))
+            );
+        }
+    }
+
+    /// Traits named like the crate's don't change what the `macro` implements.
+    mod shadowed {
+        #[allow(dead_code)]
+        trait Idx {}
+        #[allow(dead_code)]
+        trait EnumIdx {}
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub(super) enum Level {
+            Low,
+            High,
+        }
+
+        crate::decl_macro::enum_idx!(Level { Low, High });
+    }
+
+    #[test]
+    fn resolves_at_definition() {
+        assert_eq!(shadowed::Level::COUNT, 2);
+        assert_eq!(shadowed::Level::new(1), shadowed::Level::High);
+    }
+}
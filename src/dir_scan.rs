@@ -0,0 +1,204 @@
+//! `DirEntryExt2::file_name_ref` usage
+//!
+//! Tracking issue [85573].
+//!
+//! `DirEntry::file_name()` returns an `OsString`, so looking at the name of every entry in a
+//! directory costs an allocation and a copy per entry, even when most are only checked and thrown
+//! away. On Unix the name is already in the entry, and `DirEntryExt2::file_name_ref()` borrows it
+//! as an `&OsStr` instead.
+//!
+//! [`index_tree`] walks a directory tree into an `IndexVec<FileIdx, PathBuf>` of the files in it,
+//! passing each entry's name to a filter that can also keep the walk out of a directory. Only the
+//! kept paths are allocated. Without the `nightly` feature, or off Unix, each name is copied out
+//! with `file_name()`.
+//!
+//! [85573]: https://github.com/rust-lang/rust/issues/85573
+
+use std::ffi::OsStr;
+use std::fs::{self, DirEntry};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// The index of a file found by [`index_tree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileIdx(u32);
+
+impl Idx for FileIdx {
+    const MAX_INDEX: usize = u32::MAX as usize;
+
+    #[inline]
+    fn new(idx: usize) -> Self {
+        assert!(idx <= u32::MAX as usize);
+        FileIdx(idx as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+type Filter<'a> = dyn FnMut(&OsStr) -> bool + 'a;
+
+/// Whether `keep` accepts the name of `entry`.
+#[cfg(all(unix, feature = "nightly"))]
+fn name_kept(entry: &DirEntry, keep: &mut Filter<'_>) -> bool {
+    use std::os::unix::fs::DirEntryExt2;
+
+    keep(entry.file_name_ref())
+}
+
+#[cfg(not(all(unix, feature = "nightly")))]
+fn name_kept(entry: &DirEntry, keep: &mut Filter<'_>) -> bool {
+    portable::name_kept(entry, keep)
+}
+
+/// The files under `root`, in the order the directories list them, skipping every entry whose
+/// name `keep` rejects. A rejected directory is not walked. Symbolic links are not followed.
+pub fn index_tree(
+    root: &Path,
+    mut keep: impl FnMut(&OsStr) -> bool,
+) -> io::Result<IndexVec<FileIdx, PathBuf>> {
+    walk(root, &mut keep, name_kept)
+}
+
+fn walk(
+    root: &Path,
+    keep: &mut Filter<'_>,
+    name_kept: fn(&DirEntry, &mut Filter<'_>) -> bool,
+) -> io::Result<IndexVec<FileIdx, PathBuf>> {
+    let mut files = IndexVec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if !name_kept(&entry, keep) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(any(test, not(all(unix, feature = "nightly"))))]
+mod portable {
+    use std::fs::DirEntry;
+
+    use super::Filter;
+
+    pub(super) fn name_kept(entry: &DirEntry, keep: &mut Filter<'_>) -> bool {
+        keep(&entry.file_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    /// A tree of `dirs` directories, each with `files_per_dir` files and a hidden directory.
+    pub(super) fn temp_tree(name: &str, dirs: usize, files_per_dir: usize) -> PathBuf {
+        let root = env::temp_dir().join(format!("rust-unstable-dir-scan-{name}-{}", process::id()));
+        for d in 0..dirs {
+            let dir = root.join(format!("dir{d}"));
+            fs::create_dir_all(dir.join(".hidden")).unwrap();
+            fs::write(dir.join(".hidden/secret"), "").unwrap();
+            for f in 0..files_per_dir {
+                fs::write(dir.join(format!("file{f}.txt")), "").unwrap();
+            }
+        }
+        root
+    }
+
+    fn visible(name: &OsStr) -> bool {
+        !name.as_encoded_bytes().starts_with(b".")
+    }
+
+    fn sorted(files: IndexVec<FileIdx, PathBuf>, root: &Path) -> Vec<String> {
+        let mut names: Vec<String> = files
+            .iter()
+            .map(|p| {
+                p.strip_prefix(root)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .replace('\\', "/")
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn indexes_files() {
+        let root = temp_tree("index", 2, 2);
+        let all = index_tree(&root, |_| true);
+        let shown = index_tree(&root, visible);
+        let missing = index_tree(&root.join("missing"), |_| true);
+        fs::remove_dir_all(&root).unwrap();
+
+        let all = all.unwrap();
+        assert_eq!(all.len(), 6);
+        assert!(all.iter().all(|p| p.starts_with(&root)));
+        assert_eq!(
+            sorted(shown.unwrap(), &root),
+            [
+                "dir0/file0.txt",
+                "dir0/file1.txt",
+                "dir1/file0.txt",
+                "dir1/file1.txt"
+            ]
+        );
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let root = temp_tree("agree", 3, 2);
+        let from_std = index_tree(&root, visible).unwrap();
+        let portable = walk(&root, &mut visible, portable::name_kept).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(from_std, portable);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+    use test::{Bencher, black_box};
+
+    use super::tests::temp_tree;
+    use super::*;
+
+    /// A filter that keeps nothing, so that only the name checks are measured.
+    fn none(name: &OsStr) -> bool {
+        black_box(name);
+        false
+    }
+
+    #[bench]
+    fn scan_borrowed_names(b: &mut Bencher) {
+        let root = temp_tree("bench-borrowed", 1, 500);
+        let dir = root.join("dir0");
+        b.iter(|| walk(&dir, &mut none, name_kept).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[bench]
+    fn scan_owned_names(b: &mut Bencher) {
+        let root = temp_tree("bench-owned", 1, 500);
+        let dir = root.join("dir0");
+        b.iter(|| walk(&dir, &mut none, portable::name_kept).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+//! `BinaryHeap::{drain_sorted, into_iter_sorted}` usage
+//!
+//! Tracking issue [59278].
+//!
+//! `heap.drain_sorted()` removes the elements of a heap in heap order, greatest first, as a lazy
+//! iterator. `into_iter_sorted()` does the same for an owned heap. Plain `drain()` and
+//! `into_iter()` yield in arbitrary order, and `into_sorted_vec()` sorts everything up front even
+//! if only the first few are wanted. If a `drain_sorted` iterator is dropped early, including
+//! during a panic, the elements not yet yielded are dropped and the heap is left empty.
+//!
+//! [`PriorityWork`] is a work queue of indices with priorities, where [`PriorityWork::drain_ready`]
+//! hands out all queued work, most urgent first. Without the `nightly` feature the iterators
+//! repeatedly `pop`, with a guard that empties the heap when dropped like std's.
+//!
+//! [59278]: https://github.com/rust-lang/rust/issues/59278
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::bit_set::BitSet;
+use crate::step::Idx;
+
+/// Removes all elements of `heap`, greatest first.
+///
+/// Elements not yet yielded when the iterator is dropped are dropped with it.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn drain_sorted<T: Ord>(heap: &mut BinaryHeap<T>) -> impl Iterator<Item = T> + '_ {
+    heap.drain_sorted()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn drain_sorted<T: Ord>(heap: &mut BinaryHeap<T>) -> impl Iterator<Item = T> + '_ {
+    portable::DrainSorted { heap }
+}
+
+/// Consumes `heap`, yielding its elements greatest first.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn into_iter_sorted<T: Ord>(heap: BinaryHeap<T>) -> impl Iterator<Item = T> {
+    heap.into_iter_sorted()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn into_iter_sorted<T: Ord>(mut heap: BinaryHeap<T>) -> impl Iterator<Item = T> {
+    std::iter::from_fn(move || heap.pop())
+}
+
+/// Queued indices with priorities. Each index is queued at most once.
+pub struct PriorityWork<I: Idx, P: Ord> {
+    /// Ties on the priority go to the smaller index.
+    heap: BinaryHeap<(P, Reverse<usize>)>,
+    queued: BitSet<I>,
+}
+
+impl<I: Idx, P: Ord> PriorityWork<I, P> {
+    /// Creates an empty queue for indices below `domain_size`.
+    pub fn new(domain_size: usize) -> Self {
+        PriorityWork {
+            heap: BinaryHeap::new(),
+            queued: BitSet::new_empty(domain_size),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Queues `index` with `priority`. Returns false, and leaves the queue alone, if `index`
+    /// is queued already.
+    pub fn push(&mut self, index: I, priority: P) -> bool {
+        if !self.queued.insert(index) {
+            return false;
+        }
+        self.heap.push((priority, Reverse(index.index())));
+        true
+    }
+
+    /// Removes the most urgent item.
+    pub fn pop(&mut self) -> Option<(I, P)> {
+        let (priority, Reverse(index)) = self.heap.pop()?;
+        let index = I::new(index);
+        self.queued.remove(index);
+        Some((index, priority))
+    }
+
+    /// Removes all queued items, most urgent first.
+    ///
+    /// The queue is empty afterwards, even if the iterator is dropped before the end.
+    pub fn drain_ready(&mut self) -> impl Iterator<Item = (I, P)> + '_ {
+        // The drain takes all of them either way.
+        self.queued.clear();
+        drain_sorted(&mut self.heap).map(|(priority, Reverse(index))| (I::new(index), priority))
+    }
+
+    /// Consumes the queue, yielding its items most urgent first.
+    pub fn into_iter_sorted(self) -> impl Iterator<Item = (I, P)> {
+        into_iter_sorted(self.heap).map(|(priority, Reverse(index))| (I::new(index), priority))
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::collections::BinaryHeap;
+
+    pub(super) struct DrainSorted<'a, T: Ord> {
+        pub(super) heap: &'a mut BinaryHeap<T>,
+    }
+
+    impl<T: Ord> Iterator for DrainSorted<'_, T> {
+        type Item = T;
+
+        fn next(&mut self) -> Option<T> {
+            self.heap.pop()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.heap.len(), Some(self.heap.len()))
+        }
+    }
+
+    impl<T: Ord> Drop for DrainSorted<'_, T> {
+        fn drop(&mut self) {
+            // Drop the rest in heap order too, and keep going if one of them panics.
+            struct DropGuard<'r, 'a, T: Ord>(&'r mut DrainSorted<'a, T>);
+
+            impl<T: Ord> Drop for DropGuard<'_, '_, T> {
+                fn drop(&mut self) {
+                    while self.0.heap.pop().is_some() {}
+                }
+            }
+
+            while let Some(item) = self.heap.pop() {
+                let guard = DropGuard(self);
+                drop(item);
+                std::mem::forget(guard);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Tracked(u32, Rc<Cell<usize>>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    #[test]
+    fn ready_work_in_order() {
+        let mut work: PriorityWork<u32, u8> = PriorityWork::new(10);
+        assert!(work.push(3, 1));
+        assert!(work.push(7, 5));
+        assert!(work.push(1, 5));
+        assert!(!work.push(3, 9));
+        assert_eq!(work.len(), 3);
+        let order: Vec<_> = work.drain_ready().collect();
+        assert_eq!(order, [(1, 5), (7, 5), (3, 1)]);
+        assert!(work.is_empty());
+        // Drained indices can be queued again.
+        assert!(work.push(3, 2));
+        assert_eq!(work.pop(), Some((3, 2)));
+    }
+
+    #[test]
+    fn early_drop_empties() {
+        let mut work: PriorityWork<usize, i32> = PriorityWork::new(4);
+        for i in 0..4 {
+            work.push(i, i as i32);
+        }
+        assert_eq!(work.drain_ready().next(), Some((3, 3)));
+        assert!(work.is_empty());
+        assert!(work.push(0, 0));
+
+        let mut heap = BinaryHeap::from([4, 1, 3]);
+        assert_eq!(drain_sorted(&mut heap).take(1).collect::<Vec<_>>(), [4]);
+        assert!(heap.is_empty());
+        assert_eq!(
+            into_iter_sorted(BinaryHeap::from([2, 9, 5])).collect::<Vec<_>>(),
+            [9, 5, 2]
+        );
+    }
+
+    fn panic_mid_drain(drain: impl FnOnce(&mut BinaryHeap<Tracked>) -> Vec<u32>) {
+        let drops = Rc::new(Cell::new(0));
+        let mut heap: BinaryHeap<_> = (0..6).map(|i| Tracked(i, drops.clone())).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drain(&mut heap)));
+        assert!(result.is_err());
+        assert!(heap.is_empty());
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn panic_safety() {
+        panic_mid_drain(|heap| {
+            drain_sorted(heap)
+                .map(|t| {
+                    assert!(t.0 != 3, "consumer panicked");
+                    t.0
+                })
+                .collect()
+        });
+        panic_mid_drain(|heap| {
+            portable::DrainSorted { heap }
+                .map(|t| {
+                    assert!(t.0 != 3, "consumer panicked");
+                    t.0
+                })
+                .collect()
+        });
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..8u32 {
+            for take in 0..=len as usize {
+                let items = (0..len).map(|i| (i * 5) % 7);
+                let (mut std, mut portable): (BinaryHeap<_>, BinaryHeap<_>) =
+                    (items.clone().collect(), items.collect());
+                let a: Vec<_> = std.drain_sorted().take(take).collect();
+                let b: Vec<_> = portable::DrainSorted {
+                    heap: &mut portable,
+                }
+                .take(take)
+                .collect();
+                assert_eq!(a, b);
+                assert!(std.is_empty() && portable.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! `Entry::insert_entry` usage
+//!
+//! Tracking issue [65225].
+//!
+//! `map.entry(k).insert_entry(v)` sets the value for `k` whether or not it was present, and
+//! returns the resulting `OccupiedEntry` instead of a bare `&mut V`. The entry can go on to read
+//! the key, replace the value again or remove it, all without hashing and probing for `k` a second
+//! time.
+//!
+//! [`upsert_and_then`] and [`upsert_with`] wrap the insert-then-inspect patterns that used to take
+//! an `insert` followed by a `get_mut` or `remove`.
+//!
+//! [65225]: https://github.com/rust-lang/rust/issues/65225
+
+use std::collections::HashMap;
+use std::collections::hash_map::{Entry, OccupiedEntry};
+use std::hash::{BuildHasher, Hash};
+
+/// Sets `map[key] = value`, then passes the occupied entry to `f`.
+pub fn upsert_and_then<K, V, S, R>(
+    map: &mut HashMap<K, V, S>,
+    key: K,
+    value: V,
+    f: impl FnOnce(OccupiedEntry<'_, K, V>) -> R,
+) -> R
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    f(map.entry(key).insert_entry(value))
+}
+
+/// Updates the value for `key` with `update`, or inserts `insert()` if there is none. Returns the
+/// entry for further use.
+pub fn upsert_with<'a, K, V, S>(
+    map: &'a mut HashMap<K, V, S>,
+    key: K,
+    insert: impl FnOnce() -> V,
+    update: impl FnOnce(&mut V),
+) -> OccupiedEntry<'a, K, V>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    match map.entry(key) {
+        Entry::Occupied(mut entry) => {
+            update(entry.get_mut());
+            entry
+        }
+        Entry::Vacant(entry) => entry.insert_entry(insert()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upsert_then_remove() {
+        let mut refcounts: HashMap<&str, u32> = HashMap::from([("a", 2)]);
+        // Setting a count to zero drops the key, with a single lookup.
+        let removed = upsert_and_then(&mut refcounts, "a", 0, |entry| {
+            (*entry.get() == 0).then(|| entry.remove_entry())
+        });
+        assert_eq!(removed, Some(("a", 0)));
+        assert!(refcounts.is_empty());
+
+        let key_len = upsert_and_then(&mut refcounts, "bb", 1, |entry| entry.key().len());
+        assert_eq!(key_len, 2);
+        assert_eq!(refcounts["bb"], 1);
+    }
+
+    #[test]
+    fn upsert_counts() {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for c in "abca".chars() {
+            let entry = upsert_with(&mut counts, c, || 1, |n| *n += 1);
+            assert_eq!(*entry.key(), c);
+        }
+        assert_eq!(counts[&'a'], 2);
+        assert_eq!(counts[&'b'], 1);
+
+        let entry = upsert_with(&mut counts, 'c', || 1, |n| *n -= 1);
+        if *entry.get() == 0 {
+            entry.remove();
+        }
+        assert!(!counts.contains_key(&'c'));
+    }
+}
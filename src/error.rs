@@ -0,0 +1,213 @@
+//! `Error::provide` usage
+//!
+//! Tracking issue [99301].
+//!
+//! `Error::provide(&self, request)` lets an error hand out values of types the caller asks for,
+//! through a `&dyn Error`: `error::request_ref::<Backtrace>(err)` gets a captured backtrace, and
+//! `error::request_value::<T>(err)` any `T` the error chose to provide. A handler holding an error
+//! from a chain of `source`s can so pull out details without naming, and downcasting to, each
+//! concrete error type.
+//!
+//! [`IndexOutOfRange`] and [`DomainMismatch`] capture a backtrace where they are created, and
+//! provide it along with the offending index as an [`OffendingIndex`]. They come from
+//! [`IndexSlice::try_get`] and [`AnyIdx::downcast`]. Without the `nightly` feature the same data is
+//! only reachable through their fields and `backtrace` methods.
+//!
+//! The crate's error types implement `core::error::Error`, which `std::error::Error` re-exports.
+//! Without the `std` feature the crate is `no_std`, and keeps the modules that only need `core`
+//! and `alloc`: these errors, `first_chunk`'s, `iter_array_chunks`'s and `btree_cursors`', and the
+//! containers they come from. Backtrace capture, and so the `backtrace` methods, and
+//! `error_reporter`'s `Report` need std; the `no_std` test crate under `tests/no_std` links the
+//! rest without it.
+//!
+//! [99301]: https://github.com/rust-lang/rust/issues/99301
+
+use core::any::{self, TypeId};
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::backtrace::Backtrace;
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+/// The index an error is about, as provided by the crate's errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffendingIndex(pub usize);
+
+/// The error returned when an index is past the end of a container.
+#[derive(Debug)]
+pub struct IndexOutOfRange {
+    pub index: usize,
+    pub len: usize,
+    /// The index type.
+    pub domain: &'static str,
+    #[cfg(feature = "std")]
+    backtrace: Backtrace,
+}
+
+impl IndexOutOfRange {
+    pub fn new<I: Idx>(index: I, len: usize) -> Self {
+        IndexOutOfRange {
+            index: index.index(),
+            len,
+            domain: any::type_name::<I>(),
+            #[cfg(feature = "std")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Where the error was created, if backtraces are enabled.
+    #[cfg(feature = "std")]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Display for IndexOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "index {} out of range for {} elements indexed by `{}`",
+            self.index, self.len, self.domain
+        )
+    }
+}
+
+impl error::Error for IndexOutOfRange {
+    #[cfg(feature = "nightly")]
+    fn provide<'a>(&'a self, request: &mut error::Request<'a>) {
+        request
+            .provide_ref::<Backtrace>(&self.backtrace)
+            .provide_value(OffendingIndex(self.index));
+    }
+}
+
+/// The error returned when an index of one type is used as another.
+#[derive(Debug)]
+pub struct DomainMismatch {
+    pub index: usize,
+    pub expected: &'static str,
+    pub found: &'static str,
+    #[cfg(feature = "std")]
+    backtrace: Backtrace,
+}
+
+impl DomainMismatch {
+    /// Where the error was created, if backtraces are enabled.
+    #[cfg(feature = "std")]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Display for DomainMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "index {} is a `{}`, not a `{}`",
+            self.index, self.found, self.expected
+        )
+    }
+}
+
+impl error::Error for DomainMismatch {
+    #[cfg(feature = "nightly")]
+    fn provide<'a>(&'a self, request: &mut error::Request<'a>) {
+        request
+            .provide_ref::<Backtrace>(&self.backtrace)
+            .provide_value(OffendingIndex(self.index));
+    }
+}
+
+/// An index of any type, which remembers the type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnyIdx {
+    index: usize,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl AnyIdx {
+    pub fn new<I: Idx>(index: I) -> Self {
+        AnyIdx {
+            index: index.index(),
+            type_id: TypeId::of::<I>(),
+            type_name: any::type_name::<I>(),
+        }
+    }
+
+    /// The index, if it is an `I`.
+    pub fn downcast<I: Idx>(self) -> Result<I, DomainMismatch> {
+        if self.type_id == TypeId::of::<I>() {
+            Ok(I::new(self.index))
+        } else {
+            Err(DomainMismatch {
+                index: self.index,
+                expected: any::type_name::<I>(),
+                found: self.type_name,
+                #[cfg(feature = "std")]
+                backtrace: Backtrace::capture(),
+            })
+        }
+    }
+}
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Like `get`, but with an error saying what was out of range.
+    pub fn try_get(&self, index: I) -> Result<&T, IndexOutOfRange> {
+        self.get(index)
+            .ok_or_else(|| IndexOutOfRange::new(index, self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_vec::IndexVec;
+    use crate::step::CustomIndex;
+
+    #[test]
+    fn error_details() {
+        let v: IndexVec<u32, u8> = IndexVec::from([1, 2]);
+        assert_eq!(v.try_get(1).ok(), Some(&2));
+        let err = v.try_get(5).unwrap_err();
+        assert_eq!((err.index, err.len), (5, 2));
+        assert_eq!(
+            err.to_string(),
+            "index 5 out of range for 2 elements indexed by `u32`"
+        );
+        #[cfg(feature = "std")]
+        let _ = err.backtrace().status();
+
+        let idx = AnyIdx::new(CustomIndex::new(3));
+        assert_eq!(
+            idx.downcast::<CustomIndex>().ok(),
+            Some(CustomIndex::new(3))
+        );
+        let err = idx.downcast::<u32>().unwrap_err();
+        assert_eq!(err.index, 3);
+        assert!(err.found.ends_with("CustomIndex"), "{}", err.found);
+        assert!(err.to_string().ends_with("not a `u32`"));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn provided_members() {
+        use std::error::{Error, request_ref, request_value};
+
+        let v: IndexVec<u32, u8> = IndexVec::new();
+        let errors: [Box<dyn Error>; 2] = [
+            Box::new(v.try_get(7).unwrap_err()),
+            Box::new(AnyIdx::new(4usize).downcast::<u32>().unwrap_err()),
+        ];
+        let indices: Vec<_> = errors
+            .iter()
+            .map(|err| request_value::<OffendingIndex>(&**err))
+            .collect();
+        assert_eq!(indices, [Some(OffendingIndex(7)), Some(OffendingIndex(4))]);
+        for err in &errors {
+            assert!(request_ref::<Backtrace>(&**err).is_some());
+            assert!(request_value::<u32>(&**err).is_none());
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! `error::Report` usage
+//!
+//! Tracking issue [90172].
+//!
+//! `error::Report::new(err)` wraps an error for printing: its `Display` writes the error followed
+//! by each of its `source`s, on one line or, with `.pretty(true)`, one per line under "Caused by:".
+//! With `.show_backtrace(true)` a pretty report also prints a backtrace that `Error::provide`
+//! hands out. It is what a `main` returning `Result` wants to print, and every application
+//! otherwise ends up writing a loop over `source` of its own.
+//!
+//! [`IntoReport::into_report`] wraps one of the crate's errors in a [`Report`], pretty and with
+//! its backtrace, which `Report::pretty` and `Report::show_backtrace` can turn off again. Without
+//! the `nightly` feature the report is rendered in the crate in the same format. There the only
+//! backtrace it finds is the outer error's own, the one [`IntoReport::report_backtrace`] returns.
+//!
+//! [90172]: https://github.com/rust-lang/rust/issues/90172
+
+use std::backtrace::Backtrace;
+use std::{error, fmt};
+
+use crate::btree_cursors::UnorderedKey;
+use crate::error::{DomainMismatch, IndexOutOfRange};
+use crate::first_chunk::Truncated;
+use crate::iter_array_chunks::TruncatedPair;
+use crate::map_many_mut::DisjointMutError;
+use crate::once_cell_try::{DuplicateSymbol, Unreachable};
+use crate::step::Idx;
+
+/// An error that can be wrapped in a [`Report`].
+pub trait IntoReport: error::Error + Sized + 'static {
+    /// The backtrace captured with the error, if it has one.
+    fn report_backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
+    /// A report printing the error, its sources and its backtrace, one per line.
+    fn into_report(self) -> Report<Self> {
+        Report::new(self).pretty(true).show_backtrace(true)
+    }
+}
+
+/// An error wrapped for printing with its sources.
+pub struct Report<E> {
+    error: E,
+    pretty: bool,
+    show_backtrace: bool,
+}
+
+impl<E: IntoReport> Report<E> {
+    /// A report printing the error and its sources on one line.
+    pub fn new(error: E) -> Self {
+        Report {
+            error,
+            pretty: false,
+            show_backtrace: false,
+        }
+    }
+
+    /// Whether to print each source on a line of its own.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Whether a pretty report ends with the error's backtrace.
+    pub fn show_backtrace(mut self, show_backtrace: bool) -> Self {
+        self.show_backtrace = show_backtrace;
+        self
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E: IntoReport> From<E> for Report<E> {
+    fn from(error: E) -> Self {
+        Report::new(error)
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<E: IntoReport> fmt::Display for Report<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error::Report::new(&self.error)
+            .pretty(self.pretty)
+            .show_backtrace(self.show_backtrace)
+            .fmt(f)
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<E: IntoReport> fmt::Display for Report<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backtrace = self.error.report_backtrace();
+        portable::render(f, &self.error, backtrace, self.pretty, self.show_backtrace)
+    }
+}
+
+/// Prints the same as `Display`, so that `main` returning `Result<(), Report<E>>` prints the
+/// report.
+impl<E: IntoReport> fmt::Debug for Report<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl IntoReport for IndexOutOfRange {
+    fn report_backtrace(&self) -> Option<&Backtrace> {
+        Some(self.backtrace())
+    }
+}
+
+impl IntoReport for DomainMismatch {
+    fn report_backtrace(&self) -> Option<&Backtrace> {
+        Some(self.backtrace())
+    }
+}
+
+impl IntoReport for DisjointMutError {}
+impl IntoReport for DuplicateSymbol {}
+impl IntoReport for Truncated {}
+impl IntoReport for TruncatedPair {}
+impl IntoReport for UnorderedKey {}
+impl<I: Idx + 'static> IntoReport for Unreachable<I> {}
+
+#[cfg(feature = "nightly")]
+#[cfg_accessible(std::alloc::set_alloc_error_hook)]
+impl IntoReport for crate::alloc_hooks::AllocFailure {}
+#[cfg(feature = "nightly")]
+#[cfg_accessible(core::ascii::Char)]
+impl IntoReport for crate::ascii_char::AsciiError {}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::backtrace::Backtrace;
+    use std::fmt::{self, Write};
+    use std::{error, iter};
+
+    pub(super) fn render(
+        f: &mut fmt::Formatter<'_>,
+        error: &dyn error::Error,
+        backtrace: Option<&Backtrace>,
+        pretty: bool,
+        show_backtrace: bool,
+    ) -> fmt::Result {
+        let sources = iter::successors(error.source(), |e| e.source());
+        if !pretty {
+            write!(f, "{error}")?;
+            for source in sources {
+                write!(f, ": {source}")?;
+            }
+            return Ok(());
+        }
+
+        write!(f, "{error}")?;
+        let sources: Vec<_> = sources.collect();
+        if !sources.is_empty() {
+            write!(f, "\n\nCaused by:")?;
+            for (n, source) in sources.iter().enumerate() {
+                if sources.len() == 1 {
+                    write!(f, "\n      ")?;
+                } else {
+                    write!(f, "\n{n:>4}: ")?;
+                }
+                // Later lines of a message line up with its first.
+                let message = source.to_string();
+                for (i, line) in message.lines().enumerate() {
+                    if i > 0 {
+                        f.write_str("\n      ")?;
+                    }
+                    f.write_str(line)?;
+                }
+            }
+        }
+        if let Some(backtrace) = backtrace.filter(|_| show_backtrace) {
+            let mut text = String::new();
+            write!(text, "{backtrace}")?;
+            write!(f, "\n\nStack backtrace:\n{}", text.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    /// An error with a source, as an application would wrap the crate's.
+    #[derive(Debug)]
+    struct LoadFailed {
+        file: &'static str,
+        source: IndexOutOfRange,
+    }
+
+    impl fmt::Display for LoadFailed {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "failed to load {}", self.file)
+        }
+    }
+
+    impl error::Error for LoadFailed {
+        fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+            Some(&self.source)
+        }
+    }
+
+    impl IntoReport for LoadFailed {}
+
+    fn load_failed() -> LoadFailed {
+        let v: IndexVec<u32, u8> = IndexVec::from([1, 2]);
+        LoadFailed {
+            file: "a.txt",
+            source: v.try_get(5).unwrap_err(),
+        }
+    }
+
+    const OUT_OF_RANGE: &str = "index 5 out of range for 2 elements indexed by `u32`";
+
+    #[test]
+    fn renders_source_chain() {
+        let report = Report::new(load_failed());
+        assert_eq!(
+            report.to_string(),
+            format!("failed to load a.txt: {OUT_OF_RANGE}")
+        );
+        assert_eq!(format!("{report:?}"), report.to_string());
+        assert_eq!(
+            report.pretty(true).to_string(),
+            format!("failed to load a.txt\n\nCaused by:\n      {OUT_OF_RANGE}")
+        );
+
+        let err = TruncatedPair { trailing: 3 };
+        assert_eq!(err.into_report().to_string(), err.to_string());
+    }
+
+    #[test]
+    fn shows_backtrace() {
+        let report = IndexVec::<u32, u8>::new()
+            .try_get(5)
+            .unwrap_err()
+            .into_report();
+        let backtrace = report.error().backtrace().to_string();
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "index 5 out of range for 0 elements indexed by `u32`\
+                 \n\nStack backtrace:\n{}",
+                backtrace.trim_end()
+            )
+        );
+        let report = report.show_backtrace(false);
+        assert!(!report.to_string().contains("Stack backtrace"));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        use std::iter;
+
+        /// Renders `error` with both the std `Report` and the portable renderer.
+        struct Both<'a>(&'a dyn error::Error, bool, bool);
+
+        impl fmt::Display for Both<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let &Both(error, pretty, show_backtrace) = self;
+                // std shows the first backtrace along the chain.
+                let backtrace = iter::successors(Some(error), |e| e.source())
+                    .find_map(error::request_ref::<Backtrace>);
+                portable::render(f, error, backtrace, pretty, show_backtrace)
+            }
+        }
+
+        /// A chain of three, with messages over several lines.
+        #[derive(Debug)]
+        struct Chain(&'static str, Option<Box<Chain>>);
+
+        impl fmt::Display for Chain {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+
+        impl error::Error for Chain {
+            fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+                self.1.as_deref().map(|c| c as _)
+            }
+        }
+
+        let chain = Chain(
+            "outer\nsecond line",
+            Some(Box::new(Chain(
+                "middle",
+                Some(Box::new(Chain("a\nb", None))),
+            ))),
+        );
+        let errors: [&dyn error::Error; 3] = [&load_failed(), &chain, &chain.1.as_ref().unwrap()];
+        for error in errors {
+            for (pretty, show_backtrace) in [(false, false), (true, false), (true, true)] {
+                let std = error::Report::new(error)
+                    .pretty(pretty)
+                    .show_backtrace(show_backtrace);
+                assert_eq!(
+                    Both(error, pretty, show_backtrace).to_string(),
+                    std.to_string()
+                );
+            }
+        }
+    }
+}
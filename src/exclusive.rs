@@ -0,0 +1,202 @@
+//! `sync::SyncView` (formerly `sync::Exclusive`) usage
+//!
+//! Tracking issue [98407].
+//!
+//! `SyncView<T>` is `Sync` for any `T`, because a shared `&SyncView<T>` gives no access to the
+//! `T` inside at all: only `&mut SyncView<T>`, which is unique, does (`as_mut`), or `&` when `T` is
+//! `Sync` anyway. Nothing can be used from two threads at once, so there is nothing to race on.
+//! That makes a `Send` but `!Sync` value, such as a closure over a `Cell` or a coroutine, storable
+//! in a struct that is shared between threads for its other fields, without a lock around it. The
+//! type was added as `Exclusive` and has since been renamed.
+//!
+//! [`Exclusive`] is the wrapper under its old name. [`Graph`] keeps a per-node callback next to the
+//! successor lists: threads walk the graph through `&Graph`, and its owner runs the callbacks
+//! through `&mut`. Without the `nightly` feature `Exclusive` is a local newtype with the same
+//! methods.
+//!
+//! [98407]: https://github.com/rust-lang/rust/issues/98407
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+#[cfg(feature = "nightly")]
+pub use std::sync::SyncView as Exclusive;
+
+#[cfg(not(feature = "nightly"))]
+pub use portable::Exclusive;
+
+/// A graph with a callback for each node, which is `Sync` even if the callbacks aren't.
+pub struct Graph<I: Idx, F> {
+    successors: IndexVec<I, Vec<I>>,
+    callbacks: IndexVec<I, Exclusive<F>>,
+}
+
+impl<I: Idx, F: FnMut(I)> Graph<I, F> {
+    pub fn new() -> Self {
+        Graph {
+            successors: IndexVec::new(),
+            callbacks: IndexVec::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, callback: F) -> I {
+        self.successors.push(Vec::new());
+        self.callbacks.push(Exclusive::new(callback))
+    }
+
+    pub fn add_edge(&mut self, from: I, to: I) {
+        self.successors[from].push(to);
+    }
+
+    pub fn successors(&self, node: I) -> &[I] {
+        &self.successors[node]
+    }
+
+    /// Calls the callback of each node reachable from `start` once, depth first.
+    pub fn visit(&mut self, start: I) {
+        let mut seen = vec![false; self.successors.len()];
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut seen[node.index()], true) {
+                continue;
+            }
+            (self.callbacks[node].as_mut())(node);
+            stack.extend(self.successors[node].iter().rev());
+        }
+    }
+}
+
+impl<I: Idx, F: FnMut(I)> Default for Graph<I, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    /// std's `SyncView`.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Exclusive<T: ?Sized> {
+        inner: T,
+    }
+
+    // SAFETY: `&Exclusive<T>` gives no access to `T` unless `T: Sync`.
+    unsafe impl<T: ?Sized> Sync for Exclusive<T> {}
+
+    impl<T> Exclusive<T> {
+        pub const fn new(inner: T) -> Self {
+            Exclusive { inner }
+        }
+
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<T: ?Sized> Exclusive<T> {
+        pub fn from_mut(r: &mut T) -> &mut Self {
+            // SAFETY: `Exclusive<T>` is `repr(transparent)` over `T`.
+            unsafe { &mut *(r as *mut T as *mut Self) }
+        }
+    }
+
+    impl<T: ?Sized> AsMut<T> for Exclusive<T> {
+        fn as_mut(&mut self) -> &mut T {
+            &mut self.inner
+        }
+    }
+
+    impl<T: ?Sized + Sync> AsRef<T> for Exclusive<T> {
+        fn as_ref(&self) -> &T {
+            &self.inner
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::*;
+
+    fn assert_sync<T: Sync>(_: &T) {}
+
+    #[test]
+    fn share_graph_run_callbacks() {
+        let (tx, rx) = mpsc::channel();
+        let mut graph: Graph<u32, _> = Graph::new();
+        for _ in 0..4 {
+            // Each callback owns a `Cell`, so none of them is `Sync`.
+            let calls = Cell::new(0);
+            let tx = tx.clone();
+            graph.add_node(move |node| {
+                calls.set(calls.get() + 1);
+                tx.send((node, calls.get())).unwrap();
+            });
+        }
+        graph.add_edge(0, 1);
+        graph.add_edge(0, 2);
+        graph.add_edge(2, 3);
+        graph.add_edge(3, 0);
+        assert_sync(&graph);
+
+        let degrees: Vec<usize> = thread::scope(|s| {
+            let graph = &graph;
+            let handles: Vec<_> = (0..4)
+                .map(|node| s.spawn(move || graph.successors(node).len()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(degrees, [2, 0, 1, 1]);
+
+        graph.visit(0);
+        graph.visit(2);
+        drop(graph);
+        drop(tx);
+        let calls: Vec<_> = rx.iter().collect();
+        assert_eq!(
+            calls,
+            [
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (2, 2),
+                (3, 2),
+                (0, 2),
+                (1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapper_access() {
+        let mut value = Exclusive::new(RefCell::new(vec![1]));
+        assert_sync(&value);
+        value.as_mut().get_mut().push(2);
+        let mut plain = 5;
+        *Exclusive::from_mut(&mut plain).as_mut() += 1;
+        assert_eq!(plain, 6);
+        let shared = Exclusive::new(3u8);
+        assert_eq!(*shared.as_ref(), 3);
+        assert_eq!(value.into_inner().into_inner(), [1, 2]);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut std = Exclusive::new(vec![1, 2]);
+        let mut portable = portable::Exclusive::new(vec![1, 2]);
+        std.as_mut().push(3);
+        portable.as_mut().push(3);
+        assert_eq!(std.as_ref(), portable.as_ref());
+        let (mut a, mut b) = (1, 1);
+        *Exclusive::from_mut(&mut a).as_mut() += 1;
+        *portable::Exclusive::from_mut(&mut b).as_mut() += 1;
+        assert_eq!(a, b);
+        assert_eq!(std.into_inner(), portable.into_inner());
+    }
+}
@@ -0,0 +1,188 @@
+//! `ExitStatus::exit_ok` usage
+//!
+//! Tracking issue [84908].
+//!
+//! `ExitStatus::exit_ok()` turns an unsuccessful exit into an `Err(ExitStatusError)`, so that `?`
+//! can pass it on like any other error instead of every caller checking `status.success()` and
+//! making up an error of its own. The error keeps the exit code, or on Unix the signal that killed
+//! the process, and prints as "process exited unsuccessfully: ...".
+//!
+//! [`run_checked`] runs a command to completion and returns its output if it succeeded, and a
+//! [`RunError`] if it could not be started or exited unsuccessfully. The [`ExitFailure`] in the
+//! latter keeps the output too, since the child's stderr usually says what went wrong. Without the
+//! `nightly` feature the status is checked by hand, with the same message.
+//!
+//! [84908]: https://github.com/rust-lang/rust/issues/84908
+
+use std::num::NonZero;
+use std::process::{Command, ExitStatus, Output};
+use std::{error, fmt, io};
+
+/// A process that exited unsuccessfully, with what it printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitFailure {
+    #[cfg(feature = "nightly")]
+    error: std::process::ExitStatusError,
+    pub output: Output,
+}
+
+impl ExitFailure {
+    pub fn status(&self) -> ExitStatus {
+        self.output.status
+    }
+
+    /// The exit code, or `None` if the process was killed by a signal.
+    pub fn code(&self) -> Option<NonZero<i32>> {
+        #[cfg(feature = "nightly")]
+        return self.error.code_nonzero();
+        #[cfg(not(feature = "nightly"))]
+        self.status().code().and_then(NonZero::new)
+    }
+
+    /// The signal that killed the process, if one did.
+    #[cfg(unix)]
+    pub fn signal(&self) -> Option<i32> {
+        use std::os::unix::process::ExitStatusExt;
+
+        self.status().signal()
+    }
+}
+
+impl fmt::Display for ExitFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "nightly")]
+        return self.error.fmt(f);
+        #[cfg(not(feature = "nightly"))]
+        write!(f, "process exited unsuccessfully: {}", self.status())
+    }
+}
+
+impl error::Error for ExitFailure {}
+
+/// The error returned by [`run_checked`].
+#[derive(Debug)]
+pub enum RunError {
+    /// The command could not be started, or its output read.
+    Io(io::Error),
+    Failed(ExitFailure),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Io(e) => write!(f, "failed to run process: {e}"),
+            RunError::Failed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for RunError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RunError::Io(e) => Some(e),
+            RunError::Failed(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(e: io::Error) -> Self {
+        RunError::Io(e)
+    }
+}
+
+impl From<ExitFailure> for RunError {
+    fn from(e: ExitFailure) -> Self {
+        RunError::Failed(e)
+    }
+}
+
+/// `output` if its process exited successfully.
+#[cfg(feature = "nightly")]
+pub fn check_output(output: Output) -> Result<Output, ExitFailure> {
+    match output.status.exit_ok() {
+        Ok(()) => Ok(output),
+        Err(error) => Err(ExitFailure { error, output }),
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn check_output(output: Output) -> Result<Output, ExitFailure> {
+    portable::check_output(output).map_err(|output| ExitFailure { output })
+}
+
+/// Runs `command` to completion, collecting its output, and fails unless it exits successfully.
+pub fn run_checked(command: &mut Command) -> Result<Output, RunError> {
+    Ok(check_output(command.output()?)?)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::process::Output;
+
+    /// `output`, as `Ok` if its process exited successfully.
+    pub(super) fn check_output(output: Output) -> Result<Output, Output> {
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(output)
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Result<Output, RunError> {
+        run_checked(Command::new("sh").args(["-c", script]))
+    }
+
+    #[test]
+    fn exit_codes() {
+        let output = sh("echo fine").unwrap();
+        assert_eq!(output.stdout, b"fine\n");
+
+        let Err(RunError::Failed(failure)) = sh("echo oops >&2; exit 3") else {
+            panic!("expected a failure");
+        };
+        assert_eq!(failure.code(), NonZero::new(3));
+        assert_eq!(failure.signal(), None);
+        assert_eq!(failure.output.stderr, b"oops\n");
+        assert_eq!(
+            failure.to_string(),
+            "process exited unsuccessfully: exit status: 3"
+        );
+
+        let Err(RunError::Failed(failure)) = sh("kill -9 $$") else {
+            panic!("expected a failure");
+        };
+        assert_eq!((failure.code(), failure.signal()), (None, Some(9)));
+        assert_eq!(
+            failure.to_string(),
+            "process exited unsuccessfully: signal: 9 (SIGKILL)"
+        );
+    }
+
+    #[test]
+    fn spawn_errors() {
+        let err = run_checked(&mut Command::new("/nonexistent/program")).unwrap_err();
+        let RunError::Io(e) = &err else {
+            panic!("expected an I/O error, got {err}");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(error::Error::source(&err).is_some());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for script in ["true", "exit 1", "kill -9 $$"] {
+            let output = Command::new("sh").args(["-c", script]).output().unwrap();
+            let from_std = check_output(output.clone()).map_err(|e| e.to_string());
+            let portable = portable::check_output(output)
+                .map_err(|o| format!("process exited unsuccessfully: {}", o.status));
+            assert_eq!(from_std, portable, "{script}");
+        }
+    }
+}
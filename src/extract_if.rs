@@ -0,0 +1,235 @@
+//! `Vec::extract_if` usage
+//!
+//! Tracking issue [43244].
+//!
+//! `vec.extract_if(range, pred)` removes the elements of `range` for which `pred` returns true and
+//! yields them, shifting the kept ones down so the vector stays contiguous, all in one pass.
+//!
+//! [`IndexVec::extract_if`] mirrors it but also yields each removed element's original index.
+//! That needs the index at the point the element is taken out, which the std iterator doesn't
+//! expose, so the adapter is reimplemented here with the same shifting scheme.
+//!
+//! [43244]: https://github.com/rust-lang/rust/issues/43244
+
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::{ptr, slice};
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Creates an iterator which uses a closure to determine if an element in `range` should be
+    /// removed, and yields the removed elements with their original indices.
+    ///
+    /// If the iterator is dropped before it's exhausted, the remaining elements are kept. If it
+    /// is leaked, e.g. with `mem::forget`, the vector is left truncated to `range.start`, and the
+    /// rest of its elements are leaked but never dropped twice.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `range` is out of bounds.
+    pub fn extract_if<F>(&mut self, range: Range<I>, pred: F) -> ExtractIf<'_, I, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let old_len = self.raw.len();
+        let (start, end) = (range.start.index(), range.end.index());
+        assert!(
+            start <= end,
+            "slice index starts at {start} but ends at {end}"
+        );
+        assert!(
+            end <= old_len,
+            "range end index {end} out of range for length {old_len}"
+        );
+        // Only the elements from `start` on are moved, so only those are hidden from `self` while
+        // the iterator is alive.
+        // SAFETY: The first `start` elements are initialized and stay untouched.
+        unsafe { self.raw.set_len(start) };
+        ExtractIf {
+            vec: &mut self.raw,
+            idx: start,
+            del: 0,
+            end,
+            old_len,
+            pred,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator returned by [`IndexVec::extract_if`].
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ExtractIf<'a, I: Idx, T, F> {
+    vec: &'a mut Vec<T>,
+    /// The index of the next element to inspect.
+    idx: usize,
+    /// The number of elements removed so far. Kept elements are moved down by this much.
+    del: usize,
+    /// The end of the range being inspected.
+    end: usize,
+    /// The length of `vec` before `extract_if` was called.
+    old_len: usize,
+    pred: F,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T, F> Iterator for ExtractIf<'_, I, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = (I, T);
+
+    fn next(&mut self) -> Option<(I, T)> {
+        while self.idx < self.end {
+            let i = self.idx;
+            // SAFETY: All `old_len` elements are initialized except the `del` ones taken out
+            // before `idx`, and `i` is not one of those.
+            let v = unsafe { slice::from_raw_parts_mut(self.vec.as_mut_ptr(), self.old_len) };
+            let drained = (self.pred)(&mut v[i]);
+            // Update the index *after* the predicate is called, so if it panics the element at
+            // `i` is still counted as present and gets shifted back in `drop`.
+            self.idx += 1;
+            if drained {
+                self.del += 1;
+                // SAFETY: `v[i]` is initialized and is never read again, since `idx` moved past it
+                // and the next kept element or the tail overwrites it.
+                return Some((I::new(i), unsafe { ptr::read(&v[i]) }));
+            } else if self.del > 0 {
+                // SAFETY: `i - del` is a hole left by an earlier removal.
+                unsafe { ptr::copy_nonoverlapping(&v[i], &mut v[i - self.del], 1) };
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.end - self.idx))
+    }
+}
+
+impl<I: Idx, T, F> Drop for ExtractIf<'_, I, T, F> {
+    fn drop(&mut self) {
+        // SAFETY: `idx..old_len` are the uninspected elements, which are moved down over the
+        // `del` holes before them. After that `0..old_len - del` are all initialized.
+        unsafe {
+            if self.idx < self.old_len && self.del > 0 {
+                let ptr = self.vec.as_mut_ptr();
+                let src = ptr.add(self.idx);
+                src.copy_to(src.sub(self.del), self.old_len - self.idx);
+            }
+            self.vec.set_len(self.old_len - self.del);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    #[derive(Debug)]
+    struct Tracked(u32, Rc<Cell<usize>>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    fn tracked(n: u32, drops: &Rc<Cell<usize>>) -> IndexVec<u32, Tracked> {
+        (0..n).map(|i| Tracked(i, drops.clone())).collect()
+    }
+
+    #[test]
+    fn extract_with_indices() {
+        let mut v: IndexVec<u32, u32> = IndexVec::from([1, 2, 3, 4, 5, 6]);
+        let evens: Vec<_> = v.extract_if(0..6, |x| *x % 2 == 0).collect();
+        assert_eq!(evens, [(1, 2), (3, 4), (5, 6)]);
+        assert_eq!(v.raw, [1, 3, 5]);
+
+        let mut v: IndexVec<u32, u32> = IndexVec::from([1, 2, 3, 4, 5, 6]);
+        let inner: Vec<_> = v.extract_if(1..5, |x| *x % 2 == 0).collect();
+        assert_eq!(inner, [(1, 2), (3, 4)]);
+        assert_eq!(v.raw, [1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn early_drop_keeps_rest() {
+        let mut v: IndexVec<usize, i32> = IndexVec::from([0, 1, 2, 3, 4]);
+        let mut it = v.extract_if(0..5, |_| true);
+        assert_eq!(it.next(), Some((0, 0)));
+        assert_eq!(it.next(), Some((1, 1)));
+        drop(it);
+        assert_eq!(v.raw, [2, 3, 4]);
+    }
+
+    #[test]
+    fn panic_mid_drain() {
+        let drops = Rc::new(Cell::new(0));
+        let mut v = tracked(8, &drops);
+        let mut calls = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            for (_, t) in v.extract_if(0..8, |t| {
+                calls += 1;
+                assert!(calls != 5, "predicate panicked");
+                t.0 % 2 == 0
+            }) {
+                drop(t);
+            }
+        }));
+        assert!(result.is_err());
+        // 0 and 2 were extracted and dropped; 4, which the predicate panicked on, is kept.
+        assert_eq!(drops.get(), 2);
+        let left: Vec<_> = v.iter().map(|t| t.0).collect();
+        assert_eq!(left, [1, 3, 4, 5, 6, 7]);
+        drop(v);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn leak_is_sound() {
+        let drops = Rc::new(Cell::new(0));
+        let mut v = tracked(6, &drops);
+        let mut it = v.extract_if(2..6, |_| true);
+        drop(it.next());
+        std::mem::forget(it);
+        // Everything from `range.start` on is leaked, nothing is dropped twice.
+        assert_eq!(v.len(), 2);
+        drop(v);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let mut v: IndexVec<usize, i32> = IndexVec::from([0, 1]);
+        let _ = v.extract_if(0..3, |_| true);
+    }
+
+    #[test]
+    fn agrees_with_vec() {
+        let preds: [fn(&mut u32) -> bool; 4] = [|_| true, |_| false, |x| *x % 3 == 0, |x| *x > 4];
+        for pred in preds {
+            for start in 0..8 {
+                for end in start..8 {
+                    let mut std: Vec<u32> = (0..8).collect();
+                    let mut ours: IndexVec<usize, u32> = std.iter().copied().collect();
+                    let std_out: Vec<_> = std.extract_if(start..end, pred).collect();
+                    let ours_out: Vec<_> = ours.extract_if(start..end, pred).collect();
+                    assert_eq!(
+                        std_out,
+                        ours_out.iter().map(|&(_, x)| x).collect::<Vec<_>>()
+                    );
+                    assert!(ours_out.iter().all(|&(i, x)| i == x as usize));
+                    assert_eq!(std, ours.raw);
+                }
+            }
+        }
+    }
+}
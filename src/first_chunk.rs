@@ -0,0 +1,126 @@
+//! `<[T]>::{first_chunk, split_first_chunk, split_last_chunk}` usage
+//!
+//! Tracking issue [111774].
+//!
+//! `slice.split_first_chunk::<N>()` splits off the first `N` elements as a `&[T; N]`, or returns
+//! `None` if there are fewer, and `split_last_chunk` does the same at the back. The array
+//! reference can go straight into `u32::from_le_bytes` and the like, where a sub-slice would need a
+//! `try_into().unwrap()` first. These replaced the unstable `split_array_ref` family.
+//!
+//! The readers here decode fixed-size fields from the front or back of a byte stream into plain
+//! integers and typed indices, and report a short stream as a [`Truncated`] error instead of
+//! `None`.
+//!
+//! [111774]: https://github.com/rust-lang/rust/issues/111774
+
+use core::{error, fmt};
+
+use crate::step::Idx;
+
+/// The error returned when a stream ends before a field does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truncated {
+    /// The size of the field, in bytes.
+    pub needed: usize,
+    /// The bytes that were left.
+    pub available: usize,
+}
+
+impl fmt::Display for Truncated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stream truncated: need {} bytes, {} left",
+            self.needed, self.available
+        )
+    }
+}
+
+impl error::Error for Truncated {}
+
+/// Consumes the first `N` bytes of `input`.
+#[inline]
+pub fn read_array<'a, const N: usize>(input: &mut &'a [u8]) -> Result<&'a [u8; N], Truncated> {
+    let (head, rest) = input.split_first_chunk().ok_or(Truncated {
+        needed: N,
+        available: input.len(),
+    })?;
+    *input = rest;
+    Ok(head)
+}
+
+/// Consumes the last `N` bytes of `input`.
+#[inline]
+pub fn read_array_back<'a, const N: usize>(input: &mut &'a [u8]) -> Result<&'a [u8; N], Truncated> {
+    let (rest, tail) = input.split_last_chunk().ok_or(Truncated {
+        needed: N,
+        available: input.len(),
+    })?;
+    *input = rest;
+    Ok(tail)
+}
+
+/// Consumes a little-endian `u32` from the front of `input`.
+#[inline]
+pub fn read_u32_le(input: &mut &[u8]) -> Result<u32, Truncated> {
+    read_array(input).map(|bytes| u32::from_le_bytes(*bytes))
+}
+
+/// Consumes `N` indices, each stored as a little-endian `u32`, from the front of `input`. If
+/// `input` is too short, nothing is consumed.
+pub fn read_indices<I: Idx, const N: usize>(input: &mut &[u8]) -> Result<[I; N], Truncated> {
+    let needed = N * 4;
+    if input.len() < needed {
+        return Err(Truncated {
+            needed,
+            available: input.len(),
+        });
+    }
+    Ok(core::array::from_fn(|_| {
+        I::new(read_u32_le(input).unwrap() as usize)
+    }))
+}
+
+/// Returns whether `input` starts with the magic bytes `magic`, without consuming anything.
+#[inline]
+pub fn has_magic<const N: usize>(input: &[u8], magic: &[u8; N]) -> bool {
+    input.first_chunk() == Some(magic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_header() {
+        let bytes = [b'I', b'X', 3, 0, 0, 0, 9, 0, 0, 0, 0xaa, 0xbb, 0xcc, 0xdd];
+        assert!(has_magic(&bytes, b"IX"));
+        assert!(!has_magic(&bytes, b"IXY!"));
+
+        let mut input = &bytes[..];
+        assert_eq!(read_array(&mut input), Ok(b"IX"));
+        let [start, end]: [u32; 2] = read_indices(&mut input).unwrap();
+        assert_eq!((start, end), (3, 9));
+        assert_eq!(read_array_back(&mut input), Ok(&[0xaa, 0xbb, 0xcc, 0xdd]));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut input: &[u8] = &[1, 0, 0, 0, 2, 0];
+        let err = read_indices::<usize, 2>(&mut input).unwrap_err();
+        assert_eq!(
+            err,
+            Truncated {
+                needed: 8,
+                available: 6
+            }
+        );
+        assert_eq!(err.to_string(), "stream truncated: need 8 bytes, 6 left");
+        // Nothing was consumed, so the first index can still be read.
+        assert_eq!(read_u32_le(&mut input), Ok(1));
+        assert_eq!(read_u32_le(&mut input).unwrap_err().available, 2);
+        assert_eq!(read_array_back::<3>(&mut input).unwrap_err().needed, 3);
+        assert_eq!(input, [2, 0]);
+    }
+}
@@ -0,0 +1,224 @@
+//! `next_up`/`next_down` and ULP utilities
+//!
+//! Tracking issue [91399].
+//!
+//! `next_up`/`next_down` return the closest representable float above/below the input. Under the
+//! `nightly` feature they forward to std, otherwise they are implemented by stepping the bit
+//! pattern: for non-negative floats the bits increase with the value, for negative floats they
+//! decrease.
+//!
+//! The same ordering gives [`ulp_distance`], the number of representable floats between two values,
+//! and with it [`almost_equal_ulps`], a tolerance that scales with the magnitude of the inputs.
+//!
+//! [91399]: https://github.com/rust-lang/rust/issues/91399
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive floats accepted by the free functions in this module.
+pub trait Float: Copy + sealed::Sealed {
+    fn next_up(self) -> Self;
+
+    fn next_down(self) -> Self;
+
+    /// Position of `self` on the number line of representable values, with `±0.0` at zero.
+    ///
+    /// `None` for NaN.
+    fn ulp_key(self) -> Option<i128>;
+}
+
+/// Returns the least number greater than `x`.
+#[inline]
+pub fn next_up<T: Float>(x: T) -> T {
+    x.next_up()
+}
+
+/// Returns the greatest number less than `x`.
+#[inline]
+pub fn next_down<T: Float>(x: T) -> T {
+    x.next_down()
+}
+
+/// Returns how many times `next_up` takes the smaller of `a` and `b` to the larger one, or `None`
+/// if either is NaN.
+///
+/// `0.0` and `-0.0` are zero ULPs apart.
+#[inline]
+pub fn ulp_distance<T: Float>(a: T, b: T) -> Option<u128> {
+    Some(a.ulp_key()?.abs_diff(b.ulp_key()?))
+}
+
+/// Returns whether `a` and `b` are at most `max_ulps` representable values apart. NaN is never
+/// equal to anything.
+#[inline]
+pub fn almost_equal_ulps<T: Float>(a: T, b: T, max_ulps: u128) -> bool {
+    ulp_distance(a, b).is_some_and(|d| d <= max_ulps)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    macro_rules! next_up_down {
+        ($next_up:ident, $next_down:ident, $f:ident, $sign_mask:expr) => {
+            pub(super) fn $next_up(x: $f) -> $f {
+                let bits = x.to_bits();
+                if x.is_nan() || bits == $f::INFINITY.to_bits() {
+                    return x;
+                }
+                let abs = bits & !$sign_mask;
+                let next_bits = if abs == 0 {
+                    // Smallest positive subnormal, from either zero.
+                    1
+                } else if bits == abs {
+                    bits + 1
+                } else {
+                    bits - 1
+                };
+                $f::from_bits(next_bits)
+            }
+
+            pub(super) fn $next_down(x: $f) -> $f {
+                let bits = x.to_bits();
+                if x.is_nan() || bits == $f::NEG_INFINITY.to_bits() {
+                    return x;
+                }
+                let abs = bits & !$sign_mask;
+                let next_bits = if abs == 0 {
+                    // Smallest negative subnormal, from either zero.
+                    $sign_mask | 1
+                } else if bits == abs {
+                    bits - 1
+                } else {
+                    bits + 1
+                };
+                $f::from_bits(next_bits)
+            }
+        };
+    }
+
+    next_up_down!(next_up_f32, next_down_f32, f32, 0x8000_0000u32);
+    next_up_down!(next_up_f64, next_down_f64, f64, 0x8000_0000_0000_0000u64);
+}
+
+macro_rules! impl_float {
+    ($f:ident, $next_up:ident, $next_down:ident) => {
+        impl sealed::Sealed for $f {}
+
+        impl Float for $f {
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn next_up(self) -> Self {
+                <$f>::next_up(self)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn next_up(self) -> Self {
+                portable::$next_up(self)
+            }
+
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn next_down(self) -> Self {
+                <$f>::next_down(self)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn next_down(self) -> Self {
+                portable::$next_down(self)
+            }
+
+            #[inline]
+            fn ulp_key(self) -> Option<i128> {
+                if self.is_nan() {
+                    return None;
+                }
+                // Sign-magnitude to two's complement, so that `-0.0` and `0.0` share a key.
+                let magnitude = self.abs().to_bits() as i128;
+                Some(if self.is_sign_negative() {
+                    -magnitude
+                } else {
+                    magnitude
+                })
+            }
+        }
+    };
+}
+
+impl_float!(f32, next_up_f32, next_down_f32);
+impl_float!(f64, next_up_f64, next_down_f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const F64_SAMPLES: &[f64] = &[
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        0.1,
+        f64::MIN_POSITIVE,
+        -f64::MIN_POSITIVE,
+        f64::from_bits(1),
+        -f64::from_bits(1),
+        f64::MAX,
+        f64::MIN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+    ];
+
+    #[test]
+    fn steps() {
+        assert_eq!(next_up(1.0f64), 1.0 + f64::EPSILON);
+        assert_eq!(next_down(1.0f32), 1.0 - f32::EPSILON / 2.0);
+        assert_eq!(next_up(-0.0f64), f64::from_bits(1));
+        assert_eq!(next_down(0.0f64), -f64::from_bits(1));
+        assert_eq!(next_up(f64::MAX), f64::INFINITY);
+        assert_eq!(next_up(f64::INFINITY), f64::INFINITY);
+        assert_eq!(next_down(f32::NEG_INFINITY), f32::NEG_INFINITY);
+        assert!(next_up(f32::NAN).is_nan());
+    }
+
+    #[test]
+    fn portable_agrees_with_std() {
+        for &x in F64_SAMPLES {
+            assert_eq!(portable::next_up_f64(x).to_bits(), x.next_up().to_bits());
+            assert_eq!(
+                portable::next_down_f64(x).to_bits(),
+                x.next_down().to_bits()
+            );
+            let x = x as f32;
+            assert_eq!(portable::next_up_f32(x).to_bits(), x.next_up().to_bits());
+            assert_eq!(
+                portable::next_down_f32(x).to_bits(),
+                x.next_down().to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(ulp_distance(1.0f64, 1.0), Some(0));
+        assert_eq!(ulp_distance(0.0f64, -0.0), Some(0));
+        assert_eq!(ulp_distance(1.0f64, next_up(1.0)), Some(1));
+        assert_eq!(ulp_distance(next_down(0.0f32), next_up(0.0)), Some(2));
+        assert_eq!(ulp_distance(f32::INFINITY, f32::MAX), Some(1));
+        assert_eq!(ulp_distance(f64::NAN, 1.0), None);
+        assert_eq!(
+            ulp_distance(f64::MIN, f64::MAX),
+            Some(2 * f64::MAX.to_bits() as u128)
+        );
+    }
+
+    #[test]
+    fn almost_equal() {
+        let sum: f64 = (0..10).map(|_| 0.1).sum();
+        assert_ne!(sum, 1.0);
+        assert!(almost_equal_ulps(sum, 1.0, 4));
+        assert!(!almost_equal_ulps(1.0f32, 1.001, 4));
+        assert!(!almost_equal_ulps(f64::NAN, f64::NAN, u128::MAX));
+    }
+}
@@ -0,0 +1,164 @@
+//! `f16` and `f128` primitive types usage
+//!
+//! Tracking issue [116909].
+//!
+//! `f16` is IEEE 754 binary16 (1 sign bit, 5 exponent bits, 10 mantissa bits) and `f128` is
+//! binary128 (1 sign bit, 15 exponent bits, 112 mantissa bits). Widening `f16 -> f32 -> f64 -> f128`
+//! is always exact, narrowing rounds to nearest with ties to even.
+//!
+//! [`f16_to_f32_soft`] decodes `f16` bits by hand and is checked against the compiler's conversion,
+//! which is either a hardware instruction or a `compiler-builtins` routine depending on the target.
+//!
+//! [116909]: https://github.com/rust-lang/rust/issues/116909
+
+/// Widens an `f16` to `f32`. Lossless.
+#[inline]
+pub fn f16_to_f32(v: f16) -> f32 {
+    v as f32
+}
+
+/// Narrows an `f32` to `f16`, rounding to nearest. Values out of range become infinite.
+#[inline]
+pub fn f32_to_f16(v: f32) -> f16 {
+    v as f16
+}
+
+/// Widens an `f64` to `f128`. Lossless.
+#[inline]
+pub fn f64_to_f128(v: f64) -> f128 {
+    v as f128
+}
+
+/// Narrows an `f128` to `f64`, rounding to nearest. Values out of range become infinite.
+#[inline]
+pub fn f128_to_f64(v: f128) -> f64 {
+    v as f64
+}
+
+/// Decodes the bits of an `f16` into an `f32` without using the `f16` type.
+pub const fn f16_to_f32_soft(bits: u16) -> f32 {
+    const F16_EXP_BIAS: i32 = 15;
+    const F32_EXP_BIAS: i32 = 127;
+
+    let sign = ((bits as u32) & 0x8000) << 16;
+    let exp = ((bits >> 10) & 0x1F) as i32;
+    let man = (bits & 0x03FF) as u32;
+
+    let magnitude = match (exp, man) {
+        (0, 0) => 0,
+        // Subnormal: value is `man * 2^-24`, renormalize so the leading one becomes implicit.
+        (0, _) => {
+            let shift = man.leading_zeros() - (32 - 11);
+            let man = (man << shift) & 0x03FF;
+            let exp = 1 - shift as i32 - F16_EXP_BIAS + F32_EXP_BIAS;
+            ((exp as u32) << 23) | (man << 13)
+        }
+        // Infinity and NaN keep their payload.
+        (0x1F, _) => 0x7F80_0000 | (man << 13),
+        _ => (((exp - F16_EXP_BIAS + F32_EXP_BIAS) as u32) << 23) | (man << 13),
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(f16 bits, decoded value)` pairs covering each class of encoding.
+    const F16_TABLE: &[(u16, f32)] = &[
+        (0x0000, 0.0),
+        (0x8000, -0.0),
+        (0x0001, 5.960_464_5e-8), // smallest subnormal, 2^-24
+        (0x03FF, 6.097_555e-5),   // largest subnormal
+        (0x0400, 6.103_515_6e-5), // smallest normal, 2^-14
+        (0x3555, 0.333_251_95),
+        (0x3BFF, 0.999_511_7),
+        (0x3C00, 1.0),
+        (0x3C01, 1.000_976_6),
+        (0xC000, -2.0),
+        (0x7BFF, 65504.0), // largest finite
+        (0x7C00, f32::INFINITY),
+        (0xFC00, f32::NEG_INFINITY),
+    ];
+
+    #[test]
+    fn soft_decoding_table() {
+        for &(bits, expected) in F16_TABLE {
+            let soft = f16_to_f32_soft(bits);
+            assert_eq!(soft.to_bits(), expected.to_bits(), "{bits:#06x}");
+            assert_eq!(f16_to_f32(f16::from_bits(bits)).to_bits(), soft.to_bits());
+        }
+    }
+
+    #[test]
+    fn soft_decoding_agrees_for_all_bits() {
+        for bits in 0..=u16::MAX {
+            let soft = f16_to_f32_soft(bits);
+            let hard = f16_to_f32(f16::from_bits(bits));
+            if hard.is_nan() {
+                assert!(soft.is_nan(), "{bits:#06x}");
+            } else {
+                assert_eq!(soft.to_bits(), hard.to_bits(), "{bits:#06x}");
+            }
+        }
+    }
+
+    #[test]
+    fn f16_bits_round_trip() {
+        for bits in 0..=u16::MAX {
+            let v = f16::from_bits(bits);
+            assert_eq!(v.to_bits(), bits);
+            if !v.is_nan() {
+                assert_eq!(f32_to_f16(f16_to_f32(v)).to_bits(), bits);
+            }
+        }
+    }
+
+    #[test]
+    fn f16_narrowing() {
+        assert_eq!(f32_to_f16(1.0).to_bits(), 0x3C00);
+        assert_eq!(f32_to_f16(65504.0).to_bits(), 0x7BFF);
+        // Halfway between 65504 and the next (unrepresentable) step rounds up to infinity.
+        assert_eq!(f32_to_f16(65520.0), f16::INFINITY);
+        // `1 + 2^-11` is a tie between `1.0` and `1 + 2^-10`, and rounds to the even `1.0`.
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)).to_bits(), 0x3C00);
+        assert!(f32_to_f16(f32::NAN).is_nan());
+    }
+
+    #[test]
+    fn f128_bits() {
+        assert_eq!(1.0f128.to_bits(), 0x3FFF_u128 << 112);
+        assert_eq!((-2.0f128).to_bits(), 0xC000_u128 << 112);
+        assert_eq!(f128::INFINITY.to_bits(), 0x7FFF_u128 << 112);
+        assert_eq!(f128::from_bits(0x3FFF_u128 << 112), 1.0);
+    }
+
+    #[test]
+    fn f128_round_trip() {
+        for v in [
+            0.0,
+            -0.0,
+            1.0,
+            0.1,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::INFINITY,
+        ] {
+            let wide = f64_to_f128(v);
+            assert_eq!(f128::from_bits(wide.to_bits()), wide);
+            assert_eq!(f128_to_f64(wide).to_bits(), v.to_bits());
+        }
+        // `f128` has more precision than `f64`: `1 + 2^-60` survives there but not in `f64`.
+        let fine = 1.0f128 + f64_to_f128(2f64.powi(-60));
+        assert!(fine > 1.0);
+        assert_eq!(f128_to_f64(fine), 1.0);
+    }
+
+    #[test]
+    fn f16_to_f128() {
+        for &(bits, expected) in F16_TABLE {
+            let wide = f16::from_bits(bits) as f128;
+            assert_eq!(f128_to_f64(wide), expected as f64);
+        }
+    }
+}
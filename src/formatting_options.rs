@@ -0,0 +1,263 @@
+//! `fmt::FormattingOptions` usage
+//!
+//! Tracking issue [118117].
+//!
+//! A `Formatter` could only be made by the formatting macros, from the flags in a format string,
+//! so code that picks the width or fill at run time had to choose between a handful of format
+//! strings, and couldn't choose the fill at all. `FormattingOptions` holds the flags as a value,
+//! and `FormattingOptions::create_formatter` builds a `Formatter` writing to any `fmt::Write`
+//! with them, to call a `Debug` or `Display` implementation on directly.
+//!
+//! [`RenderOptions`] are the flags as plain fields, and [`write_debug`] and [`write_display`]
+//! render a value with them into any sink, so that the `Debug` output of the crate's containers
+//! can go into a log line or a buffer with the width and fill its caller picked. Without the
+//! `nightly` feature the options are turned back into a format string with runtime width and
+//! precision. A fill other than a space can't be, so then the whole output is padded at once,
+//! rather than each value the implementation pads on its own, and even if it ignores the width,
+//! as `Debug` for `str` does.
+//!
+//! [118117]: https://github.com/rust-lang/rust/issues/118117
+
+use std::fmt::{self, Alignment};
+
+/// The flags of a format string such as `{:*^12.3?}`, as values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The `#` flag, which pretty-prints `Debug` output.
+    pub alternate: bool,
+    pub fill: char,
+    pub align: Option<Alignment>,
+    pub width: Option<u16>,
+    pub precision: Option<u16>,
+}
+
+impl RenderOptions {
+    /// The options of `{}`.
+    pub const fn new() -> Self {
+        RenderOptions {
+            alternate: false,
+            fill: ' ',
+            align: None,
+            width: None,
+            precision: None,
+        }
+    }
+
+    pub const fn alternate(mut self, alternate: bool) -> Self {
+        self.alternate = alternate;
+        self
+    }
+
+    pub const fn fill(mut self, fill: char) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    pub const fn align(mut self, align: Option<Alignment>) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub const fn width(mut self, width: Option<u16>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub const fn precision(mut self, precision: Option<u16>) -> Self {
+        self.precision = precision;
+        self
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions::new()
+    }
+}
+
+/// Writes `value` to `sink` as `{:?}` with `options` would.
+pub fn write_debug<T: fmt::Debug + ?Sized>(
+    sink: &mut dyn fmt::Write,
+    value: &T,
+    options: &RenderOptions,
+) -> fmt::Result {
+    render(sink, options, &|f| value.fmt(f))
+}
+
+/// Writes `value` to `sink` as `{}` with `options` would.
+pub fn write_display<T: fmt::Display + ?Sized>(
+    sink: &mut dyn fmt::Write,
+    value: &T,
+    options: &RenderOptions,
+) -> fmt::Result {
+    render(sink, options, &|f| value.fmt(f))
+}
+
+/// `value` as `{:?}` with `options` would format it.
+pub fn debug_string<T: fmt::Debug + ?Sized>(value: &T, options: &RenderOptions) -> String {
+    let mut out = String::new();
+    write_debug(&mut out, value, options).expect("a `Debug` implementation returned an error");
+    out
+}
+
+type Render<'a> = dyn Fn(&mut fmt::Formatter<'_>) -> fmt::Result + 'a;
+
+#[cfg(feature = "nightly")]
+fn render(sink: &mut dyn fmt::Write, options: &RenderOptions, f: &Render<'_>) -> fmt::Result {
+    let mut std_options = fmt::FormattingOptions::new();
+    std_options
+        .alternate(options.alternate)
+        .fill(options.fill)
+        .align(options.align)
+        .width(options.width)
+        .precision(options.precision);
+    f(&mut std_options.create_formatter(sink))
+}
+
+#[cfg(not(feature = "nightly"))]
+fn render(sink: &mut dyn fmt::Write, options: &RenderOptions, f: &Render<'_>) -> fmt::Result {
+    portable::render(sink, options, f)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::fmt::{self, Alignment};
+
+    use super::{Render, RenderOptions};
+
+    pub(super) fn render(
+        sink: &mut dyn fmt::Write,
+        options: &RenderOptions,
+        f: &Render<'_>,
+    ) -> fmt::Result {
+        if options.fill != ' ' && options.width.is_some() {
+            return pad(sink, options, f);
+        }
+        let value = fmt::from_fn(f);
+        // A width of zero pads nothing, like no width.
+        let w = usize::from(options.width.unwrap_or(0));
+        use Alignment::{Center, Left, Right};
+        match (
+            options.alternate,
+            options.align,
+            options.precision.map(usize::from),
+        ) {
+            (false, None, None) => write!(sink, "{value:w$}"),
+            (false, None, Some(p)) => write!(sink, "{value:w$.p$}"),
+            (false, Some(Left), None) => write!(sink, "{value:<w$}"),
+            (false, Some(Left), Some(p)) => write!(sink, "{value:<w$.p$}"),
+            (false, Some(Right), None) => write!(sink, "{value:>w$}"),
+            (false, Some(Right), Some(p)) => write!(sink, "{value:>w$.p$}"),
+            (false, Some(Center), None) => write!(sink, "{value:^w$}"),
+            (false, Some(Center), Some(p)) => write!(sink, "{value:^w$.p$}"),
+            (true, None, None) => write!(sink, "{value:#w$}"),
+            (true, None, Some(p)) => write!(sink, "{value:#w$.p$}"),
+            (true, Some(Left), None) => write!(sink, "{value:<#w$}"),
+            (true, Some(Left), Some(p)) => write!(sink, "{value:<#w$.p$}"),
+            (true, Some(Right), None) => write!(sink, "{value:>#w$}"),
+            (true, Some(Right), Some(p)) => write!(sink, "{value:>#w$.p$}"),
+            (true, Some(Center), None) => write!(sink, "{value:^#w$}"),
+            (true, Some(Center), Some(p)) => write!(sink, "{value:^#w$.p$}"),
+        }
+    }
+
+    /// Renders without a width, then pads the whole output with the fill, on the right unless
+    /// aligned otherwise.
+    fn pad(sink: &mut dyn fmt::Write, options: &RenderOptions, f: &Render<'_>) -> fmt::Result {
+        let mut out = String::new();
+        render(&mut out, &options.fill(' ').width(None), f)?;
+        let len = out.chars().count();
+        let missing = usize::from(options.width.unwrap_or(0)).saturating_sub(len);
+        let (before, after) = match options.align {
+            None | Some(Alignment::Left) => (0, missing),
+            Some(Alignment::Right) => (missing, 0),
+            Some(Alignment::Center) => (missing / 2, missing - missing / 2),
+        };
+        let fill =
+            |sink: &mut dyn fmt::Write, n| (0..n).try_for_each(|_| sink.write_char(options.fill));
+        fill(sink, before)?;
+        sink.write_str(&out)?;
+        fill(sink, after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_set::BitSet;
+    use crate::index_vec::IndexVec;
+
+    #[test]
+    fn renders_containers() {
+        let mut set: BitSet<u32> = BitSet::new_empty(20);
+        set.extend([1, 2, 3, 7]);
+        let pretty = RenderOptions::new().alternate(true);
+        assert_eq!(debug_string(&set, &pretty), "{\n    1..4,\n    7,\n}");
+
+        // The width applies to each element.
+        let v: IndexVec<u32, u32> = [7, 42].into_iter().collect();
+        let wide = RenderOptions::new().width(Some(4));
+        assert_eq!(debug_string(&v, &wide), "[   7,   42]");
+        let left = wide.align(Some(Alignment::Left));
+        assert_eq!(debug_string(&v, &left), "[7   , 42  ]");
+
+        let mut sink = String::from("pi=");
+        let precise = RenderOptions::new().precision(Some(3));
+        write_display(&mut sink, &std::f64::consts::PI, &precise).unwrap();
+        assert_eq!(sink, "pi=3.142");
+    }
+
+    #[test]
+    fn fills() {
+        let centered = RenderOptions::new()
+            .fill('*')
+            .align(Some(Alignment::Center))
+            .width(Some(7));
+        let mut out = String::new();
+        write_display(&mut out, "abc", &centered).unwrap();
+        assert_eq!(out, "**abc**");
+        let right = centered.align(Some(Alignment::Right)).fill('·');
+        assert_eq!(debug_string(&5u8, &right), "······5");
+        assert_eq!(debug_string(&123456789u32, &right), "123456789");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let render_both = |options: &RenderOptions, value: &dyn fmt::Debug| {
+            let mut from_std = String::new();
+            let mut ported = String::new();
+            render(&mut from_std, options, &|f| value.fmt(f)).unwrap();
+            portable::render(&mut ported, options, &|f| value.fmt(f)).unwrap();
+            assert_eq!(from_std, ported, "{options:?}");
+        };
+        let values: [&dyn fmt::Debug; 4] = [&1.5f64, &"text", &[1u8, 20], &Some(-3i32)];
+        let aligns = [
+            None,
+            Some(Alignment::Left),
+            Some(Alignment::Right),
+            Some(Alignment::Center),
+        ];
+        for value in values {
+            for align in aligns {
+                for alternate in [false, true] {
+                    for precision in [None, Some(1)] {
+                        let options = RenderOptions::new()
+                            .alternate(alternate)
+                            .align(align)
+                            .width(Some(6))
+                            .precision(precision);
+                        render_both(&options, value);
+                    }
+                }
+            }
+        }
+        // A fill only agrees for values padded as a whole.
+        let filled = RenderOptions::new()
+            .fill('-')
+            .align(Some(Alignment::Center))
+            .width(Some(9));
+        render_both(&filled, &12u32);
+        render_both(&filled.align(Some(Alignment::Right)), &-3.5f64);
+    }
+}
@@ -0,0 +1,59 @@
+//! `slice::get_disjoint_mut` usage
+//!
+//! Tracking issue [104642].
+//!
+//! `slice.get_disjoint_mut([i0, i1, ...])` returns `[&mut T; N]` for `N` distinct in-bounds
+//! indices, or an error saying whether an index was out of bounds or two overlapped. It was added
+//! as the unstable `get_many_mut`.
+//!
+//! [`IndexSlice::get_many_mut`] is the typed-index version. It replaces the hand-written
+//! `split_at_mut` and raw-pointer code behind `pick2_mut` and `pick3_mut`, which are now thin
+//! wrappers over it.
+//!
+//! [104642]: https://github.com/rust-lang/rust/issues/104642
+
+use core::slice::GetDisjointMutError;
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Returns mutable references to the elements at `indices`, or an error if one is out of
+    /// bounds or two are equal.
+    #[inline]
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        indices: [I; N],
+    ) -> Result<[&mut T; N], GetDisjointMutError> {
+        self.raw.get_disjoint_mut(indices.map(I::index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index_vec::IndexVec;
+
+    use super::*;
+
+    #[test]
+    fn many_mut() {
+        let mut v: IndexVec<u32, i32> = IndexVec::from([1, 2, 3, 4]);
+        let [a, b, c] = v.get_many_mut([3, 0, 2]).unwrap();
+        (*a, *b, *c) = (*b, *c, *a);
+        assert_eq!(v.raw, [3, 2, 4, 1]);
+        assert_eq!(v.get_many_mut::<0>([]), Ok([]));
+    }
+
+    #[test]
+    fn errors() {
+        let mut v: IndexVec<u32, i32> = IndexVec::from([1, 2, 3]);
+        assert_eq!(
+            v.get_many_mut([0, 3]),
+            Err(GetDisjointMutError::IndexOutOfBounds)
+        );
+        assert_eq!(
+            v.get_many_mut([1, 2, 1]),
+            Err(GetDisjointMutError::OverlappingIndices)
+        );
+    }
+}
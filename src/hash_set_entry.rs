@@ -0,0 +1,206 @@
+//! `HashSet::get_or_insert_with` usage
+//!
+//! Tracking issue [60896].
+//!
+//! `set.get_or_insert_with(&key, f)` returns a reference to the value equal to `key` in the set,
+//! inserting `f(&key)` first if there is none, with a single lookup. The plain set API needs
+//! `contains` or `get` followed by `insert`, and then another `get` to borrow the stored value.
+//! `get_or_insert_owned`, which did the same with `ToOwned`, has since been removed; here it is
+//! [`get_or_insert_owned`] on top of `get_or_insert_with`.
+//!
+//! [`Interner`] is a generic interner over these, handing out references to the stored values.
+//! Without the `nightly` feature the helpers do the lookup and insertion as separate steps.
+//!
+//! [60896]: https://github.com/rust-lang/rust/issues/60896
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash, RandomState};
+
+/// Returns the value in `set` equal to `key`, inserting `f(key)` if there is none.
+///
+/// `f(key)` must be equal to `key` and hash the same, like for `HashSet::get_or_insert_with`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn get_or_insert_with<'a, T, Q, S>(
+    set: &'a mut HashSet<T, S>,
+    key: &Q,
+    f: impl FnOnce(&Q) -> T,
+) -> &'a T
+where
+    T: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    set.get_or_insert_with(key, f)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn get_or_insert_with<'a, T, Q, S>(
+    set: &'a mut HashSet<T, S>,
+    key: &Q,
+    f: impl FnOnce(&Q) -> T,
+) -> &'a T
+where
+    T: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    portable::get_or_insert_with(set, key, f)
+}
+
+/// Returns the value in `set` equal to `key`, inserting `key.to_owned()` if there is none.
+#[inline]
+pub fn get_or_insert_owned<'a, T, Q, S>(set: &'a mut HashSet<T, S>, key: &Q) -> &'a T
+where
+    T: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+    S: BuildHasher,
+{
+    get_or_insert_with(set, key, Q::to_owned)
+}
+
+/// Deduplicates values of type `T`, storing one copy of each.
+pub struct Interner<T, S = RandomState> {
+    set: HashSet<T, S>,
+}
+
+impl<T: Hash + Eq> Interner<T> {
+    pub fn new() -> Self {
+        Interner {
+            set: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> Interner<T, S> {
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Returns the stored value equal to `key`, storing `key.to_owned()` first if there is none.
+    pub fn intern<Q>(&mut self, key: &Q) -> &T
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+    {
+        get_or_insert_owned(&mut self.set, key)
+    }
+
+    /// Returns the stored value equal to `key`, storing `f(key)` first if there is none.
+    pub fn intern_with<Q>(&mut self, key: &Q, f: impl FnOnce(&Q) -> T) -> &T
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        get_or_insert_with(&mut self.set, key, f)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.set.get(key)
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::borrow::Borrow;
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, Hash};
+
+    pub(super) fn get_or_insert_with<'a, T, Q, S>(
+        set: &'a mut HashSet<T, S>,
+        key: &Q,
+        f: impl FnOnce(&Q) -> T,
+    ) -> &'a T
+    where
+        T: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+        S: BuildHasher,
+    {
+        if !set.contains(key) {
+            let value = f(key);
+            assert!(value.borrow() == key, "new value is not equal");
+            set.insert(value);
+        }
+        set.get(key).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn interned_refs() {
+        let mut interner: Interner<String> = Interner::new();
+        let a: *const String = interner.intern("a");
+        assert_eq!(interner.intern("b"), "b");
+        // The same stored value comes back.
+        assert!(std::ptr::eq(interner.intern("a"), a));
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get("b").map(String::as_str), Some("b"));
+        assert_eq!(interner.get("c"), None);
+    }
+
+    #[test]
+    fn intern_with() {
+        let mut interner: Interner<Rc<str>> = Interner::new();
+        let mut calls = 0;
+        for _ in 0..3 {
+            let s = interner.intern_with("shared", |s| {
+                calls += 1;
+                Rc::from(s)
+            });
+            assert_eq!(&**s, "shared");
+        }
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn portable_dedup() {
+        let mut set: HashSet<Vec<u8>> = HashSet::new();
+        let words: [&[u8]; 5] = [b"x", b"y", b"x", b"z", b"y"];
+        for w in words {
+            assert_eq!(portable::get_or_insert_with(&mut set, w, <[u8]>::to_vec), w);
+        }
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn portable_unequal_value() {
+        let mut set: HashSet<String> = HashSet::new();
+        portable::get_or_insert_with(&mut set, "a", |_| "b".to_owned());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let words = ["a", "b", "a", "c", "b", "a"];
+        let (mut std, mut portable) = (HashSet::<String>::new(), HashSet::<String>::new());
+        for w in words {
+            assert_eq!(
+                std.get_or_insert_with(w, str::to_owned),
+                portable::get_or_insert_with(&mut portable, w, str::to_owned)
+            );
+        }
+        assert_eq!(std, portable);
+    }
+}
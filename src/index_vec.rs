@@ -0,0 +1,498 @@
+//! `IndexVec`/`IndexSlice`, typed-index containers
+//!
+//! **This is copy of rustc_index**, trimmed down to what the examples in this crate need.
+//!
+//! An `IndexVec<I, T>` is a `Vec<T>` that can only be indexed by `I`, so indices of different
+//! domains can't be mixed up. `IndexSlice<I, T>` is the borrowed form, in the same way `[T]` is for
+//! `Vec<T>`. `IndexArray<I, T, N>` is the same for `[T; N]`, which rustc_index doesn't have.
+//!
+//! See [`rustc_index::IndexVec`].
+//!
+//! [`rustc_index::IndexVec`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index/src/vec.rs#L40
+
+use alloc::borrow::ToOwned;
+use alloc::vec::{self, Vec};
+use core::borrow::{Borrow, BorrowMut};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut, Index, IndexMut, Range};
+use core::{fmt, slice};
+
+use crate::inline_const;
+use crate::step::Idx;
+
+/// A `Vec<T>` indexed by `I`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct IndexVec<I: Idx, T> {
+    pub raw: Vec<T>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+/// A `[T]` indexed by `I`.
+#[derive(PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct IndexSlice<I: Idx, T> {
+    _marker: PhantomData<fn(&I)>,
+    pub raw: [T],
+}
+
+/// A `[T; N]` indexed by `I`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct IndexArray<I: Idx, T, const N: usize> {
+    pub raw: [T; N],
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Constructs a new, empty `IndexVec<I, T>`.
+    #[inline]
+    pub const fn new() -> Self {
+        IndexVec::from_raw(Vec::new())
+    }
+
+    /// Constructs a new `IndexVec<I, T>` from a `Vec<T>`.
+    #[inline]
+    pub const fn from_raw(raw: Vec<T>) -> Self {
+        IndexVec {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        IndexVec::from_raw(Vec::with_capacity(capacity))
+    }
+
+    /// Creates a new vector with a copy of `elem` for each index in `universe`.
+    #[inline]
+    pub fn from_elem_n(elem: T, universe_len: usize) -> Self
+    where
+        T: Clone,
+    {
+        IndexVec::from_raw(alloc::vec![elem; universe_len])
+    }
+
+    /// Create an `IndexVec` with `n` elements, where the value of each
+    /// element is the result of `func(i)`.
+    #[inline]
+    pub fn from_fn_n(func: impl FnMut(I) -> T, n: usize) -> Self {
+        IndexVec::from_raw((0..n).map(I::new).map(func).collect())
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &IndexSlice<I, T> {
+        IndexSlice::from_raw(&self.raw)
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut IndexSlice<I, T> {
+        IndexSlice::from_raw_mut(&mut self.raw)
+    }
+
+    /// Pushes an element to the array returning the index where it was pushed to.
+    #[inline]
+    pub fn push(&mut self, d: T) -> I {
+        let idx = self.next_index();
+        self.raw.push(d);
+        idx
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        self.raw.pop()
+    }
+
+    #[inline]
+    pub fn into_iter_enumerated(
+        self,
+    ) -> impl DoubleEndedIterator<Item = (I, T)> + ExactSizeIterator {
+        self.raw
+            .into_iter()
+            .enumerate()
+            .map(|(n, t)| (I::new(n), t))
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, a: usize) {
+        self.raw.truncate(a)
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.raw.clear()
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
+    }
+
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.raw.reserve(additional);
+    }
+}
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    #[inline]
+    pub fn empty<'a>() -> &'a Self {
+        Self::from_raw(&[])
+    }
+
+    #[inline]
+    pub fn from_raw(raw: &[T]) -> &Self {
+        let ptr: *const [T] = raw;
+        // SAFETY: `IndexSlice` is `repr(transparent)` over a normal slice
+        unsafe { &*(ptr as *const Self) }
+    }
+
+    #[inline]
+    pub fn from_raw_mut(raw: &mut [T]) -> &mut Self {
+        let ptr: *mut [T] = raw;
+        // SAFETY: `IndexSlice` is `repr(transparent)` over a normal slice
+        unsafe { &mut *(ptr as *mut Self) }
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Gives the next index that will be assigned when `push` is called.
+    ///
+    /// Manual bounds checks can be done using `idx < slice.next_index()`
+    /// (as opposed to `idx.index() < slice.len()`).
+    #[inline]
+    pub fn next_index(&self) -> I {
+        I::new(self.len())
+    }
+
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.raw.iter()
+    }
+
+    #[inline]
+    pub fn iter_enumerated(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (I, &T)> + ExactSizeIterator + '_ {
+        self.raw.iter().enumerate().map(|(n, t)| (I::new(n), t))
+    }
+
+    #[inline]
+    pub fn indices(&self) -> impl DoubleEndedIterator<Item = I> + ExactSizeIterator + 'static {
+        (0..self.len()).map(|n| I::new(n))
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.raw.iter_mut()
+    }
+
+    #[inline]
+    pub fn iter_enumerated_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (I, &mut T)> + ExactSizeIterator + '_ {
+        self.raw.iter_mut().enumerate().map(|(n, t)| (I::new(n), t))
+    }
+
+    #[inline]
+    pub fn last_index(&self) -> Option<I> {
+        self.len().checked_sub(1).map(I::new)
+    }
+
+    #[inline]
+    pub fn swap(&mut self, a: I, b: I) {
+        self.raw.swap(a.index(), b.index())
+    }
+
+    #[inline]
+    pub fn get(&self, index: I) -> Option<&T> {
+        self.raw.get(index.index())
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: I) -> Option<&mut T> {
+        self.raw.get_mut(index.index())
+    }
+
+    /// Returns mutable references to two distinct elements, `a` and `b`.
+    ///
+    /// Panics if `a == b`.
+    #[inline]
+    pub fn pick2_mut(&mut self, a: I, b: I) -> (&mut T, &mut T) {
+        let [a, b] = self.get_many_mut([a, b]).unwrap();
+        (a, b)
+    }
+
+    /// Returns mutable references to three distinct elements.
+    ///
+    /// Panics if the elements are not distinct.
+    #[inline]
+    pub fn pick3_mut(&mut self, a: I, b: I, c: I) -> (&mut T, &mut T, &mut T) {
+        let [a, b, c] = self.get_many_mut([a, b, c]).unwrap();
+        (a, b, c)
+    }
+}
+
+impl<I: Idx, T, const N: usize> IndexArray<I, T, N> {
+    #[inline]
+    pub const fn from_raw(raw: [T; N]) -> Self {
+        inline_const::assert_len_fits::<I, N>();
+        IndexArray {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn from_fn(mut func: impl FnMut(I) -> T) -> Self {
+        IndexArray::from_raw(core::array::from_fn(|i| func(I::new(i))))
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &IndexSlice<I, T> {
+        IndexSlice::from_raw(&self.raw)
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut IndexSlice<I, T> {
+        IndexSlice::from_raw_mut(&mut self.raw)
+    }
+}
+
+impl<I: Idx, T> Default for IndexVec<I, T> {
+    #[inline]
+    fn default() -> Self {
+        IndexVec::new()
+    }
+}
+
+impl<I: Idx, T> Deref for IndexVec<I, T> {
+    type Target = IndexSlice<I, T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<I: Idx, T> DerefMut for IndexVec<I, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<I: Idx, T, const N: usize> Deref for IndexArray<I, T, N> {
+    type Target = IndexSlice<I, T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<I: Idx, T, const N: usize> DerefMut for IndexArray<I, T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<I: Idx, T> Borrow<IndexSlice<I, T>> for IndexVec<I, T> {
+    fn borrow(&self) -> &IndexSlice<I, T> {
+        self
+    }
+}
+
+impl<I: Idx, T> BorrowMut<IndexSlice<I, T>> for IndexVec<I, T> {
+    fn borrow_mut(&mut self) -> &mut IndexSlice<I, T> {
+        self
+    }
+}
+
+impl<I: Idx, T> Index<I> for IndexSlice<I, T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: I) -> &T {
+        &self.raw[index.index()]
+    }
+}
+
+impl<I: Idx, T> IndexMut<I> for IndexSlice<I, T> {
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut T {
+        &mut self.raw[index.index()]
+    }
+}
+
+impl<I: Idx, T> Index<Range<I>> for IndexSlice<I, T> {
+    type Output = IndexSlice<I, T>;
+
+    #[inline]
+    fn index(&self, range: Range<I>) -> &Self {
+        IndexSlice::from_raw(&self.raw[range.start.index()..range.end.index()])
+    }
+}
+
+impl<I: Idx, T> IndexMut<Range<I>> for IndexSlice<I, T> {
+    #[inline]
+    fn index_mut(&mut self, range: Range<I>) -> &mut Self {
+        IndexSlice::from_raw_mut(&mut self.raw[range.start.index()..range.end.index()])
+    }
+}
+
+impl<I: Idx, T: fmt::Debug> fmt::Debug for IndexVec<I, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.raw, fmt)
+    }
+}
+
+impl<I: Idx, T: fmt::Debug, const N: usize> fmt::Debug for IndexArray<I, T, N> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.raw, fmt)
+    }
+}
+
+impl<I: Idx, T: fmt::Debug> fmt::Debug for IndexSlice<I, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.raw, fmt)
+    }
+}
+
+impl<I: Idx, T> Extend<T> for IndexVec<I, T> {
+    #[inline]
+    fn extend<J: IntoIterator<Item = T>>(&mut self, iter: J) {
+        self.raw.extend(iter);
+    }
+}
+
+impl<I: Idx, T> FromIterator<T> for IndexVec<I, T> {
+    #[inline]
+    fn from_iter<J>(iter: J) -> Self
+    where
+        J: IntoIterator<Item = T>,
+    {
+        IndexVec::from_raw(Vec::from_iter(iter))
+    }
+}
+
+impl<I: Idx, T> IntoIterator for IndexVec<I, T> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> vec::IntoIter<T> {
+        self.raw.into_iter()
+    }
+}
+
+impl<'a, I: Idx, T> IntoIterator for &'a IndexVec<I, T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, I: Idx, T> IntoIterator for &'a mut IndexVec<I, T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, I: Idx, T> IntoIterator for &'a IndexSlice<I, T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.raw.iter()
+    }
+}
+
+impl<I: Idx, T, const N: usize> From<[T; N]> for IndexVec<I, T> {
+    #[inline]
+    fn from(array: [T; N]) -> Self {
+        IndexVec::from_raw(array.into())
+    }
+}
+
+impl<I: Idx, T: Clone> ToOwned for IndexSlice<I, T> {
+    type Owned = IndexVec<I, T>;
+
+    fn to_owned(&self) -> IndexVec<I, T> {
+        IndexVec::from_raw(self.raw.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_index() {
+        let mut v: IndexVec<u32, &str> = IndexVec::new();
+        let a = v.push("a");
+        let b = v.push("b");
+        assert_eq!((a, b), (0, 1));
+        assert_eq!(v[b], "b");
+        assert_eq!(v.next_index(), 2);
+        assert_eq!(v.last_index(), Some(1));
+        let enumerated: Vec<_> = v.iter_enumerated().collect();
+        assert_eq!(enumerated, [(0, &"a"), (1, &"b")]);
+    }
+
+    #[test]
+    fn pick_mut() {
+        let mut v: IndexVec<usize, i32> = IndexVec::from([1, 2, 3]);
+        let (x, y) = v.pick2_mut(2, 0);
+        std::mem::swap(x, y);
+        assert_eq!(v.raw, [3, 2, 1]);
+        let (x, y, z) = v.pick3_mut(1, 2, 0);
+        *x += 10;
+        *y += 20;
+        *z += 30;
+        assert_eq!(v.raw, [33, 12, 21]);
+    }
+
+    #[test]
+    fn index_array() {
+        let mut a: IndexArray<u32, u32, 4> = IndexArray::from_fn(|i| i * i);
+        a[3] += 1;
+        assert_eq!(a.raw, [0, 1, 4, 10]);
+        assert_eq!(a.next_index(), 4);
+        assert_eq!(a[1..3].raw, [1, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pick2_mut_same() {
+        let mut v: IndexVec<usize, i32> = IndexVec::from([1, 2]);
+        v.pick2_mut(1, 1);
+    }
+
+    #[test]
+    fn slicing() {
+        let v: IndexVec<usize, i32> = (0..5).collect();
+        let s = &v[1..4];
+        assert_eq!(s.len(), 3);
+        assert_eq!(s[0], 1);
+        assert_eq!(s.to_owned().raw, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,97 @@
+//! Inline `const { ... }` blocks usage
+//!
+//! Tracking issue [76001].
+//!
+//! `const { expr }` evaluates `expr` at compile time wherever an expression can go, and may use
+//! the generic parameters in scope. An `assert!` inside one fails the build for the instantiation
+//! that breaks it, so generic container code can check a property of its type and const
+//! parameters once per monomorphization, for free at runtime. Before, that took an associated
+//! const on a helper `struct Assert<T>` that had to be named to be evaluated.
+//!
+//! The same syntax as a pattern, `const { MAX + 1 } => ...`, was the unstable `inline_const_pat`,
+//! which was removed again. Matches write such values as named `const` items instead, as
+//! [`unpack`] does.
+//!
+//! [`assert_len_fits`] is called by `IndexArray::from_raw`, so an `IndexArray<u32, T, N>` with
+//! more elements than `u32` can index doesn't compile. [`pack_tag`] checks its tag the same way.
+//! The 255 values above `CustomIndex::MAX` are the ones set aside for packing into enums; [`unpack`]
+//! tells them apart.
+//!
+//! [76001]: https://github.com/rust-lang/rust/issues/76001
+
+use crate::step::{CustomIndex, Idx};
+
+/// The largest raw value of a `CustomIndex`.
+pub const INDEX_MAX: u32 = CustomIndex::MAX_AS_U32;
+
+const NONE: u32 = INDEX_MAX + 1;
+const TOMBSTONE: u32 = INDEX_MAX + 2;
+
+/// A `u32` that holds either a `CustomIndex` or one of the values above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unpacked {
+    Index(u32),
+    None,
+    Tombstone,
+    /// A caller-defined tag, `2..=254`.
+    Tag(u8),
+}
+
+/// Fails the build if `N` elements can't all be indexed with `I`.
+#[inline(always)]
+pub const fn assert_len_fits<I: Idx, const N: usize>() {
+    const {
+        assert!(
+            N == 0 || N - 1 <= I::MAX_INDEX,
+            "array is too long for its index type"
+        )
+    }
+}
+
+/// The raw value of tag `TAG`, checked at compile time.
+pub const fn pack_tag<const TAG: u8>() -> u32 {
+    const {
+        assert!(
+            TAG >= 2 && TAG <= 254,
+            "tags 0 and 1 are reserved, 255 doesn't fit"
+        )
+    };
+    NONE + TAG as u32
+}
+
+/// Splits a raw value into what it stands for.
+pub const fn unpack(raw: u32) -> Unpacked {
+    match raw {
+        0..=INDEX_MAX => Unpacked::Index(raw),
+        NONE => Unpacked::None,
+        TOMBSTONE => Unpacked::Tombstone,
+        _ => Unpacked::Tag((raw - NONE) as u8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_vec::IndexArray;
+
+    #[test]
+    fn packed_values() {
+        const RENAMED: u32 = pack_tag::<2>();
+        assert_eq!(unpack(0), Unpacked::Index(0));
+        assert_eq!(unpack(INDEX_MAX), Unpacked::Index(INDEX_MAX));
+        assert_eq!(unpack(INDEX_MAX + 1), Unpacked::None);
+        assert_eq!(unpack(INDEX_MAX + 2), Unpacked::Tombstone);
+        assert_eq!(unpack(RENAMED), Unpacked::Tag(2));
+        assert_eq!(unpack(pack_tag::<254>()), Unpacked::Tag(254));
+        assert_eq!(pack_tag::<254>(), u32::MAX);
+    }
+
+    #[test]
+    fn array_lengths() {
+        assert_len_fits::<u32, 0>();
+        assert_len_fits::<u32, { u32::MAX as usize + 1 }>();
+        assert_len_fits::<usize, { usize::MAX }>();
+        let array: IndexArray<CustomIndex, u8, 3> = IndexArray::from_raw([1, 2, 3]);
+        assert_eq!(array[CustomIndex::from_u32(2)], 3);
+    }
+}
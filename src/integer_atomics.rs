@@ -0,0 +1,226 @@
+//! `AtomicU128` usage
+//!
+//! Tracking issue [99069].
+//!
+//! `AtomicU128` is the 128-bit atomic integer, on the targets that can compare and swap two words
+//! at once, like x86-64 with `cmpxchg16b` or AArch64. Two 64-bit values packed into one can then be
+//! read and updated together. With a generation counter next to an index, a compare-and-swap on
+//! the pair fails if the index was changed and changed back in between, which one that compares
+//! the index alone can't tell apart from no change at all: the ABA problem.
+//!
+//! [`VersionedIndex`] is such a pair, built on [`AtomicPair`]. Every update bumps the generation,
+//! and [`VersionedIndex::update`] only succeeds against the exact version it was given. Without the
+//! `nightly` feature, or on targets without 128-bit atomics, where std has no `AtomicU128` and the
+//! `integer_atomics` feature doesn't exist, `AtomicPair` is a `Mutex<(u64, u64)>`.
+//!
+//! [99069]: https://github.com/rust-lang/rust/issues/99069
+
+use std::marker::PhantomData;
+
+#[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+use std::sync::atomic::{AtomicU128, Ordering};
+
+#[cfg(not(all(feature = "nightly", target_has_atomic = "128")))]
+pub use portable::AtomicPair;
+
+use crate::step::Idx;
+
+/// A pair of `u64`s loaded and compared-and-swapped as one value.
+#[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+#[derive(Debug)]
+pub struct AtomicPair(AtomicU128);
+
+#[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+impl AtomicPair {
+    pub const fn new(pair: (u64, u64)) -> Self {
+        AtomicPair(AtomicU128::new(pack(pair)))
+    }
+
+    pub fn load(&self) -> (u64, u64) {
+        unpack(self.0.load(Ordering::Acquire))
+    }
+
+    /// Stores `new` if the pair is `current`, returning the previous pair either way.
+    pub fn compare_exchange(
+        &self,
+        current: (u64, u64),
+        new: (u64, u64),
+    ) -> Result<(u64, u64), (u64, u64)> {
+        self.0
+            .compare_exchange(
+                pack(current),
+                pack(new),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .map(unpack)
+            .map_err(unpack)
+    }
+}
+
+#[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+const fn pack((high, low): (u64, u64)) -> u128 {
+    ((high as u128) << 64) | low as u128
+}
+
+#[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+const fn unpack(packed: u128) -> (u64, u64) {
+    ((packed >> 64) as u64, packed as u64)
+}
+
+/// An index together with the number of times it has been updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Versioned<I> {
+    pub generation: u64,
+    pub index: I,
+}
+
+/// An index that threads update atomically, tagged with a generation so that an update based on
+/// a stale read always fails.
+#[derive(Debug)]
+pub struct VersionedIndex<I: Idx> {
+    /// The generation and the index.
+    pair: AtomicPair,
+    _marker: PhantomData<fn() -> I>,
+}
+
+impl<I: Idx> VersionedIndex<I> {
+    pub fn new(index: I) -> Self {
+        VersionedIndex {
+            pair: AtomicPair::new((0, index.index() as u64)),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn load(&self) -> Versioned<I> {
+        Self::versioned(self.pair.load())
+    }
+
+    /// Replaces the index with `index` if it is still at version `seen`, and returns the new
+    /// version, or else the current one.
+    pub fn update(&self, seen: Versioned<I>, index: I) -> Result<Versioned<I>, Versioned<I>> {
+        let current = (seen.generation, seen.index.index() as u64);
+        let new = (seen.generation.wrapping_add(1), index.index() as u64);
+        match self.pair.compare_exchange(current, new) {
+            Ok(_) => Ok(Self::versioned(new)),
+            Err(actual) => Err(Self::versioned(actual)),
+        }
+    }
+
+    /// Replaces the index whatever its version, and returns the new version.
+    pub fn store(&self, index: I) -> Versioned<I> {
+        let mut seen = self.load();
+        loop {
+            match self.update(seen, index) {
+                Ok(new) => return new,
+                Err(actual) => seen = actual,
+            }
+        }
+    }
+
+    fn versioned((generation, index): (u64, u64)) -> Versioned<I> {
+        Versioned {
+            generation,
+            index: I::new(index as usize),
+        }
+    }
+}
+
+#[cfg(any(test, not(all(feature = "nightly", target_has_atomic = "128"))))]
+mod portable {
+    use std::sync::Mutex;
+
+    /// A pair of `u64`s loaded and compared-and-swapped as one value, under a lock.
+    #[derive(Debug)]
+    pub struct AtomicPair(Mutex<(u64, u64)>);
+
+    impl AtomicPair {
+        pub const fn new(pair: (u64, u64)) -> Self {
+            AtomicPair(Mutex::new(pair))
+        }
+
+        pub fn load(&self) -> (u64, u64) {
+            *self.0.lock().unwrap()
+        }
+
+        pub fn compare_exchange(
+            &self,
+            current: (u64, u64),
+            new: (u64, u64),
+        ) -> Result<(u64, u64), (u64, u64)> {
+            let mut pair = self.0.lock().unwrap();
+            if *pair == current {
+                *pair = new;
+                Ok(current)
+            } else {
+                Err(*pair)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn stale_update_fails() {
+        let head: VersionedIndex<u32> = VersionedIndex::new(3);
+        let seen = head.load();
+        assert_eq!(
+            seen,
+            Versioned {
+                generation: 0,
+                index: 3
+            }
+        );
+        // Changed and changed back: the index is the same, the version isn't.
+        head.store(7);
+        let back = head.store(3);
+        assert_eq!(back.generation, 2);
+        assert_eq!(head.update(seen, 5), Err(back));
+        let new = head.update(back, 5).unwrap();
+        assert_eq!(head.load(), new);
+        assert_eq!(new.generation, 3);
+    }
+
+    #[test]
+    fn concurrent_updates() {
+        const THREADS: u32 = 4;
+        const PER_THREAD: u32 = 100;
+        let counter: VersionedIndex<u32> = VersionedIndex::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for _ in 0..PER_THREAD {
+                        let mut seen = counter.load();
+                        while let Err(actual) = counter.update(seen, seen.index + 1) {
+                            seen = actual;
+                        }
+                    }
+                });
+            }
+        });
+        let last = counter.load();
+        assert_eq!(last.index, THREADS * PER_THREAD);
+        assert_eq!(last.generation, u64::from(THREADS * PER_THREAD));
+    }
+
+    #[cfg(all(feature = "nightly", target_has_atomic = "128"))]
+    #[test]
+    fn portable_agrees_with_std() {
+        let (std, portable) = (AtomicPair::new((1, 2)), portable::AtomicPair::new((1, 2)));
+        assert_eq!(std.load(), portable.load());
+        assert_eq!(
+            std.compare_exchange((1, 2), (u64::MAX, 0)),
+            portable.compare_exchange((1, 2), (u64::MAX, 0))
+        );
+        assert_eq!(
+            std.compare_exchange((1, 2), (3, 3)),
+            portable.compare_exchange((1, 2), (3, 3))
+        );
+        assert_eq!(std.load(), portable.load());
+    }
+}
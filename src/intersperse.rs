@@ -0,0 +1,173 @@
+//! `Iterator::intersperse` usage
+//!
+//! Tracking issue [79524].
+//!
+//! `intersperse(sep)` yields a copy of `sep` between every two items, and `intersperse_with(f)`
+//! calls `f` for each separator instead. Together with `collect` or a `fmt::Write` sink this
+//! replaces the usual "first item, then separator + item" loop.
+//!
+//! The free functions here forward to std under the `nightly` feature and to an in-crate adapter
+//! otherwise. They are free functions rather than an extension trait since a trait method named
+//! `intersperse` would collide with the std one once it is stabilized.
+//!
+//! [79524]: https://github.com/rust-lang/rust/issues/79524
+
+use std::fmt::Write;
+
+use crate::step::Idx;
+
+/// Yields `sep` between every two items of `iter`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn intersperse<I>(iter: I, sep: I::Item) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    iter.intersperse(sep)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn intersperse<I>(iter: I, sep: I::Item) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    portable::IntersperseWith::new(iter, move || sep.clone())
+}
+
+/// Yields the result of `sep()` between every two items of `iter`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn intersperse_with<I, G>(iter: I, sep: G) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator,
+    G: FnMut() -> I::Item,
+{
+    iter.intersperse_with(sep)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn intersperse_with<I, G>(iter: I, sep: G) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator,
+    G: FnMut() -> I::Item,
+{
+    portable::IntersperseWith::new(iter, sep)
+}
+
+/// Renders `indices` separated by `sep`, e.g. `"1, 5, 9"`.
+pub fn format_indices<I: Idx>(indices: impl IntoIterator<Item = I>, sep: &str) -> String {
+    let mut out = String::new();
+    // `None` marks a separator, so no per-index strings are allocated.
+    for item in intersperse_with(indices.into_iter().map(Some), || None) {
+        match item {
+            Some(idx) => write!(out, "{}", idx.index()).unwrap(),
+            None => out.push_str(sep),
+        }
+    }
+    out
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::iter::Peekable;
+
+    pub(super) struct IntersperseWith<I: Iterator, G> {
+        iter: Peekable<I>,
+        sep: G,
+        /// Whether the last yielded item was an element, so a separator comes next.
+        needs_sep: bool,
+    }
+
+    impl<I: Iterator, G> IntersperseWith<I, G> {
+        pub(super) fn new(iter: I, sep: G) -> Self {
+            Self {
+                iter: iter.peekable(),
+                sep,
+                needs_sep: false,
+            }
+        }
+    }
+
+    impl<I, G> Iterator for IntersperseWith<I, G>
+    where
+        I: Iterator,
+        G: FnMut() -> I::Item,
+    {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<I::Item> {
+            if self.needs_sep && self.iter.peek().is_some() {
+                self.needs_sep = false;
+                Some((self.sep)())
+            } else {
+                self.needs_sep = true;
+                self.iter.next()
+            }
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let (lo, hi) = self.iter.size_hint();
+            // Between `n` remaining items go `n - 1` separators, plus one in front if due.
+            let extra = |n: usize| n.saturating_sub(1) + (self.needs_sep && n > 0) as usize;
+            (
+                lo.saturating_add(extra(lo)),
+                hi.and_then(|hi| hi.checked_add(extra(hi))),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators() {
+        let v: Vec<_> = intersperse([1, 2, 3].into_iter(), 0).collect();
+        assert_eq!(v, [1, 0, 2, 0, 3]);
+        assert_eq!(intersperse([1].into_iter(), 0).count(), 1);
+        assert_eq!(intersperse(std::iter::empty::<i32>(), 0).count(), 0);
+
+        let mut n = 0;
+        let v: Vec<_> = intersperse_with([10, 20, 30].into_iter(), || {
+            n += 1;
+            n
+        })
+        .collect();
+        assert_eq!(v, [10, 1, 20, 2, 30]);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_indices([1usize, 5, 9], ", "), "1, 5, 9");
+        assert_eq!(format_indices([7u32], ", "), "7");
+        assert_eq!(format_indices(Vec::<u32>::new(), ", "), "");
+        assert_eq!(format_indices(0usize..4, "-"), "0-1-2-3");
+    }
+
+    #[test]
+    fn portable_size_hint() {
+        for len in 0usize..4 {
+            let total = (2 * len).saturating_sub(1);
+            for consumed in 0..=total {
+                let mut it = portable::IntersperseWith::new(0..len, || usize::MAX);
+                it.by_ref().take(consumed).for_each(drop);
+                assert_eq!(it.size_hint(), (total - consumed, Some(total - consumed)));
+            }
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..5 {
+            let std: Vec<_> = (0..len).intersperse(-1).collect();
+            let portable: Vec<_> = portable::IntersperseWith::new(0..len, || -1).collect();
+            assert_eq!(std, portable);
+        }
+    }
+}
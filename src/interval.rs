@@ -0,0 +1,142 @@
+//! `IntervalSet`, a set of indices stored as sorted ranges
+//!
+//! **This is copy of rustc_index**, trimmed down to what the examples in this crate need.
+//!
+//! An `IntervalSet<I>` stores runs of consecutive indices as single `start..=end` entries, which is
+//! much smaller than a bitset when the members come in long runs, e.g. the points where a value is
+//! live.
+//!
+//! See [`rustc_index::interval::IntervalSet`].
+//!
+//! [`rustc_index::interval::IntervalSet`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index/src/interval.rs#L16
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::step::Idx;
+
+/// A set of indices below `domain_size`, stored as sorted, disjoint, non-adjacent ranges.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<I: Idx> {
+    /// Inclusive `(start, end)` pairs.
+    map: Vec<(u32, u32)>,
+    domain: usize,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx> IntervalSet<I> {
+    /// Creates an empty set over the indices `0..domain`.
+    pub fn new(domain: usize) -> IntervalSet<I> {
+        assert!(domain <= u32::MAX as usize);
+        IntervalSet {
+            map: Vec::new(),
+            domain,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn domain_size(&self) -> usize {
+        self.domain
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over the members of the set, in order.
+    pub fn iter(&self) -> impl Iterator<Item = I> + '_ {
+        self.iter_intervals()
+            .flat_map(|r| (r.start.index()..r.end.index()).map(I::new))
+    }
+
+    /// Iterates over the maximal runs of members, in order.
+    pub fn iter_intervals(&self) -> impl Iterator<Item = Range<I>> + '_ {
+        self.map
+            .iter()
+            .map(|&(start, end)| I::new(start as usize)..I::new(end as usize + 1))
+    }
+
+    /// Returns true if we increased the number of elements present.
+    pub fn insert(&mut self, point: I) -> bool {
+        self.insert_range(point..point.plus(1))
+    }
+
+    /// Returns true if we increased the number of elements present.
+    pub fn insert_range(&mut self, range: Range<I>) -> bool {
+        let (start, end) = (range.start.index(), range.end.index());
+        if start >= end {
+            return false;
+        }
+        assert!(end <= self.domain);
+        let (start, end) = (start as u32, (end - 1) as u32);
+
+        // Entries in `lo..hi` overlap or touch `start..=end` and are merged with it.
+        let lo = self.map.partition_point(|&(_, e)| e + 1 < start);
+        let hi = self.map.partition_point(|&(s, _)| s <= end + 1);
+        if lo == hi {
+            self.map.insert(lo, (start, end));
+            return true;
+        }
+        let merged = (start.min(self.map[lo].0), end.max(self.map[hi - 1].1));
+        let changed = hi - lo > 1 || self.map[lo] != merged;
+        self.map.splice(lo..hi, [merged]);
+        changed
+    }
+
+    pub fn contains(&self, needle: I) -> bool {
+        let needle = needle.index() as u32;
+        let i = self.map.partition_point(|&(start, _)| start <= needle);
+        i > 0 && needle <= self.map[i - 1].1
+    }
+}
+
+impl<I: Idx> fmt::Debug for IntervalSet<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter_intervals()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_merge() {
+        let mut set: IntervalSet<u32> = IntervalSet::new(100);
+        assert!(set.is_empty());
+        assert!(set.insert_range(10..20));
+        assert!(set.insert_range(30..40));
+        assert!(!set.insert_range(12..18));
+        assert!(!set.insert_range(12..12));
+        // Adjacent runs are merged.
+        assert!(set.insert_range(20..25));
+        assert_eq!(set.iter_intervals().collect::<Vec<_>>(), [10..25, 30..40]);
+        // One range bridging both.
+        assert!(set.insert_range(5..35));
+        assert_eq!(format!("{set:?}"), "[5..40]");
+        assert!(set.insert(99));
+        assert!(set.insert(0));
+        assert_eq!(format!("{set:?}"), "[0..1, 5..40, 99..100]");
+    }
+
+    #[test]
+    fn contains() {
+        let mut set: IntervalSet<usize> = IntervalSet::new(10);
+        set.insert_range(2..4);
+        set.insert(7);
+        let members: Vec<_> = (0..10).filter(|&i| set.contains(i)).collect();
+        assert_eq!(members, [2, 3, 7]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), members);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_domain() {
+        IntervalSet::<usize>::new(10).insert(10);
+    }
+}
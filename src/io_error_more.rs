@@ -0,0 +1,182 @@
+//! `io::ErrorKind` extended kinds usage
+//!
+//! Tracking issue [86442].
+//!
+//! `io_error_more` added error kinds for OS errors that used to come out as `Uncategorized`,
+//! which can only be told apart by their raw OS codes. Most of them, such as `StorageFull`,
+//! `CrossesDevices` and `NotADirectory`, are stable now; `FilesystemLoop`, for too many levels of
+//! symbolic links, is still behind the feature.
+//!
+//! [`classify`] sorts an `io::Error` into an [`IoClass`], the handful of cases a caller treats
+//! differently, e.g. retrying, or copying instead of renaming across devices. Without the
+//! `nightly` feature a filesystem loop is recognized by the OS code, `ELOOP` on Linux and the
+//! BSDs, and `ERROR_CANT_RESOLVE_FILENAME` on Windows.
+//!
+//! [86442]: https://github.com/rust-lang/rust/issues/86442
+
+use std::io::{self, ErrorKind};
+
+/// What an I/O error means for the code that got it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoClass {
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    /// The disk is full, or a quota is used up.
+    OutOfSpace,
+    /// Symbolic links point at each other.
+    FilesystemLoop,
+    /// A rename or link across filesystems, which takes a copy instead.
+    CrossesDevices,
+    ReadOnly,
+    /// A file where a directory was expected, or the other way round.
+    WrongFileType,
+    DirectoryNotEmpty,
+    /// The file or resource is in use.
+    Busy,
+    /// The operation may well succeed if tried again.
+    Retry,
+    Network,
+    Other,
+}
+
+/// The class of `err`.
+#[cfg(feature = "nightly")]
+pub fn classify(err: &io::Error) -> IoClass {
+    match err.kind() {
+        ErrorKind::FilesystemLoop => IoClass::FilesystemLoop,
+        kind => classify_kind(kind).unwrap_or(IoClass::Other),
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn classify(err: &io::Error) -> IoClass {
+    portable::classify(err)
+}
+
+/// The class of the kinds std has on stable.
+fn classify_kind(kind: ErrorKind) -> Option<IoClass> {
+    use ErrorKind::*;
+    let class = match kind {
+        NotFound => IoClass::NotFound,
+        PermissionDenied => IoClass::PermissionDenied,
+        AlreadyExists => IoClass::AlreadyExists,
+        StorageFull | QuotaExceeded | FileTooLarge => IoClass::OutOfSpace,
+        CrossesDevices => IoClass::CrossesDevices,
+        ReadOnlyFilesystem => IoClass::ReadOnly,
+        NotADirectory | IsADirectory => IoClass::WrongFileType,
+        DirectoryNotEmpty => IoClass::DirectoryNotEmpty,
+        ResourceBusy | ExecutableFileBusy => IoClass::Busy,
+        Interrupted | WouldBlock | TimedOut => IoClass::Retry,
+        ConnectionRefused
+        | ConnectionReset
+        | ConnectionAborted
+        | NotConnected
+        | BrokenPipe
+        | HostUnreachable
+        | NetworkUnreachable
+        | NetworkDown
+        | StaleNetworkFileHandle => IoClass::Network,
+        _ => return None,
+    };
+    Some(class)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::io;
+
+    use super::{IoClass, classify_kind};
+
+    /// The OS code for too many levels of symbolic links.
+    #[cfg(target_os = "linux")]
+    const LOOP: Option<i32> = Some(40);
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    const LOOP: Option<i32> = Some(62);
+    #[cfg(windows)]
+    const LOOP: Option<i32> = Some(1921);
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        windows
+    )))]
+    const LOOP: Option<i32> = None;
+
+    pub(super) fn classify(err: &io::Error) -> IoClass {
+        if let Some(class) = classify_kind(err.kind()) {
+            return class;
+        }
+        match err.raw_os_error() {
+            Some(code) if Some(code) == LOOP => IoClass::FilesystemLoop,
+            _ => IoClass::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Error;
+
+    use super::*;
+
+    #[test]
+    fn classes() {
+        let cases = [
+            (ErrorKind::NotFound, IoClass::NotFound),
+            (ErrorKind::QuotaExceeded, IoClass::OutOfSpace),
+            (ErrorKind::CrossesDevices, IoClass::CrossesDevices),
+            (ErrorKind::IsADirectory, IoClass::WrongFileType),
+            (ErrorKind::WouldBlock, IoClass::Retry),
+            (ErrorKind::NetworkDown, IoClass::Network),
+            (ErrorKind::InvalidData, IoClass::Other),
+        ];
+        for (kind, class) in cases {
+            assert_eq!(classify(&Error::from(kind)), class, "{kind:?}");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn os_codes() {
+        // ENOSPC, EXDEV, ENOTEMPTY, ETXTBSY, ELOOP, EBADF.
+        let cases = [
+            (28, IoClass::OutOfSpace),
+            (18, IoClass::CrossesDevices),
+            (39, IoClass::DirectoryNotEmpty),
+            (26, IoClass::Busy),
+            (40, IoClass::FilesystemLoop),
+            (9, IoClass::Other),
+        ];
+        for (code, class) in cases {
+            let err = Error::from_raw_os_error(code);
+            assert_eq!(classify(&err), class, "{err}");
+            assert_eq!(portable::classify(&err), class, "{err}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop() {
+        use std::os::unix::fs::symlink;
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("rust-unstable-loop-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        symlink(&b, &a).unwrap();
+        symlink(&a, &b).unwrap();
+        let err = fs::File::open(&a).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(classify(&err), IoClass::FilesystemLoop, "{err}");
+    }
+}
@@ -0,0 +1,207 @@
+//! `Iterator::array_chunks` usage
+//!
+//! Tracking issue [100450].
+//!
+//! `array_chunks::<N>()` groups an iterator's items into `[T; N]` arrays. If the iterator runs out
+//! mid-chunk, the partial chunk is not yielded but kept, and `into_remainder` hands it back.
+//!
+//! [`ArrayChunked`] wraps the std adapter under the `nightly` feature, and a buffering
+//! reimplementation otherwise, behind one API. [`decode_pairs`] uses it to read `(lo, hi)` index
+//! pairs from a flat stream, where a leftover item means the stream was truncated.
+//!
+//! [100450]: https://github.com/rust-lang/rust/issues/100450
+
+use alloc::vec::Vec;
+use core::{error, fmt};
+
+use crate::step::Idx;
+
+/// Extension trait adding [`array_chunked`](ArrayChunksExt::array_chunked) to every iterator.
+pub trait ArrayChunksExt: Iterator + Sized {
+    /// Returns an iterator over `N` items of `self` at a time.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `N` is zero.
+    fn array_chunked<const N: usize>(self) -> ArrayChunked<Self, N> {
+        ArrayChunked::new(self)
+    }
+}
+
+impl<I: Iterator> ArrayChunksExt for I {}
+
+/// Iterator over `N` items at a time, created by [`ArrayChunksExt::array_chunked`].
+pub struct ArrayChunked<I: Iterator, const N: usize> {
+    #[cfg(feature = "nightly")]
+    inner: core::iter::ArrayChunks<I, N>,
+    #[cfg(not(feature = "nightly"))]
+    inner: portable::ArrayChunks<I, N>,
+}
+
+impl<I: Iterator, const N: usize> ArrayChunked<I, N> {
+    #[cfg(feature = "nightly")]
+    fn new(iter: I) -> Self {
+        Self {
+            inner: iter.array_chunks(),
+        }
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    fn new(iter: I) -> Self {
+        Self {
+            inner: portable::ArrayChunks::new(iter),
+        }
+    }
+
+    /// Returns the items left over after the last full chunk, in order.
+    ///
+    /// Only meaningful once the iterator returned `None`; before that there is no remainder yet.
+    pub fn into_remainder(self) -> impl ExactSizeIterator<Item = I::Item> {
+        self.inner.into_remainder()
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for ArrayChunked<I, N> {
+    type Item = [I::Item; N];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// The error returned by [`decode_pairs`] when the stream has an odd number of items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedPair {
+    /// The unpaired last item.
+    pub trailing: usize,
+}
+
+impl fmt::Display for TruncatedPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "index stream ends with unpaired index {}", self.trailing)
+    }
+}
+
+impl error::Error for TruncatedPair {}
+
+/// Decodes a flat `lo, hi, lo, hi, ...` stream into index pairs.
+pub fn decode_pairs<I: Idx>(
+    flat: impl IntoIterator<Item = usize>,
+) -> Result<Vec<(I, I)>, TruncatedPair> {
+    let mut chunks = flat.into_iter().array_chunked::<2>();
+    let pairs = chunks
+        .by_ref()
+        .map(|[lo, hi]| (I::new(lo), I::new(hi)))
+        .collect();
+    match chunks.into_remainder().next() {
+        Some(trailing) => Err(TruncatedPair { trailing }),
+        None => Ok(pairs),
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use core::array;
+
+    pub(super) struct ArrayChunks<I: Iterator, const N: usize> {
+        iter: I,
+        /// The partial last chunk, filled from the front, once `iter` ran out.
+        remainder: [Option<I::Item>; N],
+    }
+
+    impl<I: Iterator, const N: usize> ArrayChunks<I, N> {
+        pub(super) fn new(iter: I) -> Self {
+            assert!(N != 0, "chunk size must be non-zero");
+            Self {
+                iter,
+                remainder: [const { None }; N],
+            }
+        }
+
+        pub(super) fn into_remainder(self) -> impl ExactSizeIterator<Item = I::Item> {
+            let len = self.remainder.iter().take_while(|t| t.is_some()).count();
+            self.remainder.into_iter().take(len).map(Option::unwrap)
+        }
+    }
+
+    impl<I: Iterator, const N: usize> Iterator for ArrayChunks<I, N> {
+        type Item = [I::Item; N];
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let mut buf: [Option<I::Item>; N] = [const { None }; N];
+            for (i, slot) in buf.iter_mut().enumerate() {
+                match self.iter.next() {
+                    Some(item) => *slot = Some(item),
+                    None => {
+                        if i > 0 {
+                            self.remainder = buf;
+                        }
+                        return None;
+                    }
+                }
+            }
+            Some(array::from_fn(|i| buf[i].take().unwrap()))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let (lo, hi) = self.iter.size_hint();
+            (lo / N, hi.map(|hi| hi / N))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks() {
+        let mut it = (1..=7).array_chunked::<3>();
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        assert_eq!(it.next(), Some([1, 2, 3]));
+        assert_eq!(it.next(), Some([4, 5, 6]));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.into_remainder().collect::<Vec<_>>(), [7]);
+    }
+
+    #[test]
+    fn no_remainder() {
+        let mut it = (1..=4).array_chunked::<2>();
+        assert_eq!(it.by_ref().count(), 2);
+        assert_eq!(it.into_remainder().len(), 0);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..10 {
+            let mut std = (0..len).map(|i| i.to_string()).array_chunks::<4>();
+            let mut portable = portable::ArrayChunks::<_, 4>::new((0..len).map(|i| i.to_string()));
+            assert_eq!(std.size_hint(), portable.size_hint());
+            assert_eq!(
+                std.by_ref().collect::<Vec<_>>(),
+                portable.by_ref().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                std.into_remainder().collect::<Vec<_>>(),
+                portable.into_remainder().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn pairs() {
+        let pairs: Vec<(u32, u32)> = decode_pairs([0, 4, 7, 9]).unwrap();
+        assert_eq!(pairs, [(0, 4), (7, 9)]);
+        assert_eq!(decode_pairs::<u32>([]), Ok(vec![]));
+        let err = decode_pairs::<u32>([0, 4, 7]).unwrap_err();
+        assert_eq!(err, TruncatedPair { trailing: 7 });
+        assert_eq!(err.to_string(), "index stream ends with unpaired index 7");
+    }
+}
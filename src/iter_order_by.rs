@@ -0,0 +1,237 @@
+//! `Iterator::{cmp_by, partial_cmp_by, eq_by}` usage
+//!
+//! Tracking issue [64295].
+//!
+//! `Iterator::cmp` and friends compare two iterators lexicographically, but only through the
+//! items' own `Ord`/`PartialEq` and only if both sides yield the same type. The `_by` variants
+//! take the comparison as a closure instead, so two `BitSet` iterators, or a slice against a
+//! projection of another, can be compared item by item without collecting either side.
+//!
+//! The free functions here forward to std under the `nightly` feature, and otherwise walk both
+//! iterators in lockstep until one item pair differs or a side runs out.
+//!
+//! [64295]: https://github.com/rust-lang/rust/issues/64295
+
+use std::cmp::Ordering;
+
+use crate::bit_set::BitSet;
+use crate::step::Idx;
+
+/// Lexicographically compares `a` and `b` using `cmp` on each item pair.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn cmp_by<A, B, F>(a: A, b: B, cmp: F) -> Ordering
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> Ordering,
+{
+    a.into_iter().cmp_by(b, cmp)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn cmp_by<A, B, F>(a: A, b: B, cmp: F) -> Ordering
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> Ordering,
+{
+    portable::cmp_by(a, b, cmp)
+}
+
+/// Lexicographically compares `a` and `b` using `partial_cmp` on each item pair. Returns `None`
+/// as soon as a pair is incomparable.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn partial_cmp_by<A, B, F>(a: A, b: B, partial_cmp: F) -> Option<Ordering>
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> Option<Ordering>,
+{
+    a.into_iter().partial_cmp_by(b, partial_cmp)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn partial_cmp_by<A, B, F>(a: A, b: B, partial_cmp: F) -> Option<Ordering>
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> Option<Ordering>,
+{
+    portable::partial_cmp_by(a, b, partial_cmp)
+}
+
+/// Returns whether `a` and `b` have the same length and `eq` holds for each item pair.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn eq_by<A, B, F>(a: A, b: B, eq: F) -> bool
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> bool,
+{
+    a.into_iter().eq_by(b, eq)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn eq_by<A, B, F>(a: A, b: B, eq: F) -> bool
+where
+    A: IntoIterator,
+    B: IntoIterator,
+    F: FnMut(A::Item, B::Item) -> bool,
+{
+    portable::eq_by(a, b, eq)
+}
+
+/// Compares the members of two sets as sorted sequences, e.g. `{1, 5} < {2}` and `{1} < {1, 2}`.
+///
+/// The sets may have different domain sizes.
+pub fn cmp_sets<T: Idx>(a: &BitSet<T>, b: &BitSet<T>) -> Ordering {
+    cmp_by(a, b, |x, y| x.index().cmp(&y.index()))
+}
+
+/// Returns whether the members of `set` are exactly `indices`, in order.
+pub fn set_eq_slice<T: Idx>(set: &BitSet<T>, indices: &[T]) -> bool {
+    eq_by(set, indices, |x, &y| x == y)
+}
+
+/// Lexicographically compares two slices by `key`, without collecting the keys.
+pub fn partial_cmp_slices_by_key<T, K: PartialOrd>(
+    a: &[T],
+    b: &[T],
+    mut key: impl FnMut(&T) -> K,
+) -> Option<Ordering> {
+    partial_cmp_by(a, b, |x, y| key(x).partial_cmp(&key(y)))
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::cmp::Ordering;
+
+    pub(super) fn cmp_by<A, B, F>(a: A, b: B, mut cmp: F) -> Ordering
+    where
+        A: IntoIterator,
+        B: IntoIterator,
+        F: FnMut(A::Item, B::Item) -> Ordering,
+    {
+        let (mut a, mut b) = (a.into_iter(), b.into_iter());
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => match cmp(x, y) {
+                    Ordering::Equal => {}
+                    non_eq => return non_eq,
+                },
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+            }
+        }
+    }
+
+    pub(super) fn partial_cmp_by<A, B, F>(a: A, b: B, mut partial_cmp: F) -> Option<Ordering>
+    where
+        A: IntoIterator,
+        B: IntoIterator,
+        F: FnMut(A::Item, B::Item) -> Option<Ordering>,
+    {
+        let (mut a, mut b) = (a.into_iter(), b.into_iter());
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => match partial_cmp(x, y) {
+                    Some(Ordering::Equal) => {}
+                    non_eq => return non_eq,
+                },
+                (None, None) => return Some(Ordering::Equal),
+                (None, Some(_)) => return Some(Ordering::Less),
+                (Some(_), None) => return Some(Ordering::Greater),
+            }
+        }
+    }
+
+    pub(super) fn eq_by<A, B, F>(a: A, b: B, mut eq: F) -> bool
+    where
+        A: IntoIterator,
+        B: IntoIterator,
+        F: FnMut(A::Item, B::Item) -> bool,
+    {
+        let (mut a, mut b) = (a.into_iter(), b.into_iter());
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => {
+                    if !eq(x, y) {
+                        return false;
+                    }
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(domain: usize, members: &[u32]) -> BitSet<u32> {
+        let mut set = BitSet::new_empty(domain);
+        members.iter().for_each(|&i| {
+            set.insert(i);
+        });
+        set
+    }
+
+    #[test]
+    fn sets() {
+        assert_eq!(cmp_sets(&set(10, &[1, 5]), &set(10, &[2])), Ordering::Less);
+        assert_eq!(cmp_sets(&set(10, &[1]), &set(10, &[1, 2])), Ordering::Less);
+        assert_eq!(cmp_sets(&set(10, &[3]), &set(200, &[3])), Ordering::Equal);
+        assert_eq!(cmp_sets(&set(10, &[]), &set(10, &[])), Ordering::Equal);
+        assert_eq!(
+            cmp_sets(&set(100, &[99]), &set(10, &[9])),
+            Ordering::Greater
+        );
+
+        assert!(set_eq_slice(&set(100, &[4, 70]), &[4, 70]));
+        assert!(!set_eq_slice(&set(100, &[4, 70]), &[4]));
+        assert!(!set_eq_slice(&set(100, &[4]), &[4, 70]));
+    }
+
+    #[test]
+    fn slices_by_key() {
+        let key = |s: &&str| s.len();
+        assert_eq!(
+            partial_cmp_slices_by_key(&["ab", "c"], &["xy", "zz"], key),
+            Some(Ordering::Less)
+        );
+        let key = |f: &f64| f.abs();
+        assert_eq!(
+            partial_cmp_slices_by_key(&[-1.0, 2.0], &[1.0, 2.0], key),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(partial_cmp_slices_by_key(&[f64::NAN], &[1.0], key), None);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let seqs: [&[i32]; 6] = [&[], &[1], &[1, 2], &[1, 3], &[2], &[-1, 2]];
+        for a in seqs {
+            for b in seqs {
+                let cmp = |x: &i32, y: &i32| x.abs().cmp(&y.abs());
+                assert_eq!(portable::cmp_by(a, b, cmp), a.iter().cmp_by(b, cmp));
+                let partial_cmp = |x: &i32, y: &i32| (*x > 0 && *y > 0).then(|| x.cmp(y));
+                assert_eq!(
+                    portable::partial_cmp_by(a, b, partial_cmp),
+                    a.iter().partial_cmp_by(b, partial_cmp)
+                );
+                let eq = |x: &i32, y: &i32| x.abs() == y.abs();
+                assert_eq!(portable::eq_by(a, b, eq), a.iter().eq_by(b, eq));
+            }
+        }
+    }
+}
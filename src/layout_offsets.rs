@@ -0,0 +1,171 @@
+//! `offset_of!` on enum variants and nested fields usage
+//!
+//! Tracking issues [120141] and [120140].
+//!
+//! `mem::offset_of!(Type, field)` is the byte offset of a field, as a constant. The path may go
+//! through nested fields, `offset_of!(Edge, span.len)`, and with `offset_of_enum` into an enum
+//! variant, `offset_of!(Operand, Const.span)`. Before, this took `MaybeUninit` and pointer
+//! arithmetic, which is easy to get subtly wrong. Nested fields are stable, variants are not yet.
+//!
+//! [`Reflect`] gives a packed struct a constant table of [`Field`]s, built by the `fields!`
+//! macro, that a serializer or debugger can look names up in with [`field_offset`]. The table for
+//! the [`Operand`] enum needs the `nightly` feature.
+//!
+//! [120141]: https://github.com/rust-lang/rust/issues/120141
+//! [120140]: https://github.com/rust-lang/rust/issues/120140
+
+use crate::raw_entry::Symbol;
+
+/// A named field and its byte offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    /// The path as passed to `offset_of!`, e.g. `"span.lo"`.
+    pub name: &'static str,
+    pub offset: usize,
+}
+
+/// Types with a table of their fields.
+pub trait Reflect {
+    const FIELDS: &'static [Field];
+}
+
+/// Builds a `&[Field]` for `$t` from field paths.
+macro_rules! fields {
+    ($t:ty; $($($part:tt).+),* $(,)?) => {
+        &[$(Field {
+            name: stringify!($($part).+),
+            offset: std::mem::offset_of!($t, $($part).+),
+        }),*]
+    };
+}
+
+/// A source location, packed into 8 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Span {
+    pub lo: u32,
+    pub len: u16,
+    pub ctxt: u16,
+}
+
+/// A graph edge between two indices, with where it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Edge {
+    pub source: u32,
+    pub target: u32,
+    pub span: Span,
+}
+
+/// A small tagged operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, u8)]
+pub enum Operand {
+    Local(u32),
+    Const { value: u64, span: Span },
+    Named(Symbol),
+}
+
+impl Reflect for Span {
+    const FIELDS: &'static [Field] = fields!(Span; lo, len, ctxt);
+}
+
+impl Reflect for Edge {
+    const FIELDS: &'static [Field] =
+        fields!(Edge; source, target, span, span.lo, span.len, span.ctxt);
+}
+
+#[cfg(feature = "nightly")]
+impl Reflect for Operand {
+    const FIELDS: &'static [Field] = fields!(
+        Operand;
+        Local.0,
+        Const.value,
+        Const.span,
+        Const.span.lo,
+        Const.span.len,
+        Const.span.ctxt,
+        Named.0,
+    );
+}
+
+/// Looks up the offset of the field at path `name` in `T`.
+pub fn field_offset<T: Reflect>(name: &str) -> Option<usize> {
+    T::FIELDS.iter().find(|f| f.name == name).map(|f| f.offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The offset of `field` in `base`, computed from their addresses.
+    fn distance<T, F>(base: &T, field: &F) -> usize {
+        (field as *const F).addr() - (base as *const T).addr()
+    }
+
+    #[test]
+    fn struct_offsets() {
+        let edge = Edge {
+            source: 1,
+            target: 2,
+            span: Span {
+                lo: 10,
+                len: 3,
+                ctxt: 0,
+            },
+        };
+        let manual = [
+            ("source", distance(&edge, &edge.source)),
+            ("target", distance(&edge, &edge.target)),
+            ("span", distance(&edge, &edge.span)),
+            ("span.lo", distance(&edge, &edge.span.lo)),
+            ("span.len", distance(&edge, &edge.span.len)),
+            ("span.ctxt", distance(&edge, &edge.span.ctxt)),
+        ];
+        for (name, offset) in manual {
+            assert_eq!(field_offset::<Edge>(name), Some(offset), "{name}");
+        }
+        assert_eq!(field_offset::<Edge>("span.len"), Some(12));
+        assert_eq!(field_offset::<Edge>("span.hi"), None);
+        assert_eq!(Span::FIELDS.len(), 3);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn variant_offsets() {
+        use crate::step::Idx;
+
+        let span = Span {
+            lo: 0,
+            len: 1,
+            ctxt: 2,
+        };
+        let operands = [
+            Operand::Local(4),
+            Operand::Const { value: 9, span },
+            Operand::Named(Symbol::new(7)),
+        ];
+        let mut manual = Vec::new();
+        for op in &operands {
+            match op {
+                Operand::Local(local) => manual.push(("Local.0", distance(op, local))),
+                Operand::Const { value, span } => manual.extend([
+                    ("Const.value", distance(op, value)),
+                    ("Const.span", distance(op, span)),
+                    ("Const.span.lo", distance(op, &span.lo)),
+                    ("Const.span.len", distance(op, &span.len)),
+                    ("Const.span.ctxt", distance(op, &span.ctxt)),
+                ]),
+                Operand::Named(symbol) => manual.push(("Named.0", distance(op, symbol))),
+            }
+        }
+        assert_eq!(manual.len(), Operand::FIELDS.len());
+        for (name, offset) in manual {
+            assert_eq!(field_offset::<Operand>(name), Some(offset), "{name}");
+        }
+        // The tag comes first, and every variant starts after it at the largest alignment of any,
+        // as in a `repr(C)` union.
+        assert_eq!(field_offset::<Operand>("Local.0"), Some(8));
+        assert_eq!(field_offset::<Operand>("Const.value"), Some(8));
+    }
+}
@@ -0,0 +1,319 @@
+//! `LazyCell::into_inner` usage
+//!
+//! Tracking issue [125623].
+//!
+//! `LazyCell::into_inner(cell)` takes a lazy value apart: `Ok` with the value if it was computed,
+//! or `Err` with the initializer if it wasn't. `LazyLock` has the same function. Together with
+//! `LazyCell::get`, which looks at the value without computing it, and `LazyCell::force_mut`,
+//! which computes it for a unique reference, both stable along with their `LazyLock` versions,
+//! a lazy value can be used as a cache that is thrown away and replaced when its inputs
+//! change. Without `into_inner` the old value can only be dropped, not handed back.
+//!
+//! [`CachedCell`] is such a cache, and [`SccGraph`] keeps the strongly connected components of a
+//! graph in one, resetting it whenever an edge is added. Without the `nightly` feature the cell is
+//! a local `LazyCell` built on `OnceCell`, with the same functions.
+//!
+//! [125623]: https://github.com/rust-lang/rust/issues/125623
+
+use std::mem;
+use std::rc::Rc;
+
+#[cfg(feature = "nightly")]
+use std::cell::LazyCell;
+
+#[cfg(not(feature = "nightly"))]
+use portable::LazyCell;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// A value computed on first use, which can be reset to be computed again.
+pub struct CachedCell<T, F = fn() -> T> {
+    lazy: LazyCell<T, F>,
+}
+
+impl<T, F: FnOnce() -> T> CachedCell<T, F> {
+    pub const fn new(init: F) -> Self {
+        CachedCell {
+            lazy: LazyCell::new(init),
+        }
+    }
+
+    /// The value, computed now if it hasn't been.
+    pub fn get(&self) -> &T {
+        LazyCell::force(&self.lazy)
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        LazyCell::force_mut(&mut self.lazy)
+    }
+
+    /// The value, if it has been computed.
+    pub fn peek(&self) -> Option<&T> {
+        LazyCell::get(&self.lazy)
+    }
+
+    pub fn is_computed(&self) -> bool {
+        self.peek().is_some()
+    }
+
+    /// Replaces the cache with one computed by `init`, and returns the old value if it was
+    /// computed.
+    pub fn reset(&mut self, init: F) -> Option<T> {
+        LazyCell::into_inner(mem::replace(&mut self.lazy, LazyCell::new(init))).ok()
+    }
+
+    /// The value if it was computed, or else the initializer.
+    pub fn into_inner(self) -> Result<T, F> {
+        LazyCell::into_inner(self.lazy)
+    }
+}
+
+/// The strongly connected components of a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sccs<I: Idx> {
+    component: IndexVec<I, usize>,
+    count: usize,
+}
+
+impl<I: Idx> Sccs<I> {
+    /// Computes the components with Kosaraju's algorithm. They are numbered in topological order,
+    /// so edges between components only go to higher numbers.
+    pub fn compute(successors: &IndexVec<I, Vec<I>>) -> Self {
+        let n = successors.len();
+        // Nodes in postorder of depth-first searches started from each unvisited node in turn.
+        let mut postorder = Vec::with_capacity(n);
+        let mut visited = vec![false; n];
+        for root in successors.indices() {
+            if mem::replace(&mut visited[root.index()], true) {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            while let Some((node, next)) = stack.last_mut() {
+                if let Some(&succ) = successors[*node].get(*next) {
+                    *next += 1;
+                    if !mem::replace(&mut visited[succ.index()], true) {
+                        stack.push((succ, 0));
+                    }
+                } else {
+                    postorder.push(*node);
+                    stack.pop();
+                }
+            }
+        }
+
+        let mut predecessors: IndexVec<I, Vec<I>> = IndexVec::from_elem_n(Vec::new(), n);
+        for (from, succs) in successors.iter_enumerated() {
+            for &to in succs {
+                predecessors[to].push(from);
+            }
+        }
+        let mut component = IndexVec::from_elem_n(usize::MAX, n);
+        let mut count = 0;
+        for &root in postorder.iter().rev() {
+            if component[root] != usize::MAX {
+                continue;
+            }
+            component[root] = count;
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for &pred in &predecessors[node] {
+                    if component[pred] == usize::MAX {
+                        component[pred] = count;
+                        stack.push(pred);
+                    }
+                }
+            }
+            count += 1;
+        }
+        Sccs { component, count }
+    }
+
+    /// The number of components.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn component(&self, node: I) -> usize {
+        self.component[node]
+    }
+}
+
+type PendingSccs<I> = Box<dyn FnOnce() -> Sccs<I>>;
+
+/// A graph that computes its strongly connected components when they are first asked for after a
+/// change.
+pub struct SccGraph<I: Idx> {
+    /// Shared with the pending computation, if there is one.
+    successors: Rc<IndexVec<I, Vec<I>>>,
+    sccs: CachedCell<Sccs<I>, PendingSccs<I>>,
+}
+
+impl<I: Idx> SccGraph<I> {
+    pub fn new(successors: IndexVec<I, Vec<I>>) -> Self {
+        let successors = Rc::new(successors);
+        SccGraph {
+            sccs: CachedCell::new(Self::pending(&successors)),
+            successors,
+        }
+    }
+
+    fn pending(successors: &Rc<IndexVec<I, Vec<I>>>) -> PendingSccs<I> {
+        let successors = successors.clone();
+        Box::new(move || Sccs::compute(&successors))
+    }
+
+    pub fn successors(&self, node: I) -> &[I] {
+        &self.successors[node]
+    }
+
+    /// Adds an edge, and returns the components from before it if they were computed.
+    ///
+    /// The edge lists are copied if the pending computation still shares them, i.e. if the
+    /// components weren't asked for since the last change.
+    pub fn add_edge(&mut self, from: I, to: I) -> Option<Sccs<I>> {
+        Rc::make_mut(&mut self.successors)[from].push(to);
+        self.sccs.reset(Self::pending(&self.successors))
+    }
+
+    pub fn sccs(&self) -> &Sccs<I> {
+        self.sccs.get()
+    }
+
+    /// The components, if they are up to date.
+    pub fn cached_sccs(&self) -> Option<&Sccs<I>> {
+        self.sccs.peek()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::cell::{Cell, OnceCell};
+
+    /// std's `LazyCell`.
+    pub(crate) struct LazyCell<T, F> {
+        value: OnceCell<T>,
+        /// Taken when the value is computed.
+        init: Cell<Option<F>>,
+    }
+
+    impl<T, F: FnOnce() -> T> LazyCell<T, F> {
+        pub(crate) const fn new(init: F) -> Self {
+            LazyCell {
+                value: OnceCell::new(),
+                init: Cell::new(Some(init)),
+            }
+        }
+
+        pub(crate) fn into_inner(this: Self) -> Result<T, F> {
+            match this.value.into_inner() {
+                Some(value) => Ok(value),
+                None => Err(this.init.into_inner().expect("LazyCell poisoned")),
+            }
+        }
+
+        pub(crate) fn force(this: &Self) -> &T {
+            this.value
+                .get_or_init(|| (this.init.take().expect("LazyCell poisoned"))())
+        }
+
+        pub(crate) fn force_mut(this: &mut Self) -> &mut T {
+            if this.value.get().is_none() {
+                let init = this.init.get_mut().take().expect("LazyCell poisoned");
+                let _ = this.value.set(init());
+            }
+            this.value.get_mut().expect("the value was just set")
+        }
+
+        pub(crate) fn get(this: &Self) -> Option<&T> {
+            this.value.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::LazyLock;
+
+    use super::*;
+
+    #[test]
+    fn reset_cache() {
+        let runs = Cell::new(0);
+        let init = || {
+            runs.set(runs.get() + 1);
+            vec![runs.get()]
+        };
+        let mut cache = CachedCell::new(init);
+        assert!(!cache.is_computed());
+        assert_eq!(cache.get(), &[1]);
+        assert_eq!(cache.get(), &[1]);
+        cache.get_mut().push(10);
+        assert_eq!(cache.peek(), Some(&vec![1, 10]));
+
+        assert_eq!(cache.reset(init), Some(vec![1, 10]));
+        assert_eq!(cache.peek(), None);
+        // Resetting a cache that wasn't computed drops the initializer without calling it.
+        assert_eq!(cache.reset(init), None);
+        cache.get_mut().push(20);
+        assert_eq!(cache.into_inner().ok(), Some(vec![2, 20]));
+        assert!(CachedCell::new(init).into_inner().is_err());
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn recompute_sccs() {
+        // 0 <-> 1 -> 2 -> 3 -> 2.
+        let mut graph: SccGraph<u32> =
+            SccGraph::new(IndexVec::from([vec![1], vec![0, 2], vec![3], vec![2]]));
+        assert!(graph.cached_sccs().is_none());
+        let sccs = graph.sccs();
+        assert_eq!(sccs.len(), 2);
+        assert_eq!(sccs.component(0), sccs.component(1));
+        assert!(sccs.component(1) < sccs.component(2));
+        assert_eq!(sccs.component(2), sccs.component(3));
+
+        let old = graph.add_edge(3, 0).unwrap();
+        assert_eq!(old.len(), 2);
+        assert!(graph.cached_sccs().is_none());
+        assert_eq!(graph.successors(3), [2, 0]);
+        // Two edges in a row: the second finds nothing computed to hand back.
+        assert!(graph.add_edge(2, 2).is_none());
+        assert_eq!(graph.sccs().len(), 1);
+        assert_eq!(graph.cached_sccs().unwrap().component(3), 0);
+    }
+
+    static TABLE: LazyLock<Sccs<u32>> =
+        LazyLock::new(|| Sccs::compute(&IndexVec::from([vec![0], vec![]])));
+
+    #[test]
+    fn lazy_lock_get() {
+        assert!(LazyLock::get(&TABLE).is_none());
+        assert_eq!(TABLE.len(), 2);
+        assert_eq!(LazyLock::get(&TABLE).map(|sccs| sccs.component(0)), Some(1));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let (mut std, mut portable) = (LazyCell::new(|| 7), portable::LazyCell::new(|| 7));
+        assert_eq!(LazyCell::get(&std), portable::LazyCell::get(&portable));
+        *LazyCell::force_mut(&mut std) += 1;
+        *portable::LazyCell::force_mut(&mut portable) += 1;
+        assert_eq!(LazyCell::force(&std), portable::LazyCell::force(&portable));
+        assert_eq!(
+            LazyCell::into_inner(std).ok(),
+            portable::LazyCell::into_inner(portable).ok()
+        );
+        let (std, portable) = (LazyCell::new(|| 1), portable::LazyCell::new(|| 1));
+        assert_eq!(
+            LazyCell::into_inner(std).map_err(|f| f()),
+            portable::LazyCell::into_inner(portable).map_err(|f| f())
+        );
+    }
+}
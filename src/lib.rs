@@ -1,3 +1,285 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(feature = "nightly", feature(step_trait, rustc_attrs))]
+#![cfg_attr(feature = "nightly", feature(f16, f128))]
+#![cfg_attr(feature = "nightly", feature(ascii_char))]
+#![cfg_attr(feature = "nightly", feature(pattern))]
+#![cfg_attr(feature = "nightly", feature(iter_array_chunks))]
+#![cfg_attr(feature = "nightly", feature(iter_intersperse))]
+#![cfg_attr(feature = "nightly", feature(iter_map_windows))]
+#![cfg_attr(feature = "nightly", feature(iter_order_by))]
+#![cfg_attr(feature = "nightly", feature(iter_collect_into))]
+#![cfg_attr(feature = "nightly", feature(iter_partition_in_place))]
+#![cfg_attr(feature = "nightly", feature(try_find))]
+#![cfg_attr(feature = "nightly", feature(btree_cursors))]
+#![cfg_attr(feature = "nightly", feature(linked_list_cursors))]
+#![cfg_attr(feature = "nightly", feature(hash_set_entry))]
+#![cfg_attr(
+    feature = "nightly",
+    feature(binary_heap_drain_sorted, binary_heap_into_iter_sorted)
+)]
+#![cfg_attr(feature = "nightly", feature(one_sided_range))]
+#![cfg_attr(feature = "nightly", feature(slice_split_once))]
+#![cfg_attr(feature = "nightly", feature(slice_partition_dedup))]
+#![cfg_attr(feature = "nightly", feature(array_try_from_fn, array_try_map))]
+#![cfg_attr(feature = "nightly", feature(maybe_uninit_fill))]
+#![cfg_attr(feature = "nightly", feature(vec_push_within_capacity))]
+#![cfg_attr(feature = "nightly", feature(vec_split_at_spare))]
+#![cfg_attr(feature = "nightly", feature(alloc_error_hook))]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+#![cfg_attr(feature = "nightly", feature(core_intrinsics, const_eval_select))]
+#![cfg_attr(feature = "nightly", feature(offset_of_enum))]
+#![cfg_attr(feature = "nightly", feature(variant_count))]
+#![cfg_attr(
+    feature = "nightly",
+    feature(maybe_uninit_uninit_array_transpose, maybe_uninit_array_assume_init)
+)]
+#![cfg_attr(feature = "nightly", feature(sync_unsafe_cell))]
+#![cfg_attr(feature = "nightly", feature(exclusive_wrapper))]
+#![cfg_attr(feature = "nightly", feature(mapped_lock_guards))]
+#![cfg_attr(feature = "nightly", feature(reentrant_lock))]
+#![cfg_attr(feature = "nightly", feature(once_cell_try))]
+#![cfg_attr(feature = "nightly", feature(lazy_cell_into_inner))]
+#![cfg_attr(feature = "nightly", feature(atomic_from_mut))]
+#![cfg_attr(
+    all(feature = "nightly", target_has_atomic = "128"),
+    feature(integer_atomics)
+)]
+#![cfg_attr(feature = "nightly", feature(thread_sleep_until))]
+#![cfg_attr(feature = "nightly", feature(thread_id_value))]
+#![cfg_attr(feature = "nightly", feature(panic_update_hook))]
+#![cfg_attr(feature = "nightly", feature(panic_always_abort))]
+#![cfg_attr(feature = "nightly", feature(error_generic_member_access))]
+#![cfg_attr(feature = "nightly", feature(error_reporter))]
+#![cfg_attr(feature = "nightly", feature(backtrace_frames))]
+#![cfg_attr(feature = "nightly", feature(io_error_more))]
+#![cfg_attr(feature = "nightly", feature(read_buf, core_io_borrowed_buf))]
+#![cfg_attr(feature = "nightly", feature(write_all_vectored, can_vector))]
+#![cfg_attr(feature = "nightly", feature(seek_stream_len))]
+#![cfg_attr(all(unix, feature = "nightly"), feature(unix_socket_ancillary_data))]
+#![cfg_attr(feature = "nightly", feature(tcp_linger))]
+#![cfg_attr(
+    all(target_os = "linux", feature = "nightly"),
+    feature(tcp_deferaccept)
+)]
+#![cfg_attr(all(unix, feature = "nightly"), feature(process_setsid))]
+#![cfg_attr(feature = "nightly", feature(exit_status_error))]
+#![cfg_attr(feature = "nightly", feature(exitcode_exit_method))]
+#![cfg_attr(
+    all(windows, feature = "nightly"),
+    feature(
+        windows_process_extensions_raw_attribute,
+        windows_process_extensions_main_thread_handle
+    )
+)]
+#![cfg_attr(all(unix, feature = "nightly"), feature(dir_entry_ext2))]
+#![cfg_attr(feature = "nightly", feature(os_str_slice, os_string_truncate))]
+#![cfg_attr(
+    feature = "nightly",
+    feature(duration_constructors, duration_integer_division)
+)]
+#![cfg_attr(feature = "nightly", feature(debug_closure_helpers))]
+#![cfg_attr(feature = "nightly", feature(formatting_options))]
+#![cfg_attr(feature = "nightly", feature(time_saturating_systemtime))]
+#![cfg_attr(all(test, feature = "nightly"), feature(time_systemtime_limits))]
+#![cfg_attr(feature = "nightly", feature(core_io))]
+#![cfg_attr(feature = "nightly", feature(string_remove_matches))]
+#![cfg_attr(feature = "nightly", feature(str_from_utf16_endian))]
+#![cfg_attr(feature = "nightly", feature(cow_is_borrowed))]
+#![cfg_attr(feature = "nightly", feature(get_mut_unchecked))]
+#![cfg_attr(feature = "nightly", feature(unique_rc_arc))]
+#![cfg_attr(feature = "nightly", feature(ptr_as_uninit))]
+#![cfg_attr(feature = "nightly", feature(macro_metavar_expr))]
+#![cfg_attr(feature = "nightly", feature(decl_macro))]
+#![cfg_attr(
+    feature = "nightly",
+    cfg_attr(version("1.81"), feature(macro_metavar_expr_concat))
+)]
+#![cfg_attr(feature = "nightly", feature(concat_bytes))]
+#![cfg_attr(feature = "nightly", feature(cfg_accessible, cfg_version))]
+#![cfg_attr(all(test, feature = "nightly"), feature(test))]
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod always_abort;
+#[cfg(feature = "std")]
+pub mod arc_table;
+#[cfg(feature = "std")]
+pub mod array_chunks;
+#[cfg(feature = "std")]
+pub mod array_try_map;
+#[cfg(feature = "std")]
+pub mod array_windows;
+#[cfg(feature = "std")]
+pub mod atomic_from_mut;
+#[cfg(feature = "std")]
+pub mod bit_set;
+pub mod btree_cursors;
+#[cfg(feature = "std")]
+pub mod cell_update;
+pub mod cfg_probe;
+#[cfg(feature = "std")]
+pub mod char_boundary;
+#[cfg(feature = "std")]
+pub mod collect_into;
+#[cfg(feature = "std")]
+pub mod concat_bytes;
+pub mod const_eval_select;
+#[cfg(feature = "std")]
+pub mod counting_alloc;
+#[cfg(feature = "std")]
+pub mod cow_is_borrowed;
+#[cfg(feature = "std")]
+pub mod deadline;
+#[cfg(feature = "nightly")]
+pub mod decl_macro;
+#[cfg(feature = "std")]
+pub mod dir_scan;
+#[cfg(feature = "std")]
+pub mod drain_sorted;
+#[cfg(feature = "std")]
+pub mod entry_insert;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod error_reporter;
+#[cfg(feature = "std")]
+pub mod exclusive;
+#[cfg(feature = "std")]
+pub mod exit_status_error;
+#[cfg(feature = "std")]
+pub mod extract_if;
+pub mod first_chunk;
+#[cfg(feature = "std")]
+pub mod float;
+#[cfg(feature = "std")]
+pub mod formatting_options;
+pub mod get_many_mut;
+#[cfg(feature = "std")]
+pub mod hash_set_entry;
+pub mod index_vec;
+pub mod inline_const;
+#[cfg(feature = "std")]
+pub mod integer_atomics;
+#[cfg(feature = "std")]
+pub mod intersperse;
+#[cfg(feature = "std")]
+pub mod interval;
+#[cfg(feature = "std")]
+pub mod io_error_more;
+pub mod iter_array_chunks;
+#[cfg(feature = "std")]
+pub mod iter_order_by;
+#[cfg(feature = "std")]
+pub mod layout_offsets;
+#[cfg(feature = "std")]
+pub mod lazy_cell;
+#[cfg(feature = "std")]
+pub mod linked_list_cursors;
+#[cfg(feature = "std")]
+pub mod locks;
+#[cfg(feature = "std")]
+pub mod map_many_mut;
+#[cfg(feature = "std")]
+pub mod map_windows;
+#[cfg(feature = "std")]
+pub mod midpoint;
+#[cfg(feature = "std")]
+pub mod net_ext;
+#[cfg(feature = "std")]
+pub mod new_zeroed;
+#[cfg(feature = "std")]
+pub mod num;
+#[cfg(feature = "std")]
+pub mod once_cell_try;
+#[cfg(feature = "std")]
+pub mod os_str_ext;
+#[cfg(feature = "std")]
+pub mod panic_hooks;
+#[cfg(feature = "std")]
+pub mod partition_dedup;
+#[cfg(feature = "std")]
+pub mod partition_in_place;
+#[cfg(feature = "std")]
+pub mod path_ext;
+#[cfg(feature = "std")]
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod process_ext;
+#[cfg(feature = "std")]
+pub mod ptr;
+#[cfg(feature = "std")]
+pub mod push_within_capacity;
+#[cfg(feature = "std")]
+pub mod raw_entry;
+#[cfg(feature = "std")]
+pub mod read_buf;
+#[cfg(feature = "std")]
+pub mod remove_matches;
+#[cfg(feature = "std")]
+pub mod ring_index;
+#[cfg(feature = "std")]
+pub mod saturating;
+#[cfg(feature = "std")]
+pub mod scoped_threads;
+#[cfg(feature = "std")]
+pub mod seek_stream_len;
+#[cfg(feature = "std")]
+pub mod sleep_until;
+#[cfg(feature = "std")]
+pub mod slice_flatten;
+#[cfg(feature = "std")]
+pub mod slice_split_once;
+#[cfg(feature = "std")]
+pub mod slice_take;
+#[cfg(feature = "std")]
+pub mod spawn_unchecked;
+#[cfg(feature = "std")]
+pub mod split_at_spare;
 pub mod step;
+#[cfg(feature = "std")]
+pub mod sync_unsafe_cell;
+#[cfg(feature = "std")]
+pub mod termination;
+#[cfg(feature = "std")]
+pub mod test_harness;
+#[cfg(feature = "std")]
+pub mod thread_id_value;
+#[cfg(feature = "std")]
+pub mod time;
+#[cfg(feature = "std")]
+pub mod try_find;
+#[cfg(feature = "std")]
+pub mod uninit;
+#[cfg(feature = "std")]
+pub mod uninit_array;
+#[cfg(feature = "std")]
+pub mod utf16;
+#[cfg(feature = "std")]
+pub mod utf8;
+pub mod variant_count;
+#[cfg(feature = "std")]
+pub mod vec_deque_pop_if;
+#[cfg(feature = "std")]
+pub mod vec_into_raw_parts;
+#[cfg(feature = "std")]
+pub mod work_queue;
+#[cfg(feature = "std")]
+pub mod write_all_vectored;
+#[cfg(feature = "std")]
+pub mod write_buf;
+
+// The nightly modules that also probe for what they wrap, see `cfg_probe`.
+cfg_probe::probed_modules! {
+    alloc_hooks if feature = "nightly", cfg_accessible(std::alloc::set_alloc_error_hook);
+    arena if feature = "nightly", cfg_accessible(std::alloc::Allocator);
+    ascii_char if feature = "nightly", cfg_accessible(core::ascii::Char);
+    backtrace_frames if feature = "nightly", cfg_accessible(std::backtrace::BacktraceFrame);
+    float16_128 if feature = "nightly", cfg_accessible(std::f16::consts::PI);
+    metavar_concat if feature = "nightly", cfg(version("1.81"));
+    reentrant_lock if feature = "nightly", cfg_accessible(std::sync::ReentrantLock);
+    unique_rc if feature = "nightly", cfg_accessible(std::rc::UniqueRc);
+    unix_ancillary if all(unix, feature = "nightly"),
+        cfg_accessible(std::os::unix::net::SocketAncillary);
+    windows_process if all(windows, feature = "nightly"),
+        cfg_accessible(std::os::windows::process::ProcThreadAttributeList);
+}
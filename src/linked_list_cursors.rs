@@ -0,0 +1,270 @@
+//! `LinkedList` cursor usage
+//!
+//! Tracking issue [58533].
+//!
+//! `LinkedList::cursor_front_mut` returns a `CursorMut` pointing at an element, or at the "ghost"
+//! position between the back and the front. It can walk both ways, and at the current position
+//! splice in another list or split the list in two in O(1), which is what linked lists are good
+//! for but what the rest of their API gives no way to do in the middle of the list.
+//!
+//! [`WorkList`] is a FIFO work list whose items can be grouped: a batch of follow-up items can be
+//! spliced in right after the item that produced them, the list can be cut at an item, and items
+//! can be pulled out with `LinkedList::extract_if` (stable since 1.87). Without the `nightly`
+//! feature it is backed by a `VecDeque`, where the same edits cost O(n) moves.
+//!
+//! [58533]: https://github.com/rust-lang/rust/issues/58533
+
+#[cfg(feature = "nightly")]
+use std::collections::LinkedList;
+#[cfg(not(feature = "nightly"))]
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A FIFO list of pending items.
+pub struct WorkList<T> {
+    #[cfg(feature = "nightly")]
+    items: LinkedList<T>,
+    #[cfg(not(feature = "nightly"))]
+    items: VecDeque<T>,
+}
+
+impl<T> WorkList<T> {
+    pub fn new() -> Self {
+        WorkList {
+            items: Default::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn push_back(&mut self, item: T) {
+        self.items.push_back(item);
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.items.iter()
+    }
+
+    /// Moves all of `other` in right after the first item matching `pred`, keeping its order.
+    /// Hands `other` back if no item matches.
+    #[cfg(feature = "nightly")]
+    pub fn splice_after(
+        &mut self,
+        mut pred: impl FnMut(&T) -> bool,
+        other: WorkList<T>,
+    ) -> Result<(), WorkList<T>> {
+        let mut cursor = self.items.cursor_front_mut();
+        while let Some(item) = cursor.current() {
+            if pred(item) {
+                cursor.splice_after(other.items);
+                return Ok(());
+            }
+            cursor.move_next();
+        }
+        Err(other)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn splice_after(
+        &mut self,
+        pred: impl FnMut(&T) -> bool,
+        other: WorkList<T>,
+    ) -> Result<(), WorkList<T>> {
+        portable::splice_after(&mut self.items, pred, other.items)
+            .map_err(|items| WorkList { items })
+    }
+
+    /// Splits the list before the first item matching `pred`, returning that item and everything
+    /// after it. Returns an empty list if no item matches.
+    #[cfg(feature = "nightly")]
+    pub fn split_off_at(&mut self, mut pred: impl FnMut(&T) -> bool) -> WorkList<T> {
+        let mut cursor = self.items.cursor_front_mut();
+        while let Some(item) = cursor.current() {
+            if pred(item) {
+                // From the first item this moves to the ghost position, which splits off all.
+                cursor.move_prev();
+                return WorkList {
+                    items: cursor.split_after(),
+                };
+            }
+            cursor.move_next();
+        }
+        WorkList::new()
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn split_off_at(&mut self, pred: impl FnMut(&T) -> bool) -> WorkList<T> {
+        WorkList {
+            items: portable::split_off_at(&mut self.items, pred),
+        }
+    }
+
+    /// Removes the items matching `pred` and returns them as a new list, both in order.
+    #[cfg(feature = "nightly")]
+    pub fn extract_where(&mut self, pred: impl FnMut(&mut T) -> bool) -> WorkList<T> {
+        WorkList {
+            items: self.items.extract_if(pred).collect(),
+        }
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn extract_where(&mut self, pred: impl FnMut(&mut T) -> bool) -> WorkList<T> {
+        WorkList {
+            items: portable::extract_where(&mut self.items, pred),
+        }
+    }
+}
+
+impl<T> Default for WorkList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for WorkList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> FromIterator<T> for WorkList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        WorkList {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::collections::VecDeque;
+
+    pub(super) fn splice_after<T>(
+        items: &mut VecDeque<T>,
+        pred: impl FnMut(&T) -> bool,
+        mut other: VecDeque<T>,
+    ) -> Result<(), VecDeque<T>> {
+        let Some(i) = items.iter().position(pred) else {
+            return Err(other);
+        };
+        let mut tail = items.split_off(i + 1);
+        items.append(&mut other);
+        items.append(&mut tail);
+        Ok(())
+    }
+
+    pub(super) fn split_off_at<T>(
+        items: &mut VecDeque<T>,
+        pred: impl FnMut(&T) -> bool,
+    ) -> VecDeque<T> {
+        match items.iter().position(pred) {
+            Some(i) => items.split_off(i),
+            None => VecDeque::new(),
+        }
+    }
+
+    pub(super) fn extract_where<T>(
+        items: &mut VecDeque<T>,
+        mut pred: impl FnMut(&mut T) -> bool,
+    ) -> VecDeque<T> {
+        let mut extracted = VecDeque::new();
+        let mut kept = VecDeque::with_capacity(items.len());
+        for mut item in items.drain(..) {
+            if pred(&mut item) {
+                extracted.push_back(item);
+            } else {
+                kept.push_back(item);
+            }
+        }
+        *items = kept;
+        extracted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items<T: Copy>(list: &WorkList<T>) -> Vec<T> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn fifo() {
+        let mut list: WorkList<u32> = (0..3).collect();
+        list.push_back(3);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(format!("{list:?}"), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn splice() {
+        let mut list: WorkList<u32> = [10, 20, 30].into_iter().collect();
+        // The follow-ups of 20 are processed before 30.
+        list.splice_after(|&x| x == 20, [21, 22].into_iter().collect())
+            .unwrap();
+        assert_eq!(items(&list), [10, 20, 21, 22, 30]);
+        list.splice_after(|&x| x == 30, [31].into_iter().collect())
+            .unwrap();
+        assert_eq!(items(&list), [10, 20, 21, 22, 30, 31]);
+        let rejected = list
+            .splice_after(|&x| x == 99, [1].into_iter().collect())
+            .unwrap_err();
+        assert_eq!(items(&rejected), [1]);
+    }
+
+    #[test]
+    fn split_and_extract() {
+        let mut list: WorkList<u32> = (0..6).collect();
+        let tail = list.split_off_at(|&x| x == 4);
+        assert_eq!((items(&list), items(&tail)), (vec![0, 1, 2, 3], vec![4, 5]));
+        let all = list.split_off_at(|&x| x == 0);
+        assert!(list.is_empty());
+        assert_eq!(items(&all), [0, 1, 2, 3]);
+        assert!(list.split_off_at(|_| true).is_empty());
+
+        let mut list: WorkList<u32> = (0..6).collect();
+        let odd = list.extract_where(|x| *x % 2 == 1);
+        assert_eq!((items(&list), items(&odd)), (vec![0, 2, 4], vec![1, 3, 5]));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        use std::collections::VecDeque;
+
+        for len in 0..6 {
+            for at in 0..=len {
+                let mut std: WorkList<u32> = (0..len).collect();
+                let mut portable: VecDeque<u32> = (0..len).collect();
+                let other = || [100, 101].into_iter();
+                assert_eq!(
+                    std.splice_after(|&x| x == at, other().collect()).is_ok(),
+                    portable::splice_after(&mut portable, |&x| x == at, other().collect()).is_ok()
+                );
+                assert_eq!(items(&std), Vec::from(portable.clone()));
+
+                let std_tail = std.split_off_at(|&x| x >= at);
+                let portable_tail = portable::split_off_at(&mut portable, |&x| x >= at);
+                assert_eq!(items(&std_tail), Vec::from(portable_tail));
+
+                let pred = |x: &mut u32| *x % 3 == at % 3;
+                let std_out = std.extract_where(pred);
+                let portable_out = portable::extract_where(&mut portable, pred);
+                assert_eq!(items(&std_out), Vec::from(portable_out));
+                assert_eq!(items(&std), Vec::from(portable));
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! `MutexGuard::map` and `RwLockReadGuard::map` usage
+//!
+//! Tracking issue [117108].
+//!
+//! `MutexGuard::map(guard, f)` narrows a guard over a whole value to a `MappedMutexGuard` over the
+//! part `f` picks, and `filter_map` does the same when the part may be missing. The lock stays
+//! held until the mapped guard is dropped, but its user can only reach that part. The `RwLock`
+//! guards have the same methods. Without them, a function that locks a collection can't return a
+//! reference into it, because the reference would outlive the guard it came from.
+//!
+//! [`SharedIndexVec`] is an `IndexVec` behind an `RwLock`, whose [`SharedIndexVec::read`] and
+//! [`SharedIndexVec::write`] return guards for a single element, and [`lock_element`] does the
+//! same for an `IndexVec` behind a `Mutex`. The closure-based [`SharedIndexVec::with`],
+//! [`SharedIndexVec::with_mut`] and [`with_element`] work without the `nightly` feature; they hold
+//! the lock only while the closure runs.
+//!
+//! [117108]: https://github.com/rust-lang/rust/issues/117108
+
+use std::sync::{Mutex, RwLock};
+
+#[cfg(feature = "nightly")]
+use std::sync::{
+    MappedMutexGuard, MappedRwLockReadGuard, MappedRwLockWriteGuard, MutexGuard, RwLockReadGuard,
+    RwLockWriteGuard,
+};
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// An `IndexVec` shared between threads behind a single lock.
+///
+/// All methods panic if the lock is poisoned.
+#[derive(Debug, Default)]
+pub struct SharedIndexVec<I: Idx, T> {
+    lock: RwLock<IndexVec<I, T>>,
+}
+
+impl<I: Idx, T> SharedIndexVec<I, T> {
+    pub fn new(vec: IndexVec<I, T>) -> Self {
+        SharedIndexVec {
+            lock: RwLock::new(vec),
+        }
+    }
+
+    pub fn push(&self, value: T) -> I {
+        self.lock.write().unwrap().push(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A shared guard for the element at `index`, or `None` if it is out of bounds.
+    #[cfg(feature = "nightly")]
+    pub fn read(&self, index: I) -> Option<MappedRwLockReadGuard<'_, T>> {
+        RwLockReadGuard::filter_map(self.lock.read().unwrap(), |vec| vec.get(index)).ok()
+    }
+
+    /// An exclusive guard for the element at `index`, or `None` if it is out of bounds.
+    #[cfg(feature = "nightly")]
+    pub fn write(&self, index: I) -> Option<MappedRwLockWriteGuard<'_, T>> {
+        RwLockWriteGuard::filter_map(self.lock.write().unwrap(), |vec| vec.get_mut(index)).ok()
+    }
+
+    /// Calls `f` with the element at `index` under a shared lock.
+    pub fn with<R>(&self, index: I, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.lock.read().unwrap().get(index).map(f)
+    }
+
+    /// Calls `f` with the element at `index` under an exclusive lock.
+    pub fn with_mut<R>(&self, index: I, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.lock.write().unwrap().get_mut(index).map(f)
+    }
+
+    pub fn into_inner(self) -> IndexVec<I, T> {
+        self.lock.into_inner().unwrap()
+    }
+}
+
+/// Locks `mutex` and returns a guard for the element at `index`, or `None` if it is out of bounds.
+///
+/// # Panics
+///
+/// Panics if the lock is poisoned.
+#[cfg(feature = "nightly")]
+pub fn lock_element<I: Idx, T>(
+    mutex: &Mutex<IndexVec<I, T>>,
+    index: I,
+) -> Option<MappedMutexGuard<'_, T>> {
+    MutexGuard::filter_map(mutex.lock().unwrap(), |vec| vec.get_mut(index)).ok()
+}
+
+/// Locks `mutex` and calls `f` with the element at `index`.
+///
+/// # Panics
+///
+/// Panics if the lock is poisoned.
+pub fn with_element<I: Idx, T, R>(
+    mutex: &Mutex<IndexVec<I, T>>,
+    index: I,
+    f: impl FnOnce(&mut T) -> R,
+) -> Option<R> {
+    mutex.lock().unwrap().get_mut(index).map(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn closure_access() {
+        let shared: SharedIndexVec<u32, Vec<u32>> = SharedIndexVec::default();
+        let (a, b) = (shared.push(Vec::new()), shared.push(Vec::new()));
+        thread::scope(|s| {
+            for t in 0..4 {
+                let shared = &shared;
+                s.spawn(move || {
+                    let node = if t % 2 == 0 { a } else { b };
+                    shared.with_mut(node, |v| v.push(t)).unwrap();
+                });
+            }
+        });
+        assert_eq!(shared.with(a, Vec::len), Some(2));
+        assert_eq!(shared.with(7, Vec::len), None);
+        let mut vec = shared.into_inner();
+        vec[b].sort();
+        assert_eq!(vec[b], [1, 3]);
+
+        let mutex = Mutex::new(IndexVec::<u32, u8>::from([1, 2]));
+        assert_eq!(
+            with_element(&mutex, 1, |x| std::mem::replace(x, 9)),
+            Some(2)
+        );
+        assert_eq!(with_element(&mutex, 2, |x| *x), None);
+        assert_eq!(mutex.into_inner().unwrap().raw, [1, 9]);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn element_guards() {
+        let shared: SharedIndexVec<u32, String> =
+            SharedIndexVec::new(IndexVec::from(["a".to_owned(), "b".to_owned()]));
+        shared.write(1).unwrap().push('!');
+        {
+            let (first, second) = (shared.read(0).unwrap(), shared.read(1).unwrap());
+            assert_eq!((first.as_str(), second.as_str()), ("a", "b!"));
+            // The mapped guards still hold the lock over the whole vector.
+            assert!(shared.lock.try_write().is_err());
+        }
+        assert!(shared.read(2).is_none());
+        assert!(shared.write(2).is_none());
+        assert_eq!(shared.len(), 2);
+
+        let mutex = Mutex::new(IndexVec::<u32, u8>::from([1, 2]));
+        let mut guard = lock_element(&mutex, 0).unwrap();
+        *guard += 10;
+        assert!(mutex.try_lock().is_err());
+        drop(guard);
+        assert!(lock_element(&mutex, 5).is_none());
+        assert_eq!(mutex.into_inner().unwrap().raw, [11, 2]);
+    }
+}
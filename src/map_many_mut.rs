@@ -0,0 +1,180 @@
+//! `HashMap::get_disjoint_mut` usage
+//!
+//! Tracking issue [97601].
+//!
+//! `map.get_disjoint_mut([k0, k1, ...])` returns mutable references to the values of several keys
+//! at once, which the borrow checker otherwise only allows one at a time. It started out as the
+//! unstable `map_many_mut` feature, named `get_many_mut`.
+//!
+//! The std method panics if two keys overlap. [`get_disjoint_mut`] checks for that first and
+//! returns a [`DisjointMutError`] instead, including for missing keys. [`UnionFind`] uses it to
+//! merge the values of two classes in place.
+//!
+//! [97601]: https://github.com/rust-lang/rust/issues/97601
+
+use core::{error, fmt};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// The error returned by [`get_disjoint_mut`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisjointMutError {
+    /// The key at this position is not in the map.
+    Missing(usize),
+    /// The keys at these two positions are equal.
+    Overlapping(usize, usize),
+}
+
+impl fmt::Display for DisjointMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisjointMutError::Missing(i) => write!(f, "key {i} is not in the map"),
+            DisjointMutError::Overlapping(i, j) => write!(f, "keys {i} and {j} are equal"),
+        }
+    }
+}
+
+impl error::Error for DisjointMutError {}
+
+/// Returns mutable references to the values for all `keys`, or an error if one is missing or two
+/// are equal.
+pub fn get_disjoint_mut<'a, K, V, S, Q, const N: usize>(
+    map: &'a mut HashMap<K, V, S>,
+    keys: [&Q; N],
+) -> Result<[&'a mut V; N], DisjointMutError>
+where
+    K: Borrow<Q> + Eq + Hash,
+    Q: Eq + Hash + ?Sized,
+    S: BuildHasher,
+{
+    for (j, key) in keys.iter().enumerate() {
+        if let Some(i) = keys[..j].iter().position(|k| k == key) {
+            return Err(DisjointMutError::Overlapping(i, j));
+        }
+    }
+    let values = map.get_disjoint_mut(keys);
+    if let Some(i) = values.iter().position(Option::is_none) {
+        return Err(DisjointMutError::Missing(i));
+    }
+    Ok(values.map(Option::unwrap))
+}
+
+/// Disjoint sets of keys, with a value per set.
+pub struct UnionFind<K, V> {
+    parent: HashMap<K, K>,
+    /// The value of each set, stored under its root.
+    values: HashMap<K, V>,
+}
+
+impl<K: Clone + Eq + Hash, V> UnionFind<K, V> {
+    pub fn new() -> Self {
+        UnionFind {
+            parent: HashMap::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Adds `key` as a set of its own. Returns false if it was already present.
+    pub fn insert(&mut self, key: K, value: V) -> bool {
+        if self.parent.contains_key(&key) {
+            return false;
+        }
+        self.parent.insert(key.clone(), key.clone());
+        self.values.insert(key, value);
+        true
+    }
+
+    /// Returns the root of the set containing `key`, compressing the path to it.
+    pub fn find(&mut self, key: &K) -> Option<K> {
+        let mut root = key.clone();
+        loop {
+            let parent = self.parent.get(&root)?;
+            if *parent == root {
+                break;
+            }
+            root = parent.clone();
+        }
+        let mut cur = key.clone();
+        while cur != root {
+            cur = std::mem::replace(self.parent.get_mut(&cur).unwrap(), root.clone());
+        }
+        Some(root)
+    }
+
+    /// Returns the value of the set containing `key`.
+    pub fn value(&mut self, key: &K) -> Option<&V> {
+        let root = self.find(key)?;
+        self.values.get(&root)
+    }
+
+    /// Merges the sets containing `a` and `b`. `merge` gets the value of `a`'s set, which is
+    /// kept, and that of `b`'s set, which is dropped afterwards.
+    ///
+    /// Returns `Ok(false)` if both are in the same set already, and an error if either is not
+    /// present.
+    pub fn union(
+        &mut self,
+        a: &K,
+        b: &K,
+        merge: impl FnOnce(&mut V, &mut V),
+    ) -> Result<bool, DisjointMutError> {
+        let ra = self.find(a).ok_or(DisjointMutError::Missing(0))?;
+        let rb = self.find(b).ok_or(DisjointMutError::Missing(1))?;
+        if ra == rb {
+            return Ok(false);
+        }
+        let [va, vb] = get_disjoint_mut(&mut self.values, [&ra, &rb])?;
+        merge(va, vb);
+        self.values.remove(&rb);
+        self.parent.insert(rb, ra);
+        Ok(true)
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> Default for UnionFind<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disjoint() {
+        let mut map: HashMap<&str, i32> = HashMap::from([("a", 1), ("b", 2), ("c", 3)]);
+        let [a, c] = get_disjoint_mut(&mut map, ["a", "c"]).unwrap();
+        std::mem::swap(a, c);
+        assert_eq!((map["a"], map["c"]), (3, 1));
+
+        assert_eq!(
+            get_disjoint_mut(&mut map, ["a", "b", "a"]).unwrap_err(),
+            DisjointMutError::Overlapping(0, 2)
+        );
+        let err = get_disjoint_mut(&mut map, ["a", "x"]).unwrap_err();
+        assert_eq!(err, DisjointMutError::Missing(1));
+        assert_eq!(err.to_string(), "key 1 is not in the map");
+    }
+
+    #[test]
+    fn union_merges_values() {
+        let mut uf: UnionFind<u32, Vec<u32>> = UnionFind::new();
+        for i in 0..5 {
+            assert!(uf.insert(i, vec![i]));
+        }
+        assert!(!uf.insert(0, vec![]));
+
+        let append = |a: &mut Vec<u32>, b: &mut Vec<u32>| a.append(b);
+        assert_eq!(uf.union(&0, &1, append), Ok(true));
+        assert_eq!(uf.union(&2, &3, append), Ok(true));
+        assert_eq!(uf.union(&3, &1, append), Ok(true));
+        assert_eq!(uf.union(&0, &2, append), Ok(false));
+        assert_eq!(uf.union(&0, &9, append), Err(DisjointMutError::Missing(1)));
+
+        assert_eq!(uf.find(&1), uf.find(&3));
+        assert_eq!(uf.value(&1).unwrap(), &[2, 3, 0, 1]);
+        assert_eq!(uf.value(&4).unwrap(), &[4]);
+    }
+}
@@ -0,0 +1,208 @@
+//! `Iterator::map_windows` usage
+//!
+//! Tracking issue [87155].
+//!
+//! `map_windows::<_, N>(f)` calls `f` with a `&[T; N]` of every `N` consecutive items, like
+//! `slice::array_windows` but for any iterator and without a backing slice. The adapter buffers
+//! the last `N` items internally, so `f` only gets a borrow of the window.
+//!
+//! [`deltas`] and [`collect_runs`] use windows of two to compute the steps between consecutive
+//! indices and the runs of adjacent ones, the latter feeding an [`IntervalSet`]. Without the
+//! `nightly` feature a buffering adapter in this module stands in for std's.
+//!
+//! [87155]: https://github.com/rust-lang/rust/issues/87155
+
+use crate::interval::IntervalSet;
+use crate::step::Idx;
+
+/// Calls `f` for each window of `N` consecutive items of `iter`.
+///
+/// # Panics
+///
+/// Will panic if `N` is zero.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn map_windows<I, F, R, const N: usize>(iter: I, f: F) -> impl Iterator<Item = R>
+where
+    I: Iterator,
+    F: FnMut(&[I::Item; N]) -> R,
+{
+    iter.map_windows(f)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn map_windows<I, F, R, const N: usize>(iter: I, f: F) -> impl Iterator<Item = R>
+where
+    I: Iterator,
+    F: FnMut(&[I::Item; N]) -> R,
+{
+    portable::MapWindows::new(iter, f)
+}
+
+/// Yields `b - a` for each pair of consecutive indices `a, b`.
+pub fn deltas<I: Idx>(indices: impl IntoIterator<Item = I>) -> impl Iterator<Item = isize> {
+    map_windows(indices.into_iter(), |&[a, b]: &[I; 2]| {
+        b.index() as isize - a.index() as isize
+    })
+}
+
+/// Inserts each run of adjacent indices in `indices`, e.g. `2..5` for `2, 3, 4`, into `set`.
+/// Returns the number of runs.
+pub fn collect_runs<I: Idx>(indices: &[I], set: &mut IntervalSet<I>) -> usize {
+    let (Some(&first), Some(&last)) = (indices.first(), indices.last()) else {
+        return 0;
+    };
+    // A window whose indices aren't adjacent ends a run at `a` and starts the next at `b`.
+    let breaks = map_windows(indices.iter().copied(), |&[a, b]: &[I; 2]| {
+        (b.index() != a.index() + 1).then_some((a, b))
+    });
+    let mut start = first;
+    let mut runs = 1;
+    for (end, next) in breaks.flatten() {
+        set.insert_range(start..end.plus(1));
+        start = next;
+        runs += 1;
+    }
+    set.insert_range(start..last.plus(1));
+    runs
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) struct MapWindows<I: Iterator, F, const N: usize> {
+        /// `None` once `iter` ran out, so the adapter stays fused.
+        iter: Option<I>,
+        /// Up to `2 * N` items, the current window being the last `N`. Shifting only once the
+        /// buffer is full keeps pushing amortized O(1), like a ring buffer, while the window stays
+        /// contiguous.
+        buf: Vec<I::Item>,
+        f: F,
+    }
+
+    impl<I: Iterator, F, const N: usize> MapWindows<I, F, N> {
+        pub(super) fn new(iter: I, f: F) -> Self {
+            assert!(
+                N != 0,
+                "array in `map_windows` must contain more than 0 elements"
+            );
+            Self {
+                iter: Some(iter),
+                buf: Vec::new(),
+                f,
+            }
+        }
+    }
+
+    impl<I, F, R, const N: usize> Iterator for MapWindows<I, F, N>
+    where
+        I: Iterator,
+        F: FnMut(&[I::Item; N]) -> R,
+    {
+        type Item = R;
+
+        fn next(&mut self) -> Option<R> {
+            let iter = self.iter.as_mut()?;
+            if self.buf.is_empty() {
+                self.buf.reserve_exact(2 * N);
+                self.buf.extend(iter.by_ref().take(N));
+                if self.buf.len() < N {
+                    self.iter = None;
+                    return None;
+                }
+            } else {
+                let Some(item) = iter.next() else {
+                    self.iter = None;
+                    return None;
+                };
+                if self.buf.len() == 2 * N {
+                    self.buf.drain(..N);
+                }
+                self.buf.push(item);
+            }
+            let window = self.buf[self.buf.len() - N..].try_into().unwrap();
+            Some((self.f)(window))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let Some(iter) = &self.iter else {
+                return (0, Some(0));
+            };
+            let (lo, hi) = iter.size_hint();
+            if self.buf.is_empty() {
+                // The first window takes `N` items, each later one a single item.
+                (
+                    lo.saturating_sub(N - 1),
+                    hi.map(|hi| hi.saturating_sub(N - 1)),
+                )
+            } else {
+                (lo, hi)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let sums: Vec<i32> = map_windows(1..=5, |w: &[i32; 3]| w.iter().sum()).collect();
+        assert_eq!(sums, [6, 9, 12]);
+        assert_eq!(map_windows(1..3, |_: &[i32; 3]| ()).count(), 0);
+    }
+
+    #[test]
+    fn steps() {
+        let d: Vec<_> = deltas([1usize, 4, 4, 2]).collect();
+        assert_eq!(d, [3, 0, -2]);
+        assert_eq!(deltas([7u32]).count(), 0);
+    }
+
+    #[test]
+    fn runs() {
+        let mut set = IntervalSet::new(20);
+        assert_eq!(collect_runs::<u32>(&[], &mut set), 0);
+        assert!(set.is_empty());
+        assert_eq!(collect_runs::<u32>(&[2, 3, 4, 8, 10, 11], &mut set), 3);
+        assert_eq!(
+            set.iter_intervals().collect::<Vec<_>>(),
+            [2..5, 8..9, 10..12]
+        );
+        assert_eq!(collect_runs::<u32>(&[19], &mut set), 1);
+        assert!(set.contains(19));
+    }
+
+    #[test]
+    fn portable_windows() {
+        let mut calls = 0;
+        let mut it = portable::MapWindows::new(0..10, |w: &[i32; 2]| {
+            calls += 1;
+            w[1] - w[0]
+        });
+        for remaining in (0..=9).rev() {
+            assert_eq!(it.size_hint(), (remaining, Some(remaining)));
+            assert_eq!(it.next(), (remaining > 0).then_some(1));
+        }
+        assert_eq!(it.next(), None);
+        drop(it);
+        assert_eq!(calls, 9);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..12 {
+            let f = |w: &[String; 4]| w.concat();
+            let mut std = (0..len).map(|i| i.to_string()).map_windows(f);
+            let mut portable = portable::MapWindows::new((0..len).map(|i| i.to_string()), f);
+            assert_eq!(std.size_hint(), portable.size_hint());
+            assert_eq!(
+                std.by_ref().collect::<Vec<_>>(),
+                portable.by_ref().collect::<Vec<_>>()
+            );
+            assert_eq!(std.next(), portable.next());
+        }
+    }
+}
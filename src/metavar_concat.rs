@@ -0,0 +1,86 @@
+//! `${concat(...)}` usage
+//!
+//! Tracking issue [124225].
+//!
+//! The `${concat(...)}` metavariable expression glues identifiers, metavariables holding
+//! identifiers and literals into one new identifier, so a macro can declare items named after its
+//! input. It replaces `concat_idents!`, which was removed: that could only be used in expression
+//! position, so it couldn't name an item, and the result wasn't usable as a path.
+//!
+//! [`index_domain!`](crate::index_domain) declares the items a domain of indices usually needs
+//! from the domain's name: `index_domain!(pub Local)` makes a `LocalIdx` index, a `LocalIdxVec<T>`
+//! for tables over it and a `LocalIdxSet` for sets of it, where each would be written out by hand
+//! otherwise. Like `enum_idx!`, the crate invoking the macro has to enable the feature itself.
+//! There is no stable equivalent, so the macro is only built with the `nightly` feature.
+//!
+//! [124225]: https://github.com/rust-lang/rust/issues/124225
+
+/// Declares `<Name>Idx`, a `u32` index, with `<Name>IdxVec<T>`, an `IndexVec` over it, and
+/// `<Name>IdxSet`, a `BitSet` of it.
+#[macro_export]
+macro_rules! index_domain {
+    ($(#[$attr:meta])* $vis:vis $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct ${concat($name, Idx)}(u32);
+
+        impl $crate::step::Idx for ${concat($name, Idx)} {
+            const MAX_INDEX: usize = u32::MAX as usize;
+
+            #[inline]
+            fn new(idx: usize) -> Self {
+                assert!(
+                    idx <= u32::MAX as usize,
+                    "index out of range for `{}`",
+                    stringify!(${concat($name, Idx)})
+                );
+                Self(idx as u32)
+            }
+
+            #[inline]
+            fn index(self) -> usize {
+                self.0 as usize
+            }
+        }
+
+        /// A table with an element per index.
+        $vis type ${concat($name, IdxVec)}<T> =
+            $crate::index_vec::IndexVec<${concat($name, Idx)}, T>;
+
+        /// A set of indices.
+        $vis type ${concat($name, IdxSet)} = $crate::bit_set::BitSet<${concat($name, Idx)}>;
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::step::Idx;
+
+    // By its textual scope: the module is declared by a macro, so the crate's own paths can't
+    // name macros exported from it.
+    index_domain!(
+        /// A local variable.
+        Local
+    );
+    index_domain!(pub(crate) Block);
+
+    #[test]
+    fn companions() {
+        let mut types: LocalIdxVec<&str> = LocalIdxVec::new();
+        let x = types.push("u32");
+        let y = types.push("bool");
+        assert_eq!((x, y.index()), (LocalIdx(0), 1));
+        assert_eq!(types[y], "bool");
+
+        let mut live = LocalIdxSet::new_empty(types.len());
+        assert!(live.insert(y));
+        assert!(!live.contains(x));
+
+        let blocks: BlockIdxVec<Vec<LocalIdx>> = BlockIdxVec::from_raw(vec![vec![x], vec![x, y]]);
+        assert_eq!(blocks[BlockIdx::new(1)].len(), 2);
+        assert_eq!(format!("{:?}", BlockIdx::new(3)), "BlockIdx(3)");
+        let mut visited = BlockIdxSet::new_empty(blocks.len());
+        visited.insert(BlockIdx::new(0));
+        assert_eq!(visited.iter().collect::<Vec<_>>(), [BlockIdx(0)]);
+    }
+}
@@ -0,0 +1,213 @@
+//! `midpoint` usage
+//!
+//! Tracking issue [110840].
+//!
+//! Averaging two values as `(a + b) / 2` overflows as soon as both are large, which is the classic
+//! binary search bug. The `midpoint` methods compute the average without overflow. Under the
+//! `nightly` feature they are used directly; otherwise the integer fallback is the
+//! `(a & b) + ((a ^ b) >> 1)` trick, and the float fallback halves before adding when the sum
+//! could overflow.
+//!
+//! [`idx_midpoint`] and [`partition_point`] apply this to [`Idx`] types, for binary searches over
+//! index ranges.
+//!
+//! [110840]: https://github.com/rust-lang/rust/issues/110840
+
+use std::ops::Range;
+
+use crate::step::Idx;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive numbers accepted by [`midpoint`].
+pub trait Midpoint: Copy + sealed::Sealed {
+    fn midpoint(self, other: Self) -> Self;
+}
+
+/// Calculates the middle point of `a` and `b`.
+///
+/// Integers are rounded towards zero, floats follow `f32::midpoint`/`f64::midpoint`.
+#[inline]
+pub fn midpoint<T: Midpoint>(a: T, b: T) -> T {
+    a.midpoint(b)
+}
+
+/// Calculates the middle point of two indices, rounded down.
+#[inline]
+pub fn idx_midpoint<I: Idx>(a: I, b: I) -> I {
+    I::new(midpoint(a.index(), b.index()))
+}
+
+/// Returns the first index in `range` for which `pred` is false, assuming `pred` is true for a
+/// prefix of `range` and false for the rest.
+///
+/// Returns `range.end` if `pred` holds for every index.
+pub fn partition_point<I: Idx>(range: Range<I>, mut pred: impl FnMut(I) -> bool) -> I {
+    let (mut lo, mut hi) = (range.start, range.end);
+    while lo.index() < hi.index() {
+        let mid = idx_midpoint(lo, hi);
+        if pred(mid) {
+            lo = mid.plus(1);
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    macro_rules! unsigned {
+        ($($t:ident),* $(,)?) => {$(
+            /// Floor of the average: the shared bits plus half of the differing ones.
+            pub(super) fn $t(a: $t, b: $t) -> $t {
+                (a & b) + ((a ^ b) >> 1)
+            }
+        )*};
+    }
+
+    macro_rules! signed {
+        ($($t:ident),* $(,)?) => {$(
+            /// Same as the unsigned case, but the arithmetic shift rounds towards negative
+            /// infinity, so odd negative sums are bumped back towards zero.
+            pub(super) fn $t(a: $t, b: $t) -> $t {
+                let floor = (a & b) + ((a ^ b) >> 1);
+                floor + (floor < 0 && (a ^ b) & 1 != 0) as $t
+            }
+        )*};
+    }
+
+    unsigned!(u8, u16, u32, u64, u128, usize);
+    signed!(i8, i16, i32, i64, i128, isize);
+
+    pub(super) fn f32(a: f32, b: f32) -> f32 {
+        // Every `f32` sum is exact enough in `f64` to round correctly back to `f32`.
+        ((a as f64 + b as f64) / 2.0) as f32
+    }
+
+    pub(super) fn f64(a: f64, b: f64) -> f64 {
+        const LO: f64 = f64::MIN_POSITIVE * 2.;
+        const HI: f64 = f64::MAX / 2.;
+
+        let (abs_a, abs_b) = (a.abs(), b.abs());
+        if abs_a <= HI && abs_b <= HI {
+            // Overflow is impossible.
+            (a + b) / 2.
+        } else if abs_a < LO {
+            // Not safe to halve `a`.
+            a + (b / 2.)
+        } else if abs_b < LO {
+            // Not safe to halve `b`.
+            (a / 2.) + b
+        } else {
+            // Safe to halve both.
+            (a / 2.) + (b / 2.)
+        }
+    }
+}
+
+macro_rules! impl_midpoint {
+    ($($t:ident),* $(,)?) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl Midpoint for $t {
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn midpoint(self, other: Self) -> Self {
+                <$t>::midpoint(self, other)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn midpoint(self, other: Self) -> Self {
+                portable::$t(self, other)
+            }
+        }
+    )*};
+}
+
+impl_midpoint!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(midpoint(0u8, 255), 127);
+        assert_eq!(midpoint(u64::MAX, u64::MAX - 2), u64::MAX - 1);
+        assert_eq!(midpoint(-1i32, 2), 0);
+        assert_eq!(midpoint(-3i32, 0), -1);
+        assert_eq!(midpoint(i128::MIN, i128::MAX), 0);
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(midpoint(1.0f64, 4.0), 2.5);
+        assert_eq!(midpoint(f64::MAX, f64::MAX), f64::MAX);
+        assert_eq!(midpoint(f32::MAX, -f32::MAX), 0.0);
+        assert!(midpoint(f64::NAN, 1.0).is_nan());
+    }
+
+    #[test]
+    fn portable_agrees_with_std() {
+        for a in i8::MIN..=i8::MAX {
+            for b in i8::MIN..=i8::MAX {
+                assert_eq!(portable::i8(a, b), a.midpoint(b), "{a} {b}");
+                assert_eq!(portable::u8(a as u8, b as u8), (a as u8).midpoint(b as u8));
+            }
+        }
+        macro_rules! extremes {
+            ($($t:ident),*) => {$(
+                let values = [$t::MIN, $t::MIN + 1, 0, 1, $t::MAX - 1, $t::MAX];
+                for a in values {
+                    for b in values {
+                        assert_eq!(portable::$t(a, b), a.midpoint(b));
+                    }
+                }
+            )*};
+        }
+        extremes!(u16, u32, u64, u128, usize, i16, i32, i64, i128, isize);
+        let floats = [
+            0.0,
+            -0.0,
+            1.0,
+            -7.5,
+            f64::MIN_POSITIVE,
+            f64::MIN_POSITIVE / 4.0,
+            f64::MAX,
+            f64::MIN,
+            f64::INFINITY,
+        ];
+        for a in floats {
+            for b in floats {
+                assert_eq!(portable::f64(a, b).to_bits(), a.midpoint(b).to_bits());
+                let (a, b) = (a as f32, b as f32);
+                assert_eq!(portable::f32(a, b).to_bits(), a.midpoint(b).to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn idx() {
+        assert_eq!(idx_midpoint(3usize, 8), 5);
+        assert_eq!(idx_midpoint(usize::MAX, usize::MAX - 1), usize::MAX - 1);
+    }
+
+    #[test]
+    fn binary_search() {
+        let sorted = [1, 3, 3, 5, 8, 13];
+        let point = partition_point(0usize..sorted.len(), |i| sorted[i] < 5);
+        assert_eq!(point, 3);
+        assert_eq!(point, sorted.partition_point(|&x| x < 5));
+        assert_eq!(
+            partition_point(0usize..sorted.len(), |_| true),
+            sorted.len()
+        );
+        assert_eq!(partition_point(2usize..2, |_| true), 2);
+    }
+}
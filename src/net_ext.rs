@@ -0,0 +1,176 @@
+//! `TcpStream::set_linger` and `TcpStreamExt::set_deferaccept` usage
+//!
+//! Tracking issues [88494] and [119639].
+//!
+//! `set_linger` sets `SO_LINGER`: how long closing a socket waits for unsent data to go out, where
+//! zero resets the connection instead. On Linux, `TcpStreamExt::set_deferaccept` sets
+//! `TCP_DEFER_ACCEPT`, which has the kernel hold back a connection until data arrives on it, for
+//! up to the given time, and the stable `set_quickack` turns off delayed
+//! acknowledgements. std has no way to pick the interface for UDP multicast, stable or not, so
+//! that is left out.
+//!
+//! [`TcpOptions`] collects these behind one type, with [`Capabilities`] saying which of them the
+//! target and build have. The others fail with `ErrorKind::Unsupported` instead of not compiling,
+//! so callers can treat them all as best effort. Without the `nightly` feature linger and deferred
+//! accept are unsupported everywhere.
+//!
+//! [88494]: https://github.com/rust-lang/rust/issues/88494
+//! [119639]: https://github.com/rust-lang/rust/issues/119639
+
+use std::io;
+use std::net::TcpStream;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::TcpStreamExt;
+use std::time::Duration;
+
+/// The [`TcpOptions`] this target and build support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub quickack: bool,
+    pub linger: bool,
+    pub defer_accept: bool,
+}
+
+impl Capabilities {
+    pub const CURRENT: Capabilities = Capabilities {
+        quickack: cfg!(target_os = "linux"),
+        linger: cfg!(feature = "nightly"),
+        defer_accept: cfg!(all(feature = "nightly", target_os = "linux")),
+    };
+}
+
+/// Socket options of a TCP stream beyond those on `TcpStream`.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpOptions<'a> {
+    stream: &'a TcpStream,
+}
+
+impl<'a> TcpOptions<'a> {
+    pub fn new(stream: &'a TcpStream) -> Self {
+        TcpOptions { stream }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::CURRENT
+    }
+
+    /// Sets whether acknowledgements are sent right away rather than delayed.
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        return self.stream.set_quickack(quickack);
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = quickack;
+            Err(unsupported("quickack"))
+        }
+    }
+
+    pub fn quickack(&self) -> io::Result<bool> {
+        #[cfg(target_os = "linux")]
+        return self.stream.quickack();
+        #[cfg(not(target_os = "linux"))]
+        Err(unsupported("quickack"))
+    }
+
+    /// Sets how long closing the socket waits for unsent data, or `None` to not wait.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        #[cfg(feature = "nightly")]
+        return self.stream.set_linger(linger);
+        #[cfg(not(feature = "nightly"))]
+        {
+            let _ = linger;
+            Err(unsupported("linger"))
+        }
+    }
+
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        #[cfg(feature = "nightly")]
+        return self.stream.linger();
+        #[cfg(not(feature = "nightly"))]
+        Err(unsupported("linger"))
+    }
+
+    /// Sets how long the kernel may hold back a connection that has no data yet.
+    pub fn set_defer_accept(&self, timeout: Duration) -> io::Result<()> {
+        #[cfg(all(feature = "nightly", target_os = "linux"))]
+        return self.stream.set_deferaccept(timeout);
+        #[cfg(not(all(feature = "nightly", target_os = "linux")))]
+        {
+            let _ = timeout;
+            Err(unsupported("defer_accept"))
+        }
+    }
+
+    pub fn defer_accept(&self) -> io::Result<Duration> {
+        #[cfg(all(feature = "nightly", target_os = "linux"))]
+        return self.stream.deferaccept();
+        #[cfg(not(all(feature = "nightly", target_os = "linux")))]
+        Err(unsupported("defer_accept"))
+    }
+}
+
+#[cfg(not(all(feature = "nightly", target_os = "linux")))]
+fn unsupported(option: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("the `{option}` socket option is not supported on this target or build"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// Checks that `set` then `get` round trips where `supported`, and both fail otherwise.
+    fn check<T: PartialEq + std::fmt::Debug>(
+        supported: bool,
+        set: io::Result<()>,
+        get: impl FnOnce() -> io::Result<T>,
+        expected: T,
+    ) {
+        if supported {
+            set.unwrap();
+            assert_eq!(get().unwrap(), expected);
+        } else {
+            assert_eq!(set.unwrap_err().kind(), io::ErrorKind::Unsupported);
+            assert_eq!(get().unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[test]
+    fn options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let options = TcpOptions::new(&stream);
+        let can = options.capabilities();
+
+        check(
+            can.quickack,
+            options.set_quickack(true),
+            || options.quickack(),
+            true,
+        );
+        let linger = Some(Duration::from_secs(2));
+        check(
+            can.linger,
+            options.set_linger(linger),
+            || options.linger(),
+            linger,
+        );
+        check(
+            can.linger,
+            options.set_linger(None),
+            || options.linger(),
+            None,
+        );
+        let timeout = Duration::from_secs(3);
+        check(
+            can.defer_accept,
+            options.set_defer_accept(timeout),
+            || options.defer_accept(),
+            timeout,
+        );
+    }
+}
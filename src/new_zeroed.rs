@@ -0,0 +1,143 @@
+//! `Box::new_uninit` and `Box::new_zeroed_slice` usage
+//!
+//! Tracking issues [63291] and [129396].
+//!
+//! `Box::<[T]>::new_zeroed_slice(n)` allocates `n` elements of zeroed memory as a
+//! `Box<[MaybeUninit<T>]>`, and `new_uninit_slice` the same without touching the memory. For a
+//! large allocation the zeroed form goes to `alloc_zeroed`, which can hand out fresh pages from the
+//! OS without writing to them at all. `assume_init` then turns the box into a `Box<[T]>` once the
+//! contents are valid.
+//!
+//! [`IndexVec::with_capacity_zeroed`] and [`BitSet::new_zeroed`] build on this for types that are
+//! valid when all zero, marked with the unsafe [`Zeroable`] trait. `vec![0; n]` already gets the
+//! same treatment for integers, but not for a struct of them, which is filled by cloning; the
+//! benches compare the two.
+//!
+//! [63291]: https://github.com/rust-lang/rust/issues/63291
+//! [129396]: https://github.com/rust-lang/rust/issues/129396
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// Types for which the all-zero bit pattern is a valid value.
+///
+/// # Safety
+///
+/// Implementors must be valid when all of their bytes are zero, padding included.
+pub unsafe trait Zeroable: Sized {}
+
+macro_rules! zeroable {
+    ($($t:ty)*) => {
+        // SAFETY: zero is a valid value of each of these.
+        $(unsafe impl Zeroable for $t {})*
+    };
+}
+
+zeroable!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize f32 f64 bool char);
+
+// SAFETY: an array of zero-valid elements has no other bytes.
+unsafe impl<T: Zeroable, const N: usize> Zeroable for [T; N] {}
+
+/// Allocates `n` zero values.
+pub fn zeroed_slice<T: Zeroable>(n: usize) -> Box<[T]> {
+    // SAFETY: all-zero memory is a valid `T`.
+    unsafe { Box::new_zeroed_slice(n).assume_init() }
+}
+
+impl<I: Idx, T: Zeroable> IndexVec<I, T> {
+    /// Creates a vector with a zero value for each index in `0..n`, straight from zeroed memory.
+    #[inline]
+    pub fn with_capacity_zeroed(n: usize) -> Self {
+        IndexVec::from_raw(zeroed_slice(n).into_vec())
+    }
+}
+
+impl<T: Idx> BitSet<T> {
+    /// Creates a new, empty bitset with a given `domain_size`, straight from zeroed memory.
+    #[inline]
+    pub fn new_zeroed(domain_size: usize) -> BitSet<T> {
+        BitSet::from_words(
+            domain_size,
+            zeroed_slice(domain_size.div_ceil(u64::BITS as usize)).into_vec(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeroed_containers() {
+        let v: IndexVec<u32, [u64; 3]> = IndexVec::with_capacity_zeroed(5);
+        assert_eq!(v.len(), 5);
+        assert!(v.iter().all(|w| *w == [0; 3]));
+        assert_eq!(
+            IndexVec::<u32, bool>::with_capacity_zeroed(2).raw,
+            [false; 2]
+        );
+        assert_eq!(IndexVec::<u32, char>::with_capacity_zeroed(1).raw, ['\0']);
+        assert!(IndexVec::<usize, f64>::with_capacity_zeroed(0).is_empty());
+
+        let mut set: BitSet<usize> = BitSet::new_zeroed(130);
+        assert_eq!(set, BitSet::new_empty(130));
+        assert!(set.insert(129));
+        assert_eq!(set.words().len(), 3);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    /// Large enough that the allocator maps fresh, already zeroed pages.
+    const N: usize = 1 << 22;
+
+    #[derive(Clone, Copy, Default)]
+    struct Stats {
+        count: u32,
+        weight: f32,
+        last: u64,
+    }
+
+    // SAFETY: all fields are zero-valid and there is no padding.
+    unsafe impl Zeroable for Stats {}
+
+    fn last(v: &IndexVec<usize, Stats>) -> (u32, f32, u64) {
+        let s = &v[black_box(N - 1)];
+        (s.count, s.weight, s.last)
+    }
+
+    /// One zeroed allocation.
+    #[bench]
+    fn index_vec_zeroed(b: &mut Bencher) {
+        b.iter(|| {
+            let v: IndexVec<usize, Stats> = IndexVec::with_capacity_zeroed(black_box(N));
+            last(&v)
+        });
+    }
+
+    /// `vec![Stats::default(); N]` writes every element.
+    #[bench]
+    fn index_vec_from_elem(b: &mut Bencher) {
+        b.iter(|| {
+            let v: IndexVec<usize, Stats> = IndexVec::from_elem_n(Stats::default(), black_box(N));
+            last(&v)
+        });
+    }
+
+    #[bench]
+    fn bit_set_new_zeroed(b: &mut Bencher) {
+        b.iter(|| black_box(BitSet::<usize>::new_zeroed(black_box(N * 64))));
+    }
+
+    /// `vec![0u64; n]` is already zero-allocated, so this should match the above.
+    #[bench]
+    fn bit_set_new_empty(b: &mut Bencher) {
+        b.iter(|| black_box(BitSet::<usize>::new_empty(black_box(N * 64))));
+    }
+}
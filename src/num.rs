@@ -0,0 +1,363 @@
+//! `isqrt`, `ilog` and `checked_next_multiple_of` polyfills
+//!
+//! Tracking issues [116226] (`isqrt`), [70887] (`int_log`) and [88581] (`int_roundings`).
+//!
+//! The free functions here forward to the std inherent methods under the `nightly` feature and to a
+//! portable implementation otherwise, so callers pinned to an older toolchain use one API either way.
+//!
+//! The portable implementations widen to `u128`, compute there, and narrow back. This keeps a single
+//! implementation for every primitive width at the cost of some speed on the fallback path.
+//!
+//! [116226]: https://github.com/rust-lang/rust/issues/116226
+//! [70887]: https://github.com/rust-lang/rust/issues/70887
+//! [88581]: https://github.com/rust-lang/rust/issues/88581
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive integers accepted by the free functions in this module.
+pub trait PrimInt: Copy + sealed::Sealed {
+    fn isqrt(self) -> Self;
+
+    fn checked_isqrt(self) -> Option<Self>;
+
+    fn checked_ilog(self, base: Self) -> Option<u32>;
+
+    fn checked_ilog2(self) -> Option<u32>;
+
+    fn checked_ilog10(self) -> Option<u32>;
+}
+
+/// Primitive unsigned integers accepted by [`checked_next_multiple_of`].
+pub trait PrimUnsigned: PrimInt {
+    fn checked_next_multiple_of(self, rhs: Self) -> Option<Self>;
+}
+
+/// Returns the square root of `x`, rounded down.
+///
+/// # Panics
+///
+/// Will panic if `x` is negative.
+#[inline]
+pub fn isqrt<T: PrimInt>(x: T) -> T {
+    x.isqrt()
+}
+
+/// Returns the square root of `x`, rounded down, or `None` if `x` is negative.
+#[inline]
+pub fn checked_isqrt<T: PrimInt>(x: T) -> Option<T> {
+    x.checked_isqrt()
+}
+
+/// Returns the logarithm of `x` with respect to `base`, rounded down.
+///
+/// # Panics
+///
+/// Will panic if `x` is not positive or `base` is less than 2.
+#[inline]
+pub fn ilog<T: PrimInt>(x: T, base: T) -> u32 {
+    x.checked_ilog(base)
+        .expect("argument of integer logarithm must be positive and base at least 2")
+}
+
+/// Returns the base 2 logarithm of `x`, rounded down.
+///
+/// # Panics
+///
+/// Will panic if `x` is not positive.
+#[inline]
+pub fn ilog2<T: PrimInt>(x: T) -> u32 {
+    x.checked_ilog2()
+        .expect("argument of integer logarithm must be positive")
+}
+
+/// Returns the base 10 logarithm of `x`, rounded down.
+///
+/// # Panics
+///
+/// Will panic if `x` is not positive.
+#[inline]
+pub fn ilog10<T: PrimInt>(x: T) -> u32 {
+    x.checked_ilog10()
+        .expect("argument of integer logarithm must be positive")
+}
+
+/// Returns the logarithm of `x` with respect to `base`, rounded down, or `None` if `x` is not
+/// positive or `base` is less than 2.
+#[inline]
+pub fn checked_ilog<T: PrimInt>(x: T, base: T) -> Option<u32> {
+    x.checked_ilog(base)
+}
+
+/// Returns the base 2 logarithm of `x`, rounded down, or `None` if `x` is not positive.
+#[inline]
+pub fn checked_ilog2<T: PrimInt>(x: T) -> Option<u32> {
+    x.checked_ilog2()
+}
+
+/// Returns the base 10 logarithm of `x`, rounded down, or `None` if `x` is not positive.
+#[inline]
+pub fn checked_ilog10<T: PrimInt>(x: T) -> Option<u32> {
+    x.checked_ilog10()
+}
+
+/// Returns the smallest multiple of `rhs` that is greater than or equal to `x`, or `None` if
+/// `rhs` is zero or the result would overflow.
+#[inline]
+pub fn checked_next_multiple_of<T: PrimUnsigned>(x: T, rhs: T) -> Option<T> {
+    x.checked_next_multiple_of(rhs)
+}
+
+/// Width-independent fallbacks, computed in `u128`.
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    /// Digit-by-digit square root; never overflows since `one <= x`.
+    pub(super) fn isqrt(mut x: u128) -> u128 {
+        let mut res = 0;
+        let mut one = 1 << (u128::BITS - 2);
+        while one > x {
+            one >>= 2;
+        }
+        while one != 0 {
+            if x >= res + one {
+                x -= res + one;
+                res = (res >> 1) + one;
+            } else {
+                res >>= 1;
+            }
+            one >>= 2;
+        }
+        res
+    }
+
+    pub(super) fn checked_ilog(mut x: u128, base: u128) -> Option<u32> {
+        if x == 0 || base < 2 {
+            return None;
+        }
+        let mut n = 0;
+        while x >= base {
+            x /= base;
+            n += 1;
+        }
+        Some(n)
+    }
+
+    pub(super) fn checked_ilog2(x: u128) -> Option<u32> {
+        if x == 0 {
+            return None;
+        }
+        Some(u128::BITS - 1 - x.leading_zeros())
+    }
+
+    pub(super) fn checked_next_multiple_of(x: u128, rhs: u128) -> Option<u128> {
+        match x.checked_rem(rhs)? {
+            0 => Some(x),
+            r => x.checked_add(rhs - r),
+        }
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),* $(,)?) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl PrimInt for $t {
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn isqrt(self) -> Self {
+                <$t>::isqrt(self)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn isqrt(self) -> Self {
+                // The square root of a `$t` always fits in a `$t`.
+                portable::isqrt(self as u128) as $t
+            }
+
+            #[inline]
+            fn checked_isqrt(self) -> Option<Self> {
+                Some(PrimInt::isqrt(self))
+            }
+
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn checked_ilog(self, base: Self) -> Option<u32> {
+                <$t>::checked_ilog(self, base)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn checked_ilog(self, base: Self) -> Option<u32> {
+                portable::checked_ilog(self as u128, base as u128)
+            }
+
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn checked_ilog2(self) -> Option<u32> {
+                <$t>::checked_ilog2(self)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn checked_ilog2(self) -> Option<u32> {
+                portable::checked_ilog2(self as u128)
+            }
+
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn checked_ilog10(self) -> Option<u32> {
+                <$t>::checked_ilog10(self)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn checked_ilog10(self) -> Option<u32> {
+                portable::checked_ilog(self as u128, 10)
+            }
+        }
+
+        impl PrimUnsigned for $t {
+            #[cfg(feature = "nightly")]
+            #[inline]
+            fn checked_next_multiple_of(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_next_multiple_of(self, rhs)
+            }
+
+            #[cfg(not(feature = "nightly"))]
+            #[inline]
+            fn checked_next_multiple_of(self, rhs: Self) -> Option<Self> {
+                let next = portable::checked_next_multiple_of(self as u128, rhs as u128)?;
+                <$t>::try_from(next).ok()
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),* $(,)?) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl PrimInt for $t {
+            #[inline]
+            fn isqrt(self) -> Self {
+                PrimInt::checked_isqrt(self).expect("argument of integer square root cannot be negative")
+            }
+
+            #[inline]
+            fn checked_isqrt(self) -> Option<Self> {
+                // Non-negative values share their bit pattern with the unsigned type, and the root
+                // of a non-negative `$t` is again a non-negative `$t`.
+                (self >= 0).then(|| PrimInt::isqrt(self as $u) as $t)
+            }
+
+            #[inline]
+            fn checked_ilog(self, base: Self) -> Option<u32> {
+                if self <= 0 || base < 2 {
+                    return None;
+                }
+                PrimInt::checked_ilog(self as $u, base as $u)
+            }
+
+            #[inline]
+            fn checked_ilog2(self) -> Option<u32> {
+                if self <= 0 {
+                    return None;
+                }
+                PrimInt::checked_ilog2(self as $u)
+            }
+
+            #[inline]
+            fn checked_ilog10(self) -> Option<u32> {
+                if self <= 0 {
+                    return None;
+                }
+                PrimInt::checked_ilog10(self as $u)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isqrt_small() {
+        assert_eq!(isqrt(0u32), 0);
+        assert_eq!(isqrt(1u32), 1);
+        assert_eq!(isqrt(15u32), 3);
+        assert_eq!(isqrt(16u32), 4);
+        assert_eq!(isqrt(u8::MAX), 15);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn isqrt_signed() {
+        assert_eq!(isqrt(99i64), 9);
+        assert_eq!(checked_isqrt(i32::MAX), Some(46340));
+        assert_eq!(checked_isqrt(-1i32), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn isqrt_negative() {
+        isqrt(-4i8);
+    }
+
+    #[test]
+    fn ilog_family() {
+        assert_eq!(ilog(81u32, 3), 4);
+        assert_eq!(ilog(80u32, 3), 3);
+        assert_eq!(ilog2(1u64), 0);
+        assert_eq!(ilog2(u64::MAX), 63);
+        assert_eq!(ilog10(999i32), 2);
+        assert_eq!(ilog10(1000i32), 3);
+        assert_eq!(checked_ilog(0u8, 2), None);
+        assert_eq!(checked_ilog(5u8, 1), None);
+        assert_eq!(checked_ilog2(-8i16), None);
+        assert_eq!(checked_ilog10(0usize), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ilog_zero() {
+        ilog2(0u32);
+    }
+
+    #[test]
+    fn next_multiple_of() {
+        assert_eq!(checked_next_multiple_of(16u32, 8), Some(16));
+        assert_eq!(checked_next_multiple_of(17u32, 8), Some(24));
+        assert_eq!(checked_next_multiple_of(1u32, 0), None);
+        assert_eq!(checked_next_multiple_of(u8::MAX, 2), None);
+        assert_eq!(checked_next_multiple_of(u128::MAX, 3), Some(u128::MAX));
+    }
+
+    /// The portable fallback must agree with the std methods it stands in for.
+    #[test]
+    fn portable_agrees_with_std() {
+        for x in 0..=u16::MAX {
+            assert_eq!(portable::isqrt(x as u128) as u16, x.isqrt());
+            assert_eq!(portable::checked_ilog2(x as u128), x.checked_ilog2());
+            for base in [0, 1, 2, 3, 10, 255] {
+                assert_eq!(
+                    portable::checked_ilog(x as u128, base as u128),
+                    x.checked_ilog(base)
+                );
+            }
+            for rhs in [0, 1, 7, 4096] {
+                let portable = portable::checked_next_multiple_of(x as u128, rhs as u128)
+                    .and_then(|v| u16::try_from(v).ok());
+                assert_eq!(portable, x.checked_next_multiple_of(rhs));
+            }
+        }
+        for x in [u64::MAX, u64::MAX - 1, 1 << 63, (1 << 32) - 1] {
+            assert_eq!(portable::isqrt(x as u128) as u64, x.isqrt());
+        }
+    }
+}
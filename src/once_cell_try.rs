@@ -0,0 +1,382 @@
+//! `OnceLock::get_or_try_init` usage
+//!
+//! Tracking issue [109737].
+//!
+//! `OnceLock::get_or_try_init(f)` is `get_or_init` for an `f` that can fail: the first `Ok` is
+//! stored and returned to every caller, while an `Err` is returned to the caller whose `f` produced
+//! it and leaves the cell empty, so a later call tries again. `OnceLock::wait`, which is stable,
+//! blocks until some other thread has filled the cell. Without `get_or_try_init`, a fallible
+//! initializer has to store the `Result` itself, which keeps the error around forever, or panic.
+//!
+//! [`Predefined`] is a table of symbols interned on first use, which fails if a name is listed
+//! twice, and [`ControlFlowGraph`] computes its [`Dominators`] on first use, which fails if a node
+//! can't be reached from the start. Both go through [`get_or_try_init`]. Without the `nightly`
+//! feature it computes the value before trying to store it, so threads that race on an empty cell
+//! may each run `f`; one value is kept and the others are dropped.
+//!
+//! [109737]: https://github.com/rust-lang/rust/issues/109737
+
+use core::{error, fmt};
+use std::sync::OnceLock;
+
+use crate::index_vec::IndexVec;
+use crate::raw_entry::{StrInterner, Symbol};
+use crate::step::Idx;
+
+/// Returns the value in `cell`, initializing it with `f` if it is empty.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn get_or_try_init<T, E>(
+    cell: &OnceLock<T>,
+    f: impl FnOnce() -> Result<T, E>,
+) -> Result<&T, E> {
+    cell.get_or_try_init(f)
+}
+
+/// Returns the value in `cell`, initializing it with `f` if it is empty.
+///
+/// Threads that find the cell empty at the same time may each call `f`.
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn get_or_try_init<T, E>(
+    cell: &OnceLock<T>,
+    f: impl FnOnce() -> Result<T, E>,
+) -> Result<&T, E> {
+    portable::get_or_try_init(cell, f)
+}
+
+/// The error returned when a name is listed twice in a [`Predefined`] table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateSymbol {
+    pub name: &'static str,
+}
+
+impl fmt::Display for DuplicateSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "symbol `{}` is predefined twice", self.name)
+    }
+}
+
+impl error::Error for DuplicateSymbol {}
+
+/// A fixed list of names, interned the first time the table is used.
+///
+/// The name at position `i` gets `Symbol::new(i)`, so the symbols can be written down as
+/// constants next to the list.
+pub struct Predefined {
+    names: &'static [&'static str],
+    interner: OnceLock<StrInterner>,
+}
+
+impl Predefined {
+    pub const fn new(names: &'static [&'static str]) -> Self {
+        Predefined {
+            names,
+            interner: OnceLock::new(),
+        }
+    }
+
+    /// The interner holding the names, built on first use.
+    pub fn interner(&self) -> Result<&StrInterner, DuplicateSymbol> {
+        get_or_try_init(&self.interner, || {
+            let mut interner = StrInterner::new();
+            for (i, &name) in self.names.iter().enumerate() {
+                if interner.intern(name).index() != i {
+                    return Err(DuplicateSymbol { name });
+                }
+            }
+            Ok(interner)
+        })
+    }
+
+    /// The interner, if it has been built.
+    pub fn get(&self) -> Option<&StrInterner> {
+        self.interner.get()
+    }
+
+    /// Blocks until another thread has built the interner.
+    pub fn wait(&self) -> &StrInterner {
+        self.interner.wait()
+    }
+
+    pub fn symbol(&self, name: &str) -> Result<Option<Symbol>, DuplicateSymbol> {
+        Ok(self.interner()?.get(name))
+    }
+}
+
+/// The error returned when a node can't be reached from the start of a [`ControlFlowGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unreachable<I> {
+    pub node: I,
+}
+
+impl<I: Idx> fmt::Display for Unreachable<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node {:?} is unreachable from the start node", self.node)
+    }
+}
+
+impl<I: Idx> error::Error for Unreachable<I> {}
+
+/// The immediate dominator of each node of a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators<I: Idx> {
+    start: I,
+    /// `idom[start]` is `start` itself.
+    idom: IndexVec<I, I>,
+}
+
+impl<I: Idx> Dominators<I> {
+    /// Computes the dominators with the iterative algorithm of Cooper, Harvey and Kennedy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` isn't a node of the graph, which an empty graph has none of.
+    pub fn compute(successors: &IndexVec<I, Vec<I>>, start: I) -> Result<Self, Unreachable<I>> {
+        let n = successors.len();
+        assert!(start.index() < n, "start node out of range");
+        // Postorder of a depth-first search from `start`, and each node's position in it.
+        let mut postorder = Vec::with_capacity(n);
+        let mut number = vec![usize::MAX; n];
+        let mut visited = vec![false; n];
+        visited[start.index()] = true;
+        let mut stack = vec![(start, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            if let Some(&succ) = successors[*node].get(*next) {
+                *next += 1;
+                if !std::mem::replace(&mut visited[succ.index()], true) {
+                    stack.push((succ, 0));
+                }
+            } else {
+                number[node.index()] = postorder.len();
+                postorder.push(*node);
+                stack.pop();
+            }
+        }
+        if let Some(i) = visited.iter().position(|&v| !v) {
+            return Err(Unreachable { node: I::new(i) });
+        }
+
+        let mut predecessors = vec![Vec::new(); n];
+        for (from, succs) in successors.iter_enumerated() {
+            for &to in succs {
+                predecessors[number[to.index()]].push(number[from.index()]);
+            }
+        }
+        // Indexed and valued by postorder number; `start` is the last one.
+        let mut idom = vec![None; n];
+        idom[n - 1] = Some(n - 1);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in (0..n - 1).rev() {
+                let mut new = None;
+                for &p in &predecessors[b] {
+                    if idom[p].is_some() {
+                        new = Some(new.map_or(p, |q| intersect(&idom, p, q)));
+                    }
+                }
+                if idom[b] != new {
+                    idom[b] = new;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut by_node = IndexVec::from_elem_n(start, n);
+        for (b, dom) in idom.into_iter().enumerate() {
+            let dom = dom.expect("every node is reachable");
+            by_node[postorder[b]] = postorder[dom];
+        }
+        Ok(Dominators {
+            start,
+            idom: by_node,
+        })
+    }
+
+    /// The closest strict dominator of `node`, or `None` for the start node.
+    pub fn immediate_dominator(&self, node: I) -> Option<I> {
+        (node != self.start).then(|| self.idom[node])
+    }
+
+    /// Whether every path from the start to `b` goes through `a`. Takes time proportional to the
+    /// depth of `b` in the dominator tree.
+    pub fn dominates(&self, a: I, b: I) -> bool {
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            if node == self.start {
+                return false;
+            }
+            node = self.idom[node];
+        }
+    }
+}
+
+/// Walks `a` and `b` up the dominator tree until they meet. Postorder numbers grow towards the
+/// start, so the lower one is the one to move.
+fn intersect(idom: &[Option<usize>], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while a < b {
+            a = idom[a].expect("processed");
+        }
+        while b < a {
+            b = idom[b].expect("processed");
+        }
+    }
+    a
+}
+
+/// A graph with a start node, whose dominators are computed when first asked for.
+pub struct ControlFlowGraph<I: Idx> {
+    successors: IndexVec<I, Vec<I>>,
+    start: I,
+    dominators: OnceLock<Dominators<I>>,
+}
+
+impl<I: Idx> ControlFlowGraph<I> {
+    /// # Panics
+    ///
+    /// Panics if `start` isn't a node of the graph.
+    pub fn new(successors: IndexVec<I, Vec<I>>, start: I) -> Self {
+        assert!(start.index() < successors.len(), "start node out of range");
+        ControlFlowGraph {
+            successors,
+            start,
+            dominators: OnceLock::new(),
+        }
+    }
+
+    pub fn successors(&self, node: I) -> &[I] {
+        &self.successors[node]
+    }
+
+    /// The dominators, computed on first use.
+    pub fn dominators(&self) -> Result<&Dominators<I>, Unreachable<I>> {
+        get_or_try_init(&self.dominators, || {
+            Dominators::compute(&self.successors, self.start)
+        })
+    }
+
+    /// Blocks until another thread has computed the dominators.
+    pub fn wait_dominators(&self) -> &Dominators<I> {
+        self.dominators.wait()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::sync::OnceLock;
+
+    pub(super) fn get_or_try_init<T, E>(
+        cell: &OnceLock<T>,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<&T, E> {
+        if let Some(value) = cell.get() {
+            return Ok(value);
+        }
+        let value = f()?;
+        // Fails if another thread filled the cell since, and then its value is the one kept.
+        let _ = cell.set(value);
+        Ok(cell.get().expect("the cell was just filled"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ptr, thread};
+
+    use super::*;
+
+    static KEYWORDS: Predefined = Predefined::new(&["fn", "let", "match"]);
+
+    #[test]
+    fn predefined_symbols() {
+        let interners: Vec<usize> = thread::scope(|s| {
+            let waiter = s.spawn(|| ptr::from_ref(KEYWORDS.wait()).addr());
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| ptr::from_ref(KEYWORDS.interner().unwrap()).addr()))
+                .collect();
+            let mut all: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            all.push(waiter.join().unwrap());
+            all
+        });
+        assert!(interners.iter().all(|&p| p == interners[0]));
+        assert_eq!(KEYWORDS.symbol("let"), Ok(Some(Symbol::new(1))));
+        assert_eq!(KEYWORDS.symbol("loop"), Ok(None));
+        assert_eq!(KEYWORDS.get().unwrap().resolve(Symbol::new(2)), "match");
+
+        let twice = Predefined::new(&["a", "b", "a"]);
+        assert_eq!(twice.interner().err(), Some(DuplicateSymbol { name: "a" }));
+        assert!(twice.get().is_none());
+        assert_eq!(
+            twice.symbol("b").unwrap_err().to_string(),
+            "symbol `a` is predefined twice"
+        );
+    }
+
+    #[test]
+    fn lazy_dominators() {
+        // 0 -> 1 -> 3 -> 4 -> 1, and 0 -> 2 -> 3.
+        let successors: IndexVec<u32, Vec<u32>> =
+            IndexVec::from([vec![1, 2], vec![3], vec![3], vec![4], vec![1]]);
+        let graph = ControlFlowGraph::new(successors.clone(), 0);
+        let doms: Vec<usize> = thread::scope(|s| {
+            let graph = &graph;
+            let waiter = s.spawn(move || ptr::from_ref(graph.wait_dominators()).addr());
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(move || ptr::from_ref(graph.dominators().unwrap()).addr()))
+                .collect();
+            let mut all: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            all.push(waiter.join().unwrap());
+            all
+        });
+        assert!(doms.iter().all(|&p| p == doms[0]));
+
+        let doms = graph.dominators().unwrap();
+        let idoms: Vec<_> = (0..5).map(|n| doms.immediate_dominator(n)).collect();
+        assert_eq!(idoms, [None, Some(0), Some(0), Some(0), Some(3)]);
+        assert!(doms.dominates(0, 4) && doms.dominates(3, 4) && doms.dominates(4, 4));
+        assert!(!doms.dominates(1, 3) && !doms.dominates(4, 1));
+
+        let mut successors = successors;
+        successors.push(vec![0]);
+        let graph = ControlFlowGraph::new(successors, 0);
+        assert_eq!(graph.dominators(), Err(Unreachable { node: 5 }));
+        // The error isn't stored, so the next call computes again and fails again.
+        assert_eq!(graph.dominators(), Err(Unreachable { node: 5 }));
+        assert_eq!(graph.successors(5), [0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "start node out of range")]
+    fn empty_graph() {
+        let _ = Dominators::compute(&IndexVec::<u32, Vec<u32>>::new(), 0);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let init = |ok: bool| {
+            calls.set(calls.get() + 1);
+            if ok { Ok(calls.get()) } else { Err("no") }
+        };
+        let (std, portable) = (OnceLock::new(), OnceLock::new());
+        assert_eq!(
+            get_or_try_init(&std, || init(false)),
+            portable::get_or_try_init(&portable, || init(false))
+        );
+        assert!(std.get().is_none() && portable.get().is_none());
+        assert_eq!(get_or_try_init(&std, || init(true)), Ok(&3));
+        assert_eq!(portable::get_or_try_init(&portable, || init(true)), Ok(&4));
+        // A filled cell doesn't call `f` again.
+        assert_eq!(get_or_try_init(&std, || init(false)), Ok(&3));
+        assert_eq!(portable::get_or_try_init(&portable, || init(false)), Ok(&4));
+        assert_eq!(calls.get(), 4);
+    }
+}
@@ -0,0 +1,251 @@
+//! `OsStr::slice_encoded_bytes` and `OsString::truncate` usage
+//!
+//! Tracking issues [118485] and [133262].
+//!
+//! An `OsStr` may hold bytes that aren't UTF-8 on Unix or unpaired surrogates on Windows, so
+//! code that only knows how to cut up a `str` ends up calling `to_string_lossy()` and losing them.
+//! `as_encoded_bytes()` gives the bytes to search, but until `slice_encoded_bytes()` there was no
+//! safe way back: a slice of them had to go through `from_encoded_bytes_unchecked`, promising that
+//! it was cut at a valid boundary. `slice_encoded_bytes` checks the boundary and panics if it
+//! isn't one, and `OsString::truncate` does the same in place.
+//!
+//! [`strip_prefix`], [`strip_suffix`], [`split_once`] and [`split`] work like their `str`
+//! namesakes, with the pattern a `str`, so every match falls on a valid boundary.
+//! [`split_at_ascii`] splits before an ASCII byte found by other means, and
+//! [`truncate_suffix`] removes a suffix from an `OsString` without reallocating. Without the
+//! `nightly` feature the slices are made with `from_encoded_bytes_unchecked`.
+//!
+//! [118485]: https://github.com/rust-lang/rust/issues/118485
+//! [133262]: https://github.com/rust-lang/rust/issues/133262
+
+use std::ffi::{OsStr, OsString};
+use std::ops::Range;
+
+/// `s[range]`, where both ends of `range` lie on valid `OsStr` boundaries.
+#[cfg(feature = "nightly")]
+fn slice(s: &OsStr, range: Range<usize>) -> &OsStr {
+    s.slice_encoded_bytes(range)
+}
+
+#[cfg(not(feature = "nightly"))]
+fn slice(s: &OsStr, range: Range<usize>) -> &OsStr {
+    portable::slice(s, range)
+}
+
+/// Shortens `s` to `len` bytes, where `len` lies on a valid `OsStr` boundary.
+#[cfg(feature = "nightly")]
+fn truncate(s: &mut OsString, len: usize) {
+    s.truncate(len)
+}
+
+#[cfg(not(feature = "nightly"))]
+fn truncate(s: &mut OsString, len: usize) {
+    portable::truncate(s, len)
+}
+
+/// The byte offset of the first `pattern` in `s`.
+fn find(s: &OsStr, pattern: &str) -> Option<usize> {
+    let pattern = pattern.as_bytes();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    s.as_encoded_bytes()
+        .windows(pattern.len())
+        .position(|w| w == pattern)
+}
+
+/// `s` without `prefix`, or `None` if it doesn't start with it.
+pub fn strip_prefix<'a>(s: &'a OsStr, prefix: &str) -> Option<&'a OsStr> {
+    let bytes = s.as_encoded_bytes();
+    bytes
+        .starts_with(prefix.as_bytes())
+        .then(|| slice(s, prefix.len()..bytes.len()))
+}
+
+/// `s` without `suffix`, or `None` if it doesn't end with it.
+pub fn strip_suffix<'a>(s: &'a OsStr, suffix: &str) -> Option<&'a OsStr> {
+    let bytes = s.as_encoded_bytes();
+    bytes
+        .ends_with(suffix.as_bytes())
+        .then(|| slice(s, 0..bytes.len() - suffix.len()))
+}
+
+/// `s` split around the first `delimiter`.
+pub fn split_once<'a>(s: &'a OsStr, delimiter: &str) -> Option<(&'a OsStr, &'a OsStr)> {
+    let start = find(s, delimiter)?;
+    let len = s.as_encoded_bytes().len();
+    Some((slice(s, 0..start), slice(s, start + delimiter.len()..len)))
+}
+
+/// `s` split before byte `index`, or `None` unless that byte is ASCII.
+pub fn split_at_ascii(s: &OsStr, index: usize) -> Option<(&OsStr, &OsStr)> {
+    let bytes = s.as_encoded_bytes();
+    if !bytes.get(index)?.is_ascii() {
+        return None;
+    }
+    Some((slice(s, 0..index), slice(s, index..bytes.len())))
+}
+
+/// The parts of `s` between occurrences of `delimiter`, which must not be empty.
+pub fn split<'a>(s: &'a OsStr, delimiter: &'a str) -> Split<'a> {
+    assert!(!delimiter.is_empty(), "empty delimiter");
+    Split {
+        rest: Some(s),
+        delimiter,
+    }
+}
+
+/// The iterator returned by [`split`].
+#[derive(Debug, Clone)]
+pub struct Split<'a> {
+    rest: Option<&'a OsStr>,
+    delimiter: &'a str,
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a OsStr;
+
+    fn next(&mut self) -> Option<&'a OsStr> {
+        let rest = self.rest?;
+        match split_once(rest, self.delimiter) {
+            Some((part, rest)) => {
+                self.rest = Some(rest);
+                Some(part)
+            }
+            None => self.rest.take(),
+        }
+    }
+}
+
+/// Removes `suffix` from the end of `s`, returning whether it was there.
+pub fn truncate_suffix(s: &mut OsString, suffix: &str) -> bool {
+    let Some(len) = strip_suffix(s, suffix).map(|rest| rest.len()) else {
+        return false;
+    };
+    truncate(s, len);
+    true
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::ffi::{OsStr, OsString};
+    use std::mem;
+    use std::ops::Range;
+
+    pub(super) fn slice(s: &OsStr, range: Range<usize>) -> &OsStr {
+        let bytes = &s.as_encoded_bytes()[range];
+        // SAFETY: the caller splits next to a `str` or an ASCII byte, which are valid boundaries.
+        unsafe { OsStr::from_encoded_bytes_unchecked(bytes) }
+    }
+
+    pub(super) fn truncate(s: &mut OsString, len: usize) {
+        let mut bytes = mem::take(s).into_encoded_bytes();
+        bytes.truncate(len);
+        // SAFETY: as for `slice`.
+        *s = unsafe { OsString::from_encoded_bytes_unchecked(bytes) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A string this platform can't turn into a `str`.
+    #[cfg(unix)]
+    fn invalid() -> OsString {
+        use std::os::unix::ffi::OsStringExt;
+
+        OsString::from_vec(b"\xFFname\xC3".to_vec())
+    }
+
+    #[cfg(windows)]
+    fn invalid() -> OsString {
+        use std::os::windows::ffi::OsStringExt;
+
+        // An unpaired high surrogate.
+        OsString::from_wide(&[0xD800, 'n' as u16, 0xDC00])
+    }
+
+    /// `invalid()` with `prefix` before and `suffix` after.
+    fn around(prefix: &str, suffix: &str) -> OsString {
+        let mut s = OsString::from(prefix);
+        s.push(invalid());
+        s.push(suffix);
+        s
+    }
+
+    #[test]
+    fn strips() {
+        let s = OsStr::new("--name=value");
+        assert_eq!(strip_prefix(s, "--"), Some(OsStr::new("name=value")));
+        assert_eq!(strip_prefix(s, "-n"), None);
+        assert_eq!(strip_suffix(s, "value"), Some(OsStr::new("--name=")));
+        assert_eq!(strip_suffix(s, ""), Some(s));
+
+        let s = around("--", ".txt");
+        assert!(s.to_str().is_none());
+        assert_eq!(strip_prefix(&s, "--"), Some(&*around("", ".txt")));
+        assert_eq!(strip_suffix(&s, ".txt"), Some(&*around("--", "")));
+        assert_eq!(strip_prefix(&invalid(), "x"), None);
+    }
+
+    #[test]
+    fn splits() {
+        let s = OsStr::new("a=b=c");
+        assert_eq!(
+            split_once(s, "="),
+            Some((OsStr::new("a"), OsStr::new("b=c")))
+        );
+        assert_eq!(split_once(s, ";"), None);
+        let parts: Vec<&OsStr> = split(s, "=").collect();
+        assert_eq!(parts, ["a", "b", "c"]);
+        let parts: Vec<&OsStr> = split(OsStr::new(",x,"), ",").collect();
+        assert_eq!(parts, ["", "x", ""]);
+
+        let key = around("", "");
+        let s = around("", "=ü=");
+        let (k, v) = split_once(&s, "=").unwrap();
+        assert_eq!((k, v), (&*key, OsStr::new("ü=")));
+        let parts: Vec<&OsStr> = split(&s, "=").collect();
+        assert_eq!(parts, [&*key, OsStr::new("ü"), OsStr::new("")]);
+
+        let s = around("k", ".v");
+        let dot = s
+            .as_encoded_bytes()
+            .iter()
+            .rposition(|&b| b == b'.')
+            .unwrap();
+        assert_eq!(
+            split_at_ascii(&s, dot),
+            Some((&*around("k", ""), OsStr::new(".v")))
+        );
+        assert_eq!(split_at_ascii(OsStr::new("ü"), 1), None);
+        assert_eq!(split_at_ascii(OsStr::new("ab"), 2), None);
+    }
+
+    #[test]
+    fn truncates() {
+        let mut s = around("", ".tmp");
+        let capacity = s.capacity();
+        assert!(truncate_suffix(&mut s, ".tmp"));
+        assert_eq!(s, invalid());
+        assert!(!truncate_suffix(&mut s, ".tmp"));
+        assert!(truncate_suffix(&mut s, ""));
+        assert_eq!(s, invalid());
+        assert_eq!(s.capacity(), capacity);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let s = around("a=", "=b");
+        let len = s.len();
+        for range in [0..0, 0..1, 0..2, 2..len - 2, len - 1..len] {
+            assert_eq!(portable::slice(&s, range.clone()), slice(&s, range));
+        }
+        let (mut from_std, mut ported) = (s.clone(), s);
+        truncate(&mut from_std, len - 2);
+        portable::truncate(&mut ported, len - 2);
+        assert_eq!(from_std, ported);
+    }
+}
@@ -0,0 +1,192 @@
+//! `panic::update_hook` usage
+//!
+//! Tracking issue [92649].
+//!
+//! `panic::update_hook(f)` replaces the panic hook with `f`, which is handed the previous hook to
+//! call. Adding to the hook otherwise takes a `take_hook` followed by a `set_hook`, and a hook
+//! that another thread sets in between is lost. Either way a library can add its own output to
+//! panics without dropping the hook the application set up, such as one that logs or reports.
+//!
+//! [`in_operation`] records the container operation running on the current thread, along with
+//! the index type it works on, while a closure runs. [`install`] adds a hook that prints it after
+//! the previous hook's message, so an index out of range in a deep helper says which table it
+//! came from. Without the `nightly` feature [`update_hook`] is `take_hook` and `set_hook`.
+//!
+//! [92649]: https://github.com/rust-lang/rust/issues/92649
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::panic::PanicHookInfo;
+use std::{any, fmt};
+
+use crate::step::Idx;
+use crate::write_buf::{ArrayString, Truncation};
+
+thread_local! {
+    static OPERATION: Cell<Option<Operation>> = const { Cell::new(None) };
+}
+
+/// A container operation, and the index type it is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub name: &'static str,
+    pub domain: &'static str,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` on `{}` indices", self.name, self.domain)
+    }
+}
+
+/// Runs `f` with the operation `name` on `I` recorded for this thread.
+pub fn in_operation<I: Idx, R>(name: &'static str, f: impl FnOnce() -> R) -> R {
+    /// Restores the outer operation, also on unwinding.
+    struct Restore(Option<Operation>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OPERATION.set(self.0);
+        }
+    }
+
+    let operation = Operation {
+        name,
+        domain: any::type_name::<I>(),
+    };
+    let _restore = Restore(OPERATION.replace(Some(operation)));
+    f()
+}
+
+/// The innermost operation recorded by [`in_operation`] on this thread.
+pub fn current_operation() -> Option<Operation> {
+    OPERATION.get()
+}
+
+/// Replaces the panic hook with `hook`, which is passed the previous one.
+#[cfg(feature = "nightly")]
+pub fn update_hook(
+    hook: impl Fn(&(dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static), &PanicHookInfo<'_>)
+    + Send
+    + Sync
+    + 'static,
+) {
+    std::panic::update_hook(hook);
+}
+
+/// Replaces the panic hook with `hook`, which is passed the previous one.
+#[cfg(not(feature = "nightly"))]
+pub fn update_hook(
+    hook: impl Fn(&(dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static), &PanicHookInfo<'_>)
+    + Send
+    + Sync
+    + 'static,
+) {
+    portable::update_hook(hook);
+}
+
+/// Adds a hook that prints the panicking thread's current operation, if any, after the output of
+/// the hook that was set before. Each call adds another layer, so call it once.
+pub fn install() {
+    update_hook(|previous, info| {
+        previous(info);
+        if let Some(operation) = current_operation() {
+            // Formatted on the stack, since the panic may be an allocation failing.
+            let mut note = ArrayString::<256>::with_truncation(Truncation::SILENT.marker("...\n"));
+            let _ = fmt::Write::write_fmt(
+                &mut note,
+                format_args!("note: panicked during {operation}\n"),
+            );
+            // A failed write to stderr has nowhere to be reported.
+            let _ = io::stderr().write_all(note.as_bytes());
+        }
+    });
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::panic::{self, PanicHookInfo};
+
+    pub(super) fn update_hook(
+        hook: impl Fn(&(dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static), &PanicHookInfo<'_>)
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| hook(&*previous, info)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::process::Command;
+
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    #[test]
+    fn nested_operations() {
+        assert_eq!(current_operation(), None);
+        in_operation::<u32, _>("outer", || {
+            let inner = in_operation::<usize, _>("inner", current_operation).unwrap();
+            assert_eq!(inner.to_string(), "`inner` on `usize` indices");
+            let result = panic::catch_unwind(|| in_operation::<u32, _>("unwound", || panic!()));
+            assert!(result.is_err());
+            assert_eq!(current_operation().unwrap().name, "outer");
+        });
+        assert_eq!(current_operation(), None);
+    }
+
+    /// Set in a child test process to the hook it should layer on a reporting one.
+    const CHILD: &str = "RUST_UNSTABLE_PANIC_HOOKS_CHILD";
+
+    /// Runs `child` below in a new process of this test binary, returning what it printed to
+    /// stderr.
+    fn run_child(layer: &str) -> String {
+        let output = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "panic_hooks::tests::child", "--nocapture"])
+            .env(CHILD, layer)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{layer}");
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    /// Does nothing unless run by `run_child`. The panic hook is global, so it is only changed in
+    /// a process of its own.
+    #[test]
+    fn child() {
+        let Ok(layer) = env::var(CHILD) else {
+            return;
+        };
+        panic::set_hook(Box::new(|_| {
+            let operation = current_operation().map(|op| op.to_string());
+            eprintln!("previous hook: {}", operation.as_deref().unwrap_or("none"));
+        }));
+        match layer.as_str() {
+            "install" => install(),
+            "portable" => portable::update_hook(|previous, info| previous(info)),
+            _ => {}
+        }
+
+        let mut v: IndexVec<u32, u8> = IndexVec::from([1, 2]);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            in_operation::<u32, _>("swap", || v.swap(0, 5))
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn keeps_previous_hook() {
+        let previous = "previous hook: `swap` on `u32` indices\n";
+        assert_eq!(
+            run_child("install"),
+            format!("{previous}note: panicked during `swap` on `u32` indices\n")
+        );
+        #[cfg(feature = "nightly")]
+        assert_eq!(run_child("portable"), previous);
+    }
+}
@@ -0,0 +1,150 @@
+//! `<[T]>::partition_dedup` usage
+//!
+//! Tracking issue [54279].
+//!
+//! `slice.partition_dedup()` moves all but the first of each run of equal consecutive elements to
+//! the end of the slice, and returns the unique part and the duplicates as two slices. It is
+//! `Vec::dedup` for a slice, which can't be shortened, so the duplicates are kept instead of
+//! dropped. `partition_dedup_by_key` compares a key of each element instead.
+//!
+//! [`IndexSlice::partition_dedup`] returns the length of the unique part as a typed index, and
+//! [`dedup_into_intervals`] uses it to turn a sorted buffer of points into [`IntervalSet`] runs.
+//! Without the `nightly` feature the same write-cursor loop is run on the slice directly, moving
+//! each new unique element forward with a swap.
+//!
+//! [54279]: https://github.com/rust-lang/rust/issues/54279
+
+use crate::index_vec::IndexSlice;
+use crate::interval::IntervalSet;
+use crate::step::Idx;
+
+impl<J: Idx, T> IndexSlice<J, T> {
+    /// Moves all but the first of each run of equal elements to the end, and returns the index of
+    /// the first of those.
+    #[cfg(feature = "nightly")]
+    #[inline]
+    pub fn partition_dedup(&mut self) -> J
+    where
+        T: PartialEq,
+    {
+        J::new(self.raw.partition_dedup().0.len())
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    #[inline]
+    pub fn partition_dedup(&mut self) -> J
+    where
+        T: PartialEq,
+    {
+        J::new(portable::partition_dedup_by(&mut self.raw, |a, b| a == b))
+    }
+
+    /// Like [`partition_dedup`](Self::partition_dedup), comparing `key` of the elements.
+    #[cfg(feature = "nightly")]
+    #[inline]
+    pub fn partition_dedup_by_key<K: PartialEq>(&mut self, mut key: impl FnMut(&T) -> K) -> J {
+        J::new(self.raw.partition_dedup_by_key(|t| key(t)).0.len())
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    #[inline]
+    pub fn partition_dedup_by_key<K: PartialEq>(&mut self, mut key: impl FnMut(&T) -> K) -> J {
+        J::new(portable::partition_dedup_by(&mut self.raw, |a, b| {
+            key(a) == key(b)
+        }))
+    }
+}
+
+/// Deduplicates the sorted `points` in place, adds them to `set`, and returns how many were
+/// unique. The unique points come first in `points` afterwards.
+pub fn dedup_into_intervals<J: Idx, I: Idx + Ord>(
+    points: &mut IndexSlice<J, I>,
+    set: &mut IntervalSet<I>,
+) -> J {
+    debug_assert!(points.raw.is_sorted());
+    let unique = points.partition_dedup();
+    // Consecutive points go in as one range.
+    let mut points = points.raw[..unique.index()].iter();
+    if let Some(&first) = points.next() {
+        let mut run = first..first.plus(1);
+        for &point in points {
+            if point == run.end {
+                run.end = point.plus(1);
+            } else {
+                set.insert_range(std::mem::replace(&mut run, point..point.plus(1)));
+            }
+        }
+        set.insert_range(run);
+    }
+    unique
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    /// Returns the length of the unique part.
+    pub(super) fn partition_dedup_by<T>(
+        slice: &mut [T],
+        mut same_bucket: impl FnMut(&T, &T) -> bool,
+    ) -> usize {
+        if slice.is_empty() {
+            return 0;
+        }
+        // `slice[..write]` is the unique part so far.
+        let mut write = 1;
+        for read in 1..slice.len() {
+            if !same_bucket(&slice[read], &slice[write - 1]) {
+                slice.swap(read, write);
+                write += 1;
+            }
+        }
+        write
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index_vec::IndexVec;
+
+    use super::*;
+
+    #[test]
+    fn dedup_typed() {
+        let mut v: IndexVec<u32, i32> = IndexVec::from([1, 1, 2, 3, 3, 3, 1]);
+        let unique = v.partition_dedup();
+        assert_eq!(unique, 4);
+        assert_eq!(v[0..unique].raw, [1, 2, 3, 1]);
+
+        let mut pairs: IndexVec<usize, (char, u8)> =
+            IndexVec::from([('a', 1), ('a', 2), ('b', 3), ('c', 4), ('c', 5)]);
+        let unique = pairs.partition_dedup_by_key(|&(c, _)| c);
+        assert_eq!(pairs[0..unique].raw, [('a', 1), ('b', 3), ('c', 4)]);
+        assert_eq!(IndexVec::<u32, u8>::new().partition_dedup(), 0);
+    }
+
+    #[test]
+    fn points_to_intervals() {
+        let mut points: IndexVec<usize, u32> = IndexVec::from([2, 2, 3, 4, 4, 9, 11, 12, 12]);
+        let mut set = IntervalSet::new(16);
+        set.insert(0);
+        assert_eq!(dedup_into_intervals(&mut points, &mut set), 6);
+        assert_eq!(points[0..6].raw, [2, 3, 4, 9, 11, 12]);
+        assert_eq!(format!("{set:?}"), "[0..1, 2..5, 9..10, 11..13]");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..8 {
+            for seed in 0..32u32 {
+                let mut std: Vec<u32> = (0..len).map(|i| (i * seed / 5) % 3).collect();
+                let mut portable = std.clone();
+                let n = std.partition_dedup().0.len();
+                assert_eq!(
+                    portable::partition_dedup_by(&mut portable, |a, b| a == b),
+                    n
+                );
+                assert_eq!(std, portable);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! `Iterator::partition_in_place` usage
+//!
+//! Tracking issue [62543].
+//!
+//! `partition_in_place(pred)` reorders the items of a double-ended iterator over `&mut T` so all
+//! items satisfying `pred` come first, and returns how many did. Unlike `sort_by_key` on a bool
+//! it swaps from both ends and visits each element once, but does not keep the relative order.
+//!
+//! [`IndexSlice::partition_in_place`] returns the split point as a typed index, so a compaction
+//! pass can move the live entries to the front and `truncate` at the result. Without the
+//! `nightly` feature the same two-ended swap loop is run on the slice directly.
+//!
+//! [62543]: https://github.com/rust-lang/rust/issues/62543
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Moves the elements satisfying `pred` before those that don't, in no particular order, and
+    /// returns the index of the first element that doesn't.
+    #[cfg(feature = "nightly")]
+    #[inline]
+    pub fn partition_in_place(&mut self, mut pred: impl FnMut(&T) -> bool) -> I {
+        I::new(self.raw.iter_mut().partition_in_place(|t| pred(t)))
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    #[inline]
+    pub fn partition_in_place(&mut self, pred: impl FnMut(&T) -> bool) -> I {
+        I::new(portable::partition_in_place(&mut self.raw, pred))
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn partition_in_place<T>(
+        slice: &mut [T],
+        mut pred: impl FnMut(&T) -> bool,
+    ) -> usize {
+        // Everything before `lo` is known to satisfy `pred`, everything from `hi` on is known not
+        // to.
+        let (mut lo, mut hi) = (0, slice.len());
+        loop {
+            while lo < hi && pred(&slice[lo]) {
+                lo += 1;
+            }
+            while lo < hi && !pred(&slice[hi - 1]) {
+                hi -= 1;
+            }
+            if lo == hi {
+                return lo;
+            }
+            // `slice[lo]` fails and `slice[hi - 1]` satisfies `pred`, so `lo < hi - 1`.
+            slice.swap(lo, hi - 1);
+            lo += 1;
+            hi -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index_vec::IndexVec;
+
+    use super::*;
+
+    #[test]
+    fn compaction() {
+        let mut entries: IndexVec<u32, Option<&str>> =
+            IndexVec::from([Some("a"), None, Some("b"), None, None, Some("c")]);
+        let live = entries.partition_in_place(Option::is_some);
+        assert_eq!(live, 3);
+        assert!(entries[0..live].iter().all(Option::is_some));
+        assert!(
+            entries[live..entries.next_index()]
+                .iter()
+                .all(Option::is_none)
+        );
+
+        entries.truncate(live.index());
+        let mut names: Vec<_> = entries.iter().flatten().copied().collect();
+        names.sort();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn edge_cases() {
+        let mut empty: IndexVec<usize, i32> = IndexVec::new();
+        assert_eq!(empty.partition_in_place(|_| true), 0);
+        let mut v: IndexVec<usize, i32> = IndexVec::from([1, 2, 3]);
+        assert_eq!(v.partition_in_place(|_| true), 3);
+        assert_eq!(v.partition_in_place(|_| false), 0);
+        assert_eq!(v.raw, [1, 2, 3]);
+    }
+
+    #[test]
+    fn portable_partitions() {
+        for len in 0..10 {
+            for seed in 0..16u32 {
+                let mut v: Vec<u32> = (0..len).map(|i| i * 7 + seed).collect();
+                let pred = |x: &u32| x % 3 == seed % 3;
+                let n = portable::partition_in_place(&mut v, pred);
+                assert_eq!(n, v.iter().filter(|x| pred(x)).count());
+                assert!(v[..n].iter().all(pred));
+                assert!(!v[n..].iter().any(pred));
+            }
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for len in 0..10 {
+            for mask in 0..(1u32 << len) {
+                let pred = |x: &u32| mask & (1 << x) != 0;
+                let mut std: Vec<u32> = (0..len).collect();
+                let mut portable = std.clone();
+                let n = std.iter_mut().partition_in_place(|x| pred(x));
+                assert_eq!(portable::partition_in_place(&mut portable, pred), n);
+                assert_eq!(std, portable);
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! `Path::file_prefix` and `Path::with_added_extension` usage
+//!
+//! Tracking issues [86319] and [127292].
+//!
+//! `Path::file_prefix()` is the file name up to its first `.`, not counting a leading one, where
+//! `file_stem()` stops at the last: `archive` rather than `archive.tar` for `archive.tar.gz`.
+//! `with_added_extension("gz")` appends an extension to the one already there, where
+//! `with_extension` replaces it.
+//!
+//! [`split_multi_extension`] splits a file name into its prefix and everything after, and
+//! [`with_multi_extension`] swaps the whole of the latter, so that `a.tar.gz` can become `a.zip`.
+//! The name is cut up with `os_str_ext`, so names that aren't valid UTF-8 keep their bytes.
+//!
+//! [86319]: https://github.com/rust-lang/rust/issues/86319
+//! [127292]: https://github.com/rust-lang/rust/issues/127292
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::os_str_ext::{split_at_ascii, strip_prefix};
+
+/// The file name of `path` split into its prefix and all of its extensions, as in
+/// `("archive", Some("tar.gz"))`, or `None` if there's no file name.
+pub fn split_multi_extension(path: &Path) -> Option<(&OsStr, Option<&OsStr>)> {
+    let name = path.file_name()?;
+    let prefix = path.file_prefix()?;
+    let rest = split_at_ascii(name, prefix.len()).and_then(|(_, rest)| strip_prefix(rest, "."));
+    Some((prefix, rest))
+}
+
+/// All the extensions of the file name of `path`, as in `tar.gz`.
+pub fn multi_extension(path: &Path) -> Option<&OsStr> {
+    split_multi_extension(path)?.1
+}
+
+/// `path` with all the extensions of its file name replaced by `extension`, or removed if it is
+/// empty. A `path` without a file name is returned as it is.
+pub fn with_multi_extension(path: &Path, extension: impl AsRef<OsStr>) -> PathBuf {
+    let Some((prefix, _)) = split_multi_extension(path) else {
+        return path.to_path_buf();
+    };
+    let path = path.with_file_name(prefix);
+    let extension = extension.as_ref();
+    if extension.is_empty() {
+        path
+    } else {
+        path.with_added_extension(extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(path: &str) -> Option<(&str, Option<&str>)> {
+        let (prefix, rest) = split_multi_extension(Path::new(path))?;
+        Some((prefix.to_str()?, rest.map(|r| r.to_str().unwrap())))
+    }
+
+    #[test]
+    fn splits() {
+        assert_eq!(
+            split("dir/archive.tar.gz"),
+            Some(("archive", Some("tar.gz")))
+        );
+        assert_eq!(split("notes.txt"), Some(("notes", Some("txt"))));
+        assert_eq!(split("README"), Some(("README", None)));
+        assert_eq!(split(".bashrc"), Some((".bashrc", None)));
+        assert_eq!(split(".config.toml"), Some((".config", Some("toml"))));
+        assert_eq!(split("trailing."), Some(("trailing", Some(""))));
+        assert_eq!(split("ünïcödé.tar.xz"), Some(("ünïcödé", Some("tar.xz"))));
+        assert_eq!(split("dir/.."), None);
+        assert_eq!(split("/"), None);
+        assert_eq!(multi_extension(Path::new("a.b.c")), Some(OsStr::new("b.c")));
+    }
+
+    #[test]
+    fn replaces() {
+        let path = Path::new("dir/archive.tar.gz");
+        assert_eq!(
+            with_multi_extension(path, "zip"),
+            Path::new("dir/archive.zip")
+        );
+        assert_eq!(
+            with_multi_extension(path, "tar.zst"),
+            Path::new("dir/archive.tar.zst")
+        );
+        assert_eq!(with_multi_extension(path, ""), Path::new("dir/archive"));
+        assert_eq!(
+            with_multi_extension(Path::new(".bashrc"), "bak"),
+            Path::new(".bashrc.bak")
+        );
+        assert_eq!(with_multi_extension(Path::new("/"), "x"), Path::new("/"));
+    }
+}
@@ -0,0 +1,265 @@
+//! `core::str::pattern::Pattern` usage
+//!
+//! Tracking issue [27721].
+//!
+//! `Pattern` is what lets `str::find`, `str::split`, `str::trim_matches` and friends accept a
+//! `char`, a `&str`, a closure, and so on. Implementing it for a user type means writing a
+//! [`Searcher`] that walks the haystack and reports `Match`/`Reject` steps, and a
+//! [`ReverseSearcher`] for the `rfind`/`rsplit` family.
+//!
+//! Here it is implemented for [`CharSet`], an ASCII character class stored as a 128-bit bitmap. The
+//! free functions at the bottom take the same `CharSet` on stable by falling back to a closure
+//! pattern, so callers don't gate their own code.
+//!
+//! [27721]: https://github.com/rust-lang/rust/issues/27721
+
+#[cfg(feature = "nightly")]
+use std::str::pattern::{DoubleEndedSearcher, Pattern, ReverseSearcher, SearchStep, Searcher};
+
+/// A set of ASCII characters. Non-ASCII characters are never members.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CharSet {
+    bits: u128,
+}
+
+impl CharSet {
+    /// The empty set.
+    pub const EMPTY: Self = Self { bits: 0 };
+
+    /// Creates a set from the given ASCII characters.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any character is not ASCII.
+    pub const fn from_ascii(chars: &[u8]) -> Self {
+        let mut set = Self::EMPTY;
+        let mut i = 0;
+        while i < chars.len() {
+            set = set.with_range(chars[i], chars[i]);
+            i += 1;
+        }
+        set
+    }
+
+    /// Returns the set with all characters in `start..=end` added.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `end` is not ASCII.
+    pub const fn with_range(self, start: u8, end: u8) -> Self {
+        assert!(end.is_ascii());
+        let mut bits = self.bits;
+        let mut c = start;
+        while c <= end {
+            bits |= 1 << c;
+            c += 1;
+        }
+        Self { bits }
+    }
+
+    /// Returns the union of both sets.
+    pub const fn union(self, other: Self) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    /// Returns whether `c` is in the set.
+    #[inline]
+    pub const fn contains(&self, c: char) -> bool {
+        c.is_ascii() && self.bits & (1 << c as u32) != 0
+    }
+}
+
+/// Searcher for [`CharSet`], produced by [`Pattern::into_searcher`].
+#[cfg(feature = "nightly")]
+#[derive(Debug, Clone)]
+pub struct CharSetSearcher<'a> {
+    set: CharSet,
+    haystack: &'a str,
+    /// Unsearched part of the haystack is `front..back`, both always on char boundaries.
+    front: usize,
+    back: usize,
+}
+
+#[cfg(feature = "nightly")]
+impl Pattern for CharSet {
+    type Searcher<'a> = CharSetSearcher<'a>;
+
+    fn into_searcher(self, haystack: &str) -> CharSetSearcher<'_> {
+        CharSetSearcher {
+            set: self,
+            haystack,
+            front: 0,
+            back: haystack.len(),
+        }
+    }
+
+    fn is_contained_in(self, haystack: &str) -> bool {
+        haystack.chars().any(|c| self.contains(c))
+    }
+
+    fn is_prefix_of(self, haystack: &str) -> bool {
+        haystack.chars().next().is_some_and(|c| self.contains(c))
+    }
+
+    fn is_suffix_of<'a>(self, haystack: &'a str) -> bool
+    where
+        CharSetSearcher<'a>: ReverseSearcher<'a>,
+    {
+        haystack
+            .chars()
+            .next_back()
+            .is_some_and(|c| self.contains(c))
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<'a> CharSetSearcher<'a> {
+    fn step(&self, start: usize, c: char) -> SearchStep {
+        let end = start + c.len_utf8();
+        if self.set.contains(c) {
+            SearchStep::Match(start, end)
+        } else {
+            SearchStep::Reject(start, end)
+        }
+    }
+}
+
+// SAFETY: Every step covers exactly one char starting at `front` (or ending at `back`), so the
+// returned ranges are on char boundaries, non-overlapping, and together cover the haystack.
+#[cfg(feature = "nightly")]
+unsafe impl<'a> Searcher<'a> for CharSetSearcher<'a> {
+    fn haystack(&self) -> &'a str {
+        self.haystack
+    }
+
+    fn next(&mut self) -> SearchStep {
+        let Some(c) = self.haystack[self.front..self.back].chars().next() else {
+            return SearchStep::Done;
+        };
+        let step = self.step(self.front, c);
+        self.front += c.len_utf8();
+        step
+    }
+}
+
+// SAFETY: Same argument as `Searcher`, walking from `back`.
+#[cfg(feature = "nightly")]
+unsafe impl<'a> ReverseSearcher<'a> for CharSetSearcher<'a> {
+    fn next_back(&mut self) -> SearchStep {
+        let Some(c) = self.haystack[self.front..self.back].chars().next_back() else {
+            return SearchStep::Done;
+        };
+        self.back -= c.len_utf8();
+        self.step(self.back, c)
+    }
+}
+
+// Matching single chars gives the same result from either end.
+#[cfg(feature = "nightly")]
+impl<'a> DoubleEndedSearcher<'a> for CharSetSearcher<'a> {}
+
+/// Returns the byte index of the first character of `haystack` in `set`.
+#[cfg(feature = "nightly")]
+pub fn find(haystack: &str, set: CharSet) -> Option<usize> {
+    haystack.find(set)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn find(haystack: &str, set: CharSet) -> Option<usize> {
+    haystack.find(|c| set.contains(c))
+}
+
+/// Returns the byte index of the last character of `haystack` in `set`.
+#[cfg(feature = "nightly")]
+pub fn rfind(haystack: &str, set: CharSet) -> Option<usize> {
+    haystack.rfind(set)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn rfind(haystack: &str, set: CharSet) -> Option<usize> {
+    haystack.rfind(|c| set.contains(c))
+}
+
+/// Splits `haystack` on every character in `set`.
+#[cfg(feature = "nightly")]
+pub fn split(haystack: &str, set: CharSet) -> impl DoubleEndedIterator<Item = &str> {
+    haystack.split(set)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn split(haystack: &str, set: CharSet) -> impl DoubleEndedIterator<Item = &str> {
+    haystack.split(move |c| set.contains(c))
+}
+
+/// Removes all leading and trailing characters in `set`.
+#[cfg(feature = "nightly")]
+pub fn trim(haystack: &str, set: CharSet) -> &str {
+    haystack.trim_matches(set)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn trim(haystack: &str, set: CharSet) -> &str {
+    haystack.trim_matches(|c| set.contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGITS: CharSet = CharSet::EMPTY.with_range(b'0', b'9');
+    const SEPARATORS: CharSet = CharSet::from_ascii(b" ,;");
+
+    #[test]
+    fn contains() {
+        assert!(DIGITS.contains('7'));
+        assert!(!DIGITS.contains('a'));
+        assert!(!DIGITS.contains('\u{663}')); // ARABIC-INDIC DIGIT THREE
+        assert!(DIGITS.union(SEPARATORS).contains(';'));
+    }
+
+    #[test]
+    fn find_and_rfind() {
+        assert_eq!(find("idx_42_x", DIGITS), Some(4));
+        assert_eq!(rfind("idx_42_x", DIGITS), Some(5));
+        assert_eq!(find("\u{e9}\u{e9}1", DIGITS), Some(4));
+        assert_eq!(find("none", DIGITS), None);
+    }
+
+    #[test]
+    fn split_and_trim() {
+        let parts: Vec<_> = split("1, 2;3", SEPARATORS).collect();
+        assert_eq!(parts, ["1", "", "2", "3"]);
+        let parts: Vec<_> = split("a b", SEPARATORS).rev().collect();
+        assert_eq!(parts, ["b", "a"]);
+        assert_eq!(trim("  ,x y; ", SEPARATORS), "x y");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn searcher_steps() {
+        let mut searcher = DIGITS.into_searcher("a1\u{e9}");
+        assert_eq!(searcher.next(), SearchStep::Reject(0, 1));
+        assert_eq!(searcher.next(), SearchStep::Match(1, 2));
+        assert_eq!(searcher.next(), SearchStep::Reject(2, 4));
+        assert_eq!(searcher.next(), SearchStep::Done);
+
+        let mut searcher = DIGITS.into_searcher("a1\u{e9}");
+        assert_eq!(searcher.next_back(), SearchStep::Reject(2, 4));
+        assert_eq!(searcher.next_match_back(), Some((1, 2)));
+        assert_eq!(searcher.next_back(), SearchStep::Reject(0, 1));
+        assert_eq!(searcher.next_back(), SearchStep::Done);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn std_methods() {
+        assert!("v2".contains(DIGITS));
+        assert!("2v".starts_with(DIGITS));
+        assert!("v2".ends_with(DIGITS));
+        assert_eq!("a1b22c".matches(DIGITS).count(), 3);
+        assert_eq!("a1b2".replace(DIGITS, "#"), "a#b#");
+        assert_eq!("12ab34".trim_start_matches(DIGITS), "ab34");
+    }
+}
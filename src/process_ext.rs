@@ -0,0 +1,156 @@
+//! `CommandExt::setsid` usage
+//!
+//! Tracking issue [105376].
+//!
+//! `CommandExt::setsid(true)` has a Unix child call `setsid` before it runs the program, which puts
+//! it in a session and process group of its own, away from the parent's controlling terminal. A
+//! Ctrl-C or hangup on the terminal then doesn't reach it, and neither does a signal sent to the
+//! parent's group. It replaces the usual `pre_exec` closure calling `libc::setsid`, which needs
+//! `unsafe` and a libc binding. `process_group`, which only moves the child to a new group within
+//! the same session, is stable.
+//!
+//! [`detach`] sets a `Command` up to run a background worker that outlives the parent's terminal,
+//! and [`spawn_detached`] spawns one. On Windows the child gets a new process group and no
+//! console. Without the `nightly` feature a Unix child calls `setsid` from a `pre_exec` closure,
+//! declared here rather than taken from libc.
+//!
+//! [105376]: https://github.com/rust-lang/rust/issues/105376
+
+use std::io;
+use std::process::{Child, Command};
+
+/// Sets up `command` to run detached from the parent's terminal and process group, with its
+/// standard input closed.
+#[cfg(all(unix, feature = "nightly"))]
+pub fn detach(command: &mut Command) -> &mut Command {
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    command.stdin(Stdio::null()).setsid(true)
+}
+
+#[cfg(not(all(unix, feature = "nightly")))]
+pub fn detach(command: &mut Command) -> &mut Command {
+    portable::detach(command)
+}
+
+/// Spawns `command` as set up by [`detach`].
+pub fn spawn_detached(command: &mut Command) -> io::Result<Child> {
+    detach(command).spawn()
+}
+
+#[cfg(any(test, not(all(unix, feature = "nightly"))))]
+mod portable {
+    use std::process::{Command, Stdio};
+
+    #[cfg(unix)]
+    unsafe extern "C" {
+        safe fn setsid() -> i32;
+    }
+
+    #[cfg(unix)]
+    pub(super) fn detach(command: &mut Command) -> &mut Command {
+        use std::io;
+        use std::os::unix::process::CommandExt;
+
+        let new_session = || {
+            if setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        // SAFETY: `setsid` is async-signal-safe, so the child may call it before `exec`.
+        unsafe { command.stdin(Stdio::null()).pre_exec(new_session) }
+    }
+
+    #[cfg(windows)]
+    pub(super) fn detach(command: &mut Command) -> &mut Command {
+        use std::os::windows::process::CommandExt;
+
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command
+            .stdin(Stdio::null())
+            .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(super) fn detach(command: &mut Command) -> &mut Command {
+        command.stdin(Stdio::null())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::process::Stdio;
+    use std::{env, fs, io::Read, process};
+
+    use super::*;
+
+    /// Set in a child test process, which then prints its ids.
+    const CHILD: &str = "RUST_UNSTABLE_DETACHED_CHILD";
+
+    /// The pid, process group and session of a process, from `/proc/<pid>/stat`.
+    fn ids(stat: &str) -> [u32; 3] {
+        // The command name in parentheses may hold spaces.
+        let after_name = &stat[stat.rfind(')').unwrap() + 2..];
+        let fields: Vec<&str> = after_name.split(' ').collect();
+        let pid = stat.split(' ').next().unwrap().parse().unwrap();
+        [pid, fields[2].parse().unwrap(), fields[3].parse().unwrap()]
+    }
+
+    /// Does nothing unless run by `run_child`.
+    #[test]
+    fn child() {
+        if env::var_os(CHILD).is_none() {
+            return;
+        }
+        // Standard input is closed for a detached child.
+        let mut input = Vec::new();
+        assert_eq!(io::stdin().read_to_end(&mut input).unwrap(), 0);
+        print!("ids:{}:", fs::read_to_string("/proc/self/stat").unwrap());
+    }
+
+    /// Runs `child` above through `detach`, returning its ids.
+    fn run_child(detach: fn(&mut Command) -> &mut Command) -> [u32; 3] {
+        let mut command = Command::new(env::current_exe().unwrap());
+        command
+            .args(["--exact", "process_ext::tests::child", "--nocapture"])
+            .env(CHILD, "1")
+            .stdout(Stdio::piped());
+        let output = detach(&mut command)
+            .spawn()
+            .unwrap()
+            .wait_with_output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stat = stdout.split("ids:").nth(1).unwrap();
+        ids(stat.split(':').next().unwrap())
+    }
+
+    #[test]
+    fn detached_worker() {
+        let [pid, group, session] = run_child(detach);
+        assert_eq!(group, pid);
+        assert_eq!(session, pid);
+        let [_, parent_group, parent_session] =
+            ids(&fs::read_to_string("/proc/self/stat").unwrap());
+        assert_ne!(group, parent_group);
+        assert_ne!(session, parent_session);
+
+        let child = spawn_detached(Command::new("true").stdout(Stdio::null())).unwrap();
+        assert_ne!(child.id(), process::id());
+        child.wait_with_output().unwrap();
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        // Each child leads a session and process group of its own.
+        for detach in [detach, portable::detach] {
+            let [pid, group, session] = run_child(detach);
+            assert_eq!((group, session), (pid, pid));
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! `NonNull` pointer method usage
+//!
+//! Tracking issue [75402].
+//!
+//! `NonNull<T>` has grown the methods of `*mut T`, so code that keeps its pointers non-null
+//! doesn't go through `as_ptr` and `NonNull::new_unchecked` for every step: `add`, `read`,
+//! `write`, `offset_from_unsigned` and `without_provenance`, for an address that isn't meant to be
+//! dereferenced, are stable. The unstable `as_uninit_ref` and
+//! `as_uninit_mut` borrow the pointee as a `MaybeUninit<T>`, which unlike `as_ref` doesn't claim
+//! that it is initialized yet.
+//!
+//! A [`Span`] is the memory between two `NonNull`s, which hands out its front with
+//! [`Span::take_front`]. The `arena::Bump` allocator keeps its free space in one, and gives
+//! zero-sized allocations a [`dangling`] address instead of any of it, and the stable
+//! `uninit_array::array_assume_init` reads the array through a `NonNull`. Without the `nightly`
+//! feature [`as_uninit_ref`] and [`as_uninit_mut`] cast the pointer to `NonNull<MaybeUninit<T>>` and borrow that.
+//!
+//! [75402]: https://github.com/rust-lang/rust/issues/75402
+
+use std::alloc::Layout;
+use std::mem::MaybeUninit;
+use std::num::NonZero;
+use std::ptr::NonNull;
+
+/// Borrows the possibly uninitialized value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be aligned and dereferenceable for `T`, and not be written to during `'a`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub const unsafe fn as_uninit_ref<'a, T>(ptr: NonNull<T>) -> &'a MaybeUninit<T> {
+    // SAFETY: the caller guarantees `ptr` may be borrowed for `'a`.
+    unsafe { ptr.as_uninit_ref() }
+}
+
+/// Borrows the possibly uninitialized value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be aligned and dereferenceable for `T`, and not be written to during `'a`.
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub const unsafe fn as_uninit_ref<'a, T>(ptr: NonNull<T>) -> &'a MaybeUninit<T> {
+    // SAFETY: the caller guarantees `ptr` may be borrowed for `'a`.
+    unsafe { portable::as_uninit_ref(ptr) }
+}
+
+/// Mutably borrows the possibly uninitialized value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be aligned and dereferenceable for `T`, and not be accessed otherwise during `'a`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub const unsafe fn as_uninit_mut<'a, T>(ptr: NonNull<T>) -> &'a mut MaybeUninit<T> {
+    // SAFETY: the caller guarantees `ptr` may be borrowed mutably for `'a`.
+    unsafe { ptr.as_uninit_mut() }
+}
+
+/// Mutably borrows the possibly uninitialized value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be aligned and dereferenceable for `T`, and not be accessed otherwise during `'a`.
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub const unsafe fn as_uninit_mut<'a, T>(ptr: NonNull<T>) -> &'a mut MaybeUninit<T> {
+    // SAFETY: the caller guarantees `ptr` may be borrowed mutably for `'a`.
+    unsafe { portable::as_uninit_mut(ptr) }
+}
+
+/// A pointer aligned for `layout` that points to no memory, for a zero-sized value.
+#[inline]
+pub const fn dangling(layout: Layout) -> NonNull<u8> {
+    // SAFETY: alignments are powers of two, so never zero.
+    NonNull::without_provenance(unsafe { NonZero::new_unchecked(layout.align()) })
+}
+
+/// The elements from `start` up to `end` of one allocation.
+///
+/// A `Span` doesn't borrow the memory, so the code that made it decides who may use it. Its
+/// length is the distance between the two ends, so `T` can't be zero-sized, where every element
+/// would be at the same address; making a span of a zero-sized type fails to build:
+///
+/// ```compile_fail
+/// use rust_unstable::ptr::Span;
+///
+/// let units = Span::<()>::empty();
+/// ```
+pub struct Span<T> {
+    start: NonNull<T>,
+    end: NonNull<T>,
+}
+
+impl<T> Span<T> {
+    /// A span of no elements.
+    pub const fn empty() -> Self {
+        const {
+            assert!(
+                size_of::<T>() != 0,
+                "`Span` needs a type that isn't zero-sized"
+            )
+        };
+        Span {
+            start: NonNull::dangling(),
+            end: NonNull::dangling(),
+        }
+    }
+
+    /// The elements of `slice`.
+    ///
+    /// # Safety
+    ///
+    /// `slice` must lie within one allocation, which outlives the span.
+    pub const unsafe fn new(slice: NonNull<[T]>) -> Self {
+        const {
+            assert!(
+                size_of::<T>() != 0,
+                "`Span` needs a type that isn't zero-sized"
+            )
+        };
+        let start = slice.cast::<T>();
+        Span {
+            start,
+            // SAFETY: the caller guarantees the slice is in bounds of its allocation.
+            end: unsafe { start.add(slice.len()) },
+        }
+    }
+
+    pub fn start(&self) -> NonNull<T> {
+        self.start
+    }
+
+    pub fn len(&self) -> usize {
+        // SAFETY: both ends are in the same allocation, `end` not before `start`.
+        unsafe { self.end.offset_from_unsigned(self.start) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Splits off the first `n` elements, or returns `None` and leaves the span as it is if it
+    /// is shorter.
+    pub fn take_front(&mut self, n: usize) -> Option<NonNull<[T]>> {
+        if n > self.len() {
+            return None;
+        }
+        let front = NonNull::slice_from_raw_parts(self.start, n);
+        // SAFETY: at most `len` elements on, so still in bounds.
+        self.start = unsafe { self.start.add(n) };
+        Some(front)
+    }
+}
+
+impl<T> Clone for Span<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Span<T> {}
+
+impl<T> std::fmt::Debug for Span<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Span")
+            .field("start", &self.start)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::MaybeUninit;
+    use std::ptr::NonNull;
+
+    /// # Safety
+    ///
+    /// As for `super::as_uninit_ref`.
+    pub(super) const unsafe fn as_uninit_ref<'a, T>(ptr: NonNull<T>) -> &'a MaybeUninit<T> {
+        // SAFETY: `MaybeUninit<T>` has the layout of `T`, and the caller guarantees the borrow.
+        unsafe { ptr.cast::<MaybeUninit<T>>().as_ref() }
+    }
+
+    /// # Safety
+    ///
+    /// As for `super::as_uninit_mut`.
+    pub(super) const unsafe fn as_uninit_mut<'a, T>(ptr: NonNull<T>) -> &'a mut MaybeUninit<T> {
+        // SAFETY: `MaybeUninit<T>` has the layout of `T`, and the caller guarantees the borrow.
+        unsafe { ptr.cast::<MaybeUninit<T>>().as_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let mut buf = [0u32, 1, 2, 3, 4];
+        // SAFETY: all of `buf`, which outlives the span.
+        let mut span = unsafe { Span::new(NonNull::from(&mut buf[..])) };
+        assert_eq!(span.len(), 5);
+        let front = span.take_front(2).unwrap();
+        assert_eq!(front.len(), 2);
+        assert!(span.take_front(4).is_none());
+        assert_eq!(span.len(), 3);
+        // SAFETY: `span` is within `buf`, and nothing else uses it meanwhile.
+        unsafe {
+            span.start().write(30);
+            assert_eq!(span.start().add(1).read(), 3);
+            assert_eq!(front.cast::<u32>().add(1).read(), 1);
+        }
+        assert_eq!(span.take_front(3).unwrap().len(), 3);
+        assert!(span.is_empty());
+        assert_eq!(buf, [0, 1, 30, 3, 4]);
+
+        let empty = Span::<u64>::empty();
+        assert_eq!((empty.len(), empty.is_empty()), (0, true));
+    }
+
+    #[test]
+    fn dangling_is_aligned() {
+        for align in [1, 8, 4096] {
+            let layout = Layout::from_size_align(0, align).unwrap();
+            assert_eq!(dangling(layout).addr().get(), align);
+        }
+    }
+
+    #[test]
+    fn uninit_borrows() {
+        let mut slot = MaybeUninit::<String>::uninit();
+        let ptr = NonNull::from(&mut slot).cast::<String>();
+        // SAFETY: `slot` is borrowed only through `ptr` meanwhile.
+        unsafe {
+            as_uninit_mut(ptr).write("set".to_string());
+            assert_eq!(as_uninit_ref(ptr).assume_init_ref(), "set");
+            assert_eq!(ptr.read(), "set");
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut value = 7u16;
+        let ptr = NonNull::from(&mut value);
+        // SAFETY: `value` is only borrowed through `ptr` meanwhile.
+        unsafe {
+            assert_eq!(
+                portable::as_uninit_ref(ptr).as_ptr(),
+                ptr.as_uninit_ref().as_ptr()
+            );
+            assert_eq!(
+                portable::as_uninit_mut(ptr).as_mut_ptr(),
+                ptr.as_uninit_mut().as_mut_ptr()
+            );
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! `Vec::push_within_capacity` usage
+//!
+//! Tracking issue [100486].
+//!
+//! `vec.push_within_capacity(value)` appends `value` only if there is spare capacity for it, and
+//! otherwise hands it back as `Err(value)` instead of reallocating. Code that must not allocate,
+//! such as a real-time audio callback or a signal handler, can reserve up front and then push on
+//! the hot path knowing it never reaches the allocator.
+//!
+//! [`IndexVec::push_within_capacity`] returns the index of the new element, like `push`.
+//! Without the `nightly` feature it compares the length with the capacity before pushing.
+//!
+//! [100486]: https://github.com/rust-lang/rust/issues/100486
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Appends `value` if it fits in the current capacity and returns its index, or returns it
+    /// back if it doesn't. Never allocates.
+    #[cfg(feature = "nightly")]
+    #[inline]
+    pub fn push_within_capacity(&mut self, value: T) -> Result<I, T> {
+        let index = self.next_index();
+        self.raw.push_within_capacity(value)?;
+        Ok(index)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    #[inline]
+    pub fn push_within_capacity(&mut self, value: T) -> Result<I, T> {
+        let index = self.next_index();
+        portable::push_within_capacity(&mut self.raw, value)?;
+        Ok(index)
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn push_within_capacity<T>(vec: &mut Vec<T>, value: T) -> Result<(), T> {
+        if vec.len() == vec.capacity() {
+            return Err(value);
+        }
+        vec.push(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_allocation_on_hot_path() {
+        let mut events: IndexVec<u32, (u64, &str)> = IndexVec::with_capacity(3);
+        let buffer = events.raw.as_ptr();
+        let capacity = events.capacity();
+        let mut dropped = Vec::new();
+        for (t, name) in [(1, "a"), (2, "b"), (3, "c"), (4, "d"), (5, "e")] {
+            match events.push_within_capacity((t, name)) {
+                Ok(index) => assert_eq!(events[index], (t, name)),
+                Err(event) => dropped.push(event),
+            }
+        }
+        assert_eq!(events.len(), capacity);
+        assert_eq!(events.raw.as_ptr(), buffer);
+        assert_eq!(dropped.len(), 5 - capacity);
+        assert_eq!(dropped.last(), Some(&(5, "e")));
+
+        events.clear();
+        assert_eq!(events.push_within_capacity((6, "f")), Ok(0));
+        assert_eq!(IndexVec::<u32, u8>::new().push_within_capacity(1), Err(1));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let (mut std, mut portable) = (Vec::with_capacity(2), Vec::with_capacity(2));
+        for i in 0..4 {
+            assert_eq!(
+                std.push_within_capacity(i).map(|_| ()),
+                portable::push_within_capacity(&mut portable, i)
+            );
+        }
+        assert_eq!(std, portable);
+    }
+}
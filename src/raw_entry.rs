@@ -0,0 +1,409 @@
+//! `HashMap::raw_entry` usage, and its replacement
+//!
+//! Tracking issue [56167].
+//!
+//! `raw_entry().from_key_hashed_nocheck(hash, key)` looked a key up under a hash the caller had
+//! already computed, and `raw_entry_mut()` could insert under that same hash on a miss. An
+//! interner could so hash a string once and probe twice, where `get` followed by `insert` hashes
+//! it twice and `entry` needs an owned key up front.
+//!
+//! The API was removed from std in 1.87 without being stabilized, and the `hash_raw_entry`
+//! feature no longer exists, so there is no `nightly` path here. [`StrInterner`] gets the same
+//! effect on stable: it computes the string hash once with `BuildHasher::hash_one`, and keys an
+//! inner map by that `u64` through an identity hasher, chaining strings with equal hashes.
+//! [`interner_stats`] reports how the current thread's lookups went.
+//!
+//! [56167]: https://github.com/rust-lang/rust/issues/56167
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher, RandomState};
+
+use crate::cell_update::Counters;
+use crate::cow_is_borrowed::{CowTally, is_borrowed};
+use crate::index_vec::IndexVec;
+use crate::remove_matches::{normalize_symbol, normalized};
+use crate::step::Idx;
+
+/// An interned string.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Idx for Symbol {
+    const MAX_INDEX: usize = u32::MAX as usize;
+
+    #[inline]
+    fn new(idx: usize) -> Self {
+        assert!(idx <= u32::MAX as usize);
+        Symbol(idx as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({})", self.0)
+    }
+}
+
+/// Passes precomputed `u64` hashes through unchanged.
+#[derive(Default)]
+struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    fn write(&mut self, _: &[u8]) {
+        unreachable!("only `u64` hashes are used as keys")
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The current thread's [`StrInterner`] lookups, across all interners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InternerStats {
+    /// Lookups, including the one each `intern` starts with.
+    pub lookups: usize,
+    /// Lookups that found the string.
+    pub hits: usize,
+    /// Strings added.
+    pub interned: usize,
+    /// The names [`StrInterner::intern_name`] normalized, owned if they weren't normal already.
+    pub normalized: CowTally,
+    /// The strings [`StrInterner::intern_cow`] added, borrowed if they had to be copied.
+    pub added_from: CowTally,
+}
+
+thread_local! {
+    /// `InternerStats`, in field order.
+    static STATS: Counters<7> = const { Counters::new() };
+}
+
+/// The counter in `STATS` of each field of [`InternerStats`]. A [`CowTally`] takes two, the
+/// borrowed count first.
+const LOOKUPS: usize = 0;
+const HITS: usize = 1;
+const INTERNED: usize = 2;
+const NORMALIZED: usize = 3;
+const ADDED_FROM: usize = 5;
+
+pub fn interner_stats() -> InternerStats {
+    let stats = STATS.with(Counters::get);
+    let tally = |first: usize| CowTally {
+        borrowed: stats[first],
+        owned: stats[first + 1],
+    };
+    InternerStats {
+        lookups: stats[LOOKUPS],
+        hits: stats[HITS],
+        interned: stats[INTERNED],
+        normalized: tally(NORMALIZED),
+        added_from: tally(ADDED_FROM),
+    }
+}
+
+/// Counts a `Cow` that is `borrowed` or not in the `CowTally` at counter `first`.
+fn record_cow(first: usize, borrowed: bool) {
+    let i = if borrowed { first } else { first + 1 };
+    STATS.with(|stats| stats.add(i, 1));
+}
+
+/// A string interner that hashes each string once per lookup.
+pub struct StrInterner<S = RandomState> {
+    hash_builder: S,
+    /// The most recently interned symbol for each hash.
+    heads: HashMap<u64, Symbol, BuildHasherDefault<IdentityHasher>>,
+    strings: IndexVec<Symbol, Box<str>>,
+    /// The symbol interned before this one with the same hash.
+    next: IndexVec<Symbol, Option<Symbol>>,
+}
+
+impl StrInterner {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl Default for StrInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: BuildHasher> StrInterner<S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        StrInterner {
+            hash_builder,
+            heads: HashMap::default(),
+            strings: IndexVec::new(),
+            next: IndexVec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Computes the hash that [`get_hashed`](Self::get_hashed) and
+    /// [`intern_hashed`](Self::intern_hashed) expect for `s`.
+    pub fn hash(&self, s: &str) -> u64 {
+        self.hash_builder.hash_one(s)
+    }
+
+    /// Looks `s` up under a precomputed `hash`, like `raw_entry().from_key_hashed_nocheck`.
+    ///
+    /// `hash` must be `self.hash(s)`; for any other value the lookup will likely miss.
+    pub fn get_hashed(&self, hash: u64, s: &str) -> Option<Symbol> {
+        STATS.with(|stats| stats.add(LOOKUPS, 1));
+        let mut cur = self.heads.get(&hash).copied();
+        while let Some(sym) = cur {
+            if *self.strings[sym] == *s {
+                STATS.with(|stats| stats.add(HITS, 1));
+                return Some(sym);
+            }
+            cur = self.next[sym];
+        }
+        None
+    }
+
+    /// Returns the symbol for `s` under a precomputed `hash`, interning it on a miss without
+    /// hashing it again.
+    ///
+    /// `hash` must be `self.hash(s)`; for any other value `s` may be interned twice.
+    pub fn intern_hashed(&mut self, hash: u64, s: &str) -> Symbol {
+        if let Some(sym) = self.get_hashed(hash, s) {
+            return sym;
+        }
+        self.insert(hash, s.into())
+    }
+
+    /// Adds `s`, which isn't interned yet, under its `hash`.
+    fn insert(&mut self, hash: u64, s: Box<str>) -> Symbol {
+        STATS.with(|stats| stats.add(INTERNED, 1));
+        let sym = self.strings.push(s);
+        let prev = self.heads.insert(hash, sym);
+        self.next.push(prev);
+        sym
+    }
+
+    /// Returns the symbol for `s`, interning it on a miss by moving an owned string in rather
+    /// than copying it.
+    pub fn intern_cow(&mut self, s: Cow<'_, str>) -> Symbol {
+        let hash = self.hash(&s);
+        if let Some(sym) = self.get_hashed(hash, &s) {
+            return sym;
+        }
+        record_cow(ADDED_FROM, is_borrowed(&s));
+        self.insert(hash, s.into_owned().into_boxed_str())
+    }
+
+    /// Interns `name` as [`normalized`] makes it, copying it only if it isn't normal already.
+    pub fn intern_name(&mut self, name: &str) -> Symbol {
+        let name = normalized(name);
+        record_cow(NORMALIZED, is_borrowed(&name));
+        self.intern_cow(name)
+    }
+
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.get_hashed(self.hash(s), s)
+    }
+
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        let hash = self.hash(s);
+        self.intern_hashed(hash, s)
+    }
+
+    /// Normalizes `name` in place with [`normalize_symbol`], then interns it, so that `name`
+    /// afterwards holds the string the symbol resolves to.
+    pub fn intern_normalized(&mut self, name: &mut String) -> Symbol {
+        normalize_symbol(name);
+        self.intern(name)
+    }
+
+    /// Returns the string for `sym`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `sym` was not created by this interner.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        &self.strings[sym]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hashes everything to the same value, to exercise the collision chains.
+    #[derive(Default)]
+    struct ConstHasher;
+
+    impl Hasher for ConstHasher {
+        fn write(&mut self, _: &[u8]) {}
+
+        fn finish(&self) -> u64 {
+            7
+        }
+    }
+
+    #[test]
+    fn intern_and_resolve() {
+        let mut interner = StrInterner::new();
+        let a = interner.intern("a");
+        let b = interner.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("a"), a);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.resolve(b), "b");
+        assert_eq!(interner.get("b"), Some(b));
+        assert_eq!(interner.get("c"), None);
+    }
+
+    #[test]
+    fn hash_once() {
+        let mut interner = StrInterner::new();
+        let hash = interner.hash("fn");
+        assert_eq!(interner.get_hashed(hash, "fn"), None);
+        let sym = interner.intern_hashed(hash, "fn");
+        assert_eq!(interner.get_hashed(hash, "fn"), Some(sym));
+        assert_eq!(interner.intern("fn"), sym);
+    }
+
+    #[test]
+    fn collisions() {
+        let mut interner = StrInterner::with_hasher(BuildHasherDefault::<ConstHasher>::default());
+        let words = ["let", "mut", "ref", "move"];
+        let syms: Vec<_> = words.iter().map(|w| interner.intern(w)).collect();
+        for (w, &sym) in words.iter().zip(&syms) {
+            assert_eq!(interner.intern(w), sym);
+            assert_eq!(interner.resolve(sym), *w);
+        }
+        assert_eq!(interner.len(), 4);
+        assert_eq!(interner.get("static"), None);
+    }
+
+    #[test]
+    fn normalized() {
+        let mut interner = StrInterner::new();
+        let mut spaced = String::from("Option<&'static str>");
+        let sym = interner.intern_normalized(&mut spaced);
+        assert_eq!(spaced, "Option<&str>");
+        let mut tight = String::from("Option<&str>");
+        assert_eq!(interner.intern_normalized(&mut tight), sym);
+        assert_eq!(interner.resolve(sym), "Option<&str>");
+    }
+
+    #[test]
+    fn copies_only_when_needed() {
+        let before = interner_stats();
+        let mut interner = StrInterner::new();
+        let sym = interner.intern_name("Vec<&'static str>");
+        assert_eq!(interner.intern_name("Vec<&str>"), sym);
+        interner.intern_name("u32");
+        interner.intern_cow(Cow::Owned(String::from("u64")));
+        assert_eq!(interner.resolve(sym), "Vec<&str>");
+        let after = interner_stats();
+        let delta = |before: CowTally, after: CowTally| {
+            (after.borrowed - before.borrowed, after.owned - before.owned)
+        };
+        assert_eq!(delta(before.normalized, after.normalized), (2, 1));
+        // The owned normalization and "u64" were moved in, "u32" was copied.
+        assert_eq!(delta(before.added_from, after.added_from), (1, 2));
+    }
+
+    #[test]
+    fn thread_stats() {
+        let before = interner_stats();
+        let mut interner = StrInterner::new();
+        for w in ["a", "b", "a", "a"] {
+            interner.intern(w);
+        }
+        interner.get("c");
+        let after = interner_stats();
+        assert_eq!(
+            (
+                after.lookups - before.lookups,
+                after.hits - before.hits,
+                after.interned - before.interned
+            ),
+            (5, 2, 2)
+        );
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    /// 1000 lookups of 500 distinct words, so half of them hit.
+    fn words() -> Vec<String> {
+        (0..1000).map(|i| format!("word{}", i % 500)).collect()
+    }
+
+    /// Hashes each word once, then probes for the lookup and, on a miss, for the insertion.
+    #[bench]
+    fn intern_hash_once(b: &mut Bencher) {
+        let words = words();
+        b.iter(|| {
+            let mut interner = StrInterner::new();
+            for w in &words {
+                black_box(interner.intern(black_box(w)));
+            }
+            interner.len()
+        });
+    }
+
+    /// `get` then `insert` on a plain map, which hashes each missing word twice.
+    #[bench]
+    fn intern_get_then_insert(b: &mut Bencher) {
+        let words = words();
+        b.iter(|| {
+            let mut map: HashMap<Box<str>, Symbol> = HashMap::new();
+            let mut strings: IndexVec<Symbol, Box<str>> = IndexVec::new();
+            for w in &words {
+                let w = black_box(w.as_str());
+                let sym = match map.get(w) {
+                    Some(&sym) => sym,
+                    None => {
+                        let sym = strings.push(w.into());
+                        map.insert(w.into(), sym);
+                        sym
+                    }
+                };
+                black_box(sym);
+            }
+            strings.len()
+        });
+    }
+
+    /// The `entry` API hashes once, but needs an owned key even when the word is present.
+    #[bench]
+    fn intern_entry(b: &mut Bencher) {
+        let words = words();
+        b.iter(|| {
+            let mut map: HashMap<Box<str>, Symbol> = HashMap::new();
+            for w in &words {
+                let next = Symbol::new(map.len());
+                black_box(*map.entry(black_box(w.as_str()).into()).or_insert(next));
+            }
+            map.len()
+        });
+    }
+}
@@ -0,0 +1,311 @@
+//! `BorrowedBuf` and `Read::read_buf` usage
+//!
+//! Tracking issues [78485] and [117693].
+//!
+//! `Read::read` takes a `&mut [u8]`, so a buffer has to be initialized before anything can be read
+//! into it, and reading a large blob into a fresh `Vec` writes every byte twice: once with zeroes,
+//! then with the data. A `BorrowedBuf` wraps a `&mut [MaybeUninit<u8>]` and keeps track of how
+//! much of it is filled and how much initialized, and `read_buf` reads into its unfilled part, so
+//! a reader that never looks at the buffer's old contents can fill uninitialized memory.
+//!
+//! [`read_index_vec`] and [`read_bit_set`] load what [`write_index_vec`] and [`write_bit_set`]
+//! wrote, a little-endian `u64` count followed by the little-endian elements, straight into the
+//! spare capacity of the new vector. A count of more elements than the index type can index is
+//! rejected, but otherwise the count comes from the input, which must be trusted not to ask for an
+//! absurd allocation. Without the `nightly` feature the vector is zeroed first and
+//! filled with `read_exact`; the benches compare the two.
+//!
+//! [78485]: https://github.com/rust-lang/rust/issues/78485
+//! [117693]: https://github.com/rust-lang/rust/issues/117693
+
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::{any, mem, slice};
+
+use crate::bit_set::BitSet;
+use crate::index_vec::{IndexSlice, IndexVec};
+use crate::step::Idx;
+
+/// Integers stored little-endian in a blob.
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value, with no padding.
+pub(crate) unsafe trait Word: Copy + Default {
+    /// Converts between native and little-endian order, either way.
+    fn to_le(self) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {$(
+        // SAFETY: a primitive integer.
+        unsafe impl Word for $t {
+            fn to_le(self) -> Self {
+                <$t>::to_le(self)
+            }
+        }
+    )*};
+}
+
+impl_word!(u32, u64);
+
+/// Reads `n` words into a new vector, without initializing it first.
+#[cfg(feature = "nightly")]
+pub(crate) fn read_words<T: Word>(reader: &mut impl Read, n: usize) -> io::Result<Vec<T>> {
+    use std::io::BorrowedBuf;
+    use std::mem::MaybeUninit;
+
+    let len = byte_len::<T>(n)?;
+    let mut words = Vec::<T>::with_capacity(n);
+    // SAFETY: the spare capacity holds at least `n` words, which is `len` bytes.
+    let bytes: &mut [MaybeUninit<u8>] =
+        unsafe { slice::from_raw_parts_mut(words.as_mut_ptr().cast(), len) };
+    let mut buf = BorrowedBuf::from(bytes);
+    reader.read_buf_exact(buf.unfilled())?;
+    // SAFETY: `read_buf_exact` filled all `len` bytes, and any bytes are valid words.
+    unsafe { words.set_len(n) };
+    for word in &mut words {
+        *word = word.to_le();
+    }
+    Ok(words)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn read_words<T: Word>(reader: &mut impl Read, n: usize) -> io::Result<Vec<T>> {
+    portable::read_words(reader, n)
+}
+
+/// The number of bytes in `n` words, or an error if that overflows.
+pub(crate) fn byte_len<T>(n: usize) -> io::Result<usize> {
+    n.checked_mul(mem::size_of::<T>())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "blob length overflows"))
+}
+
+/// Reads the count at the start of a blob.
+pub(crate) fn read_len(reader: &mut impl Read) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes))
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "blob length overflows"))
+}
+
+/// Reads the count at the start of an `IndexVec<I, _>` blob, failing if `I` can't index that many
+/// elements.
+pub(crate) fn read_index_len<I: Idx>(reader: &mut impl Read) -> io::Result<usize> {
+    let len = read_len(reader)?;
+    if len.checked_sub(1).is_some_and(|last| last > I::MAX_INDEX) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "blob has {len} elements, more than `{}` can index",
+                any::type_name::<I>()
+            ),
+        ));
+    }
+    Ok(len)
+}
+
+/// The little-endian bytes of `words`, borrowed where that is the native order.
+pub(crate) fn le_bytes<T: Word>(words: &[T]) -> Cow<'_, [u8]> {
+    let as_bytes = |words: &[T]| {
+        // SAFETY: `T` has no padding.
+        unsafe { slice::from_raw_parts(words.as_ptr().cast::<u8>(), mem::size_of_val(words)) }
+    };
+    if cfg!(target_endian = "little") {
+        Cow::Borrowed(as_bytes(words))
+    } else {
+        let words: Vec<T> = words.iter().map(|w| w.to_le()).collect();
+        Cow::Owned(as_bytes(&words).to_vec())
+    }
+}
+
+/// The count at the start of a blob.
+pub(crate) fn len_header(len: usize) -> [u8; 8] {
+    (len as u64).to_le_bytes()
+}
+
+fn write_words<T: Word>(writer: &mut impl Write, len: usize, words: &[T]) -> io::Result<()> {
+    writer.write_all(&len_header(len))?;
+    writer.write_all(&le_bytes(words))
+}
+
+/// Writes `v` as a blob for [`read_index_vec`].
+pub fn write_index_vec<I: Idx>(writer: &mut impl Write, v: &IndexSlice<I, u32>) -> io::Result<()> {
+    write_words(writer, v.len(), &v.raw)
+}
+
+/// Reads a blob written by [`write_index_vec`].
+pub fn read_index_vec<I: Idx>(reader: &mut impl Read) -> io::Result<IndexVec<I, u32>> {
+    let len = read_index_len::<I>(reader)?;
+    Ok(IndexVec::from_raw(read_words(reader, len)?))
+}
+
+/// Writes `set` as a blob for [`read_bit_set`].
+pub fn write_bit_set<T: Idx>(writer: &mut impl Write, set: &BitSet<T>) -> io::Result<()> {
+    write_words(writer, set.domain_size(), set.words())
+}
+
+/// Reads a blob written by [`write_bit_set`], failing if it has bits set past the domain.
+pub fn read_bit_set<T: Idx>(reader: &mut impl Read) -> io::Result<BitSet<T>> {
+    let domain_size = read_len(reader)?;
+    let words = read_words(reader, bit_set_words(domain_size))?;
+    bit_set_from_words(domain_size, words)
+}
+
+/// The number of words in a bit set blob.
+pub(crate) fn bit_set_words(domain_size: usize) -> usize {
+    domain_size.div_ceil(u64::BITS as usize)
+}
+
+/// The bit set read from a blob, failing if it has bits set past the domain.
+pub(crate) fn bit_set_from_words<T: Idx>(
+    domain_size: usize,
+    words: Vec<u64>,
+) -> io::Result<BitSet<T>> {
+    let tail = domain_size % u64::BITS as usize;
+    if let Some(&last) = words.last()
+        && tail != 0
+        && last >> tail != 0
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "bit set past the domain",
+        ));
+    }
+    Ok(BitSet::from_words(domain_size, words))
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::io::{self, Read};
+    use std::{mem, slice};
+
+    use super::{Word, byte_len};
+
+    pub(super) fn read_words<T: Word>(reader: &mut impl Read, n: usize) -> io::Result<Vec<T>> {
+        byte_len::<T>(n)?;
+        let mut words = vec![T::default(); n];
+        reader.read_exact(as_bytes_mut(&mut words))?;
+        for word in &mut words {
+            *word = word.to_le();
+        }
+        Ok(words)
+    }
+
+    /// The bytes of `words`, in memory order.
+    fn as_bytes_mut<T: Word>(words: &mut [T]) -> &mut [u8] {
+        let len = mem::size_of_val(words);
+        // SAFETY: `T` has no padding and any bytes are valid words.
+        unsafe { slice::from_raw_parts_mut(words.as_mut_ptr().cast(), len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step::CustomIndex;
+
+    #[test]
+    fn round_trips() {
+        let v: IndexVec<u32, u32> = IndexVec::from([1, 0xdead_beef, 3]);
+        let mut blob = Vec::new();
+        write_index_vec(&mut blob, &v).unwrap();
+        assert_eq!(blob.len(), 8 + 3 * 4);
+        assert_eq!(&blob[8..12], [1, 0, 0, 0]);
+        assert_eq!(read_index_vec::<u32>(&mut &blob[..]).unwrap(), v);
+
+        let mut set: BitSet<usize> = BitSet::new_empty(130);
+        set.insert(3);
+        set.insert(129);
+        let mut blob = Vec::new();
+        write_bit_set(&mut blob, &set).unwrap();
+        assert_eq!(read_bit_set::<usize>(&mut &blob[..]).unwrap(), set);
+    }
+
+    #[test]
+    fn bad_blobs() {
+        let v: IndexVec<u32, u32> = IndexVec::from([1, 2]);
+        let mut blob = Vec::new();
+        write_index_vec(&mut blob, &v).unwrap();
+        let err = read_index_vec::<u32>(&mut &blob[..blob.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut blob = 4u64.to_le_bytes().to_vec();
+        blob.extend(0b10000u64.to_le_bytes());
+        let err = read_bit_set::<usize>(&mut &blob[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn too_many_for_the_index() {
+        // One element per index fits.
+        let most = u64::from(CustomIndex::MAX.as_u32()) + 1;
+        let len = read_index_len::<CustomIndex>(&mut &most.to_le_bytes()[..]).unwrap();
+        assert_eq!(len as u64, most);
+
+        let err = read_index_vec::<CustomIndex>(&mut &(most + 1).to_le_bytes()[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "blob has {} elements, more than `{}` can index",
+                most + 1,
+                any::type_name::<CustomIndex>()
+            )
+        );
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let blob: Vec<u8> = (0..=255).collect();
+        for n in [0, 1, 64] {
+            let words: Vec<u32> = read_words(&mut &blob[..], n).unwrap();
+            assert_eq!(
+                portable::read_words::<u32>(&mut &blob[..], n).unwrap(),
+                words
+            );
+        }
+        assert!(portable::read_words::<u64>(&mut &blob[..], 33).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    /// Large enough that zeroing the vector first shows.
+    const N: usize = 1 << 22;
+
+    fn blob() -> Vec<u8> {
+        let v: IndexVec<usize, u32> = (0..N as u32).collect();
+        let mut blob = Vec::new();
+        write_index_vec(&mut blob, &v).unwrap();
+        blob
+    }
+
+    /// Reads into the uninitialized spare capacity.
+    #[bench]
+    fn read_uninit(b: &mut Bencher) {
+        let blob = blob();
+        b.iter(|| {
+            let v = read_index_vec::<usize>(&mut black_box(&blob[..])).unwrap();
+            v[N - 1]
+        });
+    }
+
+    /// Zeroes the vector, then reads over the zeroes.
+    #[bench]
+    fn read_zeroed(b: &mut Bencher) {
+        let blob = blob();
+        b.iter(|| {
+            let mut reader = black_box(&blob[..]);
+            let len = read_len(&mut reader).unwrap();
+            let words: Vec<u32> = portable::read_words(&mut reader, len).unwrap();
+            words[N - 1]
+        });
+    }
+}
@@ -0,0 +1,155 @@
+//! `sync::ReentrantLock` usage
+//!
+//! Tracking issue [121440].
+//!
+//! `ReentrantLock<T>` is a lock that the thread holding it can take again: a nested `lock()`
+//! returns another guard instead of blocking. It only hands out `&T`, since two guards of the same
+//! thread can be alive at once, so mutation goes through a `RefCell` inside. It is what guards
+//! `Stdout`, where a `Display` impl printing to stdout may itself print. A `Mutex` in the same spot
+//! deadlocks the thread on itself.
+//!
+//! [`SharedInterner`] puts a `StrInterner` behind one. [`SharedInterner::intern_with`] keeps the
+//! lock while its closure writes the string, so no other thread interns in between, and the
+//! closure may format a [`Path`], whose `Display` impl resolves symbols through the same lock.
+//! There is no stable equivalent, so the module is only built with the `nightly` feature.
+//!
+//! [121440]: https://github.com/rust-lang/rust/issues/121440
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::ReentrantLock;
+
+use crate::raw_entry::{StrInterner, Symbol};
+
+/// A string interner shared between threads.
+pub struct SharedInterner {
+    lock: ReentrantLock<RefCell<StrInterner>>,
+}
+
+impl SharedInterner {
+    pub fn new() -> Self {
+        SharedInterner {
+            lock: ReentrantLock::new(RefCell::new(StrInterner::new())),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock.lock().borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn intern(&self, s: &str) -> Symbol {
+        self.lock.lock().borrow_mut().intern(s)
+    }
+
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.lock.lock().borrow().get(s)
+    }
+
+    /// Interns the string that `write` builds, with the lock held throughout.
+    ///
+    /// `write` may use this interner again, e.g. to format a [`Path`].
+    pub fn intern_with(&self, write: impl FnOnce(&mut String) -> fmt::Result) -> Symbol {
+        let guard = self.lock.lock();
+        let mut s = String::new();
+        write(&mut s).expect("formatting into a `String` doesn't fail");
+        // Not borrowed across `write`, which may borrow it itself.
+        guard.borrow_mut().intern(&s)
+    }
+
+    /// Calls `f` with the string for `sym`.
+    ///
+    /// `f` may resolve other symbols, but must not intern.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `sym` was not created by this interner.
+    pub fn with_str<R>(&self, sym: Symbol, f: impl FnOnce(&str) -> R) -> R {
+        let guard = self.lock.lock();
+        let interner = guard.borrow();
+        f(interner.resolve(sym))
+    }
+}
+
+impl Default for SharedInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A `::`-separated path of interned segments.
+pub struct Path<'a> {
+    pub interner: &'a SharedInterner,
+    pub segments: Vec<Symbol>,
+}
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str("::")?;
+            }
+            self.interner.with_str(segment, |s| f.write_str(s))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+    use std::sync::{Mutex, TryLockError};
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn reentrant_interning() {
+        let interner = SharedInterner::new();
+        let segments = ["std", "sync"].map(|s| interner.intern(s)).to_vec();
+        let path = Path {
+            interner: &interner,
+            segments,
+        };
+        let names: Vec<Symbol> = thread::scope(|s| {
+            let (interner, path) = (&interner, &path);
+            let handles = ["Mutex", "RwLock", "Mutex"].map(|item| {
+                s.spawn(move || interner.intern_with(|buf| write!(buf, "{path}::{item}")))
+            });
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(names[0], names[2]);
+        interner.with_str(names[1], |s| assert_eq!(s, "std::sync::RwLock"));
+        assert_eq!(interner.get("std::sync::Mutex"), Some(names[0]));
+        assert_eq!(interner.len(), 4);
+    }
+
+    /// The same as `Path`, but over a `Mutex`.
+    struct MutexPath<'a>(&'a Mutex<StrInterner>, Symbol);
+
+    impl fmt::Display for MutexPath<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            // If this thread already holds the lock, `lock` would never return, so only try.
+            match self.0.try_lock() {
+                Ok(interner) => f.write_str(interner.resolve(self.1)),
+                Err(TryLockError::WouldBlock) => f.write_str("<deadlock>"),
+                Err(TryLockError::Poisoned(_)) => Err(fmt::Error),
+            }
+        }
+    }
+
+    #[test]
+    fn mutex_would_deadlock() {
+        let mutex = Mutex::new(StrInterner::new());
+        let sym = mutex.lock().unwrap().intern("core");
+        assert_eq!(MutexPath(&mutex, sym).to_string(), "core");
+
+        let mut interner = mutex.lock().unwrap();
+        let name = format!("{}::mem", MutexPath(&mutex, sym));
+        assert_eq!(name, "<deadlock>::mem");
+        interner.intern(&name);
+    }
+}
@@ -0,0 +1,347 @@
+//! `String::remove_matches` usage
+//!
+//! Tracking issue [72826].
+//!
+//! `s.remove_matches(pat)` deletes every match of a pattern from a `String` in place, in one pass
+//! that moves each kept byte once. The alternatives are `s.replace(pat, "")`, which allocates a
+//! new string even when nothing matches, and `retain`, which only takes a predicate on single
+//! `char`s.
+//!
+//! [`remove_str`] and [`remove_chars`] take a `&str` and a [`CharSet`] pattern. [`normalize_symbol`]
+//! removes the `'static`s that go without saying, those of references and those followed by more
+//! generic arguments, then drops the whitespace around punctuation and makes what is left between
+//! words one space. `StrInterner::intern_normalized`, and `StrInterner::intern_name` through
+//! [`normalized`], run it so that spellings of a type name that only differ in spacing or such a
+//! `'static` intern as one symbol. Without the `nightly` feature a
+//! `CharSet` is removed with `retain`, and a `&str` by moving the bytes between matches down over
+//! the matches.
+//!
+//! [72826]: https://github.com/rust-lang/rust/issues/72826
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::pattern::CharSet;
+
+/// ASCII whitespace, as `char::is_ascii_whitespace` defines it.
+const WHITESPACE: CharSet = CharSet::from_ascii(b" \t\n\x0C\r");
+
+/// What a type name needs no space next to: `Vec<&str>`, `(u8, u8)` and `<T as Tr>::Out` are
+/// spelled the same with or without one.
+const PUNCTUATION: CharSet = CharSet::from_ascii(b"<>,&()[]:;=+*");
+
+/// Removes every non-overlapping occurrence of `pattern` from `s`, from left to right. Occurrences
+/// that only form once others are removed stay.
+#[cfg(feature = "nightly")]
+pub fn remove_str(s: &mut String, pattern: &str) {
+    s.remove_matches(pattern);
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn remove_str(s: &mut String, pattern: &str) {
+    portable::remove_str(s, pattern);
+}
+
+/// Removes every member of `set` from `s`.
+#[cfg(feature = "nightly")]
+pub fn remove_chars(s: &mut String, set: CharSet) {
+    s.remove_matches(set);
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn remove_chars(s: &mut String, set: CharSet) {
+    portable::remove_chars(s, set);
+}
+
+/// Normalizes a type name for interning: removes the `'static` lifetimes of references and those
+/// followed by more generic arguments, then the ASCII whitespace next to punctuation and at the
+/// ends, and makes every other run of it a single space. So `&'static str` and `& str` both become
+/// `&str`, `Cow<'static, str>` becomes `Cow<str>`, and `&'static  mut dyn Fn()` becomes
+/// `&mut dyn Fn()`, keeping the spaces words need. `Foo<'static>`, where removing the lifetime
+/// would leave `Foo<>`, and a `T: 'static` bound keep theirs.
+pub fn normalize_symbol(name: &mut String) {
+    remove_redundant_statics(name);
+    normalize_spacing(name);
+}
+
+/// The `'static`s that [`normalize_symbol`] removes, with the comma after one in a list of generic
+/// arguments. A lifetime that merely starts with `'static`, like `'static_buf`, isn't one.
+fn redundant_statics(name: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    name.match_indices("'static")
+        .filter_map(|(start, lifetime)| {
+            let end = start + lifetime.len();
+            let after = &name[end..];
+            if after.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+            let before = name[..start].trim_end_matches(|c| WHITESPACE.contains(c));
+            if before.ends_with('&') {
+                return Some(start..end);
+            }
+            let rest = after.trim_start_matches(|c| WHITESPACE.contains(c));
+            rest.starts_with(',')
+                .then(|| start..name.len() - rest.len() + 1)
+        })
+}
+
+fn remove_redundant_statics(name: &mut String) {
+    let ranges: Vec<_> = redundant_statics(name).collect();
+    if ranges.is_empty() {
+        return;
+    }
+    let mut ranges = &ranges[..];
+    let mut at = 0;
+    name.retain(|c| {
+        while ranges.first().is_some_and(|r| r.end <= at) {
+            ranges = &ranges[1..];
+        }
+        let keep = ranges.first().is_none_or(|r| r.start > at);
+        at += c.len_utf8();
+        keep
+    });
+}
+
+fn normalize_spacing(name: &mut String) {
+    if is_spaced_normally(name) {
+        return;
+    }
+    let mut spaced = String::with_capacity(name.len());
+    let mut space = false;
+    for c in name.chars() {
+        if WHITESPACE.contains(c) {
+            space = !spaced.is_empty() && !spaced.ends_with(|c| PUNCTUATION.contains(c));
+        } else {
+            if space && !PUNCTUATION.contains(c) {
+                spaced.push(' ');
+            }
+            space = false;
+            spaced.push(c);
+        }
+    }
+    *name = spaced;
+}
+
+/// Whether all the whitespace in `name` is single spaces between words.
+fn is_spaced_normally(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    // Whitespace and punctuation are ASCII, so a byte of a wider char is neither.
+    let tight = |i: usize| {
+        let c = bytes[i] as char;
+        WHITESPACE.contains(c) || PUNCTUATION.contains(c)
+    };
+    bytes.iter().enumerate().all(|(i, &b)| {
+        !WHITESPACE.contains(b as char)
+            || (b == b' ' && i > 0 && i + 1 < bytes.len() && !tight(i - 1) && !tight(i + 1))
+    })
+}
+
+/// `name` normalized as by [`normalize_symbol`], borrowed if it is normal already.
+pub fn normalized(name: &str) -> Cow<'_, str> {
+    if redundant_statics(name).next().is_none() && is_spaced_normally(name) {
+        return Cow::Borrowed(name);
+    }
+    let mut name = name.to_string();
+    normalize_symbol(&mut name);
+    Cow::Owned(name)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use crate::pattern::CharSet;
+
+    pub(super) fn remove_str(s: &mut String, pattern: &str) {
+        if pattern.is_empty() {
+            return;
+        }
+        let Some(first) = s.find(pattern) else {
+            return;
+        };
+        // SAFETY: only whole matches of a `str` are removed, and what is left between them are
+        // slices of `s` cut at char boundaries, so the bytes stay valid UTF-8.
+        let bytes = unsafe { s.as_mut_vec() };
+        let (mut read, mut write) = (first, first);
+        while read < bytes.len() {
+            // `read` is at a match.
+            read += pattern.len();
+            let rest = &bytes[read..];
+            let next = rest
+                .windows(pattern.len())
+                .position(|w| w == pattern.as_bytes())
+                .unwrap_or(rest.len());
+            bytes.copy_within(read..read + next, write);
+            write += next;
+            read += next;
+        }
+        bytes.truncate(write);
+    }
+
+    pub(super) fn remove_chars(s: &mut String, set: CharSet) {
+        s.retain(|c| !set.contains(c));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes() {
+        let mut s = String::from("a--b----c-");
+        remove_str(&mut s, "--");
+        assert_eq!(s, "abc-");
+        let mut s = String::from("aaab");
+        remove_str(&mut s, "aa");
+        assert_eq!(s, "ab");
+        // Removing "ab" from "aabb" leaves a new "ab" behind.
+        let mut s = String::from("aabb");
+        remove_str(&mut s, "ab");
+        assert_eq!(s, "ab");
+        let mut s = String::from("ünï-cödé-");
+        remove_str(&mut s, "-");
+        assert_eq!(s, "ünïcödé");
+        remove_str(&mut s, "x");
+        assert_eq!(s, "ünïcödé");
+
+        let mut s = String::from("1, 2;3 ,ü");
+        remove_chars(&mut s, CharSet::from_ascii(b" ,;"));
+        assert_eq!(s, "123ü");
+    }
+
+    #[test]
+    fn normalizes() {
+        for name in ["&'static str", "& str", "&str"] {
+            let mut name = String::from(name);
+            normalize_symbol(&mut name);
+            assert_eq!(name, "&str");
+        }
+        let mut name = String::from("HashMap<&'static str,\n    Vec<u32 >>");
+        normalize_symbol(&mut name);
+        assert_eq!(name, "HashMap<&str,Vec<u32>>");
+        assert_eq!(normalized("Option<&str>"), Cow::Borrowed("Option<&str>"));
+        assert!(matches!(normalized("Option<&'static str>"), Cow::Owned(s) if s == "Option<&str>"));
+    }
+
+    #[test]
+    fn removes_only_redundant_statics() {
+        let cases = [
+            ("Cow<'static, str>", "Cow<str>"),
+            ("Foo<'a, 'static, T>", "Foo<'a,T>"),
+            ("&'static[u8]", "&[u8]"),
+            ("& 'static &'static str", "&&str"),
+            ("Foo<'static>", "Foo<'static>"),
+            ("Box<dyn Any + 'static>", "Box<dyn Any+'static>"),
+            ("T: 'static", "T:'static"),
+            ("Buf<'static_buf, u8>", "Buf<'static_buf,u8>"),
+            ("&'static_buf str", "&'static_buf str"),
+        ];
+        for (name, expected) in cases {
+            let mut normal = String::from(name);
+            normalize_symbol(&mut normal);
+            assert_eq!(normal, expected, "{name:?}");
+            assert_eq!(normalized(name), expected);
+        }
+        assert_eq!(normalized("Foo<'static>"), Cow::Borrowed("Foo<'static>"));
+        assert_eq!(
+            normalized("Buf<'static_buf>"),
+            Cow::Borrowed("Buf<'static_buf>")
+        );
+    }
+
+    #[test]
+    fn keeps_spaces_between_words() {
+        let cases = [
+            ("Box<dyn Fn()>", "Box<dyn Fn()>"),
+            ("Box< dyn\tFn( ) >", "Box<dyn Fn()>"),
+            ("impl  Iterator<Item = u8>", "impl Iterator<Item=u8>"),
+            ("& mut T", "&mut T"),
+            ("&'static mut [u8]", "&mut[u8]"),
+            ("&'a mut T", "&'a mut T"),
+            ("< T as Trait >::Out", "<T as Trait>::Out"),
+            (" *const u8 ", "*const u8"),
+            ("dyn Send + Sync", "dyn Send+Sync"),
+        ];
+        for (name, expected) in cases {
+            let mut normal = String::from(name);
+            normalize_symbol(&mut normal);
+            assert_eq!(normal, expected, "{name:?}");
+            assert_eq!(normalized(name), expected);
+            assert!(
+                matches!(normalized(expected), Cow::Borrowed(_)),
+                "{expected:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let haystacks = ["", "ab", "abab", "aabb", "xaby", "ü-ü--ü", "---"];
+        for haystack in haystacks {
+            for pattern in ["ab", "-", "--", "ü", "q"] {
+                let (mut from_std, mut ported) = (haystack.to_string(), haystack.to_string());
+                from_std.remove_matches(pattern);
+                portable::remove_str(&mut ported, pattern);
+                assert_eq!(from_std, ported, "{haystack:?} {pattern:?}");
+            }
+            let (mut from_std, mut ported) = (haystack.to_string(), haystack.to_string());
+            from_std.remove_matches(WHITESPACE.union(CharSet::from_ascii(b"-")));
+            portable::remove_chars(&mut ported, WHITESPACE.union(CharSet::from_ascii(b"-")));
+            assert_eq!(from_std, ported);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    /// Type names as `any::type_name` spells them, padded the way people write them.
+    fn names() -> Vec<String> {
+        (0..1000)
+            .map(|i| format!("HashMap<&'static str, Vec<u{}>>", 8 << (i % 4)))
+            .collect()
+    }
+
+    #[bench]
+    fn normalize_remove_matches(b: &mut Bencher) {
+        let names = names();
+        b.iter(|| {
+            for name in &names {
+                let mut name = name.clone();
+                remove_str(black_box(&mut name), "'static");
+                normalize_spacing(&mut name);
+                black_box(name);
+            }
+        });
+    }
+
+    /// The stable fallback, moving bytes down over the `'static`s.
+    #[bench]
+    fn normalize_portable(b: &mut Bencher) {
+        let names = names();
+        b.iter(|| {
+            for name in &names {
+                let mut name = name.clone();
+                portable::remove_str(black_box(&mut name), "'static");
+                normalize_spacing(&mut name);
+                black_box(name);
+            }
+        });
+    }
+
+    /// `replace` allocates a new string even when nothing matches.
+    #[bench]
+    fn normalize_replace(b: &mut Bencher) {
+        let names = names();
+        b.iter(|| {
+            for name in &names {
+                let mut name = black_box(name).replace("'static", "");
+                normalize_spacing(&mut name);
+                black_box(name);
+            }
+        });
+    }
+}
@@ -0,0 +1,340 @@
+//! `Step` for wrapping indices
+//!
+//! Tracking issue [42168].
+//!
+//! A ring buffer of `LEN` slots is indexed modulo `LEN`: the slot after the last is the first
+//! again. [`WrappingIdx<I, LEN>`] is such an index over the first `LEN` values of an index type
+//! `I`, whose `+` and `-` wrap around, and [`WrappingIdx::iter_from`] walks all `LEN` slots from
+//! any of them, wrapping once. It is an `Idx` of its own, so it indexes `IndexVec`s like any other.
+//! With the `nightly` feature it implements `Step` too, so that `a..b` is a range of slots; a
+//! `Step` has to agree with the order of the type, so ranges don't wrap.
+//!
+//! [`RingIndexVec`] is a circular log on top of an `IndexVec`: it holds the latest `LEN` values
+//! pushed, each in the slot it was written to, and pushing to a full log hands back the oldest
+//! value. The `LEN` slots must all be valid `I`s, which is checked at compile time.
+//!
+//! [42168]: https://github.com/rust-lang/rust/issues/42168
+
+use std::cmp::Ordering;
+#[cfg(feature = "nightly")]
+use std::iter::Step;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Index, IndexMut, Sub, SubAssign};
+use std::{fmt, mem};
+
+use crate::index_vec::IndexVec;
+use crate::inline_const::assert_len_fits;
+use crate::step::Idx;
+
+/// An index into a ring of `LEN` slots, whose arithmetic wraps modulo `LEN`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WrappingIdx<I: Idx, const LEN: usize> {
+    /// Less than `LEN`.
+    raw: usize,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, const LEN: usize> WrappingIdx<I, LEN> {
+    pub const ZERO: Self = WrappingIdx::from_raw(0);
+
+    /// The last slot.
+    pub const LAST: Self = WrappingIdx::from_raw(LEN - 1);
+
+    /// Fails the build if `LEN` is zero or has slots `I` can't index.
+    const fn from_raw(raw: usize) -> Self {
+        const { assert!(LEN > 0, "a ring needs at least one slot") };
+        assert_len_fits::<I, LEN>();
+        WrappingIdx {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The slot `index` wraps to.
+    pub fn wrap(index: I) -> Self {
+        Self::from_raw(index.index() % LEN)
+    }
+
+    pub fn get(self) -> I {
+        I::new(self.raw)
+    }
+
+    /// How many steps forward `other` is from `self`, less than `LEN`.
+    pub fn distance_to(self, other: Self) -> usize {
+        (other.raw + LEN - self.raw) % LEN
+    }
+
+    /// All `LEN` slots, starting at `self` and wrapping around.
+    pub fn iter_from(self) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator {
+        (0..LEN).map(move |n| self + n)
+    }
+}
+
+impl<I: Idx, const LEN: usize> Idx for WrappingIdx<I, LEN> {
+    const MAX_INDEX: usize = LEN - 1;
+
+    /// # Panics
+    ///
+    /// Panics if `idx` isn't less than `LEN`; [`WrappingIdx::wrap`] wraps it instead.
+    #[inline]
+    fn new(idx: usize) -> Self {
+        assert!(idx < LEN, "slot {idx} out of a ring of {LEN}");
+        Self::from_raw(idx)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.raw
+    }
+}
+
+impl<I: Idx, const LEN: usize> Add<usize> for WrappingIdx<I, LEN> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, n: usize) -> Self {
+        Self::from_raw((self.raw + n % LEN) % LEN)
+    }
+}
+
+impl<I: Idx, const LEN: usize> Sub<usize> for WrappingIdx<I, LEN> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, n: usize) -> Self {
+        Self::from_raw((self.raw + LEN - n % LEN) % LEN)
+    }
+}
+
+impl<I: Idx, const LEN: usize> AddAssign<usize> for WrappingIdx<I, LEN> {
+    #[inline]
+    fn add_assign(&mut self, n: usize) {
+        *self = *self + n;
+    }
+}
+
+impl<I: Idx, const LEN: usize> SubAssign<usize> for WrappingIdx<I, LEN> {
+    #[inline]
+    fn sub_assign(&mut self, n: usize) {
+        *self = *self - n;
+    }
+}
+
+impl<I: Idx, const LEN: usize> PartialOrd for WrappingIdx<I, LEN> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders slots by position in the ring, not by age.
+impl<I: Idx, const LEN: usize> Ord for WrappingIdx<I, LEN> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
+impl<I: Idx, const LEN: usize> fmt::Debug for WrappingIdx<I, LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{LEN}", self.raw)
+    }
+}
+
+/// Steps within `0..LEN` without wrapping, so that it agrees with `Ord`.
+#[cfg(feature = "nightly")]
+impl<I: Idx, const LEN: usize> Step for WrappingIdx<I, LEN> {
+    #[inline]
+    fn steps_between(start: &Self, end: &Self) -> (usize, Option<usize>) {
+        <usize as Step>::steps_between(&start.raw, &end.raw)
+    }
+
+    #[inline]
+    fn forward_checked(start: Self, n: usize) -> Option<Self> {
+        start
+            .raw
+            .checked_add(n)
+            .filter(|&raw| raw < LEN)
+            .map(Self::from_raw)
+    }
+
+    #[inline]
+    fn backward_checked(start: Self, n: usize) -> Option<Self> {
+        start.raw.checked_sub(n).map(Self::from_raw)
+    }
+}
+
+/// The latest `LEN` values pushed, in a ring indexed by [`WrappingIdx`].
+pub struct RingIndexVec<I: Idx, T, const LEN: usize> {
+    /// Grows to `LEN`, then is overwritten in place.
+    slots: IndexVec<WrappingIdx<I, LEN>, T>,
+    /// The slot the next push writes.
+    next: WrappingIdx<I, LEN>,
+}
+
+impl<I: Idx, T, const LEN: usize> RingIndexVec<I, T, LEN> {
+    pub fn new() -> Self {
+        RingIndexVec {
+            slots: IndexVec::with_capacity(LEN),
+            next: WrappingIdx::ZERO,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.slots.len() == LEN
+    }
+
+    /// Writes `value` to the next slot, and returns the slot and the oldest value if it took
+    /// that one's place.
+    pub fn push(&mut self, value: T) -> (WrappingIdx<I, LEN>, Option<T>) {
+        let slot = self.next;
+        self.next += 1;
+        if self.is_full() {
+            (slot, Some(mem::replace(&mut self.slots[slot], value)))
+        } else {
+            self.slots.push(value);
+            (slot, None)
+        }
+    }
+
+    /// The slot of the oldest value, if any.
+    pub fn oldest(&self) -> Option<WrappingIdx<I, LEN>> {
+        if self.is_empty() {
+            None
+        } else if self.is_full() {
+            Some(self.next)
+        } else {
+            Some(WrappingIdx::ZERO)
+        }
+    }
+
+    /// The slot of the latest value, if any.
+    pub fn newest(&self) -> Option<WrappingIdx<I, LEN>> {
+        (!self.is_empty()).then(|| self.next - 1)
+    }
+
+    pub fn get(&self, slot: WrappingIdx<I, LEN>) -> Option<&T> {
+        self.slots.get(slot)
+    }
+
+    /// The values with their slots, oldest first.
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (WrappingIdx<I, LEN>, &T)> + '_ {
+        let start = self.oldest().unwrap_or(WrappingIdx::ZERO);
+        start
+            .iter_from()
+            .take(self.len())
+            .map(|slot| (slot, &self.slots[slot]))
+    }
+
+    /// The values, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter_enumerated().map(|(_, value)| value)
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.next = WrappingIdx::ZERO;
+    }
+}
+
+impl<I: Idx, T, const LEN: usize> Default for RingIndexVec<I, T, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Idx, T, const LEN: usize> Index<WrappingIdx<I, LEN>> for RingIndexVec<I, T, LEN> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, slot: WrappingIdx<I, LEN>) -> &T {
+        &self.slots[slot]
+    }
+}
+
+impl<I: Idx, T, const LEN: usize> IndexMut<WrappingIdx<I, LEN>> for RingIndexVec<I, T, LEN> {
+    #[inline]
+    fn index_mut(&mut self, slot: WrappingIdx<I, LEN>) -> &mut T {
+        &mut self.slots[slot]
+    }
+}
+
+impl<I: Idx, T: fmt::Debug, const LEN: usize> fmt::Debug for RingIndexVec<I, T, LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Slot = WrappingIdx<u32, 4>;
+
+    #[test]
+    fn wraps() {
+        let last = Slot::LAST;
+        assert_eq!((last + 1, last + 6), (Slot::ZERO, Slot::new(1)));
+        assert_eq!(Slot::ZERO - 1, last);
+        assert_eq!(Slot::new(1) - usize::MAX, Slot::new(2));
+        assert_eq!(Slot::wrap(10).get(), 2);
+        assert_eq!(Slot::new(3).distance_to(Slot::new(1)), 2);
+        let mut slot = Slot::new(2);
+        slot += 3;
+        assert_eq!(slot.index(), 1);
+        let order: Vec<usize> = slot.iter_from().map(Slot::index).collect();
+        assert_eq!(order, [1, 2, 3, 0]);
+        assert_eq!(format!("{slot:?}"), "1/4");
+    }
+
+    #[test]
+    #[should_panic = "slot 4 out of a ring of 4"]
+    fn new_checks_the_ring() {
+        Slot::new(4);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn steps_without_wrapping() {
+        let slots: Vec<usize> = (Slot::new(1)..=Slot::LAST).map(Slot::index).collect();
+        assert_eq!(slots, [1, 2, 3]);
+        assert_eq!(Step::forward_checked(Slot::LAST, 1), None);
+        assert_eq!(Step::backward_checked(Slot::ZERO, 1), None);
+    }
+
+    #[test]
+    fn keeps_the_latest() {
+        let mut log: RingIndexVec<u32, String, 3> = RingIndexVec::new();
+        assert_eq!((log.oldest(), log.newest()), (None, None));
+        for word in ["a", "b"] {
+            assert_eq!(log.push(word.to_string()).1, None);
+        }
+        assert_eq!(log.iter().cloned().collect::<String>(), "ab");
+        assert_eq!(log.push("c".to_string()), (WrappingIdx::new(2), None));
+        assert!(log.is_full());
+        let (slot, evicted) = log.push("d".to_string());
+        assert_eq!((slot.index(), evicted.as_deref()), (0, Some("a")));
+        log.push("e".to_string());
+        assert_eq!(format!("{log:?}"), r#"["c", "d", "e"]"#);
+        assert_eq!(log.oldest(), Some(WrappingIdx::new(2)));
+        let newest = log.newest().unwrap();
+        assert_eq!((newest.index(), log[newest].as_str()), (1, "e"));
+        log[newest].push('!');
+        let slots: Vec<usize> = log
+            .iter_enumerated()
+            .map(|(slot, _)| slot.index())
+            .collect();
+        assert_eq!(slots, [2, 0, 1]);
+        assert_eq!(log.get(newest).unwrap(), "e!");
+
+        log.clear();
+        assert!(log.is_empty());
+        assert_eq!(log.push("f".to_string()).0, WrappingIdx::ZERO);
+    }
+}
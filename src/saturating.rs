@@ -0,0 +1,179 @@
+//! `num::Saturating` usage
+//!
+//! Tracking issue [87920].
+//!
+//! `Saturating<T>` wraps an integer whose `+`, `-` and `*` saturate at the bounds of `T`, the way
+//! `Wrapping<T>` wraps around them, so code that accumulates counts writes plain operators and
+//! never panics on overflow in debug builds.
+//!
+//! A [`SaturatingIdx<I>`] is a running position for an index type `I`, e.g. the end of a buffer
+//! that grows by the lengths of its parts. It keeps a `Saturating<usize>` and also clamps it at
+//! `I::MAX_INDEX`, so [`SaturatingIdx::get`] always gives a valid `I` where `I::plus` would
+//! panic, and [`SaturatingIdx::is_saturated`] says whether it had to clamp.
+//!
+//! [87920]: https://github.com/rust-lang/rust/issues/87920
+
+use std::fmt;
+use std::iter::Sum;
+use std::marker::PhantomData;
+use std::num::Saturating;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+use crate::step::Idx;
+
+/// An index of type `I` whose arithmetic saturates at zero and at `I::MAX_INDEX`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SaturatingIdx<I: Idx> {
+    /// At most `I::MAX_INDEX`.
+    raw: Saturating<usize>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx> SaturatingIdx<I> {
+    pub const ZERO: Self = SaturatingIdx {
+        raw: Saturating(0),
+        _marker: PhantomData,
+    };
+
+    pub const MAX: Self = SaturatingIdx {
+        raw: Saturating(I::MAX_INDEX),
+        _marker: PhantomData,
+    };
+
+    pub fn new(index: I) -> Self {
+        Self::from_saturating(Saturating(index.index()))
+    }
+
+    /// `raw` clamped at `I::MAX_INDEX`.
+    pub fn from_saturating(raw: Saturating<usize>) -> Self {
+        SaturatingIdx {
+            raw: Saturating(raw.0.min(I::MAX_INDEX)),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn to_saturating(self) -> Saturating<usize> {
+        self.raw
+    }
+
+    pub fn get(self) -> I {
+        I::new(self.raw.0)
+    }
+
+    pub fn index(self) -> usize {
+        self.raw.0
+    }
+
+    /// Whether the index is at `I::MAX_INDEX`, which is where it stays once it overflowed.
+    pub fn is_saturated(self) -> bool {
+        self.raw.0 == I::MAX_INDEX
+    }
+}
+
+impl<I: Idx> From<I> for SaturatingIdx<I> {
+    fn from(index: I) -> Self {
+        SaturatingIdx::new(index)
+    }
+}
+
+impl<I: Idx> Default for SaturatingIdx<I> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<I: Idx> Add<usize> for SaturatingIdx<I> {
+    type Output = Self;
+
+    fn add(self, other: usize) -> Self {
+        Self::from_saturating(self.raw + Saturating(other))
+    }
+}
+
+impl<I: Idx> Sub<usize> for SaturatingIdx<I> {
+    type Output = Self;
+
+    fn sub(self, other: usize) -> Self {
+        Self::from_saturating(self.raw - Saturating(other))
+    }
+}
+
+impl<I: Idx> Mul<usize> for SaturatingIdx<I> {
+    type Output = Self;
+
+    fn mul(self, other: usize) -> Self {
+        Self::from_saturating(self.raw * Saturating(other))
+    }
+}
+
+impl<I: Idx> AddAssign<usize> for SaturatingIdx<I> {
+    fn add_assign(&mut self, other: usize) {
+        *self = *self + other;
+    }
+}
+
+impl<I: Idx> SubAssign<usize> for SaturatingIdx<I> {
+    fn sub_assign(&mut self, other: usize) {
+        *self = *self - other;
+    }
+}
+
+impl<I: Idx> MulAssign<usize> for SaturatingIdx<I> {
+    fn mul_assign(&mut self, other: usize) {
+        *self = *self * other;
+    }
+}
+
+impl<I: Idx> Sum<usize> for SaturatingIdx<I> {
+    fn sum<It: Iterator<Item = usize>>(iter: It) -> Self {
+        iter.fold(Self::ZERO, |sum, n| sum + n)
+    }
+}
+
+impl<I: Idx> fmt::Debug for SaturatingIdx<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SaturatingIdx").field(&self.raw.0).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread_id_value::ThreadIdx;
+
+    #[test]
+    fn clamps_at_the_index_max() {
+        let mut end = SaturatingIdx::<u32>::new(7);
+        end += 3;
+        end -= 20;
+        assert_eq!(end, SaturatingIdx::ZERO);
+        end += u32::MAX as usize - 1;
+        assert!(!end.is_saturated());
+        end += 2;
+        assert!(end.is_saturated());
+        assert_eq!(end.get(), u32::MAX);
+        assert_eq!((end - 1).get(), u32::MAX - 1);
+
+        let big = SaturatingIdx::<usize>::new(usize::MAX / 2) * 3;
+        assert_eq!(big, SaturatingIdx::MAX);
+        assert_eq!(big.to_saturating(), Saturating(usize::MAX));
+        assert_eq!(
+            format!("{:?}", SaturatingIdx::<u32>::from(5)),
+            "SaturatingIdx(5)"
+        );
+    }
+
+    #[test]
+    fn sums_without_aborting() {
+        let lens = [u32::MAX as usize - 1500, 1000, 1000];
+        let end: SaturatingIdx<ThreadIdx> = lens.into_iter().sum();
+        assert!(end.is_saturated());
+        assert_eq!(end.index(), ThreadIdx::MAX_INDEX);
+        let end: SaturatingIdx<ThreadIdx> = [1, 2, 3].into_iter().sum();
+        assert_eq!(end.get(), ThreadIdx::new(6));
+        assert_eq!(
+            SaturatingIdx::<u32>::from_saturating(Saturating(usize::MAX)).index(),
+            u32::MAX as usize
+        );
+    }
+}
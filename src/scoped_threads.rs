@@ -0,0 +1,112 @@
+//! `thread::scope` usage
+//!
+//! Tracking issue [93203].
+//!
+//! `thread::scope(|s| ...)` runs a closure that can spawn threads with `s.spawn`, and joins them
+//! all before it returns, so they may borrow anything that outlives the call. That is all a data
+//! parallel loop over a borrowed collection needs, without a thread pool crate.
+//!
+//! [`IndexVec::par_map_scoped`] maps an `IndexVec` on a few scoped threads, one contiguous chunk
+//! each, and puts the results back together in index order. It spawns its threads on every call,
+//! so it pays off for work that is heavy next to a thread spawn. `spawn_unchecked::par_for_each`
+//! is the same split built on a scope of its own.
+//!
+//! [93203]: https://github.com/rust-lang/rust/issues/93203
+
+use std::panic;
+use std::thread;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+impl<I: Idx, T: Sync> IndexVec<I, T> {
+    /// Maps every element with `f` on up to `n_threads` threads, keeping the indices. A
+    /// `n_threads` of 0 is taken as 1, which runs `f` on the current thread.
+    ///
+    /// # Panics
+    ///
+    /// If `f` panics, panics with the same payload once all threads have stopped.
+    pub fn par_map_scoped<U: Send>(
+        &self,
+        n_threads: usize,
+        f: impl Fn(I, &T) -> U + Sync,
+    ) -> IndexVec<I, U> {
+        let n_threads = n_threads.max(1);
+        if n_threads == 1 || self.len() <= 1 {
+            return self.iter_enumerated().map(|(i, t)| f(i, t)).collect();
+        }
+        let chunk_len = self.len().div_ceil(n_threads);
+        let f = &f;
+        thread::scope(|s| {
+            let handles: Vec<_> = self
+                .raw
+                .chunks(chunk_len)
+                .enumerate()
+                .map(|(n, chunk)| {
+                    s.spawn(move || {
+                        let start = n * chunk_len;
+                        chunk
+                            .iter()
+                            .enumerate()
+                            .map(|(i, t)| f(I::new(start + i), t))
+                            .collect::<Vec<U>>()
+                    })
+                })
+                .collect();
+            let mut raw = Vec::with_capacity(self.len());
+            for handle in handles {
+                match handle.join() {
+                    Ok(part) => raw.extend(part),
+                    Err(payload) => panic::resume_unwind(payload),
+                }
+            }
+            IndexVec::from_raw(raw)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn keeps_index_order() {
+        let words: IndexVec<u32, String> = (0..37).map(|n| format!("w{n}")).collect();
+        let threads = Mutex::new(Vec::new());
+        let mapped = words.par_map_scoped(4, |i, w| {
+            threads.lock().unwrap().push(thread::current().id());
+            format!("{i}:{w}")
+        });
+        let expected: IndexVec<u32, String> = (0..37).map(|n| format!("{n}:w{n}")).collect();
+        assert_eq!(mapped, expected);
+        let threads: HashSet<_> = threads.into_inner().unwrap().into_iter().collect();
+        assert_eq!(threads.len(), 4);
+
+        // More threads than elements, and a single thread, give the same result.
+        let small: IndexVec<u32, u8> = IndexVec::from([1, 2, 3]);
+        assert_eq!(
+            small.par_map_scoped(8, |i, &x| i * 10 + u32::from(x)).raw,
+            [1, 12, 23]
+        );
+        assert_eq!(small.par_map_scoped(0, |_, &x| x * 2).raw, [2, 4, 6]);
+        assert!(
+            IndexVec::<u32, u8>::new()
+                .par_map_scoped(4, |_, &x| x)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "element 20")]
+    fn propagates_panics() {
+        let v: IndexVec<u32, u32> = (0..30).collect();
+        v.par_map_scoped(3, |i, _| {
+            if i == 20 {
+                panic!("element {i}");
+            }
+        });
+    }
+}
@@ -0,0 +1,149 @@
+//! `Seek::stream_len` usage
+//!
+//! Tracking issue [59359].
+//!
+//! `Seek::stream_len()` is the length of a seekable stream, such as a file, in bytes, leaving the
+//! position where it was. Without it that takes three seeks: to the end to find the length, and
+//! then back to where the stream was, which `Seek` implementations may or may not do faster.
+//!
+//! [`read_index_vec_checked`] and [`read_bit_set_checked`] read the same blobs as
+//! `read_buf::read_index_vec` and `read_buf::read_bit_set`, but first check that the rest of the
+//! stream holds all the elements the count says, so that a truncated or corrupt file fails with an
+//! error before the vector is allocated, not with an `UnexpectedEof` after. Without the `nightly`
+//! feature the length is found with the seeks.
+//!
+//! [59359]: https://github.com/rust-lang/rust/issues/59359
+
+use std::io::{self, Read, Seek};
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexVec;
+use crate::read_buf::{
+    bit_set_from_words, bit_set_words, byte_len, read_index_len, read_len, read_words,
+};
+use crate::step::Idx;
+
+/// The length of `stream`, in bytes.
+#[cfg(feature = "nightly")]
+pub fn stream_len(stream: &mut impl Seek) -> io::Result<u64> {
+    stream.stream_len()
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn stream_len(stream: &mut impl Seek) -> io::Result<u64> {
+    portable::stream_len(stream)
+}
+
+/// Fails unless `stream` has at least `n` more words of `T`.
+fn check_remaining<T>(stream: &mut impl Seek, n: usize) -> io::Result<()> {
+    let needed = byte_len::<T>(n)? as u64;
+    let remaining = stream_len(stream)?.saturating_sub(stream.stream_position()?);
+    if remaining < needed {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("blob declares {needed} bytes of elements, but only {remaining} are left"),
+        ));
+    }
+    Ok(())
+}
+
+/// Like `read_buf::read_index_vec`, but fails before allocating if the stream is too short.
+pub fn read_index_vec_checked<I: Idx>(
+    reader: &mut (impl Read + Seek),
+) -> io::Result<IndexVec<I, u32>> {
+    let len = read_index_len::<I>(reader)?;
+    check_remaining::<u32>(reader, len)?;
+    Ok(IndexVec::from_raw(read_words(reader, len)?))
+}
+
+/// Like `read_buf::read_bit_set`, but fails before allocating if the stream is too short.
+pub fn read_bit_set_checked<T: Idx>(reader: &mut (impl Read + Seek)) -> io::Result<BitSet<T>> {
+    let domain_size = read_len(reader)?;
+    let n = bit_set_words(domain_size);
+    check_remaining::<u64>(reader, n)?;
+    bit_set_from_words(domain_size, read_words(reader, n)?)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::io::{self, Seek, SeekFrom};
+
+    pub(super) fn stream_len(stream: &mut impl Seek) -> io::Result<u64> {
+        let position = stream.stream_position()?;
+        let len = stream.seek(SeekFrom::End(0))?;
+        if position != len {
+            stream.seek(SeekFrom::Start(position))?;
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::read_buf::{write_bit_set, write_index_vec};
+    use crate::step::CustomIndex;
+
+    #[test]
+    fn checks_length() {
+        let v: IndexVec<u32, u32> = (0..10).collect();
+        let mut blob = Vec::new();
+        write_index_vec(&mut blob, &v).unwrap();
+        let read = read_index_vec_checked::<u32>(&mut Cursor::new(&blob[..]));
+        assert_eq!(read.unwrap(), v);
+
+        let mut cut = Cursor::new(&blob[..blob.len() - 1]);
+        let err = read_index_vec_checked::<u32>(&mut cut).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "blob declares 40 bytes of elements, but only 39 are left"
+        );
+        // Nothing past the header was read.
+        assert_eq!(cut.position(), 8);
+
+        // A count no allocation could hold.
+        let mut huge = Cursor::new((u64::MAX / 8).to_le_bytes());
+        let err = read_index_vec_checked::<u32>(&mut huge).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // More elements than the index type can index.
+        let too_many = u64::from(CustomIndex::MAX.as_u32()) + 2;
+        let mut too_many = Cursor::new(too_many.to_le_bytes());
+        let err = read_index_vec_checked::<CustomIndex>(&mut too_many).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().starts_with("blob has 4294967042 elements"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn checks_files() {
+        let path = env::temp_dir().join(format!("rust-unstable-stream-len-{}", process::id()));
+        let mut set: BitSet<usize> = BitSet::new_empty(300);
+        set.insert(299);
+        let mut blob = Vec::new();
+        write_bit_set(&mut blob, &set).unwrap();
+        fs::write(&path, &blob).unwrap();
+        let read = read_bit_set_checked::<usize>(&mut fs::File::open(&path).unwrap());
+        fs::write(&path, &blob[..20]).unwrap();
+        let cut = read_bit_set_checked::<usize>(&mut fs::File::open(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), set);
+        assert_eq!(cut.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut cursor = Cursor::new([0u8; 17]);
+        cursor.set_position(5);
+        assert_eq!(portable::stream_len(&mut cursor).unwrap(), 17);
+        assert_eq!(stream_len(&mut cursor).unwrap(), 17);
+        assert_eq!(cursor.position(), 5);
+    }
+}
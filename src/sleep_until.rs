@@ -0,0 +1,236 @@
+//! `thread::sleep_until` usage
+//!
+//! Tracking issue [113752].
+//!
+//! `thread::sleep_until(deadline)` blocks the thread until an `Instant`, rather than for a
+//! `Duration` like `thread::sleep`. A periodic task that sleeps for its period after each run
+//! drifts by however long the runs take, and by the oversleep of each wakeup. Sleeping until the
+//! next multiple of the period from a fixed start doesn't, so the runs stay on their schedule.
+//! Some platforms also sleep until a deadline natively, without a time check before the call.
+//!
+//! [`Ticker`] is such a schedule, for background work like recomputing an analysis. A run that
+//! takes longer than a period skips the ticks it overran, instead of catching up with a burst of
+//! runs. Without the `nightly` feature [`sleep_until`] sleeps for the time left to the deadline.
+//!
+//! [113752]: https://github.com/rust-lang/rust/issues/113752
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "nightly")]
+use std::thread;
+
+use crate::deadline::Deadline;
+use crate::time::whole_periods;
+
+/// Blocks the current thread until `deadline`, returning at once if it has passed.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn sleep_until(deadline: Instant) {
+    thread::sleep_until(deadline)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn sleep_until(deadline: Instant) {
+    portable::sleep_until(deadline)
+}
+
+/// Deadlines a fixed period apart.
+#[derive(Debug, Clone)]
+pub struct Ticker {
+    period: Duration,
+    next: Instant,
+    missed: u64,
+}
+
+impl Ticker {
+    /// A ticker whose first tick is one `period` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn new(period: Duration) -> Self {
+        Self::starting_at(Instant::now() + period, period)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn starting_at(first: Instant, period: Duration) -> Self {
+        assert!(!period.is_zero(), "ticker period must be non-zero");
+        Ticker {
+            period,
+            next: first,
+            missed: 0,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// The deadline of the next tick.
+    pub fn next_deadline(&self) -> Instant {
+        self.next
+    }
+
+    /// The number of ticks skipped because they had already passed.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Sleeps until the next tick, and returns its deadline.
+    pub fn tick(&mut self) -> Instant {
+        let deadline = self.next;
+        sleep_until(deadline);
+        self.next = deadline + self.period;
+        let now = Instant::now();
+        if self.next <= now {
+            let behind = whole_periods(now - self.next, self.period);
+            let skipped = u32::try_from(behind + 1).unwrap_or(u32::MAX);
+            self.next += self.period * skipped;
+            self.missed += u64::from(skipped);
+        }
+        deadline
+    }
+
+    /// Calls `f` at every tick until `stop` is set, seen at the next tick.
+    pub fn run_until(&mut self, stop: &AtomicBool, mut f: impl FnMut(Instant)) {
+        loop {
+            let deadline = self.tick();
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            f(deadline);
+        }
+    }
+
+    /// Calls `f` at every tick before `deadline`.
+    pub fn run_for(&mut self, deadline: Deadline, mut f: impl FnMut(Instant)) {
+        while Deadline::at(self.next) < deadline {
+            f(self.tick());
+        }
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::thread;
+    use std::time::Instant;
+
+    pub(super) fn sleep_until(deadline: Instant) {
+        if let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            thread::sleep(left);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn no_drift() {
+        let start = Instant::now();
+        let mut ticker = Ticker::starting_at(start + PERIOD, PERIOD);
+        let mut wakeups = Vec::new();
+        for _ in 0..8 {
+            let deadline = ticker.tick();
+            wakeups.push((deadline, Instant::now()));
+            // Work taking most of the period, which a plain `sleep(PERIOD)` would add to it.
+            thread::sleep(PERIOD * 6 / 10);
+        }
+        // A loaded machine may oversleep past whole ticks, which are skipped, but every deadline
+        // stays on the schedule from `start` however late the wakeups before it were.
+        assert_eq!(wakeups[0].0, start + PERIOD);
+        assert!(wakeups.windows(2).all(|w| w[0].0 < w[1].0));
+        for &(deadline, woke) in &wakeups {
+            assert_eq!((deadline - start).as_nanos() % PERIOD.as_nanos(), 0);
+            assert!(woke >= deadline);
+        }
+        // Every tick so far was either run or counted as missed.
+        let ticks = 9 + u32::try_from(ticker.missed()).unwrap();
+        assert_eq!(ticker.next_deadline(), start + PERIOD * ticks);
+        // Sleeping for the period after the work would take nearly 13 periods for the eight ticks.
+        // On the schedule they take a little over eight, with at most one lost to a late wakeup.
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= PERIOD * 8 && elapsed < PERIOD * 12,
+            "{elapsed:?}"
+        );
+        assert!(ticker.missed() <= 1, "{}", ticker.missed());
+    }
+
+    #[test]
+    fn skips_overrun_ticks() {
+        let mut ticker = Ticker::new(PERIOD);
+        let first = ticker.next_deadline();
+        thread::sleep(PERIOD * 4 + PERIOD / 2);
+        assert_eq!(ticker.tick(), first);
+        // The ticks at 2, 3 and 4 periods had passed already.
+        assert!(ticker.missed() >= 3, "{}", ticker.missed());
+        assert!(ticker.next_deadline() > Instant::now());
+        assert_eq!(
+            (ticker.next_deadline() - first).as_nanos() % PERIOD.as_nanos(),
+            0
+        );
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let stop = AtomicBool::new(false);
+        let mut runs = 0;
+        Ticker::new(Duration::from_millis(1)).run_until(&stop, |_| {
+            runs += 1;
+            if runs == 3 {
+                stop.store(true, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(runs, 3);
+    }
+
+    #[test]
+    fn runs_until_deadline() {
+        let start = Instant::now();
+        let mut ticker = Ticker::starting_at(start + PERIOD, PERIOD);
+        let mut ticks = Vec::new();
+        let deadline = start + PERIOD * 7 / 2;
+        ticker.run_for(Deadline::at(deadline), |t| ticks.push(t));
+        // The ticks due before the deadline are those at 1 to `due` periods. Late wakeups can skip
+        // the later ones, but the first always runs, none runs at or after the deadline, and the
+        // loop only ends once the last one due has passed.
+        let due = u32::try_from(whole_periods(deadline - start, PERIOD)).unwrap();
+        assert_eq!(due, 3);
+        assert!((1..=due as usize).contains(&ticks.len()), "{ticks:?}");
+        assert_eq!(ticks[0], start + PERIOD);
+        assert!(ticks.windows(2).all(|w| w[0] < w[1]));
+        for &t in &ticks {
+            assert!(t < deadline, "{ticks:?}");
+            assert_eq!((t - start).as_nanos() % PERIOD.as_nanos(), 0);
+        }
+        assert!(Instant::now() >= start + PERIOD * due);
+
+        let mut runs = 0;
+        Ticker::new(PERIOD).run_for(Deadline::after(Duration::ZERO), |_| runs += 1);
+        assert_eq!(runs, 0);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let deadline = Instant::now() + Duration::from_millis(5);
+        portable::sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+        let deadline = Instant::now() + Duration::from_millis(5);
+        sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+        // Past deadlines return at once.
+        portable::sleep_until(deadline - Duration::from_millis(5));
+        sleep_until(deadline - Duration::from_millis(5));
+    }
+}
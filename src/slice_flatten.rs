@@ -0,0 +1,91 @@
+//! `<[[T; N]]>::{as_flattened, as_flattened_mut}` usage
+//!
+//! Tracking issue [95629].
+//!
+//! `slice.as_flattened()` views a `&[[T; N]]` as the `&[T]` of all its elements in order, without
+//! copying, and `as_flattened_mut` does the same for a mutable slice. Arrays have no padding
+//! between elements, so this is only the length multiplied by `N`. It was added as the unstable
+//! `flatten` under the `slice_flatten` feature.
+//!
+//! [`BitMatrix`] keeps each row as a `[u64; W]`, so [`BitMatrix::words`] gives all rows as a
+//! single word slice, and whole-matrix operations such as [`BitMatrix::count`] and
+//! [`BitMatrix::union`] run one [`crate::array_chunks`] kernel over it instead of one per row.
+//!
+//! [95629]: https://github.com/rust-lang/rust/issues/95629
+
+use crate::array_chunks;
+use crate::bit_set::BitMatrix;
+use crate::step::Idx;
+
+impl<R: Idx, C: Idx, const W: usize> BitMatrix<R, C, W> {
+    /// Returns the words of all rows in order, `W` per row. Column `c` of row `r` is bit `c % 64`
+    /// of word `r * W + c / 64`.
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        self.rows.as_flattened()
+    }
+
+    /// Counts the cells set to true.
+    pub fn count(&self) -> usize {
+        array_chunks::count_ones::<4>(self.words()) as usize
+    }
+
+    /// Sets `self = self | other` and returns `true` if `self` changed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the dimensions differ.
+    pub fn union(&mut self, other: &BitMatrix<R, C, W>) -> bool {
+        assert_eq!(
+            (self.num_rows(), self.num_columns()),
+            (other.num_rows(), other.num_columns())
+        );
+        array_chunks::union_with::<4>(self.rows.as_flattened_mut(), other.words())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::*;
+
+    #[test]
+    fn flat_layout() {
+        let mut matrix: BitMatrix<usize, usize, 3> = BitMatrix::new(4, 150);
+        matrix.insert(0, 0);
+        matrix.insert(1, 64);
+        matrix.insert(3, 149);
+        let words = matrix.words();
+        assert_eq!(words.len(), 4 * 3);
+        assert_eq!(mem::size_of_val(words), mem::size_of_val(&matrix.rows[..]));
+        assert_eq!(words.as_ptr(), matrix.rows.as_ptr().cast());
+        for (row, column) in [(0, 0), (1, 64), (3, 149)] {
+            assert_eq!(words[row * 3 + column / 64], 1 << (column % 64));
+        }
+        assert_eq!(words.iter().filter(|&&w| w != 0).count(), 3);
+    }
+
+    #[test]
+    fn whole_matrix_ops() {
+        let mut a: BitMatrix<u32, u32, 2> = BitMatrix::new(5, 128);
+        let mut b = a.clone();
+        assert_eq!(a.count(), 0);
+        for i in 0..5 {
+            a.insert(i, i * 20);
+            b.insert(i, 127 - i);
+        }
+        b.insert(0, 0);
+        assert!(a.union(&b));
+        assert!(!a.union(&b));
+        assert_eq!(a.count(), 10);
+        assert!(a.contains(4, 80) && a.contains(4, 123));
+    }
+
+    #[test]
+    #[should_panic]
+    fn union_dimension_mismatch() {
+        let mut a: BitMatrix<usize, usize, 1> = BitMatrix::new(2, 64);
+        a.union(&BitMatrix::new(3, 64));
+    }
+}
@@ -0,0 +1,119 @@
+//! `<[T]>::{split_once, rsplit_once}` usage
+//!
+//! Tracking issue [112811].
+//!
+//! `slice.split_once(pred)` splits a slice at the first element matching `pred` into the parts
+//! before and after it, with the matching element left out, and `rsplit_once` does the same at the
+//! last match. It is `str::split_once` for slices, and saves the `position` plus two range indexing
+//! steps that are easy to get off by one.
+//!
+//! [`split_record`] and [`split_complete`] use them to read a stream of indices in which each
+//! record ends with a sentinel value. Without the `nightly` feature [`split_once`] and
+//! [`rsplit_once`] are written with `iter().position()` and `rposition()`.
+//!
+//! [112811]: https://github.com/rust-lang/rust/issues/112811
+
+/// Splits `slice` at the first element matching `pred`, leaving that element out.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn split_once<T>(slice: &[T], pred: impl FnMut(&T) -> bool) -> Option<(&[T], &[T])> {
+    slice.split_once(pred)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn split_once<T>(slice: &[T], pred: impl FnMut(&T) -> bool) -> Option<(&[T], &[T])> {
+    portable::split_once(slice, pred)
+}
+
+/// Splits `slice` at the last element matching `pred`, leaving that element out.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn rsplit_once<T>(slice: &[T], pred: impl FnMut(&T) -> bool) -> Option<(&[T], &[T])> {
+    slice.rsplit_once(pred)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn rsplit_once<T>(slice: &[T], pred: impl FnMut(&T) -> bool) -> Option<(&[T], &[T])> {
+    portable::rsplit_once(slice, pred)
+}
+
+/// Consumes the first record of `stream`, up to and including `sentinel`, and returns it without
+/// the sentinel. Returns `None`, and leaves `stream` alone, if it holds no complete record.
+pub fn split_record<'a, T: PartialEq>(stream: &mut &'a [T], sentinel: &T) -> Option<&'a [T]> {
+    let (record, rest) = split_once(stream, |x| x == sentinel)?;
+    *stream = rest;
+    Some(record)
+}
+
+/// Splits `stream` into the complete records, each still ended by `sentinel`, and the unfinished
+/// record after the last one.
+pub fn split_complete<'a, T: PartialEq>(stream: &'a [T], sentinel: &T) -> (&'a [T], &'a [T]) {
+    match rsplit_once(stream, |x| x == sentinel) {
+        Some((_, tail)) => stream.split_at(stream.len() - tail.len()),
+        None => (&[], stream),
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn split_once<T>(slice: &[T], pred: impl FnMut(&T) -> bool) -> Option<(&[T], &[T])> {
+        let i = slice.iter().position(pred)?;
+        Some((&slice[..i], &slice[i + 1..]))
+    }
+
+    pub(super) fn rsplit_once<T>(
+        slice: &[T],
+        pred: impl FnMut(&T) -> bool,
+    ) -> Option<(&[T], &[T])> {
+        let i = slice.iter().rposition(pred)?;
+        Some((&slice[..i], &slice[i + 1..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const END: u32 = u32::MAX;
+
+    #[test]
+    fn records() {
+        let mut stream: &[u32] = &[3, 1, END, END, 7, END, 2];
+        assert_eq!(split_record(&mut stream, &END), Some(&[3, 1][..]));
+        assert_eq!(split_record(&mut stream, &END), Some(&[][..]));
+        assert_eq!(split_record(&mut stream, &END), Some(&[7][..]));
+        assert_eq!(split_record(&mut stream, &END), None);
+        assert_eq!(stream, [2]);
+    }
+
+    #[test]
+    fn complete_prefix() {
+        let buf = [3, 1, END, 7, END, 2, 5];
+        let (complete, partial) = split_complete(&buf, &END);
+        assert_eq!(complete, [3, 1, END, 7, END]);
+        assert_eq!(partial, [2, 5]);
+        assert_eq!(split_complete(&buf[..2], &END), (&[][..], &[3, 1][..]));
+        assert_eq!(split_complete(&buf[..5], &END), (&buf[..5], &[][..]));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let data = [0, 1, 0, 2, 2, 0];
+        for len in 0..=data.len() {
+            let s = &data[..len];
+            for needle in 0..3 {
+                assert_eq!(
+                    s.split_once(|&x| x == needle),
+                    portable::split_once(s, |&x| x == needle)
+                );
+                assert_eq!(
+                    s.rsplit_once(|&x| x == needle),
+                    portable::rsplit_once(s, |&x| x == needle)
+                );
+            }
+        }
+    }
+}
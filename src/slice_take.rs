@@ -0,0 +1,226 @@
+//! `<[T]>::split_off` and `OneSidedRange` usage
+//!
+//! Tracking issues [62280] and [69780].
+//!
+//! `slice.split_off(..n)` shortens a `&mut &[T]` in place and returns the part that was cut off,
+//! or `None` if `n` is out of bounds; `split_off(n..)` cuts from the back instead, and
+//! `split_off_first`/`split_off_last` take a single element. They were added as `take`,
+//! `take_first` and `take_last` under the `slice_take` feature. This is the shape of a
+//! hand-written parser: each step consumes a prefix of the input and leaves the rest for the next
+//! one.
+//!
+//! The range argument is bound by the still unstable `OneSidedRange` trait, which only the
+//! `..n`, `..=n` and `n..` ranges implement. It is re-exported here as [`OneSidedRange`], so both
+//! [`take`] and [`IndexSlice::take`] can name it. Without the `nightly` feature it is a sealed
+//! trait of this crate for the same three ranges, and `take` is done with `split_at_checked`.
+//!
+//! [62280]: https://github.com/rust-lang/rust/issues/62280
+//! [69780]: https://github.com/rust-lang/rust/issues/69780
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+#[cfg(feature = "nightly")]
+pub use std::ops::OneSidedRange;
+
+#[cfg(not(feature = "nightly"))]
+pub use portable::OneSidedRange;
+
+/// Removes the part of `input` selected by `range` and returns it, or returns `None` and leaves
+/// `input` alone if `range` is out of bounds.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn take<'a, T, R: OneSidedRange<usize>>(input: &mut &'a [T], range: R) -> Option<&'a [T]> {
+    input.split_off(range)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn take<'a, T, R: OneSidedRange<usize>>(input: &mut &'a [T], range: R) -> Option<&'a [T]> {
+    portable::split_off(input, range)
+}
+
+/// Consumes `prefix` from the front of `input`. Returns false, and leaves `input` alone, if
+/// `input` doesn't start with it.
+pub fn take_prefix<T: PartialEq>(input: &mut &[T], prefix: &[T]) -> bool {
+    if input.starts_with(prefix) {
+        take(input, ..prefix.len());
+        true
+    } else {
+        false
+    }
+}
+
+/// Consumes the longest prefix of `input` whose elements all match `pred`, and returns it.
+pub fn take_while<'a, T>(input: &mut &'a [T], mut pred: impl FnMut(&T) -> bool) -> &'a [T] {
+    let n = input.iter().position(|x| !pred(x)).unwrap_or(input.len());
+    take(input, ..n).unwrap()
+}
+
+/// Consumes a little-endian `u32` from the front of `input`.
+pub fn take_u32_le(input: &mut &[u8]) -> Option<u32> {
+    let bytes = take(input, ..4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Consumes a record of a little-endian `u32` length followed by that many bytes, and returns the
+/// bytes. If the record is cut short, `input` is left alone.
+pub fn take_len_prefixed<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let mut rest = *input;
+    let len = take_u32_le(&mut rest)?;
+    let body = take(&mut rest, ..len as usize)?;
+    *input = rest;
+    Some(body)
+}
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Removes the elements selected by `range` and returns them, or returns `None` and leaves
+    /// `self` alone if `range` is out of bounds.
+    ///
+    /// Both parts are indexed from zero afterwards.
+    pub fn take<'a, R: OneSidedRange<I>>(self: &mut &'a Self, range: R) -> Option<&'a Self> {
+        let mut raw = &self.raw;
+        let taken = match split_point(&range) {
+            (Side::Front, Some(mid)) => take(&mut raw, ..mid)?,
+            (Side::Back, Some(mid)) => take(&mut raw, mid..)?,
+            (_, None) => return None,
+        };
+        *self = IndexSlice::from_raw(raw);
+        Some(IndexSlice::from_raw(taken))
+    }
+
+    /// Removes the first element and returns it.
+    pub fn take_first<'a>(self: &mut &'a Self) -> Option<&'a T> {
+        let mut raw = &self.raw;
+        let first = raw.split_off_first()?;
+        *self = IndexSlice::from_raw(raw);
+        Some(first)
+    }
+
+    /// Removes the last element and returns it.
+    pub fn take_last<'a>(self: &mut &'a Self) -> Option<&'a T> {
+        let mut raw = &self.raw;
+        let last = raw.split_off_last()?;
+        *self = IndexSlice::from_raw(raw);
+        Some(last)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Front,
+    Back,
+}
+
+/// Returns which end a one-sided range cuts from, and where, or `None` for `..=usize::MAX`.
+fn split_point<I: Idx>(range: &impl RangeBounds<I>) -> (Side, Option<usize>) {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Unbounded, Bound::Excluded(end)) => (Side::Front, Some(end.index())),
+        (Bound::Unbounded, Bound::Included(end)) => (Side::Front, end.index().checked_add(1)),
+        (Bound::Included(start), Bound::Unbounded) => (Side::Back, Some(start.index())),
+        _ => unreachable!("not a one-sided range"),
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::ops::{RangeBounds, RangeFrom, RangeTo, RangeToInclusive};
+
+    use super::{Side, split_point};
+
+    mod sealed {
+        pub trait Sealed {}
+    }
+
+    /// A range that is unbounded on one side, like `..n`, `..=n` or `n..`.
+    pub trait OneSidedRange<T>: RangeBounds<T> + sealed::Sealed {}
+
+    impl<T> sealed::Sealed for RangeTo<T> {}
+    impl<T> OneSidedRange<T> for RangeTo<T> {}
+    impl<T> sealed::Sealed for RangeToInclusive<T> {}
+    impl<T> OneSidedRange<T> for RangeToInclusive<T> {}
+    impl<T> sealed::Sealed for RangeFrom<T> {}
+    impl<T> OneSidedRange<T> for RangeFrom<T> {}
+
+    pub(super) fn split_off<'a, T>(
+        input: &mut &'a [T],
+        range: impl OneSidedRange<usize>,
+    ) -> Option<&'a [T]> {
+        let (side, mid) = split_point(&range);
+        let (front, back) = input.split_at_checked(mid?)?;
+        match side {
+            Side::Front => {
+                *input = back;
+                Some(front)
+            }
+            Side::Back => {
+                *input = front;
+                Some(back)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index_vec::IndexVec;
+
+    use super::*;
+
+    #[test]
+    fn parse_records() {
+        let mut input: &[u8] = b"HDR\x02\0\0\0ab\x05\0\0\0cd";
+        assert!(!take_prefix(&mut input, b"BAD"));
+        assert!(take_prefix(&mut input, b"HDR"));
+        assert_eq!(take_len_prefixed(&mut input), Some(&b"ab"[..]));
+        // The second record is truncated, so nothing is consumed.
+        assert_eq!(take_len_prefixed(&mut input), None);
+        assert_eq!(input, b"\x05\0\0\0cd");
+        assert_eq!(take_u32_le(&mut input), Some(5));
+        assert_eq!(take_while(&mut input, |b| b.is_ascii_lowercase()), b"cd");
+        assert_eq!(take_u32_le(&mut input), None);
+    }
+
+    #[test]
+    fn one_sided_ranges() {
+        let mut input: &[i32] = &[1, 2, 3, 4, 5];
+        assert_eq!(take(&mut input, 3..), Some(&[4, 5][..]));
+        assert_eq!(take(&mut input, ..=0), Some(&[1][..]));
+        assert_eq!(take(&mut input, ..3), None);
+        assert_eq!(take(&mut input, ..=usize::MAX), None);
+        assert_eq!(input, [2, 3]);
+        assert_eq!(take(&mut input, 2..), Some(&[][..]));
+        assert_eq!(take(&mut input, ..2), Some(&[2, 3][..]));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn index_slice_take() {
+        let tokens: IndexVec<u32, char> = "fn(x)".chars().collect();
+        let mut rest = tokens.as_slice();
+        let keyword = rest.take(..2u32).unwrap();
+        assert_eq!(keyword.raw, ['f', 'n']);
+        assert_eq!(rest.take_first(), Some(&'('));
+        assert_eq!(rest.take_last(), Some(&')'));
+        assert_eq!(rest.take(2..), None);
+        // What is left is indexed from zero.
+        assert_eq!(rest[0], 'x');
+        assert_eq!(rest.take(0..).map(|s| s.len()), Some(1));
+        assert_eq!(rest.take_first(), None);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let data = [0u8, 1, 2, 3];
+        for n in 0..6 {
+            let (mut a, mut b) = (&data[..], &data[..]);
+            assert_eq!(a.split_off(..n), portable::split_off(&mut b, ..n));
+            assert_eq!(a.split_off(n..), portable::split_off(&mut b, n..));
+            assert_eq!(a.split_off(..=n), portable::split_off(&mut b, ..=n));
+            assert_eq!(a, b);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! `thread::Builder::spawn_unchecked` usage
+//!
+//! Tracking issue [55132].
+//!
+//! `Builder::spawn_unchecked(f)` is `spawn` without the `'static` bound on `f`: the thread may
+//! borrow from the spawning stack frame, and the caller promises, unsafely, that it is joined
+//! before those borrows end. `thread::scope` keeps the same promise safely by joining every thread
+//! before it returns, and `spawn_unchecked` is what a scope of one's own is built on.
+//!
+//! [`scoped`] is such a scope, with a [`Pool`] that threads are spawned into, and
+//! [`par_for_each`] runs a closure over an `IndexSlice` on a few of them. The soundness argument is
+//! the one `thread::scope` makes:
+//! - The `Pool` is only lent to the closure, so it can't be leaked with its threads unjoined.
+//! - `scoped` joins every thread before returning, and also when the closure panics.
+//! - `'env` is invariant, so the closure can't shorten it to borrow its own locals.
+//!
+//! [55132]: https://github.com/rust-lang/rust/issues/55132
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, JoinHandle};
+
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+/// Threads that may borrow anything that outlives `'env`.
+pub struct Pool<'env> {
+    handles: RefCell<Vec<JoinHandle<()>>>,
+    /// Invariant, like `thread::Scope`.
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> Pool<'env> {
+    /// Runs `f` on a new thread, joined before [`scoped`] returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OS fails to create the thread.
+    pub fn spawn(&self, f: impl FnOnce() + Send + 'env) {
+        // SAFETY: `scoped` joins the thread before `'env` ends, since it owns the pool and `'env`
+        // outlives its call.
+        let handle = unsafe { thread::Builder::new().spawn_unchecked(f) };
+        self.handles
+            .borrow_mut()
+            .push(handle.expect("failed to spawn thread"));
+    }
+
+    /// The number of threads spawned so far.
+    pub fn len(&self) -> usize {
+        self.handles.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Joins every thread, returning the first panic payload.
+    fn join_all(&self) -> Result<(), Box<dyn Any + Send>> {
+        let mut result = Ok(());
+        for handle in self.handles.take() {
+            if let Err(payload) = handle.join() {
+                result = result.and(Err(payload));
+            }
+        }
+        result
+    }
+}
+
+/// Calls `f` with a [`Pool`], and joins the threads spawned into it.
+///
+/// # Panics
+///
+/// If `f` or any of the threads panics, panics with the first payload, after joining them all.
+pub fn scoped<'env, R>(f: impl FnOnce(&Pool<'env>) -> R) -> R {
+    let pool = Pool {
+        handles: RefCell::new(Vec::new()),
+        _env: PhantomData,
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(&pool)));
+    let joined = pool.join_all();
+    match (result, joined) {
+        (Ok(value), Ok(())) => value,
+        (Err(payload), _) | (Ok(_), Err(payload)) => panic::resume_unwind(payload),
+    }
+}
+
+/// Calls `f` for each element of `slice`, splitting it into `threads` contiguous chunks.
+pub fn par_for_each<I: Idx, T: Sync>(
+    slice: &IndexSlice<I, T>,
+    threads: usize,
+    f: impl Fn(I, &T) + Sync,
+) {
+    let chunk_len = slice.len().div_ceil(threads.max(1)).max(1);
+    let f = &f;
+    scoped(|pool| {
+        for (n, chunk) in slice.raw.chunks(chunk_len).enumerate() {
+            pool.spawn(move || {
+                for (i, value) in chunk.iter().enumerate() {
+                    f(I::new(n * chunk_len + i), value);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    #[test]
+    fn matches_thread_scope() {
+        let weights: IndexVec<u32, usize> = (1..=100).collect();
+        let (pooled, scoped_std) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let seen = Mutex::new(Vec::new());
+        par_for_each(&weights, 3, |i, &w| {
+            pooled.fetch_add(i as usize * w, Ordering::Relaxed);
+            seen.lock().unwrap().push(i);
+        });
+        thread::scope(|s| {
+            for (n, chunk) in weights.raw.chunks(34).enumerate() {
+                let scoped_std = &scoped_std;
+                s.spawn(move || {
+                    for (i, &w) in chunk.iter().enumerate() {
+                        scoped_std.fetch_add((n * 34 + i) * w, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(pooled.into_inner(), scoped_std.into_inner());
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, (0..100).collect::<Vec<u32>>());
+        par_for_each(IndexSlice::<u32, u8>::empty(), 4, |_, _| unreachable!());
+    }
+
+    #[test]
+    fn joins_before_returning() {
+        let done = AtomicUsize::new(0);
+        let spawned = scoped(|pool| {
+            for _ in 0..3 {
+                pool.spawn(|| {
+                    thread::sleep(Duration::from_millis(10));
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+            pool.len()
+        });
+        // The threads borrowed `done`, so they must all have finished by now.
+        assert_eq!((spawned, done.load(Ordering::Relaxed)), (3, 3));
+    }
+
+    #[test]
+    fn joins_when_panicking() {
+        let done = AtomicUsize::new(0);
+        let result = panic::catch_unwind(|| {
+            scoped(|pool| {
+                pool.spawn(|| {
+                    thread::sleep(Duration::from_millis(10));
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+                panic!("scope body");
+            })
+        });
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<&str>(),
+            Some(&"scope body")
+        );
+        assert_eq!(done.load(Ordering::Relaxed), 1);
+
+        let result = panic::catch_unwind(|| {
+            scoped(|pool| {
+                pool.spawn(|| panic!("worker"));
+                pool.spawn(|| {
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            })
+        });
+        assert_eq!(result.unwrap_err().downcast_ref::<&str>(), Some(&"worker"));
+        assert_eq!(done.load(Ordering::Relaxed), 2);
+    }
+}
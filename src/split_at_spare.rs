@@ -0,0 +1,201 @@
+//! `Vec::split_at_spare_mut` usage
+//!
+//! Tracking issue [81944].
+//!
+//! `vec.split_at_spare_mut()` returns the initialized elements as `&mut [T]` together with the
+//! spare capacity as `&mut [MaybeUninit<T>]`. The stable `spare_capacity_mut` borrows the whole
+//! vector for the spare part, so the existing elements can't be read while filling it, e.g. to
+//! compute new elements from old ones.
+//!
+//! [`IndexVec::spare_fill`] returns a [`SpareFill`] guard over reserved spare capacity. Elements
+//! are written with [`SpareFill::fill_with`], or through [`SpareFill::split`] from any number of
+//! threads and then marked with [`SpareFill::set_filled`]. The vector's length only grows when
+//! the guard is committed or dropped, and then only by what was filled, also after a panic.
+//! Without the `nightly` feature `split` divides the buffer with raw pointers.
+//!
+//! [81944]: https://github.com/rust-lang/rust/issues/81944
+
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+
+use crate::index_vec::{IndexSlice, IndexVec};
+use crate::step::Idx;
+use crate::uninit;
+
+/// Spare capacity of an `IndexVec` being filled. See [`IndexVec::spare_fill`].
+pub struct SpareFill<'a, I: Idx, T> {
+    vec: &'a mut Vec<T>,
+    /// Elements at the start of the spare capacity known to be initialized.
+    filled: usize,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Reserves room for at least `additional` more elements, and returns a guard to initialize
+    /// them in place.
+    pub fn spare_fill(&mut self, additional: usize) -> SpareFill<'_, I, T> {
+        self.raw.reserve(additional);
+        SpareFill {
+            vec: &mut self.raw,
+            filled: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Idx, T> SpareFill<'_, I, T> {
+    /// The number of elements that can still be filled.
+    pub fn remaining(&self) -> usize {
+        self.vec.capacity() - self.vec.len() - self.filled
+    }
+
+    /// Returns the elements from before the fill, and the spare capacity not yet filled.
+    #[cfg(feature = "nightly")]
+    pub fn split(&mut self) -> (&mut IndexSlice<I, T>, &mut [MaybeUninit<T>]) {
+        let (init, spare) = self.vec.split_at_spare_mut();
+        (IndexSlice::from_raw_mut(init), &mut spare[self.filled..])
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn split(&mut self) -> (&mut IndexSlice<I, T>, &mut [MaybeUninit<T>]) {
+        let (init, spare) = portable::split_at_spare_mut(self.vec);
+        (IndexSlice::from_raw_mut(init), &mut spare[self.filled..])
+    }
+
+    /// Fills the next `n` elements with `f(i)`, and returns their indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if fewer than `n` remain.
+    pub fn fill_with(&mut self, n: usize, mut f: impl FnMut(I) -> T) -> Range<I> {
+        let start = self.vec.len() + self.filled;
+        let (_, spare) = self.split();
+        uninit::fill_with(&mut spare[..n], |i| f(I::new(start + i)));
+        self.filled += n;
+        I::new(start)..I::new(start + n)
+    }
+
+    /// Marks the next `n` elements as filled.
+    ///
+    /// # Safety
+    ///
+    /// The first `n` elements of the spare slice from [`split`](Self::split) must have been
+    /// initialized.
+    pub unsafe fn set_filled(&mut self, n: usize) {
+        assert!(n <= self.remaining());
+        self.filled += n;
+    }
+
+    /// Adds the filled elements to the vector, and returns their indices.
+    pub fn commit(self) -> Range<I> {
+        let start = self.vec.len();
+        // Dropping `self` grows the vector.
+        I::new(start)..I::new(start + self.filled)
+    }
+}
+
+impl<I: Idx, T> Drop for SpareFill<'_, I, T> {
+    fn drop(&mut self) {
+        let len = self.vec.len() + self.filled;
+        // SAFETY: `reserve` made room, and the first `filled` spare elements are initialized.
+        unsafe { self.vec.set_len(len) };
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::MaybeUninit;
+    use std::slice;
+
+    pub(super) fn split_at_spare_mut<T>(vec: &mut Vec<T>) -> (&mut [T], &mut [MaybeUninit<T>]) {
+        let (len, cap) = (vec.len(), vec.capacity());
+        let ptr = vec.as_mut_ptr();
+        // SAFETY: the buffer holds `len` initialized elements followed by `cap - len` spare ones,
+        // and the two slices don't overlap.
+        unsafe {
+            (
+                slice::from_raw_parts_mut(ptr, len),
+                slice::from_raw_parts_mut(ptr.add(len).cast(), cap - len),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn fill_from_existing() {
+        let mut v: IndexVec<u32, u64> = IndexVec::from([1, 1]);
+        let mut fill = v.spare_fill(4);
+        assert!(fill.remaining() >= 4);
+        // Each new element is the sum of the first two old ones, times its index.
+        let (old, spare) = fill.split();
+        let sum = old[0] + old[1];
+        uninit::fill_with(&mut spare[..2], |i| sum * (i as u64 + 2));
+        // SAFETY: two elements were just written.
+        unsafe { fill.set_filled(2) };
+        assert_eq!(fill.fill_with(2, |i| i.into()), 4..6);
+        assert_eq!(fill.commit(), 2..6);
+        assert_eq!(v.raw, [1, 1, 4, 6, 4, 5]);
+    }
+
+    #[test]
+    fn parallel_producers() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 1000;
+        let mut v: IndexVec<usize, usize> = IndexVec::new();
+        let mut fill = v.spare_fill(THREADS * PER_THREAD);
+        let (_, spare) = fill.split();
+        thread::scope(|s| {
+            for (t, chunk) in spare[..THREADS * PER_THREAD]
+                .chunks_mut(PER_THREAD)
+                .enumerate()
+            {
+                s.spawn(move || {
+                    uninit::fill_with(chunk, |i| t * PER_THREAD + i);
+                });
+            }
+        });
+        // SAFETY: each thread initialized its whole chunk.
+        unsafe { fill.set_filled(THREADS * PER_THREAD) };
+        drop(fill);
+        assert!(v.iter_enumerated().all(|(i, &x)| i == x));
+        assert_eq!(v.len(), THREADS * PER_THREAD);
+    }
+
+    #[test]
+    fn panic_keeps_filled() {
+        let mut v: IndexVec<u32, String> = IndexVec::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut fill = v.spare_fill(6);
+            fill.fill_with(2, |i| i.to_string());
+            fill.fill_with(3, |i| {
+                assert!(i != 3, "producer panicked");
+                i.to_string()
+            });
+        }));
+        assert!(result.is_err());
+        // Only the first, completed `fill_with` counts.
+        assert_eq!(v.raw, ["0", "1"]);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut v = Vec::with_capacity(5);
+        v.extend([1, 2]);
+        let (a, b) = v.split_at_spare_mut();
+        let lens = (a.len(), b.len(), a.as_ptr(), b.as_ptr().cast::<i32>());
+        let (a, b) = portable::split_at_spare_mut(&mut v);
+        assert_eq!(
+            lens,
+            (a.len(), b.len(), a.as_ptr(), b.as_ptr().cast::<i32>())
+        );
+    }
+}
@@ -7,19 +7,35 @@
 //! `CustomIndex` internally is implemented with `u32`. This emulates the result of [`rustc_index_macros::newtype_index`],
 //! which is used for [`rustc_index::IndexVec`].
 //!
+//! The 256 values above `MAX` are a niche, so `Option<CustomIndex>` is as small as a `u32`. With
+//! the `nightly` feature rustc's layout attribute declares them invalid, like `newtype_index`
+//! does. Without it the index is stored plus one in a `NonZero<u32>` instead, which leaves only
+//! zero as the niche but is enough for `Option`. The same plus-one form is what other crates store
+//! when they use the `NonZero` trick, and [`CustomIndex::to_nonzero_plus_one`] and
+//! [`CustomIndex::from_nonzero_plus_one`] convert to and from it.
+//!
 //! [42168]: https://github.com/rust-lang/rust/issues/42168
 //! [`rustc_index_macros::newtype_index`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index_macros/src/lib.rs#L38
 //! [`rustc_index::IndexVec`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_index/src/vec.rs#L40
 
 #![allow(dead_code)]
 
-use std::{fmt, hash, iter::Step};
+#[cfg(feature = "nightly")]
+use core::iter::Step;
+use core::num::NonZero;
+use core::{fmt, hash};
+
+use crate::const_eval_select::check_index;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "nightly", rustc_layout_scalar_valid_range_end(0xFFFF_FF00))]
 #[cfg_attr(feature = "nightly", rustc_pass_by_value)]
-struct CustomIndex {
+pub struct CustomIndex {
+    #[cfg(feature = "nightly")]
     private_use_as_methods_instead: u32,
+    /// The index plus one.
+    #[cfg(not(feature = "nightly"))]
+    private_use_as_methods_instead: NonZero<u32>,
 }
 
 // shave off 256 indices at the end to allow space for packing these indices into enums
@@ -43,7 +59,7 @@ impl CustomIndex {
     /// Will panic if `value` exceeds `MAX`.
     #[inline]
     pub const fn from_usize(value: usize) -> Self {
-        assert!(value <= (MAX as usize));
+        check_index(value, MAX as usize);
         // SAFETY: We just checked that `value <= max`.
         unsafe { Self::from_u32_unchecked(value as u32) }
     }
@@ -55,7 +71,7 @@ impl CustomIndex {
     /// Will panic if `value` exceeds `MAX`.
     #[inline]
     pub const fn from_u32(value: u32) -> Self {
-        assert!(value <= MAX);
+        check_index(value as usize, MAX as usize);
         // SAFETY: We just checked that `value <= max`.
         unsafe { Self::from_u32_unchecked(value) }
     }
@@ -68,7 +84,7 @@ impl CustomIndex {
     #[inline]
     pub const fn from_u16(value: u16) -> Self {
         let value = value as u32;
-        assert!(value <= MAX);
+        check_index(value as usize, MAX as usize);
         // SAFETY: We just checked that `value <= max`.
         unsafe { Self::from_u32_unchecked(value) }
     }
@@ -82,12 +98,40 @@ impl CustomIndex {
     ///
     /// Prefer using `from_u32`.
     #[inline]
+    #[cfg(feature = "nightly")]
     pub const unsafe fn from_u32_unchecked(value: u32) -> Self {
         Self {
             private_use_as_methods_instead: value,
         }
     }
 
+    /// Creates a new index from a given `u32`.
+    ///
+    /// # Safety
+    ///
+    /// The provided value must be less than or equal to the maximum value for the newtype.
+    ///
+    /// Prefer using `from_u32`.
+    #[inline]
+    #[cfg(not(feature = "nightly"))]
+    pub const unsafe fn from_u32_unchecked(value: u32) -> Self {
+        Self {
+            // SAFETY: the caller guarantees `value <= MAX`, so `value + 1` doesn't wrap to zero.
+            private_use_as_methods_instead: unsafe { NonZero::new_unchecked(value + 1) },
+        }
+    }
+
+    /// Creates an index from its value plus one, as stored by crates that use the `NonZero`
+    /// trick.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `value - 1` exceeds `MAX`.
+    #[inline]
+    pub const fn from_nonzero_plus_one(value: NonZero<u32>) -> Self {
+        Self::from_u32(value.get() - 1)
+    }
+
     /// Extracts the value of this index as a `usize`.
     #[inline]
     pub const fn index(self) -> usize {
@@ -96,10 +140,47 @@ impl CustomIndex {
 
     /// Extracts the value of this index as a `u32`.
     #[inline]
+    #[cfg(feature = "nightly")]
     pub const fn as_u32(self) -> u32 {
         self.private_use_as_methods_instead
     }
 
+    /// Extracts the value of this index as a `u32`.
+    #[inline]
+    #[cfg(not(feature = "nightly"))]
+    pub const fn as_u32(self) -> u32 {
+        self.private_use_as_methods_instead.get() - 1
+    }
+
+    /// Extracts the value of this index plus one, which is never zero.
+    #[inline]
+    pub const fn to_nonzero_plus_one(self) -> NonZero<u32> {
+        // SAFETY: `as_u32` is at most `MAX`, so adding one neither wraps nor gives zero.
+        unsafe { NonZero::new_unchecked(self.as_u32() + 1) }
+    }
+
+    /// The plain `u32` form of an optional index: zero for `None`, the index plus one otherwise.
+    #[inline]
+    pub const fn option_to_u32(index: Option<Self>) -> u32 {
+        match index {
+            Some(index) => index.to_nonzero_plus_one().get(),
+            None => 0,
+        }
+    }
+
+    /// Reads an optional index back from the form [`CustomIndex::option_to_u32`] makes.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `value - 1` exceeds `MAX`.
+    #[inline]
+    pub const fn option_from_u32(value: u32) -> Option<Self> {
+        match NonZero::new(value) {
+            Some(value) => Some(Self::from_nonzero_plus_one(value)),
+            None => None,
+        }
+    }
+
     /// Extracts the value of this index as a `usize`.
     #[inline]
     pub const fn as_usize(self) -> usize {
@@ -107,7 +188,16 @@ impl CustomIndex {
     }
 }
 
-impl std::ops::Add<usize> for CustomIndex {
+impl fmt::Debug for CustomIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The same with either representation.
+        f.debug_struct("CustomIndex")
+            .field("private_use_as_methods_instead", &self.as_u32())
+            .finish()
+    }
+}
+
+impl core::ops::Add<usize> for CustomIndex {
     type Output = Self;
 
     #[inline]
@@ -117,6 +207,8 @@ impl std::ops::Add<usize> for CustomIndex {
 }
 
 impl Idx for CustomIndex {
+    const MAX_INDEX: usize = MAX as usize;
+
     #[inline]
     fn new(idx: usize) -> Self {
         Self::from_usize(idx)
@@ -144,6 +236,24 @@ impl Step for CustomIndex {
     fn backward_checked(start: Self, u: usize) -> Option<Self> {
         Self::index(start).checked_sub(u).map(Self::from_usize)
     }
+
+    /// Used by `Range<CustomIndex>` iteration, where the range bounds already guarantee that
+    /// every step stays within `[ZERO, MAX]`.
+    #[inline]
+    unsafe fn forward_unchecked(start: Self, u: usize) -> Self {
+        debug_assert!(start.index() + u <= MAX as usize);
+        // SAFETY: The caller guarantees `start + u` does not overflow `CustomIndex`, so `u <= MAX`
+        // fits in a `u32` and the sum can neither overflow `u32` nor exceed `MAX`.
+        unsafe { Self::from_u32_unchecked(start.as_u32().unchecked_add(u as u32)) }
+    }
+
+    #[inline]
+    unsafe fn backward_unchecked(start: Self, u: usize) -> Self {
+        debug_assert!(u <= start.index());
+        // SAFETY: The caller guarantees `start - u` does not underflow `CustomIndex`, so `u <= start`
+        // fits in a `u32` and the difference stays within `[ZERO, start]`.
+        unsafe { Self::from_u32_unchecked(start.as_u32().unchecked_sub(u as u32)) }
+    }
 }
 
 impl From<CustomIndex> for u32 {
@@ -180,6 +290,9 @@ impl From<u32> for CustomIndex {
 ///
 /// Purpose: avoid mixing indexes for different bitvector domains.
 pub trait Idx: Copy + 'static + Eq + PartialEq + fmt::Debug + hash::Hash {
+    /// The largest index `new` accepts.
+    const MAX_INDEX: usize = usize::MAX;
+
     fn new(idx: usize) -> Self;
 
     fn index(self) -> usize;
@@ -196,16 +309,45 @@ pub trait Idx: Copy + 'static + Eq + PartialEq + fmt::Debug + hash::Hash {
     }
 }
 
+impl Idx for usize {
+    #[inline]
+    fn new(idx: usize) -> Self {
+        idx
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self
+    }
+}
+
+impl Idx for u32 {
+    const MAX_INDEX: usize = u32::MAX as usize;
+
+    #[inline]
+    fn new(idx: usize) -> Self {
+        check_index(idx, u32::MAX as usize);
+        idx as u32
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "nightly")]
     #[test]
     fn forward() {
         let initial = CustomIndex::new(0);
         assert_eq!(CustomIndex::forward(initial, 1), 1u32.into());
     }
 
+    #[cfg(feature = "nightly")]
     #[test]
     #[should_panic]
     fn forward_overflow() {
@@ -213,16 +355,120 @@ mod tests {
         CustomIndex::forward(initial, usize::MAX);
     }
 
+    #[cfg(feature = "nightly")]
     #[test]
     fn backward() {
         let initial = CustomIndex::new(100);
         assert_eq!(CustomIndex::backward(initial, 1), 99u32.into());
     }
 
+    #[cfg(feature = "nightly")]
     #[test]
     #[should_panic]
     fn backward_overflow() {
         let initial = CustomIndex::new(1);
         CustomIndex::backward(initial, 2);
     }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn forward_unchecked() {
+        let initial = CustomIndex::new(0);
+        // SAFETY: `0 + 1` is within `CustomIndex::MAX`.
+        let next = unsafe { CustomIndex::forward_unchecked(initial, 1) };
+        assert_eq!(next, 1u32.into());
+        // SAFETY: `MAX - 0` is exactly `CustomIndex::MAX`.
+        let max = unsafe { CustomIndex::forward_unchecked(CustomIndex::ZERO, MAX as usize) };
+        assert_eq!(max, CustomIndex::MAX);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn backward_unchecked() {
+        let initial = CustomIndex::new(100);
+        // SAFETY: `100 - 1` does not underflow.
+        let prev = unsafe { CustomIndex::backward_unchecked(initial, 1) };
+        assert_eq!(prev, 99u32.into());
+    }
+
+    #[test]
+    fn nonzero_plus_one() {
+        let max = CustomIndex::MAX.to_nonzero_plus_one();
+        assert_eq!(max.get(), MAX + 1);
+        assert_eq!(CustomIndex::from_nonzero_plus_one(max), CustomIndex::MAX);
+        let five = CustomIndex::new(5);
+        assert_eq!(five.to_nonzero_plus_one(), NonZero::new(6).unwrap());
+        assert_eq!(CustomIndex::option_to_u32(Some(five)), 6);
+        assert_eq!(CustomIndex::option_to_u32(None), 0);
+        assert_eq!(CustomIndex::option_from_u32(6), Some(five));
+        assert_eq!(CustomIndex::option_from_u32(0), None);
+        assert_eq!(
+            format!("{five:?}"),
+            "CustomIndex { private_use_as_methods_instead: 5 }"
+        );
+    }
+
+    /// Both representations leave a niche for `Option`.
+    #[test]
+    fn option_fits_in_u32() {
+        assert_eq!(size_of::<CustomIndex>(), size_of::<u32>());
+        assert_eq!(size_of::<Option<CustomIndex>>(), size_of::<u32>());
+        assert_eq!(
+            CustomIndex::option_from_u32(CustomIndex::option_to_u32(Some(CustomIndex::MAX))),
+            Some(CustomIndex::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn nonzero_plus_one_overflow() {
+        let _ = CustomIndex::from_nonzero_plus_one(NonZero::new(MAX + 2).unwrap());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn range_iteration() {
+        let mut range = CustomIndex::new(10)..CustomIndex::new(15);
+        let collected: Vec<u32> = range.clone().map(CustomIndex::as_u32).collect();
+        assert_eq!(collected, [10, 11, 12, 13, 14]);
+        assert_eq!(range.next_back(), Some(CustomIndex::new(14)));
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::{Bencher, black_box};
+
+    const LEN: usize = 100_000;
+
+    /// Steps through the range with the checked `forward`, as a `Step` impl without
+    /// `forward_unchecked` would.
+    #[bench]
+    fn forward_checked_loop(b: &mut Bencher) {
+        b.iter(|| {
+            let mut idx = CustomIndex::ZERO;
+            let mut sum = 0u64;
+            for _ in 0..black_box(LEN) {
+                sum += black_box(idx).as_u32() as u64;
+                idx = CustomIndex::forward(idx, 1);
+            }
+            sum
+        });
+    }
+
+    /// Steps through the range by `Range<CustomIndex>` iteration, which uses `forward_unchecked`.
+    #[bench]
+    fn forward_unchecked_loop(b: &mut Bencher) {
+        b.iter(|| {
+            let end = CustomIndex::from_usize(black_box(LEN));
+            let mut sum = 0u64;
+            for idx in CustomIndex::ZERO..end {
+                sum += black_box(idx).as_u32() as u64;
+            }
+            sum
+        });
+    }
 }
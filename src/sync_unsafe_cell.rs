@@ -0,0 +1,257 @@
+//! `cell::SyncUnsafeCell` usage
+//!
+//! Tracking issue [95439].
+//!
+//! `SyncUnsafeCell<T>` is an `UnsafeCell<T>` that is `Sync` when `T` is. Shared storage written
+//! through raw pointers, where the synchronization lives outside the cell, otherwise needs a
+//! `static mut`, whose references are easy to misuse and now warned about, or a wrapper around
+//! `UnsafeCell` with a hand-written `unsafe impl Sync` that can claim more than the code
+//! guarantees. With `SyncUnsafeCell` the container's `Send` and `Sync` follow from its fields.
+//!
+//! [`AppendOnlyVec`] is a fixed-capacity vector that threads push to through `&self`. Each push
+//! claims a slot with one `fetch_add`, writes it through the slot's cell and then publishes it, so
+//! readers never wait for writers. Without the `nightly` feature the cell is a local newtype with
+//! the same definition as std's.
+//!
+//! [95439]: https://github.com/rust-lang/rust/issues/95439
+
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::{fmt, iter};
+
+#[cfg(feature = "nightly")]
+use std::cell::SyncUnsafeCell;
+
+#[cfg(not(feature = "nightly"))]
+use portable::SyncUnsafeCell;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// A vector of at most `capacity` elements, appended to through shared references.
+///
+/// Invariants:
+/// - `claimed` counts the slots handed out to pushes, and may run past the capacity.
+/// - Only the push that claimed slot `i` writes `slots[i]`, and it does so before setting
+///   `ready[i]` with `Release`.
+/// - `slots[i]` is read only after `ready[i]` is seen set with `Acquire`, and never written again.
+pub struct AppendOnlyVec<I: Idx, T> {
+    slots: Box<[SyncUnsafeCell<MaybeUninit<T>>]>,
+    ready: Box<[AtomicBool]>,
+    claimed: AtomicUsize,
+    /// Pushed values are dropped by whichever thread owns the vector, so sharing it must also
+    /// require `T: Send`, which `Mutex<T>: Sync` does.
+    _send: PhantomData<Mutex<T>>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx, T> AppendOnlyVec<I, T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        AppendOnlyVec {
+            slots: iter::repeat_with(|| SyncUnsafeCell::new(MaybeUninit::uninit()))
+                .take(capacity)
+                .collect(),
+            ready: iter::repeat_with(|| AtomicBool::new(false))
+                .take(capacity)
+                .collect(),
+            claimed: AtomicUsize::new(0),
+            _send: PhantomData,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Appends `value` and returns its index, or returns it back if the vector is full.
+    pub fn push(&self, value: T) -> Result<I, T> {
+        let i = self.claimed.fetch_add(1, Ordering::Relaxed);
+        if i >= self.capacity() {
+            // Keep `claimed` from wrapping around under endless failed pushes.
+            self.claimed.store(self.capacity(), Ordering::Relaxed);
+            return Err(value);
+        }
+        // SAFETY: slot `i` was claimed by this push alone, and isn't read until `ready[i]` is set.
+        unsafe { (*self.slots[i].get()).write(value) };
+        self.ready[i].store(true, Ordering::Release);
+        Ok(I::new(i))
+    }
+
+    /// Returns the element at `index`, if its push has completed.
+    pub fn get(&self, index: I) -> Option<&T> {
+        let i = index.index();
+        if !self.ready.get(i)?.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: the slot was written before `ready[i]` was set, and isn't written again.
+        Some(unsafe { (*self.slots[i].get()).assume_init_ref() })
+    }
+
+    /// The number of pushes that claimed a slot, including ones still writing it.
+    pub fn len(&self) -> usize {
+        self.claimed.load(Ordering::Relaxed).min(self.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves the elements into an `IndexVec`.
+    pub fn into_index_vec(mut self) -> IndexVec<I, T> {
+        let len = self.len();
+        // With `self` owned no push is running, so every claimed slot is ready.
+        let raw = (0..len)
+            .map(|i| {
+                *self.ready[i].get_mut() = false;
+                // SAFETY: the slot is ready, and clearing `ready[i]` keeps `Drop` from dropping
+                // it again.
+                unsafe { self.slots[i].get_mut().assume_init_read() }
+            })
+            .collect();
+        IndexVec::from_raw(raw)
+    }
+}
+
+impl<I: Idx, T> Drop for AppendOnlyVec<I, T> {
+    fn drop(&mut self) {
+        for (slot, ready) in self.slots.iter_mut().zip(&mut self.ready) {
+            if *ready.get_mut() {
+                // SAFETY: the slot is ready, and this is its only drop.
+                unsafe { slot.get_mut().assume_init_drop() };
+            }
+        }
+    }
+}
+
+impl<I: Idx, T: fmt::Debug> fmt::Debug for AppendOnlyVec<I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ready = (0..self.len()).filter_map(|i| self.get(I::new(i)));
+        f.debug_list().entries(ready).finish()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::cell::UnsafeCell;
+
+    /// std's `SyncUnsafeCell`.
+    #[repr(transparent)]
+    pub(crate) struct SyncUnsafeCell<T: ?Sized> {
+        value: UnsafeCell<T>,
+    }
+
+    // SAFETY: like `UnsafeCell`, the cell itself gives no access without `unsafe`, whose users
+    // must synchronize.
+    unsafe impl<T: ?Sized + Sync> Sync for SyncUnsafeCell<T> {}
+
+    impl<T> SyncUnsafeCell<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            SyncUnsafeCell {
+                value: UnsafeCell::new(value),
+            }
+        }
+    }
+
+    impl<T: ?Sized> SyncUnsafeCell<T> {
+        pub(crate) const fn get(&self) -> *mut T {
+            self.value.get()
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            self.value.get_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_pushes() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 50;
+        let v: AppendOnlyVec<u32, (usize, usize)> =
+            AppendOnlyVec::with_capacity(THREADS * PER_THREAD);
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let v = &v;
+                s.spawn(move || {
+                    for n in 0..PER_THREAD {
+                        let index = v.push((t, n)).unwrap();
+                        assert_eq!(v.get(index), Some(&(t, n)));
+                    }
+                });
+            }
+            // A reader running alongside only ever sees completed pushes.
+            s.spawn(|| {
+                for i in 0..THREADS * PER_THREAD {
+                    if let Some(&(t, n)) = v.get(i as u32) {
+                        assert!(t < THREADS && n < PER_THREAD);
+                    }
+                }
+            });
+        });
+        assert_eq!(v.len(), THREADS * PER_THREAD);
+        assert_eq!(v.push((0, 0)), Err((0, 0)));
+
+        let mut all = v.into_index_vec().raw;
+        all.sort();
+        let expected: Vec<_> = (0..THREADS)
+            .flat_map(|t| (0..PER_THREAD).map(move |n| (t, n)))
+            .collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn drops_pushed() {
+        struct Tracked(Rc<Cell<usize>>);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let v: AppendOnlyVec<usize, Tracked> = AppendOnlyVec::with_capacity(4);
+        for _ in 0..3 {
+            assert!(v.push(Tracked(drops.clone())).is_ok());
+        }
+        assert!(v.get(3).is_none() && v.get(9).is_none());
+        drop(v);
+        assert_eq!(drops.get(), 3);
+
+        let v: AppendOnlyVec<usize, Tracked> = AppendOnlyVec::with_capacity(2);
+        assert!(v.push(Tracked(drops.clone())).is_ok());
+        let moved = v.into_index_vec();
+        assert_eq!(drops.get(), 3);
+        drop(moved);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let (mut std, mut portable) = (SyncUnsafeCell::new(1), portable::SyncUnsafeCell::new(1));
+        // SAFETY: nothing else accesses the cells.
+        unsafe {
+            *std.get() += 1;
+            *portable.get() += 1;
+        }
+        *std.get_mut() *= 3;
+        *portable.get_mut() *= 3;
+        assert_eq!(*std.get_mut(), *portable.get_mut());
+        assert_eq!(
+            std::mem::size_of_val(&std),
+            std::mem::size_of_val(&portable)
+        );
+    }
+}
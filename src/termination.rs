@@ -0,0 +1,238 @@
+//! `Termination` and `ExitCode::exit_process` usage
+//!
+//! Tracking issues [43301] and [97100].
+//!
+//! `main` may return any type implementing `Termination`, whose `report()` turns it into the
+//! `ExitCode` the process exits with, and `ExitCode::from(u8)` makes one out of a plain number.
+//! A `main` returning `Result<(), E>` always exits with 1, whatever went wrong.
+//! `ExitCode::exit_process()`, still unstable, exits with a code from anywhere rather than only by
+//! returning from `main`.
+//!
+//! [`Outcome`] is a `main` result that sorts failures into [`FailureKind`]s and exits with a
+//! distinct code for each, so that a script running the binary can tell bad input from an
+//! overflow or a bug. The crate's errors convert into a [`Failure`] of the right kind, so `?`
+//! works on them in a function returning `Result<(), Failure>`. [`Outcome::exit`] reports and
+//! exits right away; without the `nightly` feature it calls `process::exit` with the same code.
+//!
+//! [43301]: https://github.com/rust-lang/rust/issues/43301
+//! [97100]: https://github.com/rust-lang/rust/issues/97100
+
+use core::num::TryFromIntError;
+use core::{error, fmt};
+use std::process::{ExitCode, Termination};
+
+use crate::btree_cursors::UnorderedKey;
+use crate::error::{DomainMismatch, IndexOutOfRange};
+use crate::first_chunk::Truncated;
+use crate::iter_array_chunks::TruncatedPair;
+use crate::map_many_mut::DisjointMutError;
+use crate::once_cell_try::DuplicateSymbol;
+
+/// What went wrong, as far as the exit code goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    /// The input was malformed or inconsistent.
+    BadInput,
+    /// A value did not fit the type it had to go into.
+    Overflow,
+    /// The program broke one of its own invariants.
+    Internal,
+}
+
+impl FailureKind {
+    pub const ALL: [FailureKind; 3] = [
+        FailureKind::BadInput,
+        FailureKind::Overflow,
+        FailureKind::Internal,
+    ];
+
+    /// The code the process exits with. Internal errors use `EX_SOFTWARE` from `sysexits.h`.
+    pub const fn code(self) -> u8 {
+        match self {
+            FailureKind::BadInput => 2,
+            FailureKind::Overflow => 3,
+            FailureKind::Internal => 70,
+        }
+    }
+}
+
+/// An error that ends the program, with the kind that picks its exit code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(kind: FailureKind, error: impl fmt::Display) -> Self {
+        Failure {
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl error::Error for Failure {}
+
+macro_rules! failure_from {
+    ($kind:ident: $($error:ty),*) => {
+        $(
+            impl From<$error> for Failure {
+                fn from(e: $error) -> Self {
+                    Failure::new(FailureKind::$kind, e)
+                }
+            }
+        )*
+    };
+}
+
+failure_from!(BadInput: Truncated, TruncatedPair, UnorderedKey, DuplicateSymbol);
+failure_from!(Overflow: TryFromIntError);
+failure_from!(Internal: IndexOutOfRange, DomainMismatch, DisjointMutError);
+
+/// The result of a `main` that exits with the code of its [`FailureKind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome(pub Result<(), Failure>);
+
+impl Outcome {
+    /// Prints the failure, if any, and exits with its code.
+    #[cfg(feature = "nightly")]
+    pub fn exit(self) -> ! {
+        self.report().exit_process()
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    pub fn exit(self) -> ! {
+        portable::exit(self)
+    }
+
+    fn code(&self) -> u8 {
+        match &self.0 {
+            Ok(()) => 0,
+            Err(failure) => failure.kind.code(),
+        }
+    }
+}
+
+impl<E: Into<Failure>> From<Result<(), E>> for Outcome {
+    fn from(result: Result<(), E>) -> Self {
+        Outcome(result.map_err(Into::into))
+    }
+}
+
+/// Prints an error to stderr as `error: <message>`, like the `Result` implementation does.
+impl Termination for Outcome {
+    fn report(self) -> ExitCode {
+        if let Err(failure) = &self.0 {
+            eprintln!("error: {failure}");
+        }
+        ExitCode::from(self.code())
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::process::{self, Termination};
+
+    use super::Outcome;
+
+    pub(super) fn exit(outcome: Outcome) -> ! {
+        let code = outcome.code();
+        outcome.report();
+        process::exit(i32::from(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Output};
+    use std::{env, str};
+
+    use super::*;
+    use crate::first_chunk::read_array;
+
+    /// Set in a child test process to the exit code it should fail with.
+    const CHILD: &str = "RUST_UNSTABLE_TERMINATION_CHILD";
+    /// Set in a child test process to exit through `portable::exit`.
+    const PORTABLE: &str = "RUST_UNSTABLE_TERMINATION_PORTABLE";
+
+    fn parse(input: &[u8]) -> Result<(), Failure> {
+        let mut input = input;
+        let len = u64::from_le_bytes(*read_array(&mut input)?);
+        let _: u16 = len.try_into()?;
+        Ok(())
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(Outcome::from(parse(&[0; 8])), Outcome(Ok(())));
+        let short = parse(&[0; 3]).unwrap_err();
+        assert_eq!(short.kind, FailureKind::BadInput);
+        assert_eq!(short.message, "stream truncated: need 8 bytes, 3 left");
+        let big = parse(&u64::MAX.to_le_bytes()).unwrap_err();
+        assert_eq!(big.kind, FailureKind::Overflow);
+        let bug = Failure::from(IndexOutOfRange::new::<u32>(5, 2));
+        assert_eq!(bug.kind, FailureKind::Internal);
+
+        assert_eq!(Outcome(Ok(())).report(), ExitCode::SUCCESS);
+        assert_eq!(Outcome(Err(bug)).report(), ExitCode::from(70));
+        let codes = FailureKind::ALL.map(FailureKind::code);
+        assert!(codes.iter().all(|&c| c != 0 && c != 1 && c != 101));
+    }
+
+    /// Does nothing unless run by `run_child`.
+    #[test]
+    fn child() {
+        let Some(code) = env::var_os(CHILD) else {
+            return;
+        };
+        let code: u8 = code.to_str().unwrap().parse().unwrap();
+        let kind = *FailureKind::ALL.iter().find(|k| k.code() == code).unwrap();
+        let outcome = Outcome(Err(Failure::new(kind, format_args!("failed with {code}"))));
+        if env::var_os(PORTABLE).is_some() {
+            portable::exit(outcome);
+        }
+        outcome.exit();
+    }
+
+    fn run_child(kind: FailureKind, portable: bool) -> Output {
+        let mut command = Command::new(env::current_exe().unwrap());
+        command
+            .args(["--exact", "termination::tests::child", "--nocapture"])
+            .env(CHILD, kind.code().to_string());
+        if portable {
+            command.env(PORTABLE, "1");
+        }
+        command.output().unwrap()
+    }
+
+    #[test]
+    fn exit_codes() {
+        for kind in FailureKind::ALL {
+            let output = run_child(kind, false);
+            assert_eq!(output.status.code(), Some(i32::from(kind.code())));
+            let stderr = str::from_utf8(&output.stderr).unwrap();
+            assert!(
+                stderr.contains(&format!("error: failed with {}\n", kind.code())),
+                "{stderr}"
+            );
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for kind in FailureKind::ALL {
+            let from_std = run_child(kind, false);
+            let portable = run_child(kind, true);
+            assert_eq!(from_std.status, portable.status);
+            assert_eq!(from_std.stderr, portable.stderr);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! `#![test_runner]` usage
+//!
+//! Tracking issue [50297].
+//!
+//! With `custom_test_frameworks`, `#![test_runner(path)]` on a test crate replaces libtest's
+//! `main`: the compiler collects every item marked `#[test_case]`, and the generated `main` passes
+//! references to all of them to the function at `path`, which decides how they are picked, run
+//! and reported. A test case can be any item of the type the runner takes, not only a function,
+//! so a runner could also take tables of cases or benchmarks.
+//!
+//! [`runner`] takes functions as [`Testable`]s and collects them into an
+//! `IndexVec<TestIdx, TestFn>`, in the order the compiler found them, so that a [`Report`] has an
+//! [`Outcome`] per case. It understands test name filters, `--skip`, `--exact`, `--list` and
+//! `--quiet`, runs the selected cases one after the other, catching their panics, and lists the
+//! failures with their messages above the summary. The `custom_harness` test target runs its
+//! cases with it. The runner itself is plain code and builds without the `nightly` feature; only
+//! the crate with the attribute needs it, so that target requires the feature.
+//!
+//! [50297]: https://github.com/rust-lang/rust/issues/50297
+
+use std::any::{self, Any};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use std::{env, fmt, process};
+
+use crate::index_vec::{IndexSlice, IndexVec};
+use crate::step::Idx;
+
+/// The index of a test case, in the order the compiler collected them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TestIdx(u32);
+
+impl Idx for TestIdx {
+    const MAX_INDEX: usize = u32::MAX as usize;
+
+    #[inline]
+    fn new(idx: usize) -> Self {
+        assert!(idx <= u32::MAX as usize);
+        TestIdx(idx as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A test case the [`runner`] can run.
+pub trait Testable {
+    /// The path of the case within its crate.
+    fn name(&self) -> &'static str;
+
+    fn run(&self);
+}
+
+/// A function is named by its path, without the crate's name.
+impl<F: Fn()> Testable for F {
+    fn name(&self) -> &'static str {
+        let path = any::type_name::<F>();
+        path.split_once("::").map_or(path, |(_, rest)| rest)
+    }
+
+    fn run(&self) {
+        self()
+    }
+}
+
+/// A collected test case.
+#[derive(Clone, Copy)]
+pub struct TestFn<'a> {
+    pub name: &'static str,
+    test: &'a dyn Testable,
+}
+
+impl<'a> TestFn<'a> {
+    pub fn new(test: &'a dyn Testable) -> Self {
+        TestFn {
+            name: test.name(),
+            test,
+        }
+    }
+
+    /// Runs the case, and returns how long it took or the message it panicked with.
+    pub fn run(self) -> Outcome {
+        let start = Instant::now();
+        match panic::catch_unwind(AssertUnwindSafe(|| self.test.run())) {
+            Ok(()) => Outcome::Passed(start.elapsed()),
+            Err(payload) => Outcome::Failed(panic_message(&*payload)),
+        }
+    }
+}
+
+impl fmt::Debug for TestFn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TestFn").field(&self.name).finish()
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Collects the cases `#![test_runner]` passes in.
+pub fn collect<'a>(tests: &[&'a dyn Testable]) -> IndexVec<TestIdx, TestFn<'a>> {
+    tests.iter().map(|&test| TestFn::new(test)).collect()
+}
+
+/// Which cases to run and how to report them, from the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Run only the cases whose names contain one of these, or all if there are none.
+    pub filters: Vec<String>,
+    /// Don't run the cases whose names contain any of these.
+    pub skip: Vec<String>,
+    /// Match filters and skips against whole names.
+    pub exact: bool,
+    /// Print the names of the selected cases instead of running them.
+    pub list: bool,
+    /// Print only the failures and the summary.
+    pub quiet: bool,
+}
+
+impl Options {
+    /// Parses the arguments after the program name, as `cargo test` passes them on.
+    /// `--nocapture` and `--test-threads` are accepted and ignored, as the cases run on one
+    /// thread with their output going straight out.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--exact" => options.exact = true,
+                "--list" => options.list = true,
+                "-q" | "--quiet" => options.quiet = true,
+                "--nocapture" => {}
+                "--skip" | "--test-threads" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("`{arg}` needs a value"))?;
+                    if arg == "--skip" {
+                        options.skip.push(value);
+                    }
+                }
+                _ if arg.starts_with("--test-threads=") => {}
+                _ if arg.starts_with('-') => return Err(format!("unrecognized option `{arg}`")),
+                _ => options.filters.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether the case called `name` runs.
+    pub fn selects(&self, name: &str) -> bool {
+        let matches = |pattern: &String| {
+            if self.exact {
+                name == pattern
+            } else {
+                name.contains(pattern.as_str())
+            }
+        };
+        (self.filters.is_empty() || self.filters.iter().any(matches))
+            && !self.skip.iter().any(matches)
+    }
+}
+
+/// What happened to a test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed(Duration),
+    /// With the panic message.
+    Failed(String),
+    FilteredOut,
+}
+
+/// The outcome of every case of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub outcomes: IndexVec<TestIdx, Outcome>,
+    pub elapsed: Duration,
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Passed(_)))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Failed(_)))
+    }
+
+    pub fn filtered_out(&self) -> usize {
+        self.count(|outcome| *outcome == Outcome::FilteredOut)
+    }
+
+    /// Whether no case failed.
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+
+    fn count(&self, f: impl Fn(&Outcome) -> bool) -> usize {
+        self.outcomes.iter().filter(|outcome| f(outcome)).count()
+    }
+}
+
+/// Runs the cases `options` selects, writing a line per case unless it is quiet, then the
+/// failures and a summary.
+pub fn run(
+    tests: &IndexSlice<TestIdx, TestFn<'_>>,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<Report> {
+    let start = Instant::now();
+    let selected = tests
+        .iter()
+        .filter(|test| options.selects(test.name))
+        .count();
+    if !options.quiet {
+        writeln!(out, "running {selected} of {} test cases", tests.len())?;
+    }
+    let mut outcomes = IndexVec::with_capacity(tests.len());
+    for test in tests {
+        let outcome = if options.selects(test.name) {
+            test.run()
+        } else {
+            Outcome::FilteredOut
+        };
+        if !options.quiet {
+            match &outcome {
+                Outcome::Passed(_) => writeln!(out, "{} ... ok", test.name)?,
+                Outcome::Failed(_) => writeln!(out, "{} ... FAILED", test.name)?,
+                Outcome::FilteredOut => {}
+            }
+        }
+        outcomes.push(outcome);
+    }
+    let report = Report {
+        outcomes,
+        elapsed: start.elapsed(),
+    };
+
+    if !report.is_ok() {
+        writeln!(out, "\nfailures:")?;
+        for (idx, outcome) in report.outcomes.iter_enumerated() {
+            if let Outcome::Failed(message) = outcome {
+                writeln!(out, "    {}: {message}", tests[idx].name)?;
+            }
+        }
+    }
+    writeln!(
+        out,
+        "\ntest result: {}. {} passed; {} failed; {} filtered out; finished in {:.2}s",
+        if report.is_ok() { "ok" } else { "FAILED" },
+        report.passed(),
+        report.failed(),
+        report.filtered_out(),
+        report.elapsed.as_secs_f64()
+    )?;
+    Ok(report)
+}
+
+/// A `#![test_runner]`: runs `tests` as the command line says, and exits with 101 if any
+/// failed, like libtest.
+pub fn runner(tests: &[&dyn Testable]) {
+    let options = Options::from_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(101)
+    });
+    let tests = collect(tests);
+    let mut out = io::stdout().lock();
+    if options.list {
+        for test in tests.iter().filter(|test| options.selects(test.name)) {
+            writeln!(out, "{}: test", test.name).unwrap();
+        }
+        return;
+    }
+    let report = run(&tests, &options, &mut out).unwrap();
+    if !report.is_ok() {
+        process::exit(101);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes() {}
+
+    fn fails() {
+        panic!("expected {}, found {}", 1, 2);
+    }
+
+    fn also_passes() {}
+
+    fn args(args: &str) -> Result<Options, String> {
+        Options::from_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_and_selects() {
+        let options = args("--skip also -q passes --test-threads 1 --nocapture").unwrap();
+        assert_eq!(options.filters, ["passes"]);
+        assert_eq!(options.skip, ["also"]);
+        assert!(options.quiet && !options.exact && !options.list);
+        assert!(options.selects("tests::passes"));
+        assert!(!options.selects("tests::also_passes"));
+        assert!(!options.selects("tests::fails"));
+
+        let exact = args("--exact tests::passes").unwrap();
+        assert!(exact.selects("tests::passes"));
+        assert!(!exact.selects("tests::also_passes"));
+        assert!(Options::default().selects("anything"));
+
+        assert_eq!(
+            args("--bench").unwrap_err(),
+            "unrecognized option `--bench`"
+        );
+        assert_eq!(args("--skip").unwrap_err(), "`--skip` needs a value");
+    }
+
+    #[test]
+    fn runs_and_reports() {
+        let tests = collect(&[&passes, &fails, &also_passes]);
+        assert_eq!(tests[TestIdx::new(1)].name, "test_harness::tests::fails");
+
+        let mut out = Vec::new();
+        let report = run(&tests, &args("--skip also").unwrap(), &mut out).unwrap();
+        assert!(matches!(
+            report.outcomes[TestIdx::new(0)],
+            Outcome::Passed(_)
+        ));
+        assert_eq!(
+            report.outcomes[TestIdx::new(1)],
+            Outcome::Failed("expected 1, found 2".into())
+        );
+        assert_eq!(report.outcomes[TestIdx::new(2)], Outcome::FilteredOut);
+        assert_eq!(
+            (report.passed(), report.failed(), report.filtered_out()),
+            (1, 1, 1)
+        );
+        assert!(!report.is_ok());
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[..7],
+            [
+                "running 2 of 3 test cases",
+                "test_harness::tests::passes ... ok",
+                "test_harness::tests::fails ... FAILED",
+                "",
+                "failures:",
+                "    test_harness::tests::fails: expected 1, found 2",
+                "",
+            ]
+        );
+        assert!(lines[7].starts_with("test result: FAILED. 1 passed; 1 failed; 1 filtered out;"));
+    }
+
+    #[test]
+    fn quiet_prints_the_summary() {
+        let tests = collect(&[&passes, &also_passes]);
+        let mut out = Vec::new();
+        let report = run(&tests, &args("-q").unwrap(), &mut out).unwrap();
+        assert!(report.is_ok());
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\ntest result: ok. 2 passed; 0 failed; 0 filtered out;"));
+    }
+}
@@ -0,0 +1,161 @@
+//! `ThreadId::as_u64` usage
+//!
+//! Tracking issue [67939].
+//!
+//! `ThreadId::as_u64` exposes the number behind a thread's id. std hands the numbers out in order
+//! from 1 as threads are created and never reuses one, so they can index a table directly, with no
+//! map from ids to slots and no lock around one. Without it a `ThreadId` can only be compared and
+//! hashed.
+//!
+//! [`current_thread_idx`] is a [`ThreadIdx`] for the current thread, and a [`ThreadTable`] has one
+//! slot per index, which each thread updates on its own, e.g. with relaxed atomics. Since every
+//! thread ever created takes a number, a table must be sized for all of them, not just those that
+//! use it. Without the `nightly` feature the index is taken from a global counter the first time a
+//! thread asks for it, and kept in a `thread_local!`, so only the threads that use it count.
+//!
+//! [67939]: https://github.com/rust-lang/rust/issues/67939
+
+#[cfg(feature = "nightly")]
+use std::thread;
+
+use crate::index_vec::{IndexSlice, IndexVec};
+use crate::step::Idx;
+
+/// A dense index for a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadIdx(u32);
+
+impl Idx for ThreadIdx {
+    const MAX_INDEX: usize = u32::MAX as usize;
+
+    #[inline]
+    fn new(idx: usize) -> Self {
+        assert!(idx <= u32::MAX as usize);
+        ThreadIdx(idx as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The current thread's index, the same for every call on a thread and different between
+/// threads.
+#[cfg(feature = "nightly")]
+pub fn current_thread_idx() -> ThreadIdx {
+    // Ids start at 1.
+    ThreadIdx::new(thread::current().id().as_u64().get() as usize - 1)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn current_thread_idx() -> ThreadIdx {
+    portable::current_thread_idx()
+}
+
+/// A value for each thread, indexed by [`ThreadIdx`].
+#[derive(Debug)]
+pub struct ThreadTable<T> {
+    slots: IndexVec<ThreadIdx, T>,
+}
+
+impl<T> ThreadTable<T> {
+    /// A table for the first `threads` thread indices.
+    pub fn new(threads: usize, init: impl FnMut(ThreadIdx) -> T) -> Self {
+        ThreadTable {
+            slots: IndexVec::from_fn_n(init, threads),
+        }
+    }
+
+    /// The current thread's slot, or `None` if its index is past the end of the table.
+    pub fn current(&self) -> Option<&T> {
+        self.slots.get(current_thread_idx())
+    }
+
+    pub fn as_slice(&self) -> &IndexSlice<ThreadIdx, T> {
+        &self.slots
+    }
+
+    pub fn into_inner(self) -> IndexVec<ThreadIdx, T> {
+        self.slots
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::ThreadIdx;
+    use crate::step::Idx;
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static INDEX: ThreadIdx = ThreadIdx::new(NEXT.fetch_add(1, Ordering::Relaxed));
+    }
+
+    pub(super) fn current_thread_idx() -> ThreadIdx {
+        INDEX.with(|&idx| idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    /// Enough for every thread the test binary creates.
+    const THREADS: usize = 1 << 16;
+
+    #[test]
+    fn per_thread_counts() {
+        let table = ThreadTable::new(THREADS, |_| AtomicU64::new(0));
+        let indices: Vec<ThreadIdx> = thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        for _ in 0..100 {
+                            table.current().unwrap().fetch_add(1, Ordering::Relaxed);
+                        }
+                        assert_eq!(current_thread_idx(), current_thread_idx());
+                        current_thread_idx()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let counts = table.into_inner();
+        for &idx in &indices {
+            assert_eq!(counts[idx].load(Ordering::Relaxed), 100);
+        }
+        let used = counts
+            .iter()
+            .filter(|c| c.load(Ordering::Relaxed) > 0)
+            .count();
+        assert_eq!(used, 4);
+        assert!(ThreadTable::new(0, |_| ()).current().is_none());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let pairs: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = (0..3)
+                .map(|_| s.spawn(|| (current_thread_idx(), portable::current_thread_idx())))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        // The numbering differs, but both are fixed per thread and distinct between threads.
+        for (i, a) in pairs.iter().enumerate() {
+            for b in &pairs[i + 1..] {
+                assert!(a.0 != b.0 && a.1 != b.1);
+            }
+        }
+        assert_eq!(
+            portable::current_thread_idx(),
+            portable::current_thread_idx()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! `Duration::{from_days, from_weeks}` and `Duration::div_duration_floor` usage
+//!
+//! Tracking issues [120301] and [149573].
+//!
+//! The stable `Duration::from_mins` and `from_hours` spell out a duration in the unit
+//! it was thought of in, instead of as `from_secs(3 * 60 * 60)`; `from_days` and `from_weeks` are
+//! still unstable, and all of them panic on overflow rather than wrapping the multiplication.
+//! The stable `div_duration_f64` is the ratio of two durations, and the unstable
+//! `div_duration_floor` the number of whole times one fits in the other, without going through
+//! `as_nanos()` by hand.
+//!
+//! [`days`] and [`weeks`] build long durations, [`whole_periods`] counts the periods that fit in
+//! a span, as `sleep_until::Ticker` does for the ticks it skips, and [`Throughput`] turns a count
+//! of elements and the time they took into a rate per second, for benchmarks and progress
+//! reports. Without the `nightly` feature the same arithmetic is done on seconds and nanoseconds.
+//!
+//! [120301]: https://github.com/rust-lang/rust/issues/120301
+//! [149573]: https://github.com/rust-lang/rust/issues/149573
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A duration of `n` days of 24 hours.
+///
+/// # Panics
+///
+/// Panics if the duration doesn't fit.
+#[cfg(feature = "nightly")]
+pub const fn days(n: u64) -> Duration {
+    Duration::from_days(n)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub const fn days(n: u64) -> Duration {
+    portable::days(n)
+}
+
+/// A duration of `n` weeks of 7 days.
+///
+/// # Panics
+///
+/// Panics if the duration doesn't fit.
+#[cfg(feature = "nightly")]
+pub const fn weeks(n: u64) -> Duration {
+    Duration::from_weeks(n)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub const fn weeks(n: u64) -> Duration {
+    portable::weeks(n)
+}
+
+/// The number of whole `period`s in `span`.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+#[cfg(feature = "nightly")]
+pub const fn whole_periods(span: Duration, period: Duration) -> u128 {
+    span.div_duration_floor(period)
+}
+
+#[cfg(not(feature = "nightly"))]
+pub const fn whole_periods(span: Duration, period: Duration) -> u128 {
+    portable::whole_periods(span, period)
+}
+
+/// A number of elements processed in some time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throughput {
+    pub elements: u64,
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    /// Runs `f`, which processes `elements` elements, and times it.
+    pub fn measure<R>(elements: u64, f: impl FnOnce() -> R) -> (R, Throughput) {
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        (result, Throughput { elements, elapsed })
+    }
+
+    /// Elements per second: zero if there were none, else infinity if no time passed.
+    pub fn per_second(&self) -> f64 {
+        if self.elements == 0 {
+            // Not the NaN of `0.0 * inf` when no time passed either.
+            return 0.0;
+        }
+        self.elements as f64 * Duration::from_secs(1).div_duration_f64(self.elapsed)
+    }
+}
+
+/// Like `1.25e6 elements/s`.
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2e} elements/s", self.per_second())
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::time::Duration;
+
+    const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+    pub(super) const fn days(n: u64) -> Duration {
+        match n.checked_mul(SECS_PER_DAY) {
+            Some(secs) => Duration::from_secs(secs),
+            None => panic!("overflow in Duration::from_days"),
+        }
+    }
+
+    pub(super) const fn weeks(n: u64) -> Duration {
+        match n.checked_mul(7 * SECS_PER_DAY) {
+            Some(secs) => Duration::from_secs(secs),
+            None => panic!("overflow in Duration::from_weeks"),
+        }
+    }
+
+    pub(super) const fn whole_periods(span: Duration, period: Duration) -> u128 {
+        span.as_nanos() / period.as_nanos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use super::*;
+
+    #[test]
+    fn constructors() {
+        const RETENTION: Duration = weeks(2);
+        assert_eq!(RETENTION, days(14));
+        assert_eq!(days(1), Duration::from_hours(24));
+        assert_eq!(Duration::from_hours(1), Duration::from_mins(60));
+        let overflow = panic::catch_unwind(|| days(u64::MAX)).unwrap_err();
+        assert_eq!(
+            overflow.downcast_ref::<&str>(),
+            Some(&"overflow in Duration::from_days")
+        );
+        assert!(panic::catch_unwind(|| weeks(u64::MAX / 7 / 86400 + 1)).is_err());
+    }
+
+    #[test]
+    fn periods() {
+        let period = Duration::from_millis(10);
+        assert_eq!(whole_periods(Duration::from_millis(45), period), 4);
+        assert_eq!(whole_periods(Duration::from_millis(9), period), 0);
+        assert_eq!(whole_periods(weeks(1), Duration::from_hours(1)), 168);
+        assert!(panic::catch_unwind(|| whole_periods(period, Duration::ZERO)).is_err());
+    }
+
+    #[test]
+    fn throughput() {
+        let rate = Throughput {
+            elements: 3_000,
+            elapsed: Duration::from_millis(1_500),
+        };
+        assert_eq!(rate.per_second(), 2_000.0);
+        assert_eq!(rate.to_string(), "2.00e3 elements/s");
+        let idle = Throughput {
+            elements: 1,
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(idle.per_second(), f64::INFINITY);
+        let none = Throughput {
+            elements: 0,
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(none.per_second(), 0.0);
+        assert_eq!(none.to_string(), "0.00e0 elements/s");
+
+        let (sum, measured) = Throughput::measure(1_000, || (0..1_000u64).sum::<u64>());
+        assert_eq!(sum, 499_500);
+        assert_eq!(measured.elements, 1_000);
+        assert!(measured.per_second() > 0.0);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        for n in [0, 1, 7, 365, u64::MAX / 604_800] {
+            assert_eq!(portable::days(n), days(n));
+            assert_eq!(portable::weeks(n), weeks(n));
+        }
+        let spans = [Duration::ZERO, Duration::new(5, 999_999_999), weeks(52)];
+        let periods = [Duration::from_nanos(1), Duration::from_millis(7), days(1)];
+        for span in spans {
+            for period in periods {
+                assert_eq!(
+                    portable::whole_periods(span, period),
+                    whole_periods(span, period)
+                );
+            }
+        }
+    }
+}
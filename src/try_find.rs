@@ -0,0 +1,151 @@
+//! `Iterator::try_find` usage
+//!
+//! Tracking issue [63178].
+//!
+//! `try_find(f)` is `find` for a fallible predicate: it stops at the first item for which `f`
+//! returns `Ok(true)`, or at the first `Err`, and returns `Result<Option<Item>, E>`. Without it the
+//! error has to be smuggled out of `find` through a captured variable, or the search written as a
+//! loop.
+//!
+//! [`IndexSlice::find_index_where`] and [`try_find_reachable`] use it for typed searches over a
+//! slice and over a depth-first walk of a graph. Without the `nightly` feature [`try_find`] is the
+//! plain loop.
+//!
+//! [63178]: https://github.com/rust-lang/rust/issues/63178
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+/// Returns the first item of `iter` for which `pred` returns `Ok(true)`, or the first error.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn try_find<It, E>(
+    iter: It,
+    pred: impl FnMut(&It::Item) -> Result<bool, E>,
+) -> Result<Option<It::Item>, E>
+where
+    It: IntoIterator,
+{
+    iter.into_iter().try_find(pred)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn try_find<It, E>(
+    iter: It,
+    pred: impl FnMut(&It::Item) -> Result<bool, E>,
+) -> Result<Option<It::Item>, E>
+where
+    It: IntoIterator,
+{
+    portable::try_find(iter, pred)
+}
+
+impl<I: Idx, T> IndexSlice<I, T> {
+    /// Returns the index of the first element for which `pred` returns `Ok(true)`, or the first
+    /// error.
+    pub fn find_index_where<E>(
+        &self,
+        mut pred: impl FnMut(&T) -> Result<bool, E>,
+    ) -> Result<Option<I>, E> {
+        let found = try_find(self.iter_enumerated(), |&(_, t)| pred(t))?;
+        Ok(found.map(|(i, _)| i))
+    }
+}
+
+/// Walks the graph given by the successor lists `succ` depth-first from `start`, and returns the
+/// first node in preorder for which `pred` returns `Ok(true)`, or the first error.
+pub fn try_find_reachable<I: Idx, E>(
+    succ: &IndexSlice<I, Vec<I>>,
+    start: I,
+    pred: impl FnMut(&I) -> Result<bool, E>,
+) -> Result<Option<I>, E> {
+    let mut visited = BitSet::new_empty(succ.len());
+    let mut stack = vec![start];
+    let preorder = std::iter::from_fn(move || {
+        while let Some(node) = stack.pop() {
+            if visited.insert(node) {
+                // Reversed, so the first successor is visited first.
+                stack.extend(succ[node].iter().rev());
+                return Some(node);
+            }
+        }
+        None
+    });
+    try_find(preorder, pred)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    pub(super) fn try_find<It, E>(
+        iter: It,
+        mut pred: impl FnMut(&It::Item) -> Result<bool, E>,
+    ) -> Result<Option<It::Item>, E>
+    where
+        It: IntoIterator,
+    {
+        for item in iter {
+            if pred(&item)? {
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index_vec::IndexVec;
+
+    use super::*;
+
+    fn parse_eq(s: &&str, want: u32) -> Result<bool, std::num::ParseIntError> {
+        Ok(s.parse::<u32>()? == want)
+    }
+
+    #[test]
+    fn slice_search() {
+        let v: IndexVec<u32, &str> = IndexVec::from(["1", "2", "x", "3"]);
+        assert_eq!(v.find_index_where(|s| parse_eq(s, 2)), Ok(Some(1)));
+        // The error comes first.
+        assert!(v.find_index_where(|s| parse_eq(s, 3)).is_err());
+        assert_eq!(v[0..2].find_index_where(|s| parse_eq(s, 3)), Ok(None));
+    }
+
+    #[test]
+    fn graph_search() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, 3 -> 0.
+        let succ: IndexVec<usize, Vec<usize>> =
+            IndexVec::from([vec![1, 2], vec![3], vec![3], vec![0]]);
+        let mut order = vec![];
+        let found = try_find_reachable(&succ, 0, |&n| {
+            order.push(n);
+            Ok::<_, ()>(false)
+        });
+        assert_eq!(found, Ok(None));
+        assert_eq!(order, [0, 1, 3, 2]);
+
+        assert_eq!(
+            try_find_reachable(&succ, 1, |&n| Ok::<_, ()>(n == 2)),
+            Ok(Some(2))
+        );
+        let err = try_find_reachable(&succ, 0, |&n| if n == 3 { Err(n) } else { Ok(n == 2) });
+        assert_eq!(err, Err(3));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let items = ["1", "2", "x", "3", "2"];
+        for want in 0..4 {
+            for len in 0..=items.len() {
+                let items = &items[..len];
+                assert_eq!(
+                    portable::try_find(items, |s| parse_eq(s, want)),
+                    items.iter().try_find(|s| parse_eq(s, want))
+                );
+            }
+        }
+    }
+}
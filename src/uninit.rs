@@ -0,0 +1,250 @@
+//! `<[MaybeUninit<T>]>::{write_filled, write_with}` usage
+//!
+//! Tracking issue [117428].
+//!
+//! `buf.write_filled(value)` clones `value` into every element of an uninitialized slice and
+//! `buf.write_with(f)` writes `f(i)` to element `i`, and both return the now initialized `&mut [T]`.
+//! If a clone or `f` panics, the elements written so far are dropped. Together with the slice
+//! methods that are already stable (`write_copy_of_slice`, `write_clone_of_slice`,
+//! `assume_init_mut`), this initializes a buffer exactly once, where the usual way is to fill it
+//! with zeroes or `Default` first and then overwrite it.
+//!
+//! [`IndexVec::extend_filled`], [`IndexVec::extend_with`] and [`IndexVec::extend_from_copy_slice`]
+//! write straight into the vector's spare capacity and only then grow its length. Without the
+//! `nightly` feature [`fill`] and [`fill_with`] are loops that write element by element, with a
+//! guard that drops the written prefix on a panic like std's.
+//!
+//! [117428]: https://github.com/rust-lang/rust/issues/117428
+
+use std::mem::MaybeUninit;
+use std::ops::Range;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// Initializes every element of `buf` with a clone of `value`.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn fill<T: Clone>(buf: &mut [MaybeUninit<T>], value: T) -> &mut [T] {
+    buf.write_filled(value)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn fill<T: Clone>(buf: &mut [MaybeUninit<T>], value: T) -> &mut [T] {
+    portable::fill(buf, value)
+}
+
+/// Initializes element `i` of `buf` with `f(i)`, in order.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn fill_with<T>(buf: &mut [MaybeUninit<T>], f: impl FnMut(usize) -> T) -> &mut [T] {
+    buf.write_with(f)
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn fill_with<T>(buf: &mut [MaybeUninit<T>], f: impl FnMut(usize) -> T) -> &mut [T] {
+    portable::fill_with(buf, f)
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Appends `n` clones of `value`, and returns the indices of the new elements.
+    pub fn extend_filled(&mut self, n: usize, value: T) -> Range<I>
+    where
+        T: Clone,
+    {
+        // SAFETY: `fill` initializes all `n` elements, or drops them and unwinds.
+        unsafe {
+            self.extend_uninit(n, |spare| {
+                fill(spare, value);
+            })
+        }
+    }
+
+    /// Appends `f(i)` for each new index `i`, in order, and returns the indices of the new
+    /// elements.
+    pub fn extend_with(&mut self, n: usize, mut f: impl FnMut(I) -> T) -> Range<I> {
+        let start = self.len();
+        // SAFETY: `fill_with` initializes all `n` elements, or drops them and unwinds.
+        unsafe {
+            self.extend_uninit(n, |spare| {
+                fill_with(spare, |i| f(I::new(start + i)));
+            })
+        }
+    }
+
+    /// Appends a copy of `src`, and returns the indices of the new elements.
+    pub fn extend_from_copy_slice(&mut self, src: &[T]) -> Range<I>
+    where
+        T: Copy,
+    {
+        // SAFETY: `write_copy_of_slice` initializes all `src.len()` elements.
+        unsafe {
+            self.extend_uninit(src.len(), |spare| {
+                spare.write_copy_of_slice(src);
+            })
+        }
+    }
+
+    /// Hands the next `n` elements of spare capacity to `init`, then counts them as initialized.
+    ///
+    /// # Safety
+    ///
+    /// `init` must initialize all elements of the slice it gets, if it returns.
+    unsafe fn extend_uninit(
+        &mut self,
+        n: usize,
+        init: impl FnOnce(&mut [MaybeUninit<T>]),
+    ) -> Range<I> {
+        let start = self.len();
+        self.raw.reserve(n);
+        init(&mut self.raw.spare_capacity_mut()[..n]);
+        // SAFETY: `reserve` made room for `n` more, and the caller guarantees they are
+        // initialized.
+        unsafe { self.raw.set_len(start + n) };
+        I::new(start)..I::new(start + n)
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    /// Like std, moves `value` itself into the last element.
+    pub(super) fn fill<T: Clone>(buf: &mut [MaybeUninit<T>], value: T) -> &mut [T] {
+        let last = buf.len().wrapping_sub(1);
+        let mut value = Some(value);
+        fill_with(buf, |i| {
+            if i == last {
+                value.take().unwrap()
+            } else {
+                value.clone().unwrap()
+            }
+        })
+    }
+
+    pub(super) fn fill_with<T>(
+        buf: &mut [MaybeUninit<T>],
+        mut f: impl FnMut(usize) -> T,
+    ) -> &mut [T] {
+        /// Drops `buf[..initialized]` unless forgotten.
+        struct Guard<'a, T> {
+            buf: &'a mut [MaybeUninit<T>],
+            initialized: usize,
+        }
+
+        impl<T> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                // SAFETY: the first `initialized` elements were written.
+                unsafe { self.buf[..self.initialized].assume_init_drop() };
+            }
+        }
+
+        let mut guard = Guard {
+            buf,
+            initialized: 0,
+        };
+        while guard.initialized < guard.buf.len() {
+            guard.buf[guard.initialized].write(f(guard.initialized));
+            guard.initialized += 1;
+        }
+        let buf = ptr::addr_of_mut!(*guard.buf);
+        std::mem::forget(guard);
+        // SAFETY: every element was written, and `buf` is the exclusive borrow the guard held.
+        unsafe { (*buf).assume_init_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    #[derive(Clone)]
+    struct Tracked<'a>(&'a Cell<usize>);
+
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn fill_buffers() {
+        let mut buf = [const { MaybeUninit::uninit() }; 4];
+        assert_eq!(fill(&mut buf, 7u8), [7; 4]);
+        assert_eq!(fill_with(&mut buf, |i| i as u8 * 2), [0, 2, 4, 6]);
+        let mut strings = [const { MaybeUninit::uninit() }; 3];
+        let strings = fill_with(&mut strings, |i| i.to_string());
+        assert_eq!(strings.concat(), "012");
+        // SAFETY: `strings` is initialized and not used again.
+        unsafe { std::ptr::drop_in_place(strings) };
+    }
+
+    #[test]
+    fn extend_index_vec() {
+        let mut v: IndexVec<u32, u16> = IndexVec::from([1]);
+        assert_eq!(v.extend_filled(3, 9), 1..4);
+        assert_eq!(v.extend_with(2, |i| i as u16 * 10), 4..6);
+        assert_eq!(v.extend_from_copy_slice(&[5, 6]), 6..8);
+        assert_eq!(v.extend_filled(0, 1), 8..8);
+        assert_eq!(v.raw, [1, 9, 9, 9, 40, 50, 5, 6]);
+    }
+
+    #[test]
+    fn panic_drops_written() {
+        let drops = Cell::new(0);
+        let mut v: IndexVec<usize, Tracked<'_>> = IndexVec::new();
+        v.extend_filled(2, Tracked(&drops));
+        // The last element gets `value` itself, so nothing is dropped.
+        assert_eq!(drops.get(), 0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            v.extend_with(5, |i| {
+                assert!(i != 4, "init panicked");
+                Tracked(&drops)
+            })
+        }));
+        assert!(result.is_err());
+        // The two written by the failed call are dropped and `v` is unchanged.
+        assert_eq!((drops.get(), v.len()), (2, 2));
+        drop(v);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn portable_drops_on_panic() {
+        let drops = Cell::new(0);
+        let mut buf = [const { MaybeUninit::uninit() }; 4];
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            portable::fill_with(&mut buf, |i| {
+                assert!(i != 3, "init panicked");
+                Tracked(&drops)
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut a = [const { MaybeUninit::uninit() }; 5];
+        let mut b = [const { MaybeUninit::uninit() }; 5];
+        let f = |i: usize| (i * 7 % 5) as u32;
+        assert_eq!(a.write_with(f), portable::fill_with(&mut b, f));
+
+        for len in 0..3 {
+            let (std_drops, portable_drops) = (Cell::new(0), Cell::new(0));
+            let mut a: Vec<_> = (0..len).map(|_| MaybeUninit::uninit()).collect();
+            let mut b: Vec<_> = (0..len).map(|_| MaybeUninit::uninit()).collect();
+            a.write_filled(Tracked(&std_drops));
+            portable::fill(&mut b, Tracked(&portable_drops));
+            assert_eq!(std_drops.get(), portable_drops.get());
+        }
+    }
+}
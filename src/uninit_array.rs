@@ -0,0 +1,234 @@
+//! `MaybeUninit::transpose` and `MaybeUninit::array_assume_init` usage
+//!
+//! Tracking issue [96097].
+//!
+//! `MaybeUninit<[T; N]>::transpose` turns an uninitialized array into an array of uninitialized
+//! elements, `[MaybeUninit<T>; N]`, and back, and `MaybeUninit::array_assume_init` turns an array
+//! of elements that are all initialized into `[T; N]`. They replace `mem::transmute`, which can't
+//! see that the sizes match for a generic `N`, and the `uninit_array` function, which was removed
+//! in favour of `[const { MaybeUninit::uninit() }; N]`.
+//!
+//! [`ArrayBuilder`] fills a `[T; N]` one element at a time and drops what it holds if it is
+//! dropped before it is full, e.g. when the code producing the elements fails or panics.
+//! `IndexArray::try_from_fn` and the stable `array_try_map::try_from_fn` are built on it. Without
+//! the `nightly` feature the conversions cast the array pointer and read through it.
+//!
+//! [96097]: https://github.com/rust-lang/rust/issues/96097
+
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::{fmt, ptr};
+
+/// An array of `N` elements being filled in order.
+pub struct ArrayBuilder<T, const N: usize> {
+    array: [MaybeUninit<T>; N],
+    /// The first `len` elements of `array` are initialized.
+    len: usize,
+}
+
+/// An array of uninitialized elements.
+#[cfg(feature = "nightly")]
+#[inline]
+pub fn uninit_array<T, const N: usize>() -> [MaybeUninit<T>; N] {
+    MaybeUninit::<[T; N]>::uninit().transpose()
+}
+
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub fn uninit_array<T, const N: usize>() -> [MaybeUninit<T>; N] {
+    [const { MaybeUninit::uninit() }; N]
+}
+
+/// Takes the elements out of `array`.
+///
+/// # Safety
+///
+/// Every element of `array` must be initialized.
+#[cfg(feature = "nightly")]
+#[inline]
+pub unsafe fn array_assume_init<T, const N: usize>(array: [MaybeUninit<T>; N]) -> [T; N] {
+    // SAFETY: the caller guarantees every element is initialized.
+    unsafe { MaybeUninit::array_assume_init(array) }
+}
+
+/// Takes the elements out of `array`.
+///
+/// # Safety
+///
+/// Every element of `array` must be initialized.
+#[cfg(not(feature = "nightly"))]
+#[inline]
+pub unsafe fn array_assume_init<T, const N: usize>(array: [MaybeUninit<T>; N]) -> [T; N] {
+    // SAFETY: the caller guarantees every element is initialized.
+    unsafe { portable::array_assume_init(array) }
+}
+
+impl<T, const N: usize> ArrayBuilder<T, N> {
+    pub fn new() -> Self {
+        ArrayBuilder {
+            array: uninit_array(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Appends `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the array is already full.
+    #[track_caller]
+    pub fn push(&mut self, value: T) {
+        assert!(!self.is_full(), "array builder is full");
+        self.array[self.len].write(value);
+        self.len += 1;
+    }
+
+    /// The elements pushed so far.
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: the first `len` elements are initialized.
+        unsafe { self.array[..self.len].assume_init_ref() }
+    }
+
+    /// Returns the array if it is full, or `self` back if it isn't.
+    pub fn try_build(self) -> Result<[T; N], Self> {
+        if !self.is_full() {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        // SAFETY: all elements are initialized, and `this` is never dropped, so they are moved
+        // out only once.
+        Ok(unsafe { array_assume_init(ptr::read(&this.array)) })
+    }
+
+    /// Returns the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the array isn't full.
+    #[track_caller]
+    pub fn build(self) -> [T; N] {
+        match self.try_build() {
+            Ok(array) => array,
+            Err(this) => panic!("array builder has {} of {N} elements", this.len),
+        }
+    }
+}
+
+impl<T, const N: usize> Default for ArrayBuilder<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayBuilder<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` elements are initialized, and not used again.
+        unsafe { self.array[..self.len].assume_init_drop() };
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayBuilder<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::mem::{ManuallyDrop, MaybeUninit};
+    use std::ptr::NonNull;
+
+    /// # Safety
+    ///
+    /// Every element of `array` must be initialized.
+    pub(super) unsafe fn array_assume_init<T, const N: usize>(
+        array: [MaybeUninit<T>; N],
+    ) -> [T; N] {
+        let array = ManuallyDrop::new(array);
+        // SAFETY: `[MaybeUninit<T>; N]` has the layout of `[T; N]`, the caller guarantees the
+        // elements are initialized, and `array` isn't dropped.
+        unsafe { NonNull::from(&*array).cast::<[T; N]>().read() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Counts its drops in the shared counter.
+    struct Tracked<'a>(&'a Cell<usize>);
+
+    impl Drop for Tracked<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn build_incrementally() {
+        let mut builder = ArrayBuilder::<String, 3>::new();
+        assert!(builder.is_empty());
+        builder.push("a".to_owned());
+        builder.push("b".to_owned());
+        assert_eq!(builder.as_slice(), ["a", "b"]);
+        assert_eq!(format!("{builder:?}"), r#"["a", "b"]"#);
+        let mut builder = builder.try_build().unwrap_err();
+        builder.push("c".to_owned());
+        assert!(builder.is_full());
+        assert_eq!(builder.build(), ["a", "b", "c"]);
+        assert_eq!(ArrayBuilder::<u8, 0>::new().build(), []);
+    }
+
+    #[test]
+    fn drops_partial() {
+        let drops = Cell::new(0);
+        let mut builder = ArrayBuilder::<_, 4>::new();
+        builder.push(Tracked(&drops));
+        builder.push(Tracked(&drops));
+        drop(builder);
+        assert_eq!(drops.get(), 2);
+
+        let mut builder = ArrayBuilder::<_, 2>::new();
+        builder.push(Tracked(&drops));
+        builder.push(Tracked(&drops));
+        let array = builder.build();
+        assert_eq!(drops.get(), 2);
+        drop(array);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "array builder is full")]
+    fn push_past_end() {
+        let mut builder = ArrayBuilder::<u8, 1>::new();
+        builder.push(1);
+        builder.push(2);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let array = [1, 2, 3].map(MaybeUninit::new);
+        // SAFETY: every element was initialized.
+        let (std, portable) = unsafe {
+            (
+                MaybeUninit::array_assume_init(array),
+                portable::array_assume_init(array),
+            )
+        };
+        assert_eq!(std, portable);
+    }
+}
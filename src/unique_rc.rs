@@ -0,0 +1,257 @@
+//! `rc::UniqueRc` usage
+//!
+//! Tracking issue [112566].
+//!
+//! A `UniqueRc<T>` is an `Rc<T>` that is known to be the only strong reference, so it derefs
+//! mutably, and `UniqueRc::downgrade` already hands out `Weak`s to it. Those don't upgrade until
+//! `UniqueRc::into_rc` freezes it into an ordinary `Rc`, when they all start to work at once. A
+//! node can so give its children a `Weak` link back before it is finished, and still be mutated
+//! to add the children afterwards, without a `RefCell` around the fields or
+//! `Rc::new_cyclic`, whose closure has to produce the whole value in one go.
+//!
+//! [`Node`] is a tree node with an owning list of children and a weak parent link, and
+//! [`build_tree`] makes one from the index-based form the rest of the crate uses: a successor list
+//! per node, as in `exclusive::Graph`. Any node can then walk up to the root or down to the
+//! leaves without the tables. Building, walking and dropping a tree keep their own stacks instead
+//! of recursing, so a long chain doesn't overflow the thread's. There is no stable equivalent, so the module is only built with the
+//! `nightly` feature.
+//!
+//! [112566]: https://github.com/rust-lang/rust/issues/112566
+
+use std::rc::{Rc, UniqueRc, Weak};
+use std::{fmt, mem};
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexSlice;
+use crate::step::Idx;
+
+/// A node of a tree of `Rc`s, which owns its children and links back to its parent.
+pub struct Node<T> {
+    pub value: T,
+    parent: Weak<Node<T>>,
+    children: Vec<Rc<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    /// The parent, or `None` for the root.
+    pub fn parent(&self) -> Option<Rc<Node<T>>> {
+        self.parent.upgrade()
+    }
+
+    pub fn children(&self) -> &[Rc<Node<T>>] {
+        &self.children
+    }
+
+    /// This node, then its ancestors up to the root.
+    pub fn ancestors(self: &Rc<Self>) -> impl Iterator<Item = Rc<Node<T>>> {
+        std::iter::successors(Some(Rc::clone(self)), |node| node.parent())
+    }
+
+    /// The number of nodes in this subtree.
+    pub fn subtree_len(&self) -> usize {
+        let (mut len, mut stack) = (0, vec![self]);
+        while let Some(node) = stack.pop() {
+            len += 1;
+            stack.extend(node.children.iter().map(|child| &**child));
+        }
+        len
+    }
+
+    /// Calls `f` on each value of this subtree, parents before children.
+    pub fn for_each_preorder(&self, f: &mut impl FnMut(&T)) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            f(&node.value);
+            // Reversed, so that the first child is popped first.
+            stack.extend(node.children.iter().rev().map(|child| &**child));
+        }
+    }
+}
+
+/// Frees the subtree a node is the last owner of one node at a time, where dropping the children
+/// list would recurse once per level.
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut doomed = mem::take(&mut self.children);
+        while let Some(child) = doomed.pop() {
+            // A child still shared elsewhere only loses a reference; one that isn't gives up its
+            // children first, so its own drop has none left.
+            if let Some(mut child) = Rc::into_inner(child) {
+                doomed.append(&mut child.children);
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Node<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The parent is left out, or printing would go round in circles.
+        f.debug_struct("Node")
+            .field("value", &self.value)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+/// The tree below `root` in `successors`, with node `i` holding `value(i)`, called parents before
+/// children.
+///
+/// # Panics
+///
+/// Panics if a node is reached twice, so `successors` below `root` must be a tree.
+pub fn build_tree<I: Idx, T>(
+    root: I,
+    successors: &IndexSlice<I, Vec<I>>,
+    mut value: impl FnMut(I) -> T,
+) -> Rc<Node<T>> {
+    let mut seen = BitSet::new_empty(successors.len());
+    let mut start = |index: I, parent| {
+        assert!(seen.insert(index), "node {} reached twice", index.index());
+        Pending {
+            index,
+            node: UniqueRc::new(Node {
+                value: value(index),
+                parent,
+                children: Vec::with_capacity(successors[index].len()),
+            }),
+        }
+    };
+    // The path from the root to the node being built. Each node is frozen once all its children
+    // are, and moved into its parent's list.
+    let mut path = vec![start(root, Weak::new())];
+    loop {
+        let top = path.last_mut().unwrap();
+        if let Some(&child) = successors[top.index].get(top.node.children.len()) {
+            // Doesn't upgrade yet, but will once the parent is frozen.
+            let link = UniqueRc::downgrade(&top.node);
+            path.push(start(child, link));
+            continue;
+        }
+        let node = UniqueRc::into_rc(path.pop().unwrap().node);
+        match path.last_mut() {
+            Some(parent) => parent.node.children.push(node),
+            None => return node,
+        }
+    }
+}
+
+/// A node of [`build_tree`] whose children aren't all built yet.
+struct Pending<I, T> {
+    index: I,
+    node: UniqueRc<Node<T>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_vec::IndexVec;
+
+    /// 0 ─┬─ 1 ─┬─ 3
+    ///    │     └─ 4 ── 5
+    ///    └─ 2
+    fn successors() -> IndexVec<u32, Vec<u32>> {
+        IndexVec::from_raw(vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![],
+            vec![],
+            vec![5],
+            vec![],
+        ])
+    }
+
+    /// Every node, found by walking `root`.
+    fn nodes(root: &Rc<Node<u32>>) -> Vec<Rc<Node<u32>>> {
+        let mut nodes = vec![Rc::clone(root)];
+        let mut i = 0;
+        while let Some(node) = nodes.get(i) {
+            let children = node.children().to_vec();
+            nodes.extend(children);
+            i += 1;
+        }
+        nodes
+    }
+
+    #[test]
+    fn links_both_ways() {
+        let succ = successors();
+        let root = build_tree(0, &succ, |i| i * 10);
+        assert_eq!(root.subtree_len(), 6);
+        assert!(root.parent().is_none());
+        let five = &root.children()[0].children()[1].children()[0];
+        let path: Vec<u32> = five.ancestors().map(|node| node.value).collect();
+        assert_eq!(path, [50, 40, 10, 0]);
+        assert_eq!(
+            format!("{:?}", root.children()[1]),
+            "Node { value: 20, children: [] }"
+        );
+
+        // The parent links are weak, so the tree is freed with its root.
+        let leaf = Rc::downgrade(five);
+        drop(root);
+        assert!(leaf.upgrade().is_none());
+    }
+
+    #[test]
+    fn agrees_with_index_form() {
+        let succ = successors();
+        let root = build_tree(0, &succ, |i| i);
+        let mut parent: IndexVec<u32, Option<u32>> = IndexVec::from_raw(vec![None; succ.len()]);
+        for (from, tos) in succ.raw.iter().enumerate() {
+            for &to in tos {
+                parent[to] = Some(from as u32);
+            }
+        }
+        for node in nodes(&root) {
+            let i = node.value;
+            assert_eq!(node.parent().map(|p| p.value), parent[i]);
+            let children: Vec<u32> = node.children().iter().map(|c| c.value).collect();
+            assert_eq!(children, succ[i]);
+        }
+
+        // The same depth-first order as a walk of the tables.
+        let mut from_tree = Vec::new();
+        root.for_each_preorder(&mut |&i| from_tree.push(i));
+        let (mut from_index, mut stack) = (Vec::new(), vec![0u32]);
+        while let Some(i) = stack.pop() {
+            from_index.push(i);
+            stack.extend(succ[i].iter().rev());
+        }
+        assert_eq!(from_tree, from_index);
+
+        // A subtree needs only its own rows.
+        assert_eq!(build_tree(1, &succ, |i| i).subtree_len(), 4);
+    }
+
+    #[test]
+    fn deep_chains_do_not_recurse() {
+        const LEN: u32 = 200_000;
+        let succ: IndexVec<u32, Vec<u32>> =
+            (0..LEN).map(|i| if i + 1 < LEN { vec![i + 1] } else { vec![] }).collect();
+        let root = build_tree(0, &succ, |i| i);
+        assert_eq!(root.subtree_len(), LEN as usize);
+        let mut next = 0;
+        root.for_each_preorder(&mut |&i| {
+            assert_eq!(i, next);
+            next += 1;
+        });
+        assert_eq!(next, LEN);
+
+        let mut leaf = Rc::clone(&root);
+        while let Some(child) = leaf.children().first() {
+            leaf = Rc::clone(child);
+        }
+        assert_eq!(leaf.ancestors().count(), LEN as usize);
+        // Dropped with the root, without a frame per level.
+        let leaf_link = Rc::downgrade(&leaf);
+        drop((leaf, root));
+        assert!(leaf_link.upgrade().is_none());
+    }
+
+    #[test]
+    #[should_panic = "node 1 reached twice"]
+    fn rejects_shared_nodes() {
+        let succ: IndexVec<u32, Vec<u32>> = IndexVec::from_raw(vec![vec![1, 1], vec![]]);
+        build_tree(0, &succ, |i| i);
+    }
+}
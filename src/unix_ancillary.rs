@@ -0,0 +1,191 @@
+//! `SocketAncillary` usage
+//!
+//! Tracking issue [76915].
+//!
+//! A Unix socket can carry ancillary data next to the bytes of a message: open file descriptors,
+//! which the receiving process gets duplicates of, and, on Linux, the credentials of the sending
+//! process, filled in by the kernel. `SocketAncillary` is a buffer for these control messages, and
+//! `UnixStream::send_vectored_with_ancillary` and `recv_vectored_with_ancillary` take one along
+//! with the data.
+//!
+//! [`send_with_fds`] and [`recv_with_fds`] wrap that in terms of `BorrowedFd` and `OwnedFd`, so
+//! that the received descriptors are closed when dropped instead of leaking as raw numbers. On
+//! Linux, [`pass_credentials`] asks for the sender's credentials on every message a socket
+//! receives, and [`Received::credentials`] holds them. The module is only built on Unix targets
+//! with the `nightly` feature.
+//!
+//! [76915]: https://github.com/rust-lang/rust/issues/76915
+
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{AncillaryData, SocketAncillary, UnixStream};
+
+/// The credentials of the process that sent a message.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// A message read by [`recv_with_fds`].
+#[derive(Debug)]
+pub struct Received {
+    /// The number of bytes read into the buffer.
+    pub len: usize,
+    pub fds: Vec<OwnedFd>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub credentials: Option<Credentials>,
+    /// Whether control data was cut off for lack of room. Descriptors that didn't fit are closed.
+    pub truncated: bool,
+}
+
+/// Room for a control message carrying `len` bytes, with its header and padding.
+fn control_space(len: usize) -> usize {
+    let align = |n: usize| n.next_multiple_of(mem::size_of::<usize>());
+    // `cmsg_len` is at most a `usize`, followed by two `c_int`s.
+    align(mem::size_of::<usize>() + 2 * mem::size_of::<i32>()) + align(len)
+}
+
+/// Sends `data` on `stream`, along with duplicates of `fds` for the receiver, and returns the
+/// number of bytes sent.
+pub fn send_with_fds(
+    stream: &UnixStream,
+    data: &[u8],
+    fds: &[BorrowedFd<'_>],
+) -> io::Result<usize> {
+    let raw: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+    let mut buf = vec![0; control_space(mem::size_of_val(&raw[..]))];
+    let mut ancillary = SocketAncillary::new(&mut buf);
+    if !raw.is_empty() && !ancillary.add_fds(&raw) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many file descriptors for one message",
+        ));
+    }
+    stream.send_vectored_with_ancillary(&[IoSlice::new(data)], &mut ancillary)
+}
+
+/// Receives a message on `stream` into `buf`, with room for at least `max_fds` descriptors
+/// sent with it.
+pub fn recv_with_fds(stream: &UnixStream, buf: &mut [u8], max_fds: usize) -> io::Result<Received> {
+    let mut space = control_space(max_fds * mem::size_of::<RawFd>());
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        // A `struct ucred`.
+        space += control_space(3 * mem::size_of::<u32>());
+    }
+    let mut control = vec![0; space];
+    let mut ancillary = SocketAncillary::new(&mut control);
+    let len = stream.recv_vectored_with_ancillary(&mut [IoSliceMut::new(buf)], &mut ancillary)?;
+
+    let mut received = Received {
+        len,
+        fds: Vec::new(),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        credentials: None,
+        truncated: ancillary.truncated(),
+    };
+    for message in ancillary.messages() {
+        let Ok(message) = message else {
+            continue;
+        };
+        match message {
+            AncillaryData::ScmRights(fds) => {
+                // SAFETY: the kernel installed these descriptors in this process for the message,
+                // and nothing else knows about them.
+                received
+                    .fds
+                    .extend(fds.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }));
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            AncillaryData::ScmCredentials(mut creds) => {
+                received.credentials = creds.next().map(|c| Credentials {
+                    pid: c.get_pid(),
+                    uid: c.get_uid(),
+                    gid: c.get_gid(),
+                });
+            }
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+    }
+    Ok(received)
+}
+
+/// Has the kernel attach the sender's credentials to every message `stream` receives.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn pass_credentials(stream: &UnixStream) -> io::Result<()> {
+    #[cfg(target_os = "android")]
+    use std::os::android::net::UnixSocketExt;
+    #[cfg(target_os = "linux")]
+    use std::os::linux::net::UnixSocketExt;
+
+    stream.set_passcred(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::AsFd;
+
+    use super::*;
+
+    #[test]
+    fn passes_fds() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (mut reader, writer) = io::pipe().unwrap();
+        let (_, other) = io::pipe().unwrap();
+        let sent = send_with_fds(&a, b"two", &[writer.as_fd(), other.as_fd()]).unwrap();
+        assert_eq!(sent, 3);
+        drop(writer);
+
+        let mut buf = [0; 8];
+        let received = recv_with_fds(&b, &mut buf, 4).unwrap();
+        assert_eq!(&buf[..received.len], b"two");
+        assert!(!received.truncated);
+        let mut fds = received.fds.into_iter();
+        // The received descriptor is a new one for the same pipe.
+        let mut file = File::from(fds.next().unwrap());
+        file.write_all(b"through the pipe").unwrap();
+        drop(file);
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "through the pipe");
+        assert_eq!(fds.len(), 1);
+    }
+
+    #[test]
+    fn plain_and_truncated() {
+        let (a, b) = UnixStream::pair().unwrap();
+        send_with_fds(&a, b"none", &[]).unwrap();
+        let mut buf = [0; 8];
+        let received = recv_with_fds(&b, &mut buf, 0).unwrap();
+        assert_eq!((received.len, received.fds.len()), (4, 0));
+
+        let (_, writer) = io::pipe().unwrap();
+        let fds = [writer.as_fd(); 20];
+        send_with_fds(&a, b"many", &fds).unwrap();
+        let received = recv_with_fds(&b, &mut buf, 0).unwrap();
+        assert!(received.truncated);
+        assert!(received.fds.len() < fds.len());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn passes_credentials() {
+        use std::os::unix::fs::MetadataExt;
+        use std::{fs, process};
+
+        let (a, b) = UnixStream::pair().unwrap();
+        pass_credentials(&b).unwrap();
+        send_with_fds(&a, b"me", &[]).unwrap();
+        let mut buf = [0; 2];
+        let credentials = recv_with_fds(&b, &mut buf, 0).unwrap().credentials.unwrap();
+        assert_eq!(credentials.pid, process::id() as i32);
+        let me = fs::metadata("/proc/self").unwrap();
+        assert_eq!((credentials.uid, credentials.gid), (me.uid(), me.gid()));
+    }
+}
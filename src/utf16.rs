@@ -0,0 +1,246 @@
+//! `String::from_utf16le` and `String::from_utf16be_lossy` usage
+//!
+//! Tracking issue [116258].
+//!
+//! `String::from_utf16` takes `&[u16]`, which data read from a file or the network isn't: it is
+//! `&[u8]` in a byte order fixed by the format, usually little-endian for anything written on
+//! Windows, and not aligned for `u16`. `from_utf16le`, `from_utf16be` and their `_lossy`
+//! variants decode the bytes directly. The strict ones fail on an unpaired surrogate or an odd
+//! number of bytes, and the lossy ones put U+FFFD in place of either.
+//!
+//! [`decode`] and [`decode_lossy`] pick the variant for an [`Endian`], and [`strip_bom`] finds it
+//! from a byte order mark. [`load_symbols`] reads a symbol table of NUL-terminated UTF-16 names,
+//! as Windows tools write them, into a `StrInterner`. Without the `nightly` feature each pair of
+//! bytes is turned into a `u16` with `from_le_bytes` or `from_be_bytes`, which swaps them on a
+//! host of the other order, and decoded with `char::decode_utf16`.
+//!
+//! [116258]: https://github.com/rust-lang/rust/issues/116258
+
+use std::string::FromUtf16Error;
+
+use crate::raw_entry::StrInterner;
+
+/// The byte order of UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// `bytes` without its byte order mark, and the order the mark gives, or `None` if it has none.
+pub fn strip_bom(bytes: &[u8]) -> Option<(Endian, &[u8])> {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => Some((Endian::Little, rest)),
+        [0xFE, 0xFF, rest @ ..] => Some((Endian::Big, rest)),
+        _ => None,
+    }
+}
+
+/// Decodes UTF-16 `bytes` in the byte order `endian`, failing if they aren't valid UTF-16 or
+/// their number is odd.
+#[cfg(feature = "nightly")]
+pub fn decode(bytes: &[u8], endian: Endian) -> Result<String, FromUtf16Error> {
+    match endian {
+        Endian::Little => String::from_utf16le(bytes),
+        Endian::Big => String::from_utf16be(bytes),
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn decode(bytes: &[u8], endian: Endian) -> Result<String, FromUtf16Error> {
+    portable::decode(bytes, endian)
+}
+
+/// Decodes UTF-16 `bytes` in the byte order `endian`, with U+FFFD for each unpaired surrogate
+/// and for an odd last byte.
+#[cfg(feature = "nightly")]
+pub fn decode_lossy(bytes: &[u8], endian: Endian) -> String {
+    match endian {
+        Endian::Little => String::from_utf16le_lossy(bytes),
+        Endian::Big => String::from_utf16be_lossy(bytes),
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn decode_lossy(bytes: &[u8], endian: Endian) -> String {
+    portable::decode_lossy(bytes, endian)
+}
+
+/// Interns the names of a symbol table: UTF-16 strings each ending in a NUL code unit, in the
+/// order given by a leading byte order mark, or `default` without one. A last name without its
+/// NUL is interned too.
+pub fn load_symbols(bytes: &[u8], default: Endian) -> Result<StrInterner, FromUtf16Error> {
+    let (endian, mut rest) = strip_bom(bytes).unwrap_or((default, bytes));
+    let mut interner = StrInterner::new();
+    while !rest.is_empty() {
+        let nul = rest
+            .chunks(2)
+            .position(|unit| unit == [0, 0])
+            .map_or(rest.len(), |i| 2 * i);
+        interner.intern(&decode(&rest[..nul], endian)?);
+        rest = rest.get(nul + 2..).unwrap_or_default();
+    }
+    Ok(interner)
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::char::{self, REPLACEMENT_CHARACTER};
+    use std::string::FromUtf16Error;
+
+    use super::Endian;
+
+    /// The code units of `bytes`, leaving out an odd last byte.
+    fn units(bytes: &[u8], endian: Endian) -> impl Iterator<Item = u16> + '_ {
+        let from_bytes = match endian {
+            Endian::Little => u16::from_le_bytes,
+            Endian::Big => u16::from_be_bytes,
+        };
+        bytes
+            .chunks_exact(2)
+            .map(move |pair| from_bytes([pair[0], pair[1]]))
+    }
+
+    pub(super) fn decode(bytes: &[u8], endian: Endian) -> Result<String, FromUtf16Error> {
+        if !bytes.len().is_multiple_of(2) {
+            // `FromUtf16Error` can't be made directly, so take the one for a lone surrogate.
+            return String::from_utf16(&[0xD800]);
+        }
+        char::decode_utf16(units(bytes, endian))
+            .collect::<Result<String, _>>()
+            .or_else(|_| String::from_utf16(&[0xD800]))
+    }
+
+    pub(super) fn decode_lossy(bytes: &[u8], endian: Endian) -> String {
+        let mut s: String = char::decode_utf16(units(bytes, endian))
+            .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+            .collect();
+        if !bytes.len().is_multiple_of(2) {
+            s.push(REPLACEMENT_CHARACTER);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(s: &str, endian: Endian) -> Vec<u8> {
+        s.encode_utf16()
+            .flat_map(|unit| match endian {
+                Endian::Little => unit.to_le_bytes(),
+                Endian::Big => unit.to_be_bytes(),
+            })
+            .collect()
+    }
+
+    /// A xorshift generator, so the fuzz tests see the same inputs on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random bytes, biased towards surrogates and NULs.
+        fn bytes(&mut self) -> Vec<u8> {
+            let len = self.next() as usize % 24;
+            (0..len)
+                .map(|_| match self.next() % 4 {
+                    0 => 0xD8 + self.next() as u8 % 8,
+                    1 => 0,
+                    _ => self.next() as u8,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn decodes() {
+        for endian in [Endian::Little, Endian::Big] {
+            let bytes = encode("Vec<ü> 🦀", endian);
+            assert_eq!(decode(&bytes, endian).unwrap(), "Vec<ü> 🦀");
+            assert_eq!(decode_lossy(&bytes, endian), "Vec<ü> 🦀");
+            // The crab's high surrogate is left unpaired, and one byte of the low one.
+            assert!(decode(&bytes[..bytes.len() - 1], endian).is_err());
+            assert_eq!(
+                decode_lossy(&bytes[..bytes.len() - 1], endian),
+                "Vec<ü> \u{FFFD}\u{FFFD}"
+            );
+        }
+        // The crab's high surrogate without its low one.
+        let lone = &encode("a🦀", Endian::Little)[..4];
+        assert!(decode(lone, Endian::Little).is_err());
+        assert_eq!(decode_lossy(lone, Endian::Little), "a\u{FFFD}");
+        assert_eq!(decode(&[0, b'a'], Endian::Big).unwrap(), "a");
+        assert_eq!(decode(&[0, b'a'], Endian::Little).unwrap(), "\u{6100}");
+    }
+
+    #[test]
+    fn symbol_tables() {
+        let mut table = vec![0xFE, 0xFF];
+        for name in ["main", "Vec::<ü>::push", "main"] {
+            table.extend(encode(name, Endian::Big));
+            table.extend([0, 0]);
+        }
+        table.extend(encode("tail", Endian::Big));
+        let interner = load_symbols(&table, Endian::Little).unwrap();
+        assert_eq!(interner.len(), 3);
+        assert_eq!(
+            interner
+                .get("Vec::<ü>::push")
+                .map(|sym| interner.resolve(sym)),
+            Some("Vec::<ü>::push")
+        );
+        assert!(interner.get("tail").is_some());
+
+        let no_bom = encode("a\0b\0", Endian::Little);
+        assert_eq!(load_symbols(&no_bom, Endian::Little).unwrap().len(), 2);
+        assert!(
+            load_symbols(&no_bom, Endian::Big)
+                .unwrap()
+                .get("a")
+                .is_none()
+        );
+        assert!(load_symbols(&[0xFF, 0xFE, 0, 0xD8, 0, 0], Endian::Little).is_err());
+        assert!(load_symbols(&[], Endian::Little).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fuzz_round_trips() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let s = decode_lossy(&rng.bytes(), Endian::Little);
+            for endian in [Endian::Little, Endian::Big] {
+                let bytes = encode(&s, endian);
+                assert_eq!(decode(&bytes, endian).unwrap(), s);
+                assert_eq!(decode_lossy(&bytes, endian), s);
+            }
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn fuzz_agrees_with_std() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let bytes = rng.bytes();
+            for endian in [Endian::Little, Endian::Big] {
+                assert_eq!(
+                    portable::decode(&bytes, endian).ok(),
+                    decode(&bytes, endian).ok(),
+                    "{bytes:x?}"
+                );
+                assert_eq!(
+                    portable::decode_lossy(&bytes, endian),
+                    decode_lossy(&bytes, endian),
+                    "{bytes:x?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! `str::Utf8Chunks` usage
+//!
+//! Tracking issue [99543].
+//!
+//! `Utf8Chunks` splits a byte slice into alternating valid `&str` runs and invalid byte sequences,
+//! with the same boundaries `String::from_utf8_lossy` uses to place replacement characters. Unlike
+//! `from_utf8_lossy` it never allocates, so [`Utf8Decoder`] can render lossy text straight into
+//! any `fmt::Write` sink while counting what it replaced.
+//!
+//! Input may arrive in arbitrary pieces: a sequence that is cut off at the end of one piece is
+//! carried over and completed by the next.
+//!
+//! [99543]: https://github.com/rust-lang/rust/issues/99543
+
+use std::fmt::{self, Write};
+use std::str;
+
+/// What to write in place of each invalid byte sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Write the given character once per invalid sequence. `Char('\u{FFFD}')` matches
+    /// `String::from_utf8_lossy`.
+    Char(char),
+    /// Write every invalid byte as a `\xNN` escape.
+    Escape,
+    /// Drop invalid sequences.
+    Skip,
+}
+
+impl Default for Replacement {
+    fn default() -> Self {
+        Self::Char(char::REPLACEMENT_CHARACTER)
+    }
+}
+
+/// Byte counts collected while decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Utf8Stats {
+    /// Bytes that were part of valid UTF-8.
+    pub valid_bytes: usize,
+    /// Bytes that were part of an invalid sequence.
+    pub invalid_bytes: usize,
+    /// Number of invalid sequences, i.e. how many times a replacement was applied.
+    pub invalid_sequences: usize,
+}
+
+/// Streaming lossy UTF-8 decoder.
+#[derive(Debug, Clone, Default)]
+pub struct Utf8Decoder {
+    replacement: Replacement,
+    stats: Utf8Stats,
+    /// Prefix of a multi-byte sequence cut off at the end of the previous input.
+    carry: [u8; 4],
+    carry_len: usize,
+}
+
+impl Utf8Decoder {
+    pub fn new(replacement: Replacement) -> Self {
+        Self {
+            replacement,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the statistics collected so far.
+    pub fn stats(&self) -> Utf8Stats {
+        self.stats
+    }
+
+    /// Decodes `bytes` into `out`, holding back a trailing incomplete sequence until the next call.
+    pub fn feed<W: Write>(&mut self, mut bytes: &[u8], out: &mut W) -> fmt::Result {
+        while self.carry_len > 0 {
+            let Some((&b, rest)) = bytes.split_first() else {
+                return Ok(());
+            };
+            self.carry[self.carry_len] = b;
+            self.carry_len += 1;
+            bytes = rest;
+
+            let carry = self.carry;
+            match str::from_utf8(&carry[..self.carry_len]) {
+                Ok(s) => {
+                    self.carry_len = 0;
+                    self.valid(s, out)?;
+                }
+                // Still a prefix of a valid sequence, needs more bytes.
+                Err(e) if e.error_len().is_none() => {}
+                Err(e) => {
+                    // The carried prefix turned out to be invalid, and the new byte is not part of
+                    // it. Replace the prefix and decode the new byte along with the rest.
+                    let invalid_len = e.error_len().unwrap();
+                    debug_assert_eq!(invalid_len, self.carry_len - 1);
+                    self.carry_len = 0;
+                    self.invalid(&carry[..invalid_len], out)?;
+                    self.feed(&[b], out)?;
+                }
+            }
+        }
+
+        let mut chunks = bytes.utf8_chunks().peekable();
+        while let Some(chunk) = chunks.next() {
+            self.valid(chunk.valid(), out)?;
+            let invalid = chunk.invalid();
+            if chunks.peek().is_none() && is_incomplete(invalid) {
+                self.carry[..invalid.len()].copy_from_slice(invalid);
+                self.carry_len = invalid.len();
+            } else if !invalid.is_empty() {
+                self.invalid(invalid, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes a pending incomplete sequence as invalid and returns the final statistics.
+    pub fn finish<W: Write>(mut self, out: &mut W) -> Result<Utf8Stats, fmt::Error> {
+        if self.carry_len > 0 {
+            let carry = self.carry;
+            self.invalid(&carry[..self.carry_len], out)?;
+        }
+        Ok(self.stats)
+    }
+
+    fn valid<W: Write>(&mut self, s: &str, out: &mut W) -> fmt::Result {
+        self.stats.valid_bytes += s.len();
+        out.write_str(s)
+    }
+
+    fn invalid<W: Write>(&mut self, bytes: &[u8], out: &mut W) -> fmt::Result {
+        self.stats.invalid_bytes += bytes.len();
+        self.stats.invalid_sequences += 1;
+        match self.replacement {
+            Replacement::Char(c) => out.write_char(c),
+            Replacement::Escape => bytes.iter().try_for_each(|b| write!(out, "\\x{b:02X}")),
+            Replacement::Skip => Ok(()),
+        }
+    }
+}
+
+/// Returns whether `invalid` is the start of a valid sequence that was cut off.
+fn is_incomplete(invalid: &[u8]) -> bool {
+    !invalid.is_empty() && str::from_utf8(invalid).is_err_and(|e| e.error_len().is_none())
+}
+
+/// Decodes all of `bytes` into `out` in one go.
+pub fn decode_lossy<W: Write>(
+    bytes: &[u8],
+    replacement: Replacement,
+    out: &mut W,
+) -> Result<Utf8Stats, fmt::Error> {
+    let mut decoder = Utf8Decoder::new(replacement);
+    decoder.feed(bytes, out)?;
+    decoder.finish(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: &[&[u8]] = &[
+        b"",
+        b"plain ascii",
+        "caf\u{e9} \u{1F980}".as_bytes(),
+        b"bad \xFF byte",
+        b"cut \xE2\x82",
+        b"\xF0\x9F\xA6",
+        b"\xE2\x82 mid",
+        b"\xC0\x80 overlong",
+        b"\xED\xA0\x80 surrogate",
+        b"\x80\x80\x80",
+        b"\xF4\x90\x80\x80 too large",
+    ];
+
+    fn decode_in_pieces(
+        bytes: &[u8],
+        piece: usize,
+        replacement: Replacement,
+    ) -> (String, Utf8Stats) {
+        let mut out = String::new();
+        let mut decoder = Utf8Decoder::new(replacement);
+        for piece in bytes.chunks(piece) {
+            decoder.feed(piece, &mut out).unwrap();
+        }
+        let stats = decoder.finish(&mut out).unwrap();
+        (out, stats)
+    }
+
+    #[test]
+    fn matches_from_utf8_lossy() {
+        for &bytes in INPUTS {
+            let expected = String::from_utf8_lossy(bytes);
+            let mut out = String::new();
+            decode_lossy(bytes, Replacement::default(), &mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        for &bytes in INPUTS {
+            let mut expected = String::new();
+            let stats = decode_lossy(bytes, Replacement::Escape, &mut expected).unwrap();
+            for piece in 1..=4 {
+                let streamed = decode_in_pieces(bytes, piece, Replacement::Escape);
+                assert_eq!(streamed, (expected.clone(), stats), "{bytes:?} by {piece}");
+            }
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let mut out = String::new();
+        let stats = decode_lossy(b"ok\xFF\xFEok\xE2\x82", Replacement::Skip, &mut out).unwrap();
+        assert_eq!(out, "okok");
+        assert_eq!(
+            stats,
+            Utf8Stats {
+                valid_bytes: 4,
+                invalid_bytes: 4,
+                invalid_sequences: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn replacements() {
+        let mut out = String::new();
+        decode_lossy(b"a\xFFb", Replacement::Char('?'), &mut out).unwrap();
+        assert_eq!(out, "a?b");
+
+        out.clear();
+        decode_lossy(b"a\xE2\x82b", Replacement::Escape, &mut out).unwrap();
+        assert_eq!(out, "a\\xE2\\x82b");
+    }
+
+    #[test]
+    fn carry_completed_across_pieces() {
+        let mut out = String::new();
+        let mut decoder = Utf8Decoder::new(Replacement::default());
+        decoder.feed(b"\xF0\x9F", &mut out).unwrap();
+        assert_eq!(out, "");
+        decoder.feed(b"\xA6\x80!", &mut out).unwrap();
+        assert_eq!(out, "\u{1F980}!");
+        assert_eq!(decoder.stats().invalid_bytes, 0);
+    }
+}
@@ -0,0 +1,216 @@
+//! `mem::variant_count` usage
+//!
+//! Tracking issues [73662] and [83527].
+//!
+//! `mem::variant_count::<E>()` is the number of variants of the enum `E`, as a constant. A table
+//! with one entry per variant can be sized and checked with it, and stays right when a variant is
+//! added, where a hand-written `COUNT` constant next to the enum silently goes stale.
+//!
+//! [`EnumIdx`] is an [`Idx`] over the variants of a fieldless enum, with their number as
+//! [`EnumIdx::COUNT`]. [`enum_idx!`](crate::enum_idx) implements both for an enum from a list of
+//! its variants. With the `nightly` feature `COUNT` is `variant_count`, checked at compile time
+//! against the length of the list; without it, it is the length of the list. Either way the
+//! exhaustive `match` the macro generates rejects a list that misses a variant, and a const check
+//! one out of declaration order. [`IndexVec::from_variants`] builds a vector with an entry per
+//! variant, and [`IndexVec::assert_covers_variants`] checks one is complete.
+//!
+//! The length of the list and the position of each variant in it come from the unstable
+//! `macro_metavar_expr`: in a repetition, `${index()}` expands to the number of the current
+//! round and `${count($v)}` to the number of rounds, so one arm emits a check per variant that its
+//! discriminant is its position. Without the `nightly` feature a helper macro walks the list one
+//! variant at a time instead, passing the position on as `0 + 1 + ...`, which nests one level
+//! deeper per variant and so runs into the recursion limit for long enums.
+//!
+//! [73662]: https://github.com/rust-lang/rust/issues/73662
+//! [83527]: https://github.com/rust-lang/rust/issues/83527
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// An index over the variants of an enum, in declaration order.
+pub trait EnumIdx: Idx {
+    /// Every variant, in order.
+    const VARIANTS: &'static [Self];
+
+    /// The number of variants.
+    const COUNT: usize;
+}
+
+/// Implements [`Idx`] and [`EnumIdx`] for a fieldless enum, given all of its variants in order.
+///
+/// With the `nightly` feature the expansion uses `macro_metavar_expr`, which the crate invoking
+/// the macro has to enable as well, since that is where the macro's body is parsed.
+///
+/// A list out of order fails to build:
+///
+/// ```compile_fail
+/// #![cfg_attr(feature = "nightly", feature(macro_metavar_expr))]
+/// use rust_unstable::variant_count::EnumIdx;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// enum Phase {
+///     Parse,
+///     Resolve,
+/// }
+///
+/// rust_unstable::enum_idx!(Phase { Resolve, Parse });
+///
+/// const COUNT: usize = Phase::COUNT;
+/// ```
+#[macro_export]
+macro_rules! enum_idx {
+    ($t:ident { $($v:ident),+ $(,)? }) => {
+        impl $crate::step::Idx for $t {
+            const MAX_INDEX: usize = <$t as $crate::variant_count::EnumIdx>::COUNT - 1;
+
+            #[inline]
+            fn new(idx: usize) -> Self {
+                <$t as $crate::variant_count::EnumIdx>::VARIANTS[idx]
+            }
+
+            #[inline]
+            fn index(self) -> usize {
+                // An exhaustive `match`, so that a variant missing from the list doesn't compile.
+                match self {
+                    $($t::$v => $t::$v as usize),+
+                }
+            }
+        }
+
+        impl $crate::variant_count::EnumIdx for $t {
+            const VARIANTS: &'static [Self] = &[$($t::$v),+];
+
+            const COUNT: usize = $crate::__enum_idx_count!($t { $($v),+ });
+        }
+    };
+}
+
+/// Checks that each variant's discriminant is its position in the list, and evaluates to the
+/// number of variants.
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __enum_idx_count {
+    ($t:ident { $($v:ident),+ }) => {{
+        $(
+            assert!(
+                $t::$v as usize == ${index()},
+                "`enum_idx!` must list the variants in declaration order"
+            );
+        )+
+        $crate::__variant_count!($t, ${count($v)})
+    }};
+}
+
+/// Checks that each variant's discriminant is its position in the list, and evaluates to the
+/// number of variants.
+#[cfg(not(feature = "nightly"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __enum_idx_count {
+    ($t:ident { $($v:ident),+ }) => {
+        $crate::__enum_idx_count!(@ $t, 0; $($v),+)
+    };
+    (@ $t:ident, $i:expr; $v:ident $(, $rest:ident)*) => {{
+        assert!(
+            $t::$v as usize == $i,
+            "`enum_idx!` must list the variants in declaration order"
+        );
+        $crate::__enum_idx_count!(@ $t, $i + 1; $($rest),*)
+    }};
+    (@ $t:ident, $i:expr;) => {
+        $crate::__variant_count!($t, $i)
+    };
+}
+
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __variant_count {
+    ($t:ident, $listed:expr) => {{
+        let count = $crate::variant_count::variant_count::<$t>();
+        assert!(count == $listed, "`enum_idx!` must list every variant once");
+        count
+    }};
+}
+
+#[cfg(not(feature = "nightly"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __variant_count {
+    ($t:ident, $listed:expr) => {
+        $listed
+    };
+}
+
+/// The number of variants of `E`, for [`enum_idx!`](crate::enum_idx) to use without the caller
+/// enabling the feature.
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+pub const fn variant_count<E>() -> usize {
+    core::mem::variant_count::<E>()
+}
+
+impl<E: EnumIdx, T> IndexVec<E, T> {
+    /// Creates a vector with `f(variant)` for every variant.
+    pub fn from_variants(f: impl FnMut(E) -> T) -> Self {
+        E::VARIANTS.iter().copied().map(f).collect()
+    }
+
+    /// # Panics
+    ///
+    /// Panics unless there is exactly one element per variant.
+    #[track_caller]
+    pub fn assert_covers_variants(&self) {
+        assert_eq!(
+            self.len(),
+            E::COUNT,
+            "expected one element per variant of {}",
+            core::any::type_name::<E>()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Phase {
+        Parse,
+        Resolve,
+        Typeck,
+        Codegen,
+    }
+
+    crate::enum_idx!(Phase {
+        Parse,
+        Resolve,
+        Typeck,
+        Codegen,
+    });
+
+    #[test]
+    fn table_per_variant() {
+        assert_eq!(Phase::COUNT, 4);
+        assert_eq!(Phase::MAX_INDEX, 3);
+        assert_eq!(Phase::new(2), Phase::Typeck);
+        assert_eq!(Phase::Codegen.index(), 3);
+
+        let mut times: IndexVec<Phase, u32> =
+            IndexVec::from_variants(|p: Phase| p.index() as u32 * 10);
+        times.assert_covers_variants();
+        times[Phase::Resolve] += 5;
+        assert_eq!(times.raw, [0, 15, 20, 30]);
+        let names: Vec<_> = times.indices().map(|p| format!("{p:?}")).collect();
+        assert_eq!(names, ["Parse", "Resolve", "Typeck", "Codegen"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected one element per variant")]
+    fn incomplete_table() {
+        let mut timings: IndexVec<Phase, u32> = IndexVec::from_variants(|_| 0);
+        timings.pop();
+        timings.assert_covers_variants();
+    }
+}
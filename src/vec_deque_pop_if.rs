@@ -0,0 +1,94 @@
+//! `VecDeque::{pop_front_if, pop_back_if}` usage
+//!
+//! Tracking issue [135889].
+//!
+//! `deque.pop_front_if(pred)` removes and returns the front element only if `pred` holds for it,
+//! and `pop_back_if` does the same at the back. Before these, a conditional dequeue was a `front()`
+//! peek followed by a separate `pop_front()`, with nothing tying the two together.
+//!
+//! [`WorkQueue`] gets the same methods, which also keep its dedup set in sync with the deque, and a
+//! [`WorkQueue::pop_while`] loop on top. [`WorkQueue::as_slices`] and
+//! [`WorkQueue::make_contiguous`] expose the queued indices as slices, read-only so the set can't
+//! go stale.
+//!
+//! [135889]: https://github.com/rust-lang/rust/issues/135889
+
+use crate::step::Idx;
+use crate::work_queue::WorkQueue;
+
+impl<T: Idx> WorkQueue<T> {
+    /// Removes the index at the front if `pred` holds for it.
+    #[inline]
+    pub fn pop_if(&mut self, pred: impl FnOnce(T) -> bool) -> Option<T> {
+        let element = self.deque.pop_front_if(|e| pred(*e))?;
+        self.set.remove(element);
+        Some(element)
+    }
+
+    /// Removes the index at the back if `pred` holds for it.
+    #[inline]
+    pub fn pop_back_if(&mut self, pred: impl FnOnce(T) -> bool) -> Option<T> {
+        let element = self.deque.pop_back_if(|e| pred(*e))?;
+        self.set.remove(element);
+        Some(element)
+    }
+
+    /// Removes indices from the front for as long as `pred` holds.
+    ///
+    /// Indices not taken stay queued, also if the iterator is dropped early.
+    pub fn pop_while<P>(&mut self, mut pred: P) -> impl Iterator<Item = T>
+    where
+        P: FnMut(T) -> bool,
+    {
+        std::iter::from_fn(move || self.pop_if(&mut pred))
+    }
+
+    /// Returns the queued indices, front to back, as two slices.
+    #[inline]
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        self.deque.as_slices()
+    }
+
+    /// Returns the queued indices, front to back, as one slice, moving them in place if needed.
+    #[inline]
+    pub fn make_contiguous(&mut self) -> &[T] {
+        self.deque.make_contiguous()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_if_keeps_set_in_sync() {
+        let mut queue: WorkQueue<u32> = WorkQueue::with_all(5);
+        assert_eq!(queue.pop_if(|i| i > 0), None);
+        assert_eq!(queue.pop_if(|i| i == 0), Some(0));
+        assert_eq!(queue.pop_back_if(|i| i % 2 == 0), Some(4));
+        assert_eq!(queue.pop_back_if(|i| i % 2 == 0), None);
+        // Popped indices can be queued again, the rest can't.
+        assert!(queue.insert(0));
+        assert!(!queue.insert(3));
+        assert_eq!(queue.make_contiguous(), [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn pop_while_and_slices() {
+        let mut queue: WorkQueue<usize> = WorkQueue::with_none(8);
+        for i in [5, 6, 7, 1, 2] {
+            queue.insert(i);
+        }
+        assert_eq!(queue.pop_while(|i| i > 4).collect::<Vec<_>>(), [5, 6, 7]);
+        // Wrap the ring buffer around so the queue spans both slices.
+        for i in [3, 4, 0, 5, 6, 7] {
+            queue.insert(i);
+        }
+        let (front, back) = queue.as_slices();
+        assert_eq!([front, back].concat(), [1, 2, 3, 4, 0, 5, 6, 7]);
+        assert_eq!(queue.make_contiguous(), [1, 2, 3, 4, 0, 5, 6, 7]);
+        assert_eq!(queue.as_slices().1, []);
+        assert_eq!(queue.pop_while(|i| i < 3).count(), 2);
+        assert_eq!(queue.pop(), Some(3));
+    }
+}
@@ -0,0 +1,149 @@
+//! `Vec::into_raw_parts` usage
+//!
+//! Tracking issue [65816].
+//!
+//! `vec.into_raw_parts()` gives up ownership of a vector's buffer as its pointer, length and
+//! capacity, and `Vec::from_raw_parts` takes it back. The triple is what a C API can hold on to,
+//! where a `Vec` can't cross. The older way, `ManuallyDrop` plus the three getters, is easy to
+//! get wrong in the order of the calls.
+//!
+//! [`RawParts`] is that triple as a `#[repr(C)]` struct that still owns the buffer: it can be
+//! passed to and returned from `extern "C"` functions by value, and dropping it on the Rust side
+//! drops the elements and frees the buffer. [`IndexVec::into_raw_parts`] and
+//! [`IndexVec::from_raw_parts`] convert between the two without `unsafe`; only a triple that
+//! comes from elsewhere needs the unsafe [`RawParts::from_raw`].
+//!
+//! [65816]: https://github.com/rust-lang/rust/issues/65816
+
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+
+use crate::index_vec::IndexVec;
+use crate::step::Idx;
+
+/// The buffer of an `IndexVec<I, T>`, as a C struct `{ T *ptr; size_t len; size_t cap; }`.
+#[repr(C)]
+pub struct RawParts<I: Idx, T> {
+    ptr: *mut T,
+    len: usize,
+    cap: usize,
+    _marker: PhantomData<fn(&I)>,
+    /// The elements are owned, for drop check.
+    _owned: PhantomData<T>,
+}
+
+impl<I: Idx, T> RawParts<I, T> {
+    /// Takes ownership of a buffer given as a triple.
+    ///
+    /// # Safety
+    ///
+    /// `ptr`, `len` and `cap` must come from a `Vec<T>` (or `RawParts<_, T>`), as for
+    /// `Vec::from_raw_parts`, and nothing else may use the buffer afterwards.
+    pub unsafe fn from_raw(ptr: *mut T, len: usize, cap: usize) -> Self {
+        RawParts {
+            ptr,
+            len,
+            cap,
+            _marker: PhantomData,
+            _owned: PhantomData,
+        }
+    }
+
+    /// Gives up ownership of the buffer, returning the triple.
+    pub fn into_raw(self) -> (*mut T, usize, usize) {
+        let this = ManuallyDrop::new(self);
+        (this.ptr, this.len, this.cap)
+    }
+
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+}
+
+impl<I: Idx, T> Drop for RawParts<I, T> {
+    fn drop(&mut self) {
+        // SAFETY: the fields come from a vector, and `self` owns its buffer.
+        drop(unsafe { Vec::from_raw_parts(self.ptr, self.len, self.cap) });
+    }
+}
+
+impl<I: Idx, T> IndexVec<I, T> {
+    /// Hands the buffer over as a [`RawParts`].
+    #[inline]
+    pub fn into_raw_parts(self) -> RawParts<I, T> {
+        let (ptr, len, cap) = self.raw.into_raw_parts();
+        // SAFETY: the triple was just taken from a vector.
+        unsafe { RawParts::from_raw(ptr, len, cap) }
+    }
+
+    /// Takes the buffer back from a [`RawParts`].
+    #[inline]
+    pub fn from_raw_parts(parts: RawParts<I, T>) -> Self {
+        let (ptr, len, cap) = parts.into_raw();
+        // SAFETY: `RawParts` only ever holds the triple of a vector, which it owned.
+        IndexVec::from_raw(unsafe { Vec::from_raw_parts(ptr, len, cap) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Stands in for a C function that takes the buffer, edits it in place and hands it back.
+    extern "C" fn c_double_all(parts: RawParts<u32, u32>) -> RawParts<u32, u32> {
+        // SAFETY: the pointer is valid for `len` elements.
+        let slice = unsafe { std::slice::from_raw_parts_mut(parts.as_mut_ptr(), parts.len()) };
+        for x in slice {
+            *x *= 2;
+        }
+        parts
+    }
+
+    #[test]
+    fn round_trip_through_ffi() {
+        let mut v: IndexVec<u32, u32> = IndexVec::with_capacity(8);
+        v.extend([1, 2, 3]);
+        let parts = v.into_raw_parts();
+        assert_eq!((parts.len(), parts.capacity()), (3, 8));
+        let v = IndexVec::from_raw_parts(c_double_all(parts));
+        assert_eq!(v.raw, [2, 4, 6]);
+        assert_eq!(v.capacity(), 8);
+
+        let (ptr, len, cap) = v.into_raw_parts().into_raw();
+        // SAFETY: the triple came from `into_raw` and isn't used again.
+        let v = IndexVec::<u32, u32>::from_raw_parts(unsafe { RawParts::from_raw(ptr, len, cap) });
+        assert_eq!(v.raw, [2, 4, 6]);
+    }
+
+    #[test]
+    fn dropping_parts_drops_elements() {
+        struct Tracked<'a>(&'a Cell<usize>);
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let v: IndexVec<usize, _> = (0..4).map(|_| Tracked(&drops)).collect();
+        let parts = v.into_raw_parts();
+        assert_eq!(drops.get(), 0);
+        drop(parts);
+        assert_eq!(drops.get(), 4);
+    }
+}
@@ -0,0 +1,183 @@
+//! `CommandExt::spawn_with_attributes` and `ChildExt::main_thread_handle` usage
+//!
+//! Tracking issues [114854] and [96723].
+//!
+//! `CreateProcess` takes a list of process and thread attributes alongside its creation flags:
+//! the process the child should inherit from as its parent, mitigation policies such as blocking
+//! dynamic code, whether the child may create processes of its own, and more. On Windows,
+//! `ProcThreadAttributeList` builds such a list out of raw attribute numbers and values, and
+//! `CommandExt::spawn_with_attributes` spawns a command with it. `ChildExt::main_thread_handle`
+//! gives the child's first thread, which a child created with `CREATE_SUSPENDED` needs resumed
+//! before it runs at all.
+//!
+//! [`SpawnAttributes`] is a safe builder over the attributes and flags that take plain values, so
+//! that the attribute numbers and value types can't be mixed up, and borrows the parent process
+//! handle until the spawn. [`SpawnAttributes::spawn_suspended`] returns a [`SuspendedChild`] to be
+//! resumed once the caller has set it up. It is the counterpart of `process_ext` on Unix, and is
+//! only built on Windows with the `nightly` feature.
+//!
+//! [114854]: https://github.com/rust-lang/rust/issues/114854
+//! [96723]: https://github.com/rust-lang/rust/issues/96723
+
+use std::io;
+use std::os::windows::io::{AsRawHandle, BorrowedHandle, RawHandle};
+use std::os::windows::process::{ChildExt, CommandExt, ProcThreadAttributeList};
+use std::process::{Child, Command};
+
+const PROC_THREAD_ATTRIBUTE_PARENT_PROCESS: usize = 0x0002_0000;
+const PROC_THREAD_ATTRIBUTE_MITIGATION_POLICY: usize = 0x0002_0007;
+const PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY: usize = 0x0002_000E;
+const PROCESS_CREATION_CHILD_PROCESS_RESTRICTED: u32 = 0x01;
+
+const CREATE_SUSPENDED: u32 = 0x0000_0004;
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn ResumeThread(thread: RawHandle) -> u32;
+}
+
+/// Attributes and creation flags for spawning a `Command`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpawnAttributes<'a> {
+    parent: Option<BorrowedHandle<'a>>,
+    mitigation_policy: Option<u64>,
+    child_process_policy: Option<u32>,
+    creation_flags: u32,
+}
+
+impl<'a> SpawnAttributes<'a> {
+    pub fn new() -> Self {
+        SpawnAttributes::default()
+    }
+
+    /// Makes `process` the child's parent, which it inherits handles and the console from. The
+    /// handle needs the `PROCESS_CREATE_PROCESS` access right.
+    pub fn parent(mut self, process: BorrowedHandle<'a>) -> Self {
+        self.parent = Some(process);
+        self
+    }
+
+    /// Sets the `PROCESS_CREATION_MITIGATION_POLICY_*` flags for the child.
+    pub fn mitigation_policy(mut self, policy: u64) -> Self {
+        self.mitigation_policy = Some(policy);
+        self
+    }
+
+    /// Stops the child from creating processes of its own.
+    pub fn restrict_child_processes(mut self) -> Self {
+        self.child_process_policy = Some(PROCESS_CREATION_CHILD_PROCESS_RESTRICTED);
+        self
+    }
+
+    /// Gives the child a process group of its own, so that it doesn't get the parent's Ctrl-C.
+    pub fn new_process_group(mut self) -> Self {
+        self.creation_flags |= CREATE_NEW_PROCESS_GROUP;
+        self
+    }
+
+    /// Runs a console child without a console window.
+    pub fn no_window(mut self) -> Self {
+        self.creation_flags |= CREATE_NO_WINDOW;
+        self
+    }
+
+    /// Spawns `command` with these attributes.
+    pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        self.spawn_with_flags(command, self.creation_flags)
+    }
+
+    /// Spawns `command` with these attributes, with its main thread suspended.
+    pub fn spawn_suspended(&self, command: &mut Command) -> io::Result<SuspendedChild> {
+        let child = self.spawn_with_flags(command, self.creation_flags | CREATE_SUSPENDED)?;
+        Ok(SuspendedChild { child })
+    }
+
+    fn spawn_with_flags(&self, command: &mut Command, flags: u32) -> io::Result<Child> {
+        let parent = self.parent.map(|process| process.as_raw_handle());
+        let mut list = ProcThreadAttributeList::build();
+        if let Some(parent) = &parent {
+            list = list.attribute(PROC_THREAD_ATTRIBUTE_PARENT_PROCESS, parent);
+        }
+        if let Some(policy) = &self.mitigation_policy {
+            list = list.attribute(PROC_THREAD_ATTRIBUTE_MITIGATION_POLICY, policy);
+        }
+        if let Some(policy) = &self.child_process_policy {
+            list = list.attribute(PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY, policy);
+        }
+        let list = list.finish()?;
+        command.creation_flags(flags).spawn_with_attributes(&list)
+    }
+}
+
+/// A child spawned by [`SpawnAttributes::spawn_suspended`], which doesn't run until resumed.
+///
+/// Dropping it leaves the process suspended; kill it through [`SuspendedChild::child`] instead.
+#[derive(Debug)]
+pub struct SuspendedChild {
+    child: Child,
+}
+
+impl SuspendedChild {
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Lets the child run.
+    pub fn resume(self) -> io::Result<Child> {
+        let thread = self.child.main_thread_handle();
+        // SAFETY: the handle is the child's main thread, which stays open as long as the `Child`.
+        if unsafe { ResumeThread(thread.as_raw_handle()) } == u32::MAX {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(self.child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::windows::io::AsHandle;
+    use std::process::Stdio;
+
+    use super::*;
+
+    fn exit_with(code: u32) -> Command {
+        let mut command = Command::new("cmd");
+        command.args(["/c", &format!("exit {code}")]);
+        command
+    }
+
+    #[test]
+    fn attributes() {
+        let attributes = SpawnAttributes::new()
+            .restrict_child_processes()
+            .mitigation_policy(0)
+            .new_process_group()
+            .no_window();
+        let status = attributes.spawn(&mut exit_with(3)).unwrap().wait().unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        // A child that waits on its input stands in as the parent.
+        let mut parent = Command::new("cmd").stdin(Stdio::piped()).spawn().unwrap();
+        let status = SpawnAttributes::new()
+            .parent(parent.as_handle())
+            .spawn(&mut exit_with(5))
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(status.code(), Some(5));
+        parent.kill().unwrap();
+        parent.wait().unwrap();
+    }
+
+    #[test]
+    fn suspended() {
+        let mut suspended = SpawnAttributes::new()
+            .spawn_suspended(&mut exit_with(4))
+            .unwrap();
+        assert!(suspended.child().try_wait().unwrap().is_none());
+        let status = suspended.resume().unwrap().wait().unwrap();
+        assert_eq!(status.code(), Some(4));
+    }
+}
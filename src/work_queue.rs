@@ -0,0 +1,99 @@
+//! `WorkQueue`, a deduplicating FIFO queue of indices
+//!
+//! **This is copy of rustc_data_structures**, trimmed down to what the examples in this crate need.
+//!
+//! A `WorkQueue<T>` is a `VecDeque<T>` paired with a [`BitSet<T>`] of the queued indices, so an
+//! index that is already waiting is not queued a second time. Dataflow-style fixpoint loops push
+//! every node whose inputs changed and let the set absorb the repeats.
+//!
+//! See [`rustc_data_structures::work_queue::WorkQueue`].
+//!
+//! [`rustc_data_structures::work_queue::WorkQueue`]: https://github.com/rust-lang/rust/blob/5e17a2a91dd7dbefd8b4a1087c2e42257457deeb/compiler/rustc_data_structures/src/work_queue.rs
+
+use std::collections::VecDeque;
+
+use crate::bit_set::BitSet;
+use crate::step::Idx;
+
+/// A FIFO queue of indices in `0..len` that holds each index at most once.
+pub struct WorkQueue<T: Idx> {
+    pub(crate) deque: VecDeque<T>,
+    pub(crate) set: BitSet<T>,
+}
+
+impl<T: Idx> WorkQueue<T> {
+    /// Creates an empty queue for indices below `len`.
+    #[inline]
+    pub fn with_none(len: usize) -> Self {
+        WorkQueue {
+            deque: VecDeque::with_capacity(len),
+            set: BitSet::new_empty(len),
+        }
+    }
+
+    /// Creates a queue holding all indices below `len`, in order.
+    #[inline]
+    pub fn with_all(len: usize) -> Self {
+        WorkQueue {
+            deque: (0..len).map(T::new).collect(),
+            set: BitSet::new_filled(len),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.deque.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.deque.is_empty()
+    }
+
+    /// Queues `element` at the back. Returns false if it was queued already.
+    #[inline]
+    pub fn insert(&mut self, element: T) -> bool {
+        if self.set.insert(element) {
+            self.deque.push_back(element);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the index at the front.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let element = self.deque.pop_front()?;
+        self.set.remove(element);
+        Some(element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_until_popped() {
+        let mut queue: WorkQueue<u32> = WorkQueue::with_none(4);
+        assert!(queue.insert(2));
+        assert!(queue.insert(0));
+        assert!(!queue.insert(2));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop(), Some(2));
+        // Once popped it can be queued again.
+        assert!(queue.insert(2));
+        assert_eq!(
+            (queue.pop(), queue.pop(), queue.pop()),
+            (Some(0), Some(2), None)
+        );
+
+        let mut all: WorkQueue<usize> = WorkQueue::with_all(3);
+        assert!(!all.insert(1));
+        assert_eq!(
+            std::iter::from_fn(|| all.pop()).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    }
+}
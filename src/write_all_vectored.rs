@@ -0,0 +1,206 @@
+//! `Write::write_all_vectored` and `Write::is_write_vectored` usage
+//!
+//! Tracking issues [70436] and [69941].
+//!
+//! `write_vectored` hands a writer several buffers at once, which a file or socket passes to the
+//! OS as one `writev` call instead of one `write` per buffer or a copy into one buffer first. It
+//! may write only part of them, and `write_all_vectored` keeps going until all of it is written.
+//! Writers that can't do better implement `write_vectored` by writing the first buffer alone, and
+//! `is_write_vectored` says which kind a writer is.
+//!
+//! [`write_index_vec_vectored`] and [`write_bit_set_vectored`] write the same blobs as
+//! `read_buf::write_index_vec` and `read_buf::write_bit_set`, passing the count header and the
+//! elements, borrowed straight from the container on little-endian targets, as two buffers. To a
+//! writer that doesn't vector they are written one after the other. Without the `nightly` feature
+//! there is no way to ask, so the buffers are written with a loop over `write_vectored`, which
+//! comes down to the same thing.
+//!
+//! [70436]: https://github.com/rust-lang/rust/issues/70436
+//! [69941]: https://github.com/rust-lang/rust/issues/69941
+
+use std::io::{self, IoSlice, Write};
+
+use crate::bit_set::BitSet;
+use crate::index_vec::IndexSlice;
+use crate::read_buf::{Word, le_bytes, len_header};
+use crate::step::Idx;
+
+/// Writes all of `bufs`, in one call where `writer` supports that.
+#[cfg(feature = "nightly")]
+pub fn write_all_slices(writer: &mut impl Write, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+    if writer.is_write_vectored() {
+        writer.write_all_vectored(bufs)
+    } else {
+        bufs.iter().try_for_each(|buf| writer.write_all(buf))
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+pub fn write_all_slices(writer: &mut impl Write, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+    portable::write_all_slices(writer, bufs)
+}
+
+fn write_words<T: Word>(writer: &mut impl Write, len: usize, words: &[T]) -> io::Result<()> {
+    let header = len_header(len);
+    let words = le_bytes(words);
+    write_all_slices(writer, &mut [IoSlice::new(&header), IoSlice::new(&words)])
+}
+
+/// Writes `v` as a blob for `read_buf::read_index_vec`.
+pub fn write_index_vec_vectored<I: Idx>(
+    writer: &mut impl Write,
+    v: &IndexSlice<I, u32>,
+) -> io::Result<()> {
+    write_words(writer, v.len(), &v.raw)
+}
+
+/// Writes `set` as a blob for `read_buf::read_bit_set`.
+pub fn write_bit_set_vectored<T: Idx>(writer: &mut impl Write, set: &BitSet<T>) -> io::Result<()> {
+    write_words(writer, set.domain_size(), set.words())
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use std::io::{self, IoSlice, Write};
+
+    pub(super) fn write_all_slices(
+        writer: &mut impl Write,
+        mut bufs: &mut [IoSlice<'_>],
+    ) -> io::Result<()> {
+        // Drops leading empty buffers.
+        IoSlice::advance_slices(&mut bufs, 0);
+        while !bufs.is_empty() {
+            match writer.write_vectored(bufs) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, thread};
+
+    use super::*;
+    use crate::index_vec::IndexVec;
+    use crate::read_buf::{read_bit_set, read_index_vec, write_index_vec};
+
+    /// A writer taking at most three bytes a call, counting its calls.
+    struct Trickle {
+        out: Vec<u8>,
+        vectored: bool,
+        calls: usize,
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            let n = buf.len().min(3);
+            self.out.extend(&buf[..n]);
+            Ok(n)
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            if !self.vectored {
+                let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| b);
+                return self.write(buf);
+            }
+            self.calls += 1;
+            let mut n = 0;
+            for buf in bufs {
+                let take = buf.len().min(3 - n);
+                self.out.extend(&buf[..take]);
+                n += take;
+            }
+            Ok(n)
+        }
+
+        #[cfg(feature = "nightly")]
+        fn is_write_vectored(&self) -> bool {
+            self.vectored
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sample() -> IndexVec<u32, u32> {
+        (0..100).map(|n| n * 7).collect()
+    }
+
+    #[test]
+    fn same_blob() {
+        let v = sample();
+        let mut expected = Vec::new();
+        write_index_vec(&mut expected, &v).unwrap();
+        let mut out = Vec::new();
+        write_index_vec_vectored(&mut out, &v).unwrap();
+        assert_eq!(out, expected);
+
+        for vectored in [true, false] {
+            let mut w = Trickle {
+                out: Vec::new(),
+                vectored,
+                calls: 0,
+            };
+            write_index_vec_vectored(&mut w, &v).unwrap();
+            assert_eq!(w.out, expected, "{vectored}");
+            // 408 bytes at three a call, where only a vectored writer has a call straddle the
+            // header.
+            let calls = if vectored { 136 } else { 137 };
+            assert_eq!(w.calls, calls, "{vectored}");
+        }
+    }
+
+    #[test]
+    fn over_pipe() {
+        let v = sample();
+        let (mut reader, mut writer) = io::pipe().unwrap();
+        let written = thread::scope(|s| {
+            let handle = s.spawn(|| {
+                write_index_vec_vectored(&mut writer, &v)?;
+                drop(writer);
+                io::Result::Ok(())
+            });
+            let read = read_index_vec::<u32>(&mut reader).unwrap();
+            handle.join().unwrap().unwrap();
+            read
+        });
+        assert_eq!(written, v);
+    }
+
+    #[test]
+    fn to_file() {
+        let path = env::temp_dir().join(format!("rust-unstable-vectored-{}", process::id()));
+        let mut set: BitSet<usize> = BitSet::new_empty(200);
+        set.insert(0);
+        set.insert(199);
+        write_bit_set_vectored(&mut fs::File::create(&path).unwrap(), &set).unwrap();
+        let read = read_bit_set::<usize>(&mut fs::File::open(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), set);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let parts: [&[u8]; 3] = [b"abcd", b"", b"efghij"];
+        for vectored in [true, false] {
+            let trickle = || Trickle {
+                out: Vec::new(),
+                vectored,
+                calls: 0,
+            };
+            let (mut std, mut portable) = (trickle(), trickle());
+            write_all_slices(&mut std, &mut parts.map(IoSlice::new)).unwrap();
+            portable::write_all_slices(&mut portable, &mut parts.map(IoSlice::new)).unwrap();
+            assert_eq!(std.out, b"abcdefghij");
+            assert_eq!(portable.out, std.out);
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! `BorrowedBuf` as a `fmt::Write` sink
+//!
+//! Tracking issues [117693] and [154046].
+//!
+//! `core::io::BorrowedBuf` is the part of the `read_buf` API that doesn't need `std`, now also
+//! reachable from `core`: a borrowed `&mut [MaybeUninit<u8>]` with a count of how much of it is
+//! filled, and a cursor that appends to the unfilled part. Nothing about it is specific to
+//! reading, so it also makes a stack buffer to format into without allocating, where the usual
+//! `String` can't be used: in a panic hook, which may run because an allocation failed, or
+//! without an allocator at all.
+//!
+//! [`WriteBuf`] implements `fmt::Write` over a borrowed buffer, and [`ArrayString`] over one of
+//! its own, so `write!(buf, "{set:?}")` renders the crate's index containers into either. Output
+//! that doesn't fit is cut at a char boundary as its [`Truncation`] says: with or without a marker
+//! such as `...` at the end, and with the write that didn't fit returning `Ok` or `fmt::Error`.
+//! `panic_hooks::install` formats its note this way. Without the `nightly` feature the buffer is
+//! a slice and a length in this crate.
+//!
+//! [117693]: https://github.com/rust-lang/rust/issues/117693
+//! [154046]: https://github.com/rust-lang/rust/issues/154046
+
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::{fmt, str};
+
+use crate::char_boundary::truncate_to_boundary;
+
+/// What a sink does with output that doesn't fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truncation {
+    /// Written at the end when output is cut. Its length is kept free for it, so output that
+    /// comes within that of the capacity is cut too.
+    pub marker: &'static str,
+    /// Whether the write that doesn't fit returns `fmt::Error`, which stops the formatting there,
+    /// rather than `Ok`, which lets it run on to be discarded.
+    pub error: bool,
+}
+
+impl Truncation {
+    /// Keeps what fits, without a marker, and reports success.
+    pub const SILENT: Truncation = Truncation {
+        marker: "",
+        error: false,
+    };
+
+    pub const fn marker(mut self, marker: &'static str) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    pub const fn error(mut self, error: bool) -> Self {
+        self.error = error;
+        self
+    }
+}
+
+impl Default for Truncation {
+    fn default() -> Self {
+        Truncation::SILENT
+    }
+}
+
+#[cfg(feature = "nightly")]
+type Buf<'a> = core::io::BorrowedBuf<'a>;
+
+#[cfg(not(feature = "nightly"))]
+type Buf<'a> = portable::BorrowedBuf<'a>;
+
+/// Appends `bytes` to the filled part of `buf`, which has room for them.
+#[cfg(feature = "nightly")]
+fn append(buf: &mut Buf<'_>, bytes: &[u8]) {
+    buf.unfilled().append(bytes);
+}
+
+#[cfg(not(feature = "nightly"))]
+fn append(buf: &mut Buf<'_>, bytes: &[u8]) {
+    buf.append(bytes);
+}
+
+/// A `fmt::Write` sink over a borrowed buffer, which is never grown.
+pub struct WriteBuf<'a> {
+    buf: Buf<'a>,
+    truncation: Truncation,
+    truncated: bool,
+}
+
+impl<'a> WriteBuf<'a> {
+    /// A sink over `bytes`, which truncates silently.
+    pub fn new(bytes: &'a mut [u8]) -> Self {
+        WriteBuf::with_truncation(bytes, Truncation::SILENT)
+    }
+
+    pub fn with_truncation(bytes: &'a mut [u8], truncation: Truncation) -> Self {
+        // SAFETY: `MaybeUninit<u8>` has the layout of `u8`, and only initialized bytes are written
+        // through it.
+        let bytes = unsafe { &mut *(bytes as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        WriteBuf::uninit(bytes, truncation)
+    }
+
+    /// A sink over uninitialized `bytes`, which are only ever written.
+    pub fn uninit(bytes: &'a mut [MaybeUninit<u8>], truncation: Truncation) -> Self {
+        WriteBuf {
+            buf: Buf::from(bytes),
+            truncation,
+            truncated: false,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether some output was cut.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// What was written, including the marker if it was cut.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: only whole `str`s, or prefixes of them cut at char boundaries, are appended.
+        unsafe { str::from_utf8_unchecked(self.buf.filled()) }
+    }
+
+    /// What was written, for as long as the buffer is borrowed.
+    pub fn into_str(self) -> &'a str {
+        // SAFETY: as for `as_str`.
+        unsafe { str::from_utf8_unchecked(self.buf.into_filled()) }
+    }
+
+    /// Empties the sink, so that it can be written again from the start.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.truncated = false;
+    }
+}
+
+impl fmt::Write for WriteBuf<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let overflow = if self.truncation.error {
+            Err(fmt::Error)
+        } else {
+            Ok(())
+        };
+        if self.truncated {
+            return overflow;
+        }
+        let room = self.capacity() - self.len();
+        if s.len() <= room.saturating_sub(self.truncation.marker.len()) {
+            append(&mut self.buf, s.as_bytes());
+            return Ok(());
+        }
+        let marker = truncate_to_boundary(self.truncation.marker, room);
+        let kept = truncate_to_boundary(s, room - marker.len());
+        append(&mut self.buf, kept.as_bytes());
+        append(&mut self.buf, marker.as_bytes());
+        self.truncated = true;
+        overflow
+    }
+}
+
+impl fmt::Debug for WriteBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteBuf")
+            .field("contents", &self.as_str())
+            .field("capacity", &self.capacity())
+            .field("truncated", &self.truncated)
+            .finish()
+    }
+}
+
+/// A string of at most `N` bytes, stored inline, which `fmt::Write` truncates rather than grows.
+pub struct ArrayString<const N: usize> {
+    bytes: [MaybeUninit<u8>; N],
+    /// The first `len` bytes of `bytes` are initialized, and valid UTF-8.
+    len: usize,
+    truncation: Truncation,
+    truncated: bool,
+}
+
+impl<const N: usize> ArrayString<N> {
+    /// An empty string, which truncates silently.
+    pub const fn new() -> Self {
+        ArrayString::with_truncation(Truncation::SILENT)
+    }
+
+    pub const fn with_truncation(truncation: Truncation) -> Self {
+        ArrayString {
+            bytes: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+            truncation,
+            truncated: false,
+        }
+    }
+
+    /// `args` formatted into a new string, such as `ArrayString::<64>::format(format_args!(..))`.
+    pub fn format(args: fmt::Arguments<'_>) -> Self {
+        let mut s = ArrayString::new();
+        // A silent truncation never fails, so an error is the `Display` impl's own.
+        let _ = fmt::Write::write_fmt(&mut s, args);
+        s
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the first `len` bytes are initialized and valid UTF-8.
+        unsafe { str::from_utf8_unchecked(self.bytes[..self.len].assume_init_ref()) }
+    }
+
+    /// Whether some output was cut.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+}
+
+impl<const N: usize> fmt::Write for ArrayString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut spare = WriteBuf::uninit(&mut self.bytes[self.len..], self.truncation);
+        spare.truncated = self.truncated;
+        let result = fmt::Write::write_str(&mut spare, s);
+        self.len += spare.len();
+        self.truncated = spare.truncated;
+        result
+    }
+}
+
+impl<const N: usize> Deref for ArrayString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        ArrayString::new()
+    }
+}
+
+impl<const N: usize> Clone for ArrayString<N> {
+    fn clone(&self) -> Self {
+        let mut clone = ArrayString::with_truncation(self.truncation);
+        clone.bytes[..self.len].write_copy_of_slice(self.as_bytes());
+        clone.len = self.len;
+        clone.truncated = self.truncated;
+        clone
+    }
+}
+
+impl<const N: usize> fmt::Display for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self)
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(any(test, not(feature = "nightly")))]
+mod portable {
+    use core::mem::MaybeUninit;
+
+    /// The subset of `core::io::BorrowedBuf` that `WriteBuf` uses, always fully initialized.
+    pub(super) struct BorrowedBuf<'a> {
+        bytes: &'a mut [MaybeUninit<u8>],
+        /// The first `filled` bytes of `bytes` are initialized.
+        filled: usize,
+    }
+
+    impl<'a> BorrowedBuf<'a> {
+        pub(super) fn capacity(&self) -> usize {
+            self.bytes.len()
+        }
+
+        pub(super) fn len(&self) -> usize {
+            self.filled
+        }
+
+        pub(super) fn filled(&self) -> &[u8] {
+            // SAFETY: the first `filled` bytes are initialized.
+            unsafe { self.bytes[..self.filled].assume_init_ref() }
+        }
+
+        pub(super) fn into_filled(self) -> &'a [u8] {
+            // SAFETY: as for `filled`.
+            unsafe { self.bytes[..self.filled].assume_init_ref() }
+        }
+
+        pub(super) fn clear(&mut self) {
+            self.filled = 0;
+        }
+
+        /// Appends `bytes`, panicking if there isn't room, like `BorrowedCursor::append`.
+        pub(super) fn append(&mut self, bytes: &[u8]) {
+            let end = self.filled + bytes.len();
+            self.bytes[self.filled..end].write_copy_of_slice(bytes);
+            self.filled = end;
+        }
+    }
+
+    impl<'a> From<&'a mut [MaybeUninit<u8>]> for BorrowedBuf<'a> {
+        fn from(bytes: &'a mut [MaybeUninit<u8>]) -> Self {
+            BorrowedBuf { bytes, filled: 0 }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::*;
+    use crate::bit_set::BitSet;
+
+    #[test]
+    fn writes_and_truncates() {
+        let mut bytes = [0; 8];
+        let mut buf = WriteBuf::new(&mut bytes);
+        write!(buf, "{}-{}", 12, 34).unwrap();
+        assert_eq!(buf.as_str(), "12-34");
+        assert!(!buf.is_truncated());
+        // "ü" is two bytes, and only one is left after "12-34ab".
+        write!(buf, "abü").unwrap();
+        assert_eq!(buf.as_str(), "12-34ab");
+        assert!(buf.is_truncated());
+        write!(buf, "c").unwrap();
+        assert_eq!(buf.len(), 7);
+        buf.clear();
+        write!(buf, "ü").unwrap();
+        assert_eq!(buf.into_str(), "ü");
+
+        let mut empty = [];
+        let mut buf = WriteBuf::new(&mut empty);
+        write!(buf, "").unwrap();
+        assert!(!buf.is_truncated());
+        write!(buf, "x").unwrap();
+        assert!(buf.is_truncated() && buf.is_empty());
+    }
+
+    #[test]
+    fn policies() {
+        let dots = Truncation::SILENT.marker("...");
+        let mut bytes = [MaybeUninit::uninit(); 8];
+        let mut buf = WriteBuf::uninit(&mut bytes, dots);
+        write!(buf, "{}", 12345).unwrap();
+        assert_eq!(buf.as_str(), "12345");
+        // The last three bytes are kept for the marker.
+        write!(buf, "6").unwrap();
+        assert_eq!(buf.as_str(), "12345...");
+        assert!(format!("{buf:?}").contains("truncated: true"));
+
+        let mut bytes = [0; 2];
+        let mut buf = WriteBuf::with_truncation(&mut bytes, dots.error(true));
+        // A marker longer than the buffer is cut too.
+        assert_eq!(write!(buf, "abc"), Err(fmt::Error));
+        assert_eq!(buf.as_str(), "..");
+        assert_eq!(write!(buf, "d"), Err(fmt::Error));
+    }
+
+    #[test]
+    fn array_string() {
+        let mut set: BitSet<u32> = BitSet::new_empty(64);
+        set.extend([1, 2, 3, 9, 40]);
+        let s = ArrayString::<32>::format(format_args!("{set:?}"));
+        assert_eq!(s.as_str(), "{1..4, 9, 40}");
+        assert!(!s.is_truncated());
+
+        let mut s = ArrayString::<8>::with_truncation(Truncation::SILENT.marker("~"));
+        let _ = write!(s, "{set:?}");
+        assert_eq!(&*s, "{1..4, ~");
+        assert!(s.is_truncated());
+        let clone = s.clone();
+        s.clear();
+        write!(s, "{}", clone.len()).unwrap();
+        assert_eq!((clone.as_str(), s.as_str()), ("{1..4, ~", "8"));
+        assert_eq!(
+            format!("[{clone:>10}] {clone:?}"),
+            r#"[  {1..4, ~] "{1..4, ~""#
+        );
+        assert_eq!(ArrayString::<0>::default().as_str(), "");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn portable_agrees_with_std() {
+        let mut std_bytes = [MaybeUninit::uninit(); 6];
+        let mut ported_bytes = [MaybeUninit::uninit(); 6];
+        let mut from_std = core::io::BorrowedBuf::from(&mut std_bytes[..]);
+        let mut ported = portable::BorrowedBuf::from(&mut ported_bytes[..]);
+        for bytes in [&b"ab"[..], b"", b"cde"] {
+            append(&mut from_std, bytes);
+            ported.append(bytes);
+            assert_eq!(from_std.filled(), ported.filled());
+            assert_eq!(from_std.len(), ported.len());
+            assert_eq!(from_std.capacity(), ported.capacity());
+        }
+        from_std.clear();
+        ported.clear();
+        assert_eq!(from_std.into_filled(), ported.into_filled());
+    }
+}
@@ -0,0 +1,66 @@
+//! Test cases collected with `#[test_case]` and run by `test_harness::runner` in place of
+//! libtest, so `cargo test --test custom_harness -- <filter>` goes through its filtering too.
+
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_unstable::test_harness::runner)]
+
+use std::panic;
+
+use rust_unstable::step::Idx;
+use rust_unstable::test_harness::{self, Options, Outcome, TestIdx, Testable};
+
+// The `#[test_case]` functions themselves can't be named, as the attribute makes their names
+// hygienic, so the cases below collect local functions.
+#[test_case]
+fn cases_are_named_by_path() {
+    fn local() {}
+    assert_eq!(local.name(), "cases_are_named_by_path::local");
+    assert_eq!(nested::first.name(), "nested::first");
+}
+
+#[test_case]
+fn failures_do_not_stop_the_run() {
+    fn fails() {
+        panic!("on purpose");
+    }
+    fn passes() {}
+
+    let tests = test_harness::collect(&[&fails, &passes]);
+    let quiet = Options {
+        quiet: true,
+        ..Options::default()
+    };
+    let mut out = Vec::new();
+    // The cases run one at a time on this thread, so the hook can be swapped for the run.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let report = test_harness::run(&tests, &quiet, &mut out);
+    panic::set_hook(hook);
+
+    let report = report.unwrap();
+    assert_eq!(
+        report.outcomes[TestIdx::new(0)],
+        Outcome::Failed("on purpose".to_string())
+    );
+    assert_eq!((report.passed(), report.failed()), (1, 1));
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("failures_do_not_stop_the_run::fails: on purpose"));
+}
+
+mod nested {
+    use super::*;
+
+    pub(super) fn first() {}
+
+    fn second() {}
+
+    #[test_case]
+    fn collects_in_order() {
+        let tests = test_harness::collect(&[&second, &first]);
+        let names: Vec<_> = tests
+            .iter_enumerated()
+            .map(|(idx, test)| (idx.index(), test.name))
+            .collect();
+        assert_eq!(names, [(0, "nested::second"), (1, "nested::first")]);
+    }
+}
@@ -0,0 +1,46 @@
+//! `enum_idx!` from another crate, which only enables the feature the expansion needs with the
+//! crate's `nightly` feature.
+
+#![cfg_attr(feature = "nightly", feature(macro_metavar_expr))]
+
+use rust_unstable::step::Idx;
+use rust_unstable::variant_count::EnumIdx;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Phase {
+    Parse,
+    Resolve,
+    Codegen,
+}
+
+rust_unstable::enum_idx!(Phase {
+    Parse,
+    Resolve,
+    Codegen,
+});
+
+#[cfg(feature = "nightly")]
+mod by_macro {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub(super) enum Level {
+        Low,
+        High,
+    }
+
+    rust_unstable::decl_macro::enum_idx!(Level { Low, High });
+}
+
+#[test]
+fn usable_from_other_crates() {
+    assert_eq!(Phase::COUNT, 3);
+    assert_eq!(Phase::new(2), Phase::Codegen);
+    assert_eq!(Phase::Resolve.index(), 1);
+}
+
+#[cfg(feature = "nightly")]
+#[test]
+fn macro_usable_from_other_crates() {
+    use by_macro::Level;
+    assert_eq!(Level::COUNT, 2);
+    assert_eq!(Level::new(1), Level::High);
+}
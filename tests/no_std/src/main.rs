@@ -0,0 +1,104 @@
+//! A `no_std` binary using `rust-unstable` without its default features, so that it links without
+//! std. It only gets `core`, `alloc` and the C library, and hands every error it gets from the
+//! crate to `check` as a `&dyn core::error::Error`. `tests/no_std.rs` builds and runs it.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::error::Error;
+use core::ffi::{c_char, c_int, c_void};
+use core::fmt::Write;
+use core::panic::PanicInfo;
+
+use rust_unstable::btree_cursors::OrderedIndexMap;
+use rust_unstable::error::{AnyIdx, IndexOutOfRange};
+use rust_unstable::first_chunk::read_u32_le;
+use rust_unstable::index_vec::IndexVec;
+use rust_unstable::iter_array_chunks::decode_pairs;
+
+#[link(name = "c")]
+unsafe extern "C" {
+    safe fn abort() -> !;
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+/// `malloc`, which aligns for any type of at most 16 bytes, as the crate's containers need.
+struct Malloc;
+
+unsafe impl GlobalAlloc for Malloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.align() > 16 {
+            return core::ptr::null_mut();
+        }
+        unsafe { malloc(layout.size()).cast() }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        unsafe { free(ptr.cast()) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Malloc = Malloc;
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    abort()
+}
+
+/// The prebuilt `alloc` is compiled to unwind and refers to the personality routine std would
+/// provide, though with `panic = "abort"` nothing calls it.
+#[unsafe(no_mangle)]
+extern "C" fn rust_eh_personality() {}
+
+fn print(line: &str) {
+    // SAFETY: `line` is valid for reads of its length.
+    unsafe { write(1, line.as_ptr().cast(), line.len()) };
+}
+
+/// Prints the error's message, and whether it is the expected one.
+fn check(err: &dyn Error, expected: &str) -> bool {
+    let mut line = String::new();
+    let _ = writeln!(line, "{err}");
+    print(&line);
+    line.trim_end() == expected
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn main(_argc: c_int, _argv: *const *const c_char) -> c_int {
+    let v: IndexVec<u32, u8> = IndexVec::from_raw(vec![1, 2]);
+    let out_of_range: IndexOutOfRange = v.try_get(5).unwrap_err();
+    let mismatch = AnyIdx::new(3usize).downcast::<u32>().unwrap_err();
+
+    let mut map = OrderedIndexMap::new();
+    map.insert(1usize, 'a');
+    map.insert(3, 'c');
+
+    let ok = [
+        check(
+            &out_of_range,
+            "index 5 out of range for 2 elements indexed by `u32`",
+        ),
+        check(&mismatch, "index 3 is a `usize`, not a `u32`"),
+        check(
+            &read_u32_le(&mut &[1, 2][..]).unwrap_err(),
+            "stream truncated: need 4 bytes, 2 left",
+        ),
+        check(
+            &decode_pairs::<u32>([0, 1, 2]).unwrap_err(),
+            "index stream ends with unpaired index 2",
+        ),
+        check(
+            &map.insert_after(1, 4, 'd').unwrap_err(),
+            "key 4 is not ordered between its neighbors",
+        ),
+    ];
+    if ok.iter().all(|&ok| ok) { 0 } else { 1 }
+}